@@ -18,6 +18,16 @@
                     pattern: ctx.passwordPattern,
                 }) %>
             </li>
+            <li>
+                <%= ctx.makeTextInput({
+                    text: 'Two-factor code',
+                    name: 'totp-code',
+                    pattern: '[0-9]{6}',
+                    inputmode: 'numeric',
+                    autocomplete: 'one-time-code',
+                }) %>
+                <p class='hint'>Only needed if two-factor authentication is enabled for the account.</p>
+            </li>
             <li>
                 <%= ctx.makeCheckbox({
                     text: 'Remember me',
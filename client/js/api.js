@@ -22,6 +22,7 @@ class Api extends events.EventTarget {
         this.user = null;
         this.userName = null;
         this.userPassword = null;
+        this.totpCode = null;
         this.token = null;
         this.cache = {};
         this.allRanks = [
@@ -226,6 +227,7 @@ class Api extends events.EventTarget {
                     this.userName = userName;
                     this.token = response.token;
                     this.userPassword = null;
+                    this.totpCode = null;
                 },
                 (error) => {
                     reject(error);
@@ -253,11 +255,12 @@ class Api extends events.EventTarget {
         });
     }
 
-    login(userName, userPassword, doRemember) {
+    login(userName, userPassword, doRemember, totpCode) {
         this.cache = {};
         return new Promise((resolve, reject) => {
             this.userName = userName;
             this.userPassword = userPassword;
+            this.totpCode = totpCode || null;
             this.get("/user/" + userName + "?bump-login=true").then(
                 (response) => {
                     const options = {};
@@ -293,6 +296,7 @@ class Api extends events.EventTarget {
         this.user = null;
         this.userName = null;
         this.userPassword = null;
+        this.totpCode = null;
         this.token = null;
         this.dispatchEvent(new CustomEvent("logout"));
     }
@@ -467,6 +471,9 @@ class Api extends events.EventTarget {
                             }
                         )
                     );
+                    if (this.totpCode) {
+                        req.set("X-Totp-Code", this.totpCode);
+                    }
                 }
             } catch (e) {
                 reject(
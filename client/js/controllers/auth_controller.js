@@ -22,7 +22,12 @@ class LoginController {
         this._loginView.clearMessages();
         this._loginView.disableForm();
         api.forget();
-        api.login(e.detail.name, e.detail.password, e.detail.remember).then(
+        api.login(
+            e.detail.name,
+            e.detail.password,
+            e.detail.remember,
+            e.detail.totpCode
+        ).then(
             () => {
                 const ctx = router.show(uri.formatClientLink());
                 ctx.controller.showSuccess("Logged in");
@@ -38,6 +38,7 @@ class LoginView extends events.EventTarget {
                         name: this._userNameInputNode.value,
                         password: this._passwordInputNode.value,
                         remember: this._rememberInputNode.checked,
+                        totpCode: this._totpCodeInputNode.value,
                     },
                 })
             );
@@ -56,6 +57,10 @@ class LoginView extends events.EventTarget {
         return this._formNode.querySelector("[name=password]");
     }
 
+    get _totpCodeInputNode() {
+        return this._formNode.querySelector("[name=totp-code]");
+    }
+
     get _rememberInputNode() {
         return this._formNode.querySelector("[name=remember-user]");
     }
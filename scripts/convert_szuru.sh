@@ -524,7 +524,7 @@ echo "  ✓ Post checksums recomputed"
 echo "  ✓ Post signatures recomputed"
 echo ""
 print_warning "Remaining manual tasks:"
-echo "  • Reset user passwords as needed"
+echo "  • Set password_secret in config.toml to the secret from config.yaml, or reset user passwords"
 echo "  • Migrate config.yaml settings to config.toml manually"
 echo "  • Check for tags/pools renamed due to case conflicts (*_name_modified_*)"
 echo ""
@@ -75,7 +75,7 @@ ALTER TABLE public."user"
 ALTER COLUMN "avatar_style" TYPE SMALLINT USING "avatar_style"::SMALLINT;
 
 -- Insert into Oxibooru table
--- Password hash and salt won't be useful in Oxibooru, but there's nothing we can really do about that
+-- Password hashes are verified the way Szurubooru did and rehashed when users log in
 INSERT INTO oxi."user" ("id", "name", "password_hash", "password_salt", "email", "rank", "avatar_style", "creation_time", "last_login_time", "last_edit_time") OVERRIDING SYSTEM VALUE
 SELECT "id", "name", "password_hash", "password_salt", "email", "rank", "avatar_style", "creation_time" AT TIME ZONE 'UTC', "last_login_time" AT TIME ZONE 'UTC', CURRENT_TIMESTAMP FROM public."user";
 
@@ -0,0 +1,30 @@
+CREATE OR REPLACE FUNCTION update_post_statistics() RETURNS TRIGGER AS $$
+DECLARE
+    count_change BIGINT;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        count_change := 1;
+        INSERT INTO "post_statistics" ("post_id") VALUES (NEW."id");
+    ELSIF TG_OP = 'DELETE' THEN
+        count_change := -1;
+    ELSE
+        count_change := 0;
+    END IF;
+
+    UPDATE "database_statistics"
+    SET "post_count" = "post_count" + count_change,
+        "disk_usage" = "disk_usage" + COALESCE(NEW."file_size", 0) + COALESCE(NEW."generated_thumbnail_size", 0) + COALESCE(NEW."custom_thumbnail_size", 0)
+                                    - COALESCE(OLD."file_size", 0) - COALESCE(OLD."generated_thumbnail_size", 0) - COALESCE(OLD."custom_thumbnail_size", 0);
+
+    UPDATE "user_statistics"
+    SET "upload_count" = "upload_count" + count_change
+    WHERE "user_id" = COALESCE(NEW."user_id", OLD."user_id");
+
+    RETURN COALESCE(NEW, OLD);
+END;
+$$ LANGUAGE plpgsql;
+
+DROP INDEX "idx_post_deletion_time";
+
+ALTER TABLE "post"
+DROP COLUMN "deletion_time";
@@ -0,0 +1,34 @@
+ALTER TABLE "post"
+ADD "status" SMALLINT NOT NULL DEFAULT 0;
+
+CREATE INDEX "idx_post_status" ON "post" USING BTREE ("status") WHERE "status" <> 0;
+
+-- Only approved posts count towards post and upload counts
+CREATE OR REPLACE FUNCTION update_post_statistics() RETURNS TRIGGER AS $$
+DECLARE
+    count_change BIGINT;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        INSERT INTO "post_statistics" ("post_id") VALUES (NEW."id");
+    END IF;
+
+    count_change := 0;
+    IF TG_OP <> 'DELETE' AND NEW."deletion_time" IS NULL AND NEW."status" = 0 THEN
+        count_change := count_change + 1;
+    END IF;
+    IF TG_OP <> 'INSERT' AND OLD."deletion_time" IS NULL AND OLD."status" = 0 THEN
+        count_change := count_change - 1;
+    END IF;
+
+    UPDATE "database_statistics"
+    SET "post_count" = "post_count" + count_change,
+        "disk_usage" = "disk_usage" + COALESCE(NEW."file_size", 0) + COALESCE(NEW."generated_thumbnail_size", 0) + COALESCE(NEW."custom_thumbnail_size", 0)
+                                    - COALESCE(OLD."file_size", 0) - COALESCE(OLD."generated_thumbnail_size", 0) - COALESCE(OLD."custom_thumbnail_size", 0);
+
+    UPDATE "user_statistics"
+    SET "upload_count" = "upload_count" + count_change
+    WHERE "user_id" = COALESCE(NEW."user_id", OLD."user_id");
+
+    RETURN COALESCE(NEW, OLD);
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1 @@
+DROP TABLE "report";
\ No newline at end of file
@@ -0,0 +1,15 @@
+CREATE TABLE "report" (
+    "id" BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    "user_id" BIGINT REFERENCES "user" ON DELETE SET NULL,
+    "resource_type" SMALLINT NOT NULL,
+    "resource_id" TEXT NOT NULL,
+    "reason" TEXT NOT NULL,
+    "status" SMALLINT NOT NULL DEFAULT 0,
+    "resolver_id" BIGINT REFERENCES "user" ON DELETE SET NULL,
+    "creation_time" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "resolution_time" TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX "idx_report_user_id" ON "report" USING BTREE ("user_id");
+CREATE INDEX "idx_report_resolver_id" ON "report" USING BTREE ("resolver_id");
+CREATE INDEX "idx_report_status" ON "report" USING BTREE ("status");
\ No newline at end of file
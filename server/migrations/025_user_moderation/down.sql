@@ -0,0 +1,2 @@
+DROP TABLE "user_note";
+DROP TABLE "user_sanction";
\ No newline at end of file
@@ -0,0 +1,23 @@
+CREATE TABLE "user_sanction" (
+    "id" BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    "user_id" BIGINT NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "issuer_id" BIGINT REFERENCES "user" ON DELETE SET NULL,
+    "kind" SMALLINT NOT NULL,
+    "reason" TEXT NOT NULL,
+    "creation_time" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "expiration_time" TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX "idx_user_sanction_user_id" ON "user_sanction" USING BTREE ("user_id");
+CREATE INDEX "idx_user_sanction_issuer_id" ON "user_sanction" USING BTREE ("issuer_id");
+
+CREATE TABLE "user_note" (
+    "id" BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    "user_id" BIGINT NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "author_id" BIGINT REFERENCES "user" ON DELETE SET NULL,
+    "text" TEXT NOT NULL,
+    "creation_time" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX "idx_user_note_user_id" ON "user_note" USING BTREE ("user_id");
+CREATE INDEX "idx_user_note_author_id" ON "user_note" USING BTREE ("author_id");
\ No newline at end of file
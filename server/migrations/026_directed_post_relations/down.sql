@@ -0,0 +1,24 @@
+DELETE FROM "post_relation" WHERE "kind" <> 0;
+
+CREATE OR REPLACE FUNCTION update_post_relation_statistics() RETURNS TRIGGER AS $$
+DECLARE
+    count_change BIGINT;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        count_change := 1;
+    ELSE
+        count_change := -1;
+    END IF;
+
+    UPDATE "post_statistics"
+    SET "relation_count" = "relation_count" + count_change
+    WHERE "post_id" = COALESCE(NEW."parent_id", OLD."parent_id");
+
+    RETURN COALESCE(NEW, OLD);
+END;
+$$ LANGUAGE plpgsql;
+
+DROP INDEX "idx_post_relation_unique_parent";
+ALTER TABLE "post_relation" DROP CONSTRAINT "post_relation_pkey";
+ALTER TABLE "post_relation" ADD PRIMARY KEY ("parent_id", "child_id");
+ALTER TABLE "post_relation" DROP COLUMN "kind";
\ No newline at end of file
@@ -0,0 +1,30 @@
+-- Kind 0 is a symmetric relation stored as a pair of rows, kind 1 is a directed parent -> child relation
+ALTER TABLE "post_relation" ADD "kind" SMALLINT NOT NULL DEFAULT 0;
+ALTER TABLE "post_relation" DROP CONSTRAINT "post_relation_pkey";
+ALTER TABLE "post_relation" ADD PRIMARY KEY ("parent_id", "child_id", "kind");
+
+-- A post can have at most one parent
+CREATE UNIQUE INDEX "idx_post_relation_unique_parent" ON "post_relation" USING BTREE ("child_id") WHERE "kind" = 1;
+
+-- Only symmetric relations count towards relation_count
+CREATE OR REPLACE FUNCTION update_post_relation_statistics() RETURNS TRIGGER AS $$
+DECLARE
+    count_change BIGINT;
+BEGIN
+    IF COALESCE(NEW."kind", OLD."kind") <> 0 THEN
+        RETURN COALESCE(NEW, OLD);
+    END IF;
+
+    IF TG_OP = 'INSERT' THEN
+        count_change := 1;
+    ELSE
+        count_change := -1;
+    END IF;
+
+    UPDATE "post_statistics"
+    SET "relation_count" = "relation_count" + count_change
+    WHERE "post_id" = COALESCE(NEW."parent_id", OLD."parent_id");
+
+    RETURN COALESCE(NEW, OLD);
+END;
+$$ LANGUAGE plpgsql;
\ No newline at end of file
@@ -0,0 +1,8 @@
+DROP FUNCTION update_post_file_statistics CASCADE;
+
+DROP TABLE "post_file_signature";
+
+UPDATE "database_statistics"
+SET "disk_usage" = "disk_usage" - COALESCE((SELECT SUM("file_size" + "generated_thumbnail_size") FROM "post_file"), 0);
+
+DROP TABLE "post_file";
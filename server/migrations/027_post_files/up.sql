@@ -0,0 +1,36 @@
+CREATE TABLE "post_file" (
+    "id" BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    "post_id" BIGINT NOT NULL REFERENCES "post" ON DELETE CASCADE,
+    "order" INTEGER NOT NULL,
+    "file_size" BIGINT NOT NULL,
+    "width" INTEGER NOT NULL,
+    "height" INTEGER NOT NULL,
+    "type" SMALLINT NOT NULL,
+    "mime_type" SMALLINT NOT NULL,
+    "checksum" BYTEA NOT NULL UNIQUE,
+    "checksum_md5" BYTEA NOT NULL,
+    "generated_thumbnail_size" BIGINT NOT NULL DEFAULT 0,
+    "creation_time" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX "idx_post_file_post_id" ON "post_file" USING BTREE ("post_id", "order");
+
+CREATE TABLE "post_file_signature" (
+    "post_file_id" BIGINT PRIMARY KEY REFERENCES "post_file" ON DELETE CASCADE,
+    "signature" BIGINT[] NOT NULL,
+    "words" INTEGER[] NOT NULL
+);
+
+CREATE INDEX "idx_post_file_signature_words" ON "post_file_signature" USING GIN ("words" gin__int_ops);
+
+CREATE FUNCTION update_post_file_statistics() RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE "database_statistics"
+    SET "disk_usage" = "disk_usage" + COALESCE(NEW."file_size", 0) + COALESCE(NEW."generated_thumbnail_size", 0)
+                                    - COALESCE(OLD."file_size", 0) - COALESCE(OLD."generated_thumbnail_size", 0);
+    RETURN COALESCE(NEW, OLD);
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE CONSTRAINT TRIGGER post_file_update_trigger AFTER INSERT OR UPDATE OR DELETE ON "post_file"
+DEFERRABLE INITIALLY DEFERRED FOR EACH ROW EXECUTE FUNCTION update_post_file_statistics();
@@ -0,0 +1 @@
+DROP TABLE "wiki_page";
\ No newline at end of file
@@ -0,0 +1,9 @@
+CREATE TABLE "wiki_page" (
+    "id" BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    "title" CITEXT UNIQUE,
+    "tag_id" BIGINT UNIQUE REFERENCES "tag" ON DELETE CASCADE,
+    "body" TEXT NOT NULL,
+    "creation_time" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "last_edit_time" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (("title" IS NULL) <> ("tag_id" IS NULL))
+);
\ No newline at end of file
@@ -0,0 +1,3 @@
+DROP TABLE "comment_mention";
+
+ALTER TABLE "comment" DROP COLUMN "parent_comment_id";
\ No newline at end of file
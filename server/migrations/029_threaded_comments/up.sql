@@ -0,0 +1,10 @@
+ALTER TABLE "comment" ADD "parent_comment_id" BIGINT REFERENCES "comment" ON DELETE SET NULL;
+CREATE INDEX "idx_comment_parent_comment_id" ON "comment" USING BTREE ("parent_comment_id");
+
+CREATE TABLE "comment_mention" (
+    "comment_id" BIGINT NOT NULL REFERENCES "comment" ON DELETE CASCADE,
+    "user_id" BIGINT NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    PRIMARY KEY ("comment_id", "user_id")
+);
+
+CREATE INDEX "idx_comment_mention_user_id" ON "comment_mention" USING BTREE ("user_id");
\ No newline at end of file
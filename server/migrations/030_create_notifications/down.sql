@@ -0,0 +1,2 @@
+DROP TABLE "muted_notification";
+DROP TABLE "notification";
\ No newline at end of file
@@ -0,0 +1,21 @@
+CREATE TABLE "notification" (
+    "id" BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    "user_id" BIGINT NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "kind" SMALLINT NOT NULL,
+    "actor_id" BIGINT REFERENCES "user" ON DELETE SET NULL,
+    "post_id" BIGINT REFERENCES "post" ON DELETE CASCADE,
+    "comment_id" BIGINT REFERENCES "comment" ON DELETE CASCADE,
+    "is_read" BOOLEAN NOT NULL DEFAULT FALSE,
+    "creation_time" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX "idx_notification_user_id_is_read" ON "notification" USING BTREE ("user_id", "is_read");
+CREATE INDEX "idx_notification_actor_id" ON "notification" USING BTREE ("actor_id");
+CREATE INDEX "idx_notification_post_id" ON "notification" USING BTREE ("post_id");
+CREATE INDEX "idx_notification_comment_id" ON "notification" USING BTREE ("comment_id");
+
+CREATE TABLE "muted_notification" (
+    "user_id" BIGINT NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "kind" SMALLINT NOT NULL,
+    PRIMARY KEY ("user_id", "kind")
+);
\ No newline at end of file
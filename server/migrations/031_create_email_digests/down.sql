@@ -0,0 +1,3 @@
+DROP TABLE "saved_search";
+DROP TABLE "watched_tag";
+DROP TABLE "digest_subscription";
\ No newline at end of file
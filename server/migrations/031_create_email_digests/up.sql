@@ -0,0 +1,20 @@
+CREATE TABLE "digest_subscription" (
+    "user_id" BIGINT PRIMARY KEY REFERENCES "user" ON DELETE CASCADE,
+    "frequency" SMALLINT NOT NULL,
+    "last_post_id" BIGINT NOT NULL,
+    "last_sent_time" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE "watched_tag" (
+    "user_id" BIGINT NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "tag_id" BIGINT NOT NULL REFERENCES "tag" ON DELETE CASCADE,
+    PRIMARY KEY ("user_id", "tag_id")
+);
+
+CREATE INDEX "idx_watched_tag_tag_id" ON "watched_tag" USING BTREE ("tag_id");
+
+CREATE TABLE "saved_search" (
+    "user_id" BIGINT NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "query" TEXT NOT NULL,
+    PRIMARY KEY ("user_id", "query")
+);
\ No newline at end of file
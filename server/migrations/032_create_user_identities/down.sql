@@ -0,0 +1 @@
+DROP TABLE "user_identity";
\ No newline at end of file
@@ -0,0 +1,9 @@
+CREATE TABLE "user_identity" (
+    "issuer" TEXT NOT NULL,
+    "subject" TEXT NOT NULL,
+    "user_id" BIGINT NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "creation_time" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY ("issuer", "subject")
+);
+
+CREATE INDEX "idx_user_identity_user_id" ON "user_identity" USING BTREE ("user_id");
\ No newline at end of file
@@ -0,0 +1,2 @@
+DROP TABLE "totp_recovery_code";
+DROP TABLE "user_totp";
\ No newline at end of file
@@ -0,0 +1,12 @@
+CREATE TABLE "user_totp" (
+    "user_id" BIGINT PRIMARY KEY REFERENCES "user" ON DELETE CASCADE,
+    "secret" BYTEA NOT NULL,
+    "enabled" BOOLEAN NOT NULL DEFAULT FALSE,
+    "creation_time" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE "totp_recovery_code" (
+    "user_id" BIGINT NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "code_hash" TEXT NOT NULL,
+    PRIMARY KEY ("user_id", "code_hash")
+);
\ No newline at end of file
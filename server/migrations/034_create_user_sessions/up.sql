@@ -0,0 +1,10 @@
+CREATE TABLE "user_session" (
+    "id" BIGSERIAL PRIMARY KEY,
+    "user_id" BIGINT NOT NULL REFERENCES "user" ON DELETE CASCADE,
+    "secret_hash" TEXT NOT NULL UNIQUE,
+    "creation_time" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "last_usage_time" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "expiration_time" TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX "idx_user_session_user_id" ON "user_session" USING BTREE ("user_id");
\ No newline at end of file
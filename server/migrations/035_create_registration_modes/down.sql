@@ -0,0 +1,2 @@
+DROP TABLE "invite_code";
+DROP TABLE "email_verification";
\ No newline at end of file
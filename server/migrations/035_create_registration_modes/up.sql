@@ -0,0 +1,14 @@
+CREATE TABLE "email_verification" (
+    "user_id" BIGINT PRIMARY KEY REFERENCES "user" ON DELETE CASCADE,
+    "rank" SMALLINT NOT NULL,
+    "creation_time" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE "invite_code" (
+    "code" TEXT PRIMARY KEY,
+    "creator_id" BIGINT REFERENCES "user" ON DELETE SET NULL,
+    "max_uses" INTEGER NOT NULL CHECK ("max_uses" > 0),
+    "use_count" INTEGER NOT NULL DEFAULT 0,
+    "expiration_time" TIMESTAMP WITH TIME ZONE,
+    "creation_time" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
\ No newline at end of file
@@ -0,0 +1,26 @@
+CREATE TABLE "audit_log" (
+    "id" BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    "event" SMALLINT NOT NULL,
+    "user_id" BIGINT,
+    "user_name" TEXT,
+    "target" TEXT,
+    "details" TEXT,
+    "ip_address" TEXT,
+    "user_agent" TEXT,
+    "creation_time" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX "idx_audit_log_event" ON "audit_log" USING BTREE ("event");
+CREATE INDEX "idx_audit_log_user_name" ON "audit_log" USING BTREE ("user_name");
+CREATE INDEX "idx_audit_log_creation_time" ON "audit_log" USING BTREE ("creation_time");
+
+-- Entries are never modified once written. Deletes are still allowed so that old entries can be purged.
+-- user_id intentionally has no foreign key, as ON DELETE SET NULL would count as a modification.
+CREATE FUNCTION reject_audit_log_update() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'Audit log entries cannot be modified';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_update_trigger BEFORE UPDATE ON "audit_log"
+FOR EACH ROW EXECUTE FUNCTION reject_audit_log_update();
\ No newline at end of file
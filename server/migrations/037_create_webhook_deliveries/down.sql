@@ -0,0 +1,4 @@
+DROP TABLE "webhook_delivery";
+DROP TABLE "webhook_event";
+DROP TRIGGER webhook_event_trigger ON "snapshot";
+DROP FUNCTION queue_webhook_event;
\ No newline at end of file
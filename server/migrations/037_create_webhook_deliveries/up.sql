@@ -0,0 +1,30 @@
+-- Every new snapshot is queued here in the same transaction that creates it,
+-- so no snapshot can be missed by webhooks, even if the server is restarted.
+CREATE TABLE "webhook_event" (
+    "snapshot_id" BIGINT PRIMARY KEY REFERENCES "snapshot" ON DELETE CASCADE
+);
+
+CREATE FUNCTION queue_webhook_event() RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO "webhook_event" ("snapshot_id") VALUES (NEW."id");
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER webhook_event_trigger AFTER INSERT ON "snapshot"
+FOR EACH ROW EXECUTE FUNCTION queue_webhook_event();
+
+CREATE TABLE "webhook_delivery" (
+    "id" BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    "snapshot_id" BIGINT NOT NULL REFERENCES "snapshot" ON DELETE CASCADE,
+    "url" TEXT NOT NULL,
+    "status" SMALLINT NOT NULL DEFAULT 0,
+    "attempt_count" INTEGER NOT NULL DEFAULT 0,
+    "last_error" TEXT,
+    "next_attempt_time" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "last_attempt_time" TIMESTAMP WITH TIME ZONE,
+    "creation_time" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX "idx_webhook_delivery_snapshot_id" ON "webhook_delivery" USING BTREE ("snapshot_id");
+CREATE INDEX "idx_webhook_delivery_status_next_attempt_time" ON "webhook_delivery" USING BTREE ("status", "next_attempt_time");
\ No newline at end of file
@@ -0,0 +1,62 @@
+DROP TRIGGER post_listing_trigger ON "post";
+DROP FUNCTION update_post_listing_statistics;
+DROP TRIGGER post_tag_usage_trigger ON "post_tag";
+DROP FUNCTION update_tag_usage_statistics;
+
+CREATE OR REPLACE FUNCTION update_post_tag_statistics() RETURNS TRIGGER AS $$
+DECLARE
+    count_change BIGINT;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        count_change := 1;
+    ELSE
+        count_change := -1;
+    END IF;
+
+    UPDATE "post_statistics"
+    SET "tag_count" = "tag_count" + count_change
+    WHERE "post_id" = COALESCE(NEW."post_id", OLD."post_id");
+
+    UPDATE "tag_statistics"
+    SET "usage_count" = "usage_count" + count_change
+    WHERE "tag_id" = COALESCE(NEW."tag_id", OLD."tag_id");
+
+    RETURN COALESCE(NEW, OLD);
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION update_tag_statistics() RETURNS TRIGGER AS $$
+DECLARE
+    count_change BIGINT;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        count_change := 1;
+        INSERT INTO "tag_statistics" ("tag_id") VALUES (NEW."id");
+    ELSIF TG_OP = 'DELETE' THEN
+        count_change := -1;
+    ELSE
+        IF NEW."category_id" IS DISTINCT FROM OLD."category_id" THEN
+            UPDATE "tag_category_statistics"
+            SET "usage_count" = "usage_count" - 1
+            WHERE "category_id" = OLD."category_id";
+
+            UPDATE "tag_category_statistics"
+            SET "usage_count" = "usage_count" + 1
+            WHERE "category_id" = NEW."category_id";
+        END IF;
+        RETURN NEW;
+    END IF;
+
+    UPDATE "database_statistics"
+    SET "tag_count" = "tag_count" + count_change;
+
+    UPDATE "tag_category_statistics"
+    SET "usage_count" = "usage_count" + count_change
+    WHERE "category_id" = COALESCE(NEW."category_id", OLD."category_id");
+
+    RETURN COALESCE(NEW, OLD);
+END;
+$$ LANGUAGE plpgsql;
+
+UPDATE "tag_statistics"
+SET "usage_count" = (SELECT COUNT(*) FROM "post_tag" WHERE "post_tag"."tag_id" = "tag_statistics"."tag_id");
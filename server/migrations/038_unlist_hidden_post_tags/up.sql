@@ -0,0 +1,110 @@
+-- Tag usage counts only include posts that are listed, which excludes soft-deleted and unapproved posts.
+-- Usage is tracked by immediate triggers, as deferred ones would see the final state of a post
+-- instead of the state it had when its tags changed.
+CREATE OR REPLACE FUNCTION update_post_tag_statistics() RETURNS TRIGGER AS $$
+DECLARE
+    count_change BIGINT;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        count_change := 1;
+    ELSE
+        count_change := -1;
+    END IF;
+
+    UPDATE "post_statistics"
+    SET "tag_count" = "tag_count" + count_change
+    WHERE "post_id" = COALESCE(NEW."post_id", OLD."post_id");
+
+    RETURN COALESCE(NEW, OLD);
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION update_tag_usage_statistics() RETURNS TRIGGER AS $$
+BEGIN
+    -- Tags of hard-deleted posts are uncounted by post_listing_trigger before the post is removed
+    IF NOT EXISTS (SELECT 1 FROM "post" WHERE "id" = COALESCE(NEW."post_id", OLD."post_id") AND "deletion_time" IS NULL AND "status" = 0) THEN
+        RETURN COALESCE(NEW, OLD);
+    END IF;
+
+    -- Statistics of tags created in this transaction haven't been inserted by the deferred tag trigger yet
+    IF TG_OP = 'INSERT' THEN
+        INSERT INTO "tag_statistics" ("tag_id", "usage_count") VALUES (NEW."tag_id", 1)
+        ON CONFLICT ("tag_id") DO UPDATE SET "usage_count" = "tag_statistics"."usage_count" + 1;
+    ELSE
+        UPDATE "tag_statistics"
+        SET "usage_count" = "usage_count" - 1
+        WHERE "tag_id" = OLD."tag_id";
+    END IF;
+
+    RETURN COALESCE(NEW, OLD);
+END;
+$$ LANGUAGE plpgsql;
+
+-- Usage of a new tag may already be counted by the time its statistics are inserted
+CREATE OR REPLACE FUNCTION update_tag_statistics() RETURNS TRIGGER AS $$
+DECLARE
+    count_change BIGINT;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        count_change := 1;
+        INSERT INTO "tag_statistics" ("tag_id") VALUES (NEW."id") ON CONFLICT DO NOTHING;
+    ELSIF TG_OP = 'DELETE' THEN
+        count_change := -1;
+    ELSE
+        IF NEW."category_id" IS DISTINCT FROM OLD."category_id" THEN
+            UPDATE "tag_category_statistics"
+            SET "usage_count" = "usage_count" - 1
+            WHERE "category_id" = OLD."category_id";
+
+            UPDATE "tag_category_statistics"
+            SET "usage_count" = "usage_count" + 1
+            WHERE "category_id" = NEW."category_id";
+        END IF;
+        RETURN NEW;
+    END IF;
+
+    UPDATE "database_statistics"
+    SET "tag_count" = "tag_count" + count_change;
+
+    UPDATE "tag_category_statistics"
+    SET "usage_count" = "usage_count" + count_change
+    WHERE "category_id" = COALESCE(NEW."category_id", OLD."category_id");
+
+    RETURN COALESCE(NEW, OLD);
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER post_tag_usage_trigger AFTER INSERT OR DELETE ON "post_tag"
+FOR EACH ROW EXECUTE FUNCTION update_tag_usage_statistics();
+
+CREATE FUNCTION update_post_listing_statistics() RETURNS TRIGGER AS $$
+DECLARE
+    was_listed BOOLEAN;
+    is_listed BOOLEAN;
+BEGIN
+    was_listed := OLD."deletion_time" IS NULL AND OLD."status" = 0;
+    IF TG_OP = 'UPDATE' THEN
+        is_listed := NEW."deletion_time" IS NULL AND NEW."status" = 0;
+    ELSE
+        is_listed := FALSE;
+    END IF;
+
+    IF was_listed <> is_listed THEN
+        UPDATE "tag_statistics"
+        SET "usage_count" = "usage_count" + CASE WHEN is_listed THEN 1 ELSE -1 END
+        WHERE "tag_id" IN (SELECT "tag_id" FROM "post_tag" WHERE "post_id" = OLD."id");
+    END IF;
+
+    RETURN COALESCE(NEW, OLD);
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER post_listing_trigger BEFORE UPDATE OF "deletion_time", "status" OR DELETE ON "post"
+FOR EACH ROW EXECUTE FUNCTION update_post_listing_statistics();
+
+UPDATE "tag_statistics"
+SET "usage_count" = (
+    SELECT COUNT(*) FROM "post_tag"
+    INNER JOIN "post" ON "post"."id" = "post_tag"."post_id"
+    WHERE "post_tag"."tag_id" = "tag_statistics"."tag_id" AND "post"."deletion_time" IS NULL AND "post"."status" = 0
+);
@@ -0,0 +1,11 @@
+DROP INDEX "idx_report_open_resource";
+
+UPDATE "report"
+SET "resource_id" = "tag_name"."name"
+FROM "tag_name"
+WHERE "report"."resource_type" = 4 AND "tag_name"."tag_id"::TEXT = "report"."resource_id" AND "tag_name"."order" = 0;
+
+UPDATE "report"
+SET "resource_id" = "user"."name"
+FROM "user"
+WHERE "report"."resource_type" = 8 AND "user"."id"::TEXT = "report"."resource_id";
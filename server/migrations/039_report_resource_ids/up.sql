@@ -0,0 +1,24 @@
+-- Tags and users are reported by ID, so that reports still point to them after they are renamed
+UPDATE "report"
+SET "resource_id" = "tag_name"."tag_id"::TEXT
+FROM "tag_name"
+WHERE "report"."resource_type" = 4 AND "tag_name"."name" = "report"."resource_id";
+
+UPDATE "report"
+SET "resource_id" = "user"."id"::TEXT
+FROM "user"
+WHERE "report"."resource_type" = 8 AND "user"."name" = "report"."resource_id";
+
+-- Each user can only have one open report per resource, so later duplicates are dismissed
+UPDATE "report"
+SET "status" = 2, "resolution_time" = CURRENT_TIMESTAMP
+WHERE "status" = 0 AND EXISTS (
+    SELECT 1 FROM "report" AS "earlier_report"
+    WHERE "earlier_report"."status" = 0
+      AND "earlier_report"."user_id" = "report"."user_id"
+      AND "earlier_report"."resource_type" = "report"."resource_type"
+      AND "earlier_report"."resource_id" = "report"."resource_id"
+      AND "earlier_report"."id" < "report"."id"
+);
+
+CREATE UNIQUE INDEX "idx_report_open_resource" ON "report" USING BTREE ("user_id", "resource_type", "resource_id") WHERE "status" = 0;
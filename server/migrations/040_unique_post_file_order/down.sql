@@ -0,0 +1,2 @@
+ALTER TABLE "post_file" DROP CONSTRAINT "post_file_order_key";
+CREATE INDEX "idx_post_file_post_id" ON "post_file" USING BTREE ("post_id", "order");
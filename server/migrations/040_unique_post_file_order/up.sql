@@ -0,0 +1,12 @@
+-- Renumber files so that no two files of the same post share a position
+UPDATE "post_file"
+SET "order" = "numbered_file"."order"
+FROM (
+    SELECT "id", (ROW_NUMBER() OVER (PARTITION BY "post_id" ORDER BY "order", "id") - 1)::INTEGER AS "order"
+    FROM "post_file"
+) AS "numbered_file"
+WHERE "post_file"."id" = "numbered_file"."id";
+
+-- Deferred so that files can be reordered one row at a time within a transaction
+DROP INDEX "idx_post_file_post_id";
+ALTER TABLE "post_file" ADD CONSTRAINT "post_file_order_key" UNIQUE ("post_id", "order") DEFERRABLE INITIALLY DEFERRED;
@@ -0,0 +1 @@
+ALTER TABLE "user_totp" DROP COLUMN "last_used_step";
\ No newline at end of file
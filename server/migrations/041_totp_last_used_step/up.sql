@@ -0,0 +1,2 @@
+-- Codes are only accepted for time steps after the last one used, so that they can't be replayed
+ALTER TABLE "user_totp" ADD COLUMN "last_used_step" BIGINT;
\ No newline at end of file
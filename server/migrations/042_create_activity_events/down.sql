@@ -0,0 +1,4 @@
+DROP TRIGGER activity_event_trigger ON "snapshot";
+DROP FUNCTION queue_activity_event;
+DROP SEQUENCE "activity_event_sequence";
+DROP TABLE "activity_event";
\ No newline at end of file
@@ -0,0 +1,20 @@
+-- Every new snapshot is queued here in the same transaction that creates it. Events are numbered
+-- after that transaction has committed, by one transaction at a time, so that event numbers follow
+-- commit order and activity streams can't skip snapshots that were committed out of order.
+CREATE TABLE "activity_event" (
+    "snapshot_id" BIGINT PRIMARY KEY REFERENCES "snapshot" ON DELETE CASCADE,
+    "sequence" BIGINT UNIQUE,
+    "creation_time" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE SEQUENCE "activity_event_sequence";
+
+CREATE FUNCTION queue_activity_event() RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO "activity_event" ("snapshot_id") VALUES (NEW."id");
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER activity_event_trigger AFTER INSERT ON "snapshot"
+FOR EACH ROW EXECUTE FUNCTION queue_activity_event();
\ No newline at end of file
@@ -0,0 +1,198 @@
+//! Snapshots are pushed to subscribers of the activity stream as soon as they are noticed. A trigger
+//! queues an event for every snapshot in the same transaction that creates it, and a background task
+//! numbers queued events once that transaction has committed. Only one transaction numbers events at
+//! a time, even across server instances, so event numbers follow commit order and a snapshot that
+//! commits after a newer one can't be skipped. Numbered events are broadcast to every subscriber,
+//! which then decides whether its client is allowed to see them. Subscribers that fall behind are
+//! disconnected, and clients catch up on what they missed by reconnecting with the number of the last
+//! event they received.
+
+use crate::api::error::{ApiError, ApiResult};
+use crate::app::AppState;
+use crate::config::Config;
+use crate::model::enums::ResourceType;
+use crate::model::snapshot::Snapshot;
+use crate::resource::field::Mask;
+use crate::resource::snapshot::SnapshotInfo;
+use crate::schema::{activity_event, snapshot};
+use crate::string::SmallString;
+use diesel::dsl::max;
+use diesel::sql_types::BigInt;
+use diesel::{ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl, SelectableHelper};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use tokio::sync::{Notify, broadcast};
+
+/// How often the background task checks for new snapshots when it isn't woken up.
+/// Snapshots created by other server instances are only noticed on these checks.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Maximum number of missed events sent to a client that reconnects.
+/// Older events are purged, as they would never be sent again.
+pub const MAX_REPLAYED_EVENTS: i64 = 100;
+
+/// Number of events a subscriber can fall behind before it is disconnected.
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Maximum number of events numbered or loaded per query.
+const BATCH_SIZE: usize = 100;
+
+/// Key of the advisory lock held by the transaction that numbers queued events.
+const NUMBERING_LOCK_KEY: i64 = 0x6163_7469_7669_7479;
+
+static WAKE_UP: Notify = Notify::const_new();
+static CHANNEL: LazyLock<broadcast::Sender<Arc<Activity>>> = LazyLock::new(|| broadcast::channel(CHANNEL_CAPACITY).0);
+
+/// A snapshot as it is broadcast to subscribers.
+pub struct Activity {
+    /// Number of the event, which is sent to clients as the event ID.
+    pub event_id: i64,
+    pub resource_type: ResourceType,
+    pub resource_id: SmallString,
+    /// Serialized snapshot resource.
+    pub payload: String,
+    /// Serialized snapshot resource without the user who created it.
+    pub anonymous_payload: String,
+}
+
+/// Wakes up the background task, so that new snapshots are broadcast without waiting for the next check.
+pub fn wake_up() {
+    WAKE_UP.notify_one();
+}
+
+/// Waits until the background task is woken up or the check interval has elapsed.
+pub async fn wait_for_work() {
+    let _ = tokio::time::timeout(CHECK_INTERVAL, WAKE_UP.notified()).await;
+}
+
+/// Returns a receiver for every event broadcast from now on.
+pub fn subscribe() -> broadcast::Receiver<Arc<Activity>> {
+    CHANNEL.subscribe()
+}
+
+/// Numbers queued events, broadcasts events newer than `last_seen` and returns the number of the newest event.
+/// Events are numbered even if nobody is subscribed, as subscribers of other server instances rely on it.
+/// If nobody is subscribed or nothing has been seen yet, events are skipped without being loaded.
+pub async fn broadcast_new(state: &AppState, last_seen: Option<i64>) -> ApiResult<i64> {
+    loop {
+        let numbered_count = state
+            .connection_pool
+            .transaction(|conn| number_queued_events(conn))
+            .await?;
+        if numbered_count < BATCH_SIZE {
+            break;
+        }
+    }
+
+    let Some(mut last_seen) = last_seen.filter(|_| CHANNEL.receiver_count() > 0) else {
+        let mut conn = state.connection_pool.get().await?;
+        return latest_event_id(conn.as_mut()).map_err(ApiError::from);
+    };
+
+    loop {
+        let config = Arc::clone(&state.config);
+        let activities = state
+            .connection_pool
+            .transaction(move |conn| {
+                let events = activity_event::table
+                    .inner_join(snapshot::table)
+                    .select((activity_event::sequence.assume_not_null(), Snapshot::as_select()))
+                    .filter(activity_event::sequence.gt(last_seen))
+                    .order(activity_event::sequence)
+                    .limit(BATCH_SIZE as i64)
+                    .load(conn)?;
+                new_batch(conn, &config, events)
+            })
+            .await?;
+        let loaded_count = activities.len();
+
+        for activity in activities {
+            last_seen = activity.event_id;
+            // Sending only fails if every subscriber has disconnected in the meantime
+            let _ = CHANNEL.send(Arc::new(activity));
+        }
+        if loaded_count < BATCH_SIZE {
+            return Ok(last_seen);
+        }
+    }
+}
+
+/// Loads the most recent events after `last_event_id`, oldest first.
+pub fn missed_since(conn: &mut PgConnection, config: &Config, last_event_id: i64) -> ApiResult<Vec<Activity>> {
+    let mut events: Vec<(i64, Snapshot)> = activity_event::table
+        .inner_join(snapshot::table)
+        .select((activity_event::sequence.assume_not_null(), Snapshot::as_select()))
+        .filter(activity_event::sequence.gt(last_event_id))
+        .order(activity_event::sequence.desc())
+        .limit(MAX_REPLAYED_EVENTS)
+        .load(conn)?;
+    events.reverse();
+    new_batch(conn, config, events)
+}
+
+/// Numbers the oldest events whose snapshots have been committed and purges events that are too old
+/// to be replayed. Returns the number of events numbered.
+fn number_queued_events(conn: &mut PgConnection) -> QueryResult<usize> {
+    // The lock is held until commit, so events numbered later never become visible before events numbered earlier
+    diesel::sql_query("SELECT pg_advisory_xact_lock($1)")
+        .bind::<BigInt, _>(NUMBERING_LOCK_KEY)
+        .execute(conn)?;
+    let numbered_count = diesel::sql_query(
+        "UPDATE activity_event SET sequence = numbered.sequence
+        FROM (
+            SELECT snapshot_id, nextval('activity_event_sequence') AS sequence
+            FROM (
+                SELECT snapshot_id FROM activity_event
+                WHERE sequence IS NULL
+                ORDER BY snapshot_id
+                LIMIT $1
+            ) AS queued
+        ) AS numbered
+        WHERE activity_event.snapshot_id = numbered.snapshot_id",
+    )
+    .bind::<BigInt, _>(BATCH_SIZE as i64)
+    .execute(conn)?;
+
+    if numbered_count > 0 {
+        let latest_event_id = latest_event_id(conn)?;
+        diesel::delete(activity_event::table)
+            .filter(activity_event::sequence.le(latest_event_id - MAX_REPLAYED_EVENTS))
+            .execute(conn)?;
+    }
+    Ok(numbered_count)
+}
+
+fn latest_event_id(conn: &mut PgConnection) -> QueryResult<i64> {
+    activity_event::table
+        .select(max(activity_event::sequence))
+        .first::<Option<i64>>(conn)
+        .map(|event_id| event_id.unwrap_or(0))
+}
+
+fn new_batch(conn: &mut PgConnection, config: &Config, events: Vec<(i64, Snapshot)>) -> ApiResult<Vec<Activity>> {
+    let (event_ids, snapshots): (Vec<_>, Vec<_>) = events.into_iter().unzip();
+    let resources: Vec<_> = snapshots
+        .iter()
+        .map(|snapshot| (snapshot.resource_type, snapshot.resource_id.clone()))
+        .collect();
+    let infos = SnapshotInfo::new_batch(conn, config, snapshots, Mask::all())?;
+    event_ids
+        .into_iter()
+        .zip(resources)
+        .zip(infos)
+        .map(|((event_id, (resource_type, resource_id)), info)| -> ApiResult<Activity> {
+            let mut value = serde_json::to_value(info)?;
+            let payload = value.to_string();
+            if let Some(object) = value.as_object_mut() {
+                object.remove("user");
+            }
+            Ok(Activity {
+                event_id,
+                resource_type,
+                resource_id,
+                payload,
+                anonymous_payload: value.to_string(),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,105 @@
+use crate::admin::input::PostEditor;
+use crate::admin::post::user_query;
+use crate::admin::{AdminResult, PRINT_INTERVAL, ProgressReporter, input};
+use crate::app::AppState;
+use crate::content::hash::PostHash;
+use crate::content::{auto_tag, decode};
+use crate::model::tag::TagImplication;
+use crate::schema::{post, post_tag, tag_name};
+use crate::time::Timer;
+use crate::update::tag::TagDiff;
+use crate::{admin, update};
+use diesel::{Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::collections::HashSet;
+use tracing::{Level, error};
+
+/// Runs the auto-tagging model on existing posts and adds predicted tags they are missing,
+/// along with any tags implied by them. Only tags that already exist are added.
+pub fn auto_tag_posts(state: &AppState, editor: &mut PostEditor) {
+    if state.config.auto_tag().is_none() {
+        error!("Auto-tagging is not configured.\n");
+        return;
+    }
+
+    input::user_input_loop(state, editor, |state: &AppState, editor: &mut PostEditor| {
+        let post_ids = user_query(state, editor)?;
+
+        let _timer = Timer::new("auto_tag_posts");
+        let progress = ProgressReporter::new(Level::INFO, "Posts auto-tagged", PRINT_INTERVAL);
+        post_ids
+            .into_par_iter()
+            .try_for_each(|post_id| auto_tag_in_parallel(state, post_id, &progress))
+    });
+}
+
+/// Adds predicted tags to post with id `post_id`. Designed to operate in a parallel iterator.
+fn auto_tag_in_parallel(state: &AppState, post_id: i64, progress: &ProgressReporter) -> AdminResult<()> {
+    admin::is_cancelled()?;
+
+    let mut conn = state.connection_pool.get_blocking()?;
+    let (mime_type, custom_thumbnail_size) = match post::table
+        .find(post_id)
+        .select((post::mime_type, post::custom_thumbnail_size))
+        .first(&mut conn)
+        .optional()
+    {
+        Ok(Some(content_info)) => content_info,
+        Ok(None) => return Ok(()), // Post must have been deleted after starting task, skip
+        Err(err) => {
+            error!("Cannot retrieve post content info for post {post_id} for reason: {err}");
+            return Ok(());
+        }
+    };
+
+    let content_path = PostHash::new(&state.config, post_id, Some(custom_thumbnail_size)).content_path(mime_type);
+    let image = match decode::representative_image(&state.config, &content_path, mime_type) {
+        Ok(image) => image,
+        Err(err) => {
+            error!("Unable to get representative image for post {post_id} for reason: {err}");
+            return Ok(());
+        }
+    };
+
+    let predicted_names: Vec<_> = auto_tag::predict(&state.config, &image)
+        .into_iter()
+        .map(|prediction| prediction.tag)
+        .collect();
+    let transaction_result = conn.transaction(|conn| {
+        let mut tag_ids: HashSet<i64> = tag_name::table
+            .select(tag_name::tag_id)
+            .filter(tag_name::name.eq_any(&predicted_names))
+            .load::<i64>(conn)?
+            .into_iter()
+            .collect();
+        let predicted_ids: Vec<_> = tag_ids.iter().copied().collect();
+        tag_ids.extend(
+            TagImplication::descendants(conn, &predicted_ids)?
+                .into_iter()
+                .map(|implication| implication.child_id),
+        );
+
+        let current_tag_ids: Vec<i64> = post_tag::table
+            .select(post_tag::tag_id)
+            .filter(post_tag::post_id.eq(post_id))
+            .load(conn)?;
+        for tag_id in current_tag_ids {
+            tag_ids.remove(&tag_id);
+        }
+        if tag_ids.is_empty() {
+            return Ok(());
+        }
+
+        let diff = TagDiff {
+            post_id,
+            added: tag_ids.into_iter().collect(),
+            removed: Vec::new(),
+        };
+        update::tag::apply_tag_diffs(conn, admin::client(), &[diff])
+    });
+    match transaction_result {
+        Ok(()) => progress.increment(),
+        Err(err) => error!("Auto-tagging failed for post {post_id} for reason: {err}"),
+    }
+    Ok(())
+}
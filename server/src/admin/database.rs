@@ -1,22 +1,200 @@
+use crate::admin::input::{self, CancelType, PostEditor};
 use crate::admin::{AdminResult, PRINT_INTERVAL, ProgressReporter};
 use crate::app::AppState;
-use crate::content::hash::PostHash;
+use crate::content::hash::{PostFileHash, PostHash};
 use crate::filesystem::Directory;
-use crate::model::enums::MimeType;
+use crate::model::enums::{MimeType, PostStatus, RelationKind};
 use crate::schema::{
     comment, comment_score, comment_statistics, database_statistics, pool, pool_category, pool_category_statistics,
-    pool_post, pool_statistics, post, post_favorite, post_feature, post_note, post_relation, post_score,
+    pool_post, pool_statistics, post, post_favorite, post_feature, post_file, post_note, post_relation, post_score,
     post_statistics, post_tag, tag, tag_category, tag_category_statistics, tag_implication, tag_statistics,
     tag_suggestion, user, user_statistics,
 };
+use crate::string::{SmallString, lower};
 use crate::time::{DateTime, Timer};
 use crate::{admin, filesystem};
 use diesel::dsl::{count, max, sum};
-use diesel::{ExpressionMethods, NullableExpressionMethods, QueryDsl, RunQueryDsl};
+use diesel::{ExpressionMethods, NullableExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::collections::{BTreeMap, HashSet};
 use std::ffi::OsStr;
-use tracing::{Level, error, warn};
+use std::path::{Path, PathBuf};
+use tracing::{Level, error, info, warn};
 use walkdir::WalkDir;
 
+/// Searches the data directory for files that don't belong to any post or user, as well as
+/// posts and users whose files are missing from disk. Orphaned files can optionally be deleted
+/// and missing thumbnails can optionally be regenerated. Disk usage is recalculated afterwards.
+pub fn collect_garbage(state: &AppState, editor: &mut PostEditor) {
+    let options = input::confirm("Delete orphaned files? (y/N): ", editor).and_then(|delete_orphans| {
+        input::confirm("Regenerate missing thumbnails? (y/N): ", editor)
+            .map(|regenerate_thumbnails| (delete_orphans, regenerate_thumbnails))
+    });
+    let (delete_orphans, regenerate_thumbnails) = match options {
+        Ok(options) => options,
+        Err(CancelType::Stop) => return,
+        Err(CancelType::Exit) => std::process::exit(0),
+    };
+    if let Err(err) = collect_garbage_impl(state, delete_orphans, regenerate_thumbnails) {
+        error!("{err}");
+    }
+}
+
+pub fn collect_garbage_impl(state: &AppState, delete_orphans: bool, regenerate_thumbnails: bool) -> AdminResult<()> {
+    let _timer = Timer::new("collect_garbage");
+    let mut conn = state.connection_pool.get_blocking()?;
+    let posts: BTreeMap<i64, (MimeType, i64)> = post::table
+        .select((post::id, (post::mime_type, post::custom_thumbnail_size)))
+        .load::<(i64, (MimeType, i64))>(&mut conn)?
+        .into_iter()
+        .collect();
+    let post_files: BTreeMap<i64, MimeType> = post_file::table
+        .select((post_file::id, post_file::mime_type))
+        .load(&mut conn)?
+        .into_iter()
+        .collect();
+    let users: Vec<(SmallString, i64)> = user::table
+        .select((lower(user::name), user::custom_avatar_size))
+        .order_by(user::id)
+        .load(&mut conn)?;
+
+    // Content files are orphaned if their post doesn't exist or if they have the right name but the wrong
+    // extension, which can happen if the content of a post was replaced with a file of a different type.
+    // Files that belong to an existing post but have a different name are left alone, as they can be fixed
+    // by resetting filenames.
+    let content_orphans = find_orphans(state, Directory::Posts, |path| {
+        let Some((post_id, &(mime_type, _))) = admin::get_post_id(path).and_then(|id| posts.get_key_value(&id)) else {
+            return true;
+        };
+        let expected_path = PostHash::new(&state.config, *post_id, None).content_path(mime_type);
+        if path == expected_path {
+            false
+        } else if path.with_extension("") == expected_path.with_extension("") {
+            true
+        } else {
+            warn!("Content file {} of post {post_id} is misnamed", path.display());
+            false
+        }
+    })?;
+    let thumbnail_orphans = |directory: Directory| {
+        find_orphans(state, directory, |path| {
+            let Some(post_id) = admin::get_post_id(path).filter(|id| posts.contains_key(id)) else {
+                return true;
+            };
+            let post_hash = PostHash::new(&state.config, post_id, None);
+            let expected_path = match directory {
+                Directory::CustomThumbnails => post_hash.custom_thumbnail_path(),
+                _ => post_hash.generated_thumbnail_path(),
+            };
+            if path != expected_path {
+                warn!("Thumbnail {} of post {post_id} is misnamed", path.display());
+            }
+            false
+        })
+    };
+    let generated_thumbnail_orphans = thumbnail_orphans(Directory::GeneratedThumbnails)?;
+    let custom_thumbnail_orphans = thumbnail_orphans(Directory::CustomThumbnails)?;
+    let post_file_orphans = find_orphans(state, Directory::PostFiles, |path| {
+        let Some((&post_file_id, &mime_type)) = admin::get_post_id(path).and_then(|id| post_files.get_key_value(&id))
+        else {
+            return true;
+        };
+        path != PostFileHash::new(&state.config, post_file_id).content_path(mime_type)
+    })?;
+    let post_file_thumbnail_orphans = find_orphans(state, Directory::PostFileThumbnails, |path| {
+        let Some(post_file_id) = admin::get_post_id(path).filter(|id| post_files.contains_key(id)) else {
+            return true;
+        };
+        path != PostFileHash::new(&state.config, post_file_id).thumbnail_path()
+    })?;
+    let avatar_paths: HashSet<PathBuf> = users
+        .iter()
+        .map(|(name, _)| state.config.custom_avatar_path(name))
+        .collect();
+    let avatar_orphans = find_orphans(state, Directory::Avatars, |path| !avatar_paths.contains(path))?;
+
+    let orphans = [
+        content_orphans,
+        generated_thumbnail_orphans,
+        custom_thumbnail_orphans,
+        post_file_orphans,
+        post_file_thumbnail_orphans,
+        avatar_orphans,
+    ];
+    let orphans_found = ProgressReporter::new(Level::WARN, "Orphaned files found", None);
+    let orphans_deleted = delete_orphans.then(|| ProgressReporter::new(Level::INFO, "Orphaned files deleted", None));
+    for path in orphans.iter().flatten() {
+        admin::is_cancelled()?;
+
+        warn!("Orphaned file: {}", path.display());
+        orphans_found.increment();
+        if let Some(orphans_deleted) = &orphans_deleted {
+            match std::fs::remove_file(path) {
+                Ok(()) => orphans_deleted.increment(),
+                Err(err) => error!("Could not delete {} for reason: {err}", path.display()),
+            }
+        }
+    }
+
+    let missing_content = ProgressReporter::new(Level::WARN, "Posts with missing content", None);
+    let mut missing_generated_thumbnails = Vec::new();
+    let mut missing_custom_thumbnails = Vec::new();
+    for (&post_id, &(mime_type, custom_thumbnail_size)) in &posts {
+        admin::is_cancelled()?;
+
+        let post_hash = PostHash::new(&state.config, post_id, Some(custom_thumbnail_size));
+        if !post_hash.content_path(mime_type).try_exists()? {
+            warn!("Content of post {post_id} is missing");
+            missing_content.increment();
+        }
+        if !post_hash.generated_thumbnail_path().try_exists()? {
+            warn!("Generated thumbnail of post {post_id} is missing");
+            missing_generated_thumbnails.push(post_id);
+        }
+        if custom_thumbnail_size > 0 && !post_hash.custom_thumbnail_path().try_exists()? {
+            warn!("Custom thumbnail of post {post_id} is missing");
+            missing_custom_thumbnails.push(post_id);
+        }
+    }
+
+    for (&post_file_id, &mime_type) in &post_files {
+        admin::is_cancelled()?;
+
+        if !PostFileHash::new(&state.config, post_file_id)
+            .content_path(mime_type)
+            .try_exists()?
+        {
+            warn!("Content of post file {post_file_id} is missing");
+            missing_content.increment();
+        }
+    }
+
+    let missing_avatars = ProgressReporter::new(Level::WARN, "Users with missing avatars", None);
+    for (name, custom_avatar_size) in &users {
+        if *custom_avatar_size > 0 && !state.config.custom_avatar_path(name).try_exists()? {
+            warn!("Custom avatar of user {name} is missing");
+            missing_avatars.increment();
+        }
+    }
+
+    if regenerate_thumbnails {
+        // Custom thumbnails can't be recovered, so fall back to generated thumbnails instead
+        diesel::update(post::table)
+            .set(post::custom_thumbnail_size.eq(0))
+            .filter(post::id.eq_any(&missing_custom_thumbnails))
+            .execute(&mut conn)?;
+
+        let progress = ProgressReporter::new(Level::INFO, "Thumbnails regenerated", PRINT_INTERVAL);
+        missing_generated_thumbnails
+            .into_par_iter()
+            .try_for_each(|post_id| admin::post::regenerate_thumbnail_in_parallel(state, post_id, &progress))?;
+    }
+
+    reset_disk_usage(&mut conn)?;
+    info!("Disk usage recalculated");
+    Ok(())
+}
+
 /// Renames post files and thumbnails.
 /// Useful when the content hash changes.
 pub fn reset_filenames(state: &AppState) {
@@ -214,7 +392,11 @@ pub fn reset_relation_stats(state: &AppState) -> AdminResult<()> {
 
     let comment_count: i64 = comment::table.count().first(&mut conn)?;
     let pool_count: i64 = pool::table.count().first(&mut conn)?;
-    let post_count: i64 = post::table.count().first(&mut conn)?;
+    let post_count: i64 = post::table
+        .filter(post::deletion_time.is_null())
+        .filter(post::status.eq(PostStatus::Approved))
+        .count()
+        .first(&mut conn)?;
     let tag_count: i64 = tag::table.count().first(&mut conn)?;
     let user_count: i64 = user::table.count().first(&mut conn)?;
     diesel::update(database_statistics::table)
@@ -288,7 +470,10 @@ pub fn reset_relation_stats(state: &AppState) -> AdminResult<()> {
     for tag_id in tag_ids {
         admin::is_cancelled()?;
         let usage_count: i64 = post_tag::table
+            .inner_join(post::table)
             .filter(post_tag::tag_id.eq(tag_id))
+            .filter(post::deletion_time.is_null())
+            .filter(post::status.eq(PostStatus::Approved))
             .count()
             .first(&mut conn)?;
         let implication_count: i64 = tag_implication::table
@@ -321,7 +506,12 @@ pub fn reset_relation_stats(state: &AppState) -> AdminResult<()> {
             .filter(post_favorite::user_id.eq(user_id))
             .count()
             .first(&mut conn)?;
-        let upload_count: i64 = post::table.filter(post::user_id.eq(user_id)).count().first(&mut conn)?;
+        let upload_count: i64 = post::table
+            .filter(post::user_id.eq(user_id))
+            .filter(post::deletion_time.is_null())
+            .filter(post::status.eq(PostStatus::Approved))
+            .count()
+            .first(&mut conn)?;
         diesel::update(user_statistics::table.find(user_id))
             .set((
                 user_statistics::comment_count.eq(comment_count),
@@ -354,6 +544,7 @@ pub fn reset_relation_stats(state: &AppState) -> AdminResult<()> {
             .first(&mut conn)?;
         let relation_count: i64 = post_relation::table
             .filter(post_relation::child_id.eq(post_id))
+            .filter(post_relation::kind.eq(RelationKind::Symmetric))
             .count()
             .first(&mut conn)?;
         let score: Option<i64> = post_score::table
@@ -400,6 +591,18 @@ pub fn reset_relation_stats(state: &AppState) -> AdminResult<()> {
     Ok(())
 }
 
+/// Recalculates total disk usage from the cached content, thumbnail, post file, and avatar sizes.
+pub fn reset_disk_usage(conn: &mut PgConnection) -> QueryResult<()> {
+    diesel::sql_query(
+        r#"UPDATE "database_statistics"
+           SET "disk_usage" = (SELECT COALESCE(SUM("file_size" + "generated_thumbnail_size" + "custom_thumbnail_size"), 0) FROM "post")
+                            + (SELECT COALESCE(SUM("file_size" + "generated_thumbnail_size"), 0) FROM "post_file")
+                            + (SELECT COALESCE(SUM("custom_avatar_size"), 0) FROM "user")"#,
+    )
+    .execute(conn)
+    .map(|_| ())
+}
+
 /// Recalculates cached file sizes, row counts, and table statistics.
 /// Useful for when the statistics become inconsistent with database
 /// or when migrating from an older version without statistics.
@@ -446,3 +649,26 @@ pub fn reset_statistics_impl(state: &AppState) -> AdminResult<()> {
     reset_thumbnail_sizes_impl(state)?;
     reset_relation_stats(state)
 }
+
+/// Walks through `directory` and returns the paths of all files for which `is_orphan` returns `true`.
+fn find_orphans<F>(state: &AppState, directory: Directory, is_orphan: F) -> AdminResult<Vec<PathBuf>>
+where
+    F: Fn(&Path) -> bool,
+{
+    let directory_path = state.config.path(directory);
+    if !directory_path.try_exists()? {
+        return Ok(Vec::new());
+    }
+
+    let mut orphans = Vec::new();
+    for entry in WalkDir::new(directory_path) {
+        admin::is_cancelled()?;
+
+        let entry = entry?;
+        let path = entry.path();
+        if !path.is_dir() && is_orphan(path) {
+            orphans.push(path.to_path_buf());
+        }
+    }
+    Ok(orphans)
+}
@@ -151,6 +151,15 @@ where
     }
 }
 
+/// Prompts the user with a yes/no question with message `prompt`.
+/// Any response other than "y" or "yes" is interpreted as a no.
+pub fn confirm<E>(prompt: &str, editor: &mut Editor<EnumCompleter<E>, DefaultHistory>) -> Result<bool, CancelType>
+where
+    E: IntoEnumIterator + Into<&'static str>,
+{
+    read(prompt, editor).map(|response| matches!(response.to_lowercase().as_str(), "y" | "yes"))
+}
+
 /// Repeatedly performs some `function` that prompts for user input until it returns
 /// either [`LoopState::Stop`] or [`LoopState::Exit`], the latter of which terminates
 /// the program immediately.
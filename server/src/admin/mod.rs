@@ -14,6 +14,8 @@ use strum::{EnumIter, EnumMessage, EnumString, IntoEnumIterator, IntoStaticStr};
 use thiserror::Error;
 use tracing::{Level, debug, error, info, trace, warn};
 
+#[cfg(feature = "auto_tag")]
+mod auto_tag;
 pub mod database;
 mod input;
 pub mod post;
@@ -74,8 +76,15 @@ impl From<ApiError> for AdminError {
 #[derive(Clone, Copy, EnumIter, EnumString, EnumMessage, IntoStaticStr)]
 #[strum(serialize_all = "snake_case")]
 pub enum AdminTask {
+    #[strum(message = "Add missing implied tags to existing posts")]
+    ApplyTagImplications,
+    #[cfg(feature = "auto_tag")]
+    #[strum(message = "Add tags predicted by the auto-tagging model to existing posts")]
+    AutoTagPosts,
     #[strum(message = "Checks integrity of post files")]
     CheckIntegrity,
+    #[strum(message = "Find orphaned files and missing post content")]
+    CollectGarbage,
     #[strum(message = "Recompute post checksums")]
     RecomputeChecksums,
     #[strum(message = "Rebuild post signatures")]
@@ -86,6 +95,8 @@ pub enum AdminTask {
     RecomputePostTypes,
     #[strum(message = "Regenerate post thumbnails")]
     RegenerateThumbnails,
+    #[strum(message = "Count users with outdated password hashes")]
+    ReportPasswordHashes,
     #[strum(message = "Reset user passwords")]
     ResetPasswords,
     #[strum(message = "Rebuild data directory")]
@@ -196,10 +207,7 @@ fn is_cancelled() -> Result<(), CancelType> {
 }
 
 fn client() -> Client {
-    Client {
-        id: None,
-        rank: UserRank::Administrator,
-    }
+    Client::new(None, UserRank::Administrator)
 }
 
 /// Runs a single task. This function is designed to only establish a connection to the database
@@ -208,12 +216,17 @@ fn client() -> Client {
 fn run_task(state: &AppState, task: AdminTask, post_editor: &mut PostEditor, user_editor: &mut UserEditor) {
     CANCELLED.store(false, Ordering::SeqCst);
     match task {
+        AdminTask::ApplyTagImplications => post::apply_tag_implications(state, post_editor),
+        #[cfg(feature = "auto_tag")]
+        AdminTask::AutoTagPosts => auto_tag::auto_tag_posts(state, post_editor),
         AdminTask::CheckIntegrity => post::check_integrity(state, post_editor),
+        AdminTask::CollectGarbage => database::collect_garbage(state, post_editor),
         AdminTask::RecomputeChecksums => post::recompute_checksums(state, post_editor),
         AdminTask::RecomputeSignatures => post::recompute_signatures(state, post_editor),
         AdminTask::RecomputeIndex => post::recompute_indexes(state, post_editor),
         AdminTask::RecomputePostTypes => post::recompute_post_types(state, post_editor),
         AdminTask::RegenerateThumbnails => post::regenerate_thumbnails(state, post_editor),
+        AdminTask::ReportPasswordHashes => user::report_password_hashes(state),
         AdminTask::ResetPasswords => user::reset_password(state, user_editor),
         AdminTask::ResetFilenames => database::reset_filenames(state),
         AdminTask::ResetStatistics => database::reset_statistics(state),
@@ -6,14 +6,16 @@ use crate::content::signature::SIGNATURE_VERSION;
 use crate::content::thumbnail::{ThumbnailCategory, ThumbnailType};
 use crate::content::{decode, hash, signature, thumbnail};
 use crate::model::post::{CompressedSignature, NewPostSignature};
+use crate::model::tag::TagName;
 use crate::schema::{database_statistics, post, post_signature};
 use crate::search::Builder;
 use crate::search::post::{QueryBuilder, Token};
 use crate::time::{DateTime, Timer};
-use crate::{admin, filesystem};
+use crate::{admin, filesystem, update};
 use diesel::dsl::exists;
 use diesel::{Connection, ExpressionMethods, Insertable, OptionalExtension, QueryDsl, RunQueryDsl};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::collections::HashSet;
 use tracing::{Level, error, info, warn};
 
 /// Checks the integrity of all posts on the filesystem by comparing the stored
@@ -113,6 +115,47 @@ pub fn regenerate_thumbnails(state: &AppState, editor: &mut PostEditor) {
     });
 }
 
+/// Adds tags which are implied by the current tags of each post but are missing because the
+/// implication was created after the post was last tagged. Tags that only exist because of a
+/// since-deleted implication can optionally be removed as well. Changes are listed before being
+/// applied, so the task doubles as a dry run if the user declines to apply them.
+pub fn apply_tag_implications(state: &AppState, editor: &mut PostEditor) {
+    const BATCH_SIZE: usize = 100;
+
+    input::user_input_loop(state, editor, |state: &AppState, editor: &mut PostEditor| {
+        let post_ids = user_query(state, editor)?;
+        let remove_stale = input::confirm("Remove tags left over from deleted implications? (y/N): ", editor)?;
+
+        let _timer = Timer::new("apply_tag_implications");
+        let mut conn = state.connection_pool.get_blocking()?;
+        let diffs = update::tag::implication_diffs(&mut conn, &post_ids, remove_stale)?;
+        let tag_ids: HashSet<i64> = diffs
+            .iter()
+            .flat_map(|diff| diff.added.iter().chain(&diff.removed))
+            .copied()
+            .collect();
+        let tag_ids: Vec<_> = tag_ids.into_iter().collect();
+        let primary_names = TagName::primary_names(&mut conn, &tag_ids)?;
+        let names_of = |ids: &[i64]| ids.iter().map(|id| &*primary_names[id]).collect::<Vec<_>>().join(" ");
+        for diff in &diffs {
+            info!("Post {}: added [{}], removed [{}]", diff.post_id, names_of(&diff.added), names_of(&diff.removed));
+        }
+        info!("Found {} posts with inconsistent tags", diffs.len());
+        if diffs.is_empty() || !input::confirm("Apply changes? (y/N): ", editor)? {
+            return Ok(());
+        }
+
+        let progress = ProgressReporter::new(Level::INFO, "Posts updated", PRINT_INTERVAL);
+        for batch in diffs.chunks(BATCH_SIZE) {
+            admin::is_cancelled()?;
+
+            conn.transaction(|conn| update::tag::apply_tag_diffs(conn, admin::client(), batch))?;
+            batch.iter().for_each(|_| progress.increment());
+        }
+        Ok(())
+    });
+}
+
 /// Checks content integrity for post with id `post_id`. Designed to operate in a parallel iterator.
 fn check_integrity_in_parallel(
     state: &AppState,
@@ -346,7 +389,11 @@ fn recompute_post_type_in_parallel(state: &AppState, post_id: i64, progress: &Pr
 }
 
 /// Regenerates thumbnail for post with id `post_id`. Designed to operate in a parallel iterator.
-fn regenerate_thumbnail_in_parallel(state: &AppState, post_id: i64, progress: &ProgressReporter) -> AdminResult<()> {
+pub(super) fn regenerate_thumbnail_in_parallel(
+    state: &AppState,
+    post_id: i64,
+    progress: &ProgressReporter,
+) -> AdminResult<()> {
     admin::is_cancelled()?;
 
     let mut conn = state.connection_pool.get_blocking()?;
@@ -391,7 +438,7 @@ fn regenerate_thumbnail_in_parallel(state: &AppState, post_id: i64, progress: &P
     Ok(())
 }
 
-fn user_query(state: &AppState, editor: &mut PostEditor) -> AdminResult<Vec<i64>> {
+pub(super) fn user_query(state: &AppState, editor: &mut PostEditor) -> AdminResult<Vec<i64>> {
     loop {
         let ctx = state.clone().make_context(admin::client());
         let user_input =
@@ -1,12 +1,15 @@
+use crate::admin::AdminResult;
 use crate::admin::input::{self, UserEditor};
 use crate::api;
 use crate::app::AppState;
-use crate::auth::password;
+use crate::auth::password::{self, HashKind};
 use crate::config::RegexType;
 use crate::schema::user;
 use crate::string::SecretString;
 use diesel::dsl::exists;
 use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use strum::IntoEnumIterator;
+use tracing::{error, info};
 
 /// This function prompts the user for input again to reset passwords for specific users.
 pub fn reset_password(state: &AppState, editor: &mut UserEditor) {
@@ -42,3 +45,32 @@ pub fn reset_password(state: &AppState, editor: &mut UserEditor) {
         Ok(())
     });
 }
+
+/// Reports how many users have each kind of password hash. Outdated hashes are replaced
+/// the next time their owner logs in, so this shows how many users haven't done so yet.
+pub fn report_password_hashes(state: &AppState) {
+    if let Err(err) = report_password_hashes_impl(state) {
+        error!("{err}");
+    }
+}
+
+fn report_password_hashes_impl(state: &AppState) -> AdminResult<()> {
+    let mut conn = state.connection_pool.get_blocking()?;
+    let hashes: Vec<(String, String)> = user::table
+        .select((user::password_hash, user::password_salt))
+        .load(&mut conn)?;
+
+    let hash_kinds: Vec<HashKind> = hashes
+        .iter()
+        .map(|(hash, salt)| password::hash_kind(&state.config, hash, salt))
+        .collect();
+    for kind in HashKind::iter() {
+        let count = hash_kinds.iter().filter(|&&hash_kind| hash_kind == kind).count();
+        let kind_name: &str = kind.into();
+        info!("Users with {kind_name} password hashes: {count}");
+    }
+
+    let outdated_count = hash_kinds.iter().filter(|hash_kind| hash_kind.is_outdated()).count();
+    info!("{outdated_count} of {} users have outdated password hashes.\n", hash_kinds.len());
+    Ok(())
+}
@@ -0,0 +1,427 @@
+use crate::activity::{self, Activity};
+use crate::api::doc::ACTIVITY_TAG;
+use crate::api::error::{ApiError, ApiResult};
+use crate::api::{comment, post, tag, tag_category};
+use crate::app::{AppState, Context};
+use crate::audit::RequestInfo;
+use crate::config::Action;
+use crate::db::AsyncConnectionPool;
+use crate::extract::{Ctx, Query};
+use crate::model::enums::ResourceType;
+use axum::http::{HeaderMap, HeaderValue};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use diesel::PgConnection;
+use futures_core::Stream;
+use serde::{Deserialize, Deserializer};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, LazyLock, Mutex, PoisonError};
+use std::task::Poll;
+use tokio::sync::broadcast::error::TryRecvError;
+use tokio::sync::{broadcast, mpsc};
+use tracing::warn;
+use utoipa::IntoParams;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new().routes(routes!(stream))
+}
+
+/// Header sent by reconnecting clients, containing the ID of the last event they received.
+const LAST_EVENT_ID: &str = "Last-Event-ID";
+
+/// Number of events that can be waiting to be sent to a client.
+const EVENT_BUFFER_SIZE: usize = 16;
+
+/// Maximum number of streams that a user, or an anonymous client's IP address, can have open at once.
+const MAX_STREAMS_PER_CLIENT: usize = 4;
+
+/// Maximum number of broadcast snapshots whose visibility is checked at once.
+const MAX_BATCH_SIZE: usize = 64;
+
+/// Number of open streams of each user, or of each IP address for anonymous clients.
+static OPEN_STREAMS: LazyLock<Mutex<HashMap<StreamOwner, usize>>> = LazyLock::new(Mutex::default);
+
+/// Represents parameters of a request to stream site activity.
+#[derive(Deserialize, IntoParams)]
+struct StreamParams {
+    /// Comma-separated list of resource types to stream. Defaults to every resource type.
+    #[param(value_type = Option<String>, example = "post,comment")]
+    #[serde(default, deserialize_with = "deserialize_resource_types")]
+    types: Option<Vec<ResourceType>>,
+}
+
+/// Whoever an open stream counts against.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum StreamOwner {
+    User(i64),
+    Address(Option<String>),
+}
+
+/// Counts an open stream against its owner for as long as it is alive.
+struct StreamPermit(StreamOwner);
+
+impl StreamPermit {
+    /// Counts a new stream against `owner`, unless they already have too many streams open.
+    fn acquire(owner: StreamOwner) -> ApiResult<Self> {
+        let mut open_streams = OPEN_STREAMS.lock().unwrap_or_else(PoisonError::into_inner);
+        let stream_count = open_streams.entry(owner.clone()).or_default();
+        if *stream_count >= MAX_STREAMS_PER_CLIENT {
+            return Err(ApiError::TooManyActivityStreams);
+        }
+        *stream_count += 1;
+        Ok(Self(owner))
+    }
+}
+
+impl Drop for StreamPermit {
+    fn drop(&mut self) {
+        let mut open_streams = OPEN_STREAMS.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(stream_count) = open_streams.get_mut(&self.0) {
+            *stream_count -= 1;
+            if *stream_count == 0 {
+                open_streams.remove(&self.0);
+            }
+        }
+    }
+}
+
+/// Events waiting to be sent to a single client.
+struct Subscription(mpsc::Receiver<Event>);
+
+impl Stream for Subscription {
+    type Item = Result<Event, Infallible>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_recv(cx).map(|event| event.map(Ok))
+    }
+}
+
+/// Streams site activity as server-sent events.
+///
+/// Each event contains a snapshot resource. Event IDs increase in the order in
+/// which snapshots were committed, which can differ from the order of snapshot
+/// IDs. Snapshots are only sent if the client could see the resource they are
+/// about through the rest of the API, taking privileges and preferences into
+/// account. Snapshots of resources that no longer exist are only sent to clients
+/// that can list snapshots, and so is the user who created each snapshot.
+///
+/// Clients that reconnect with a `Last-Event-ID` header are first sent up to 100
+/// of the most recent snapshots they missed. Clients that fall too far behind are
+/// disconnected, so that they can catch up this way.
+///
+/// Each user can have up to 4 streams open at once, as can each IP address for
+/// anonymous clients.
+#[utoipa::path(
+    get,
+    path = "/activity",
+    tag = ACTIVITY_TAG,
+    params(
+        StreamParams,
+        ("Last-Event-ID" = Option<i64>, Header, description = "ID of the last event received"),
+    ),
+    responses(
+        (status = 200, description = "Stream of snapshot resources", content_type = "text/event-stream"),
+        (status = 403, description = "Privileges are too low"),
+        (status = 429, description = "Too many streams are open at once"),
+    ),
+)]
+async fn stream(
+    Ctx(ctx, connection_pool): Ctx,
+    headers: HeaderMap,
+    Query(params): Query<StreamParams>,
+    request_info: RequestInfo,
+) -> ApiResult<Sse<Subscription>> {
+    ctx.verify_privilege(Action::ActivityStream)?;
+    let owner = match ctx.client.id {
+        Some(user_id) => StreamOwner::User(user_id),
+        None => StreamOwner::Address(request_info.ip_address),
+    };
+    let permit = StreamPermit::acquire(owner)?;
+
+    let last_event_id: Option<i64> = headers
+        .get(LAST_EVENT_ID)
+        .map(HeaderValue::to_str)
+        .transpose()?
+        .map(str::parse)
+        .transpose()?;
+
+    // Subscribe before looking up missed snapshots, so that none are lost in between
+    let activities = activity::subscribe();
+    let missed_activities = match last_event_id {
+        Some(last_event_id) => {
+            let config = Arc::clone(&ctx.config);
+            connection_pool
+                .transaction(move |conn| activity::missed_since(conn, &config, last_event_id))
+                .await?
+        }
+        None => Vec::new(),
+    };
+
+    let (sender, receiver) = mpsc::channel(EVENT_BUFFER_SIZE);
+    let subscriber = Subscriber {
+        ctx,
+        connection_pool,
+        types: params.types,
+        last_event_id: last_event_id.unwrap_or(0),
+        _permit: permit,
+    };
+    tokio::spawn(subscriber.forward(missed_activities, activities, sender));
+    Ok(Sse::new(Subscription(receiver)).keep_alive(KeepAlive::default()))
+}
+
+/// Decides which activity is sent to a client.
+struct Subscriber {
+    ctx: Context,
+    connection_pool: AsyncConnectionPool,
+    types: Option<Vec<ResourceType>>,
+    last_event_id: i64,
+    _permit: StreamPermit,
+}
+
+impl Subscriber {
+    /// Sends the `missed` activities followed by every broadcast activity that the client can see.
+    /// Returns once the client disconnects or falls too far behind.
+    async fn forward(
+        mut self,
+        missed: Vec<Activity>,
+        mut activities: broadcast::Receiver<Arc<Activity>>,
+        sender: mpsc::Sender<Event>,
+    ) {
+        let mut batch: Vec<_> = missed.into_iter().map(Arc::new).collect();
+        loop {
+            if batch.is_empty() {
+                tokio::select! {
+                    received = activities.recv() => match received {
+                        Ok(activity) => batch.push(activity),
+                        // Lagging clients are disconnected, so that they catch up by reconnecting
+                        Err(_) => return,
+                    },
+                    () = sender.closed() => return,
+                }
+                // Check everything that has been broadcast in the meantime along with it
+                while batch.len() < MAX_BATCH_SIZE {
+                    match activities.try_recv() {
+                        Ok(activity) => batch.push(activity),
+                        Err(TryRecvError::Empty) => break,
+                        Err(_) => return,
+                    }
+                }
+            }
+
+            // Skip activity that has already been sent, as missed and broadcast activity can overlap
+            batch.retain(|activity| activity.event_id > self.last_event_id);
+            let Some(last_activity) = batch.last() else {
+                continue;
+            };
+            self.last_event_id = last_activity.event_id;
+
+            let events = match self.events(std::mem::take(&mut batch)).await {
+                Ok(events) => events,
+                Err(err) => {
+                    warn!("Failed to check visibility of activity. Details:\n{err}");
+                    return;
+                }
+            };
+            for event in events {
+                if sender.send(event).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Converts the `activities` that the client should be sent into events.
+    /// Resources are looked up in a single transaction for the whole batch.
+    async fn events(&self, activities: Vec<Arc<Activity>>) -> ApiResult<Vec<Event>> {
+        let activities: Vec<_> = activities
+            .into_iter()
+            .filter(|activity| self.is_interested_in(activity.resource_type))
+            .collect();
+
+        let needs_lookup = activities
+            .iter()
+            .any(|activity| requires_lookup(activity.resource_type));
+        let visible_activities = if needs_lookup {
+            let ctx = self.ctx.clone();
+            self.connection_pool
+                .transaction(move |conn| filter_visible(conn, &ctx, activities))
+                .await?
+        } else {
+            activities
+        };
+
+        let show_user = self.ctx.has_privilege(Action::SnapshotList);
+        Ok(visible_activities
+            .iter()
+            .map(|activity| {
+                let payload = if show_user {
+                    &activity.payload
+                } else {
+                    &activity.anonymous_payload
+                };
+                Event::default().id(activity.event_id.to_string()).data(payload)
+            })
+            .collect())
+    }
+
+    /// Checks if the client asked for snapshots of given type and can view resources of that type.
+    fn is_interested_in(&self, resource_type: ResourceType) -> bool {
+        self.types.as_ref().is_none_or(|types| types.contains(&resource_type))
+            && view_privileges(resource_type)
+                .iter()
+                .all(|&action| self.ctx.has_privilege(action))
+    }
+}
+
+/// Returns the privileges needed to view resources of given type.
+fn view_privileges(resource_type: ResourceType) -> &'static [Action] {
+    match resource_type {
+        ResourceType::Comment => &[Action::CommentView],
+        ResourceType::Pool => &[Action::PoolView],
+        ResourceType::PoolCategory => &[Action::PoolCategoryView],
+        ResourceType::Post => &[Action::PostView],
+        ResourceType::Report => &[Action::ReportView],
+        ResourceType::Tag => &[Action::TagView],
+        ResourceType::TagCategory => &[Action::TagCategoryView],
+        ResourceType::User => &[Action::UserSanctionList, Action::UserNoteList],
+        ResourceType::WikiPage => &[Action::WikiPageView],
+        _ => &[Action::SnapshotList],
+    }
+}
+
+/// Returns whether the resource that snapshots of given type are about has to be looked up to decide visibility.
+fn requires_lookup(resource_type: ResourceType) -> bool {
+    matches!(
+        resource_type,
+        ResourceType::Comment | ResourceType::Post | ResourceType::Tag | ResourceType::TagCategory
+    )
+}
+
+/// Filters out `activities` about resources that aren't visible to the client.
+fn filter_visible(
+    conn: &mut PgConnection,
+    ctx: &Context,
+    activities: Vec<Arc<Activity>>,
+) -> ApiResult<Vec<Arc<Activity>>> {
+    let mut visible_activities = Vec::with_capacity(activities.len());
+    for activity in activities {
+        if is_visible(conn, ctx, &activity)? {
+            visible_activities.push(activity);
+        }
+    }
+    Ok(visible_activities)
+}
+
+/// Checks if the resource `activity` is about is visible to the client, applying the same rules as
+/// the endpoints that retrieve it. Resources that no longer exist are only visible to clients that
+/// can list snapshots.
+fn is_visible(conn: &mut PgConnection, ctx: &Context, activity: &Activity) -> ApiResult<bool> {
+    let resource_id = &activity.resource_id;
+    let visibility = match activity.resource_type {
+        ResourceType::Comment => comment::verify_visibility(conn, ctx, resource_id.parse()?),
+        ResourceType::Post => post::verify_visibility(conn, ctx, resource_id.parse()?),
+        ResourceType::Tag => tag::verify_visibility(conn, ctx, resource_id).map(|_| ()),
+        ResourceType::TagCategory => tag_category::verify_visibility(conn, ctx, resource_id).map(|_| ()),
+        _ => Ok(()),
+    };
+    match visibility {
+        Ok(()) => Ok(true),
+        Err(ApiError::Hidden(_)) => Ok(false),
+        Err(ApiError::NotFound(_)) => Ok(ctx.has_privilege(Action::SnapshotList)),
+        Err(err) => Err(err),
+    }
+}
+
+fn deserialize_resource_types<'de, D>(deserializer: D) -> Result<Option<Vec<ResourceType>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Some(type_list) = Option::<Cow<str>>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    type_list
+        .split(',')
+        .map(|type_str| {
+            ResourceType::from_str(type_str.trim())
+                .map_err(|_| serde::de::Error::custom(format!("invalid resource type `{type_str}`")))
+        })
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MAX_STREAMS_PER_CLIENT, StreamOwner, StreamPermit, is_visible};
+    use crate::activity::Activity;
+    use crate::api::error::{ApiError, ApiResult};
+    use crate::auth::Client;
+    use crate::config;
+    use crate::extract::Ctx;
+    use crate::model::enums::{ResourceType, UserRank};
+    use crate::test::*;
+    use serial_test::parallel;
+    use std::sync::Arc;
+
+    fn activity(resource_type: ResourceType, resource_id: &str) -> Activity {
+        Activity {
+            event_id: 1,
+            resource_type,
+            resource_id: resource_id.into(),
+            payload: String::new(),
+            anonymous_payload: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn unauthorized() -> ApiResult<()> {
+        verify_response_with_user(UserRank::Restricted, "GET /activity", "activity/unauthorized").await
+    }
+
+    #[test]
+    #[parallel]
+    fn visibility() -> ApiResult<()> {
+        let mut conn = get_connection()?;
+        let Ctx(mut anonymous_ctx, _) = get_state().make_context(Client::new(None, UserRank::Anonymous));
+        let mut config = config::test_config(None);
+        config.anonymous_preferences.hide_unsafe = true;
+        anonymous_ctx.config = Arc::new(config);
+
+        assert!(is_visible(&mut conn, &anonymous_ctx, &activity(ResourceType::Post, "1"))?);
+        assert!(is_visible(&mut conn, &anonymous_ctx, &activity(ResourceType::Comment, "1"))?);
+        assert!(is_visible(&mut conn, &anonymous_ctx, &activity(ResourceType::Pool, "1"))?);
+
+        // Post 5 is unsafe, and so is the comment on it
+        assert!(!is_visible(&mut conn, &anonymous_ctx, &activity(ResourceType::Post, "5"))?);
+        assert!(!is_visible(&mut conn, &anonymous_ctx, &activity(ResourceType::Comment, "4"))?);
+
+        // Resources that no longer exist are only visible to those who can list snapshots
+        let Ctx(admin_ctx, _) = get_state().make_context(Client::new(Some(5), UserRank::Administrator));
+        assert!(!is_visible(&mut conn, &anonymous_ctx, &activity(ResourceType::Comment, "99"))?);
+        assert!(is_visible(&mut conn, &admin_ctx, &activity(ResourceType::Comment, "99"))?);
+        Ok(())
+    }
+
+    #[test]
+    #[parallel]
+    fn stream_limit() {
+        let owner = StreamOwner::User(-1);
+        let permits: Vec<_> = (0..MAX_STREAMS_PER_CLIENT)
+            .map(|_| StreamPermit::acquire(owner.clone()))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert!(matches!(StreamPermit::acquire(owner.clone()), Err(ApiError::TooManyActivityStreams)));
+
+        // Other clients can still open streams
+        assert!(StreamPermit::acquire(StreamOwner::Address(None)).is_ok());
+
+        // Closing streams makes room for new ones
+        drop(permits);
+        assert!(StreamPermit::acquire(owner).is_ok());
+    }
+}
@@ -0,0 +1,144 @@
+use crate::api::doc::AUDIT_LOG_TAG;
+use crate::api::error::{ApiError, ApiResult};
+use crate::app::AppState;
+use crate::config::Action;
+use crate::extract::{Ctx, Json, PageParams, PagedResponse, Query, ResourceParams};
+use crate::resource::audit_log::{AuditLogEntryInfo, Field};
+use crate::search::Builder;
+use crate::search::audit_log::QueryBuilder;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new().routes(routes!(list))
+}
+
+/// Searches the audit log.
+///
+/// The audit log records logins, failed authentication attempts, creation and deletion of
+/// user tokens, password resets, rank changes, and requests refused for insufficient privileges.
+/// Entries can't be modified, and are purged after `audit_log_retention_days`.
+///
+/// **Anonymous tokens**
+///
+/// Same as `user` token.
+///
+/// **Named tokens**
+///
+/// | Key                              | Description                                                |
+/// | -------------------------------- | ---------------------------------------------------------- |
+/// | `id`                             | specific entry ID                                          |
+/// | `event`                          | `login`, `failed_authentication`, `token_creation`, etc.   |
+/// | `user`                           | involving given user (accepts wildcards)                   |
+/// | `target`                         | acting on given user or route (accepts wildcards)          |
+/// | `ip`                             | sent from given IP address (accepts wildcards)             |
+/// | `creation-date`, `creation-time` | happened at given date                                     |
+///
+/// **Sort style tokens**
+///
+/// | Value                            | Description               |
+/// | -------------------------------- | ------------------------- |
+/// | `random`                         | as random as it can get   |
+/// | `id`                             | newest to oldest          |
+/// | `event`                          | event kind                |
+/// | `user`                           | user name, Z to A         |
+/// | `target`                         | target, Z to A            |
+/// | `ip`                             | IP address                |
+/// | `creation-date`, `creation-time` | newest to oldest          |
+///
+/// **Special tokens**
+///
+/// None.
+#[utoipa::path(
+    get,
+    path = "/audit-log",
+    tag = AUDIT_LOG_TAG,
+    params(ResourceParams, PageParams),
+    responses(
+        (status = 200, body = PagedResponse<AuditLogEntryInfo>),
+        (status = 403, description = "Privileges are too low"),
+    ),
+)]
+async fn list(
+    Ctx(ctx, connection_pool): Ctx,
+    Query(resource): Query<ResourceParams<Field>>,
+    Query(page): Query<PageParams>,
+) -> ApiResult<Json<PagedResponse<AuditLogEntryInfo>>> {
+    ctx.verify_privilege(Action::AuditLogList)?;
+
+    let offset = page.offset.unwrap_or(0);
+    let limit = page.limit();
+    connection_pool
+        .transaction(move |conn| {
+            let mut query_builder = QueryBuilder::new(&ctx, resource.criteria())?;
+            query_builder.set_offset_and_limit(offset, limit);
+
+            let (total, selected_entries) = query_builder.list(conn)?;
+            Ok::<_, ApiError>(Json(PagedResponse {
+                query: resource.query,
+                offset,
+                limit,
+                total,
+                results: AuditLogEntryInfo::new_batch_from_ids(conn, &selected_entries, resource.fields)?,
+            }))
+        })
+        .await
+}
+
+#[cfg(test)]
+mod test {
+    use crate::api::error::ApiResult;
+    use crate::auth::header;
+    use crate::model::enums::UserRank;
+    use crate::search::audit_log::Token;
+    use crate::test::*;
+    use serial_test::parallel;
+    use strum::IntoEnumIterator;
+
+    // Exclude fields that involve creation_time
+    const FIELDS: &str = "&fields=id,event,user,target,details,ipAddress,userAgent";
+
+    #[tokio::test]
+    #[parallel]
+    async fn list() -> ApiResult<()> {
+        // Other tests write to the audit log, so searches are limited to the entries created with the database
+        const QUERY: &str = "GET /audit-log/?query";
+        const PARAMS: &str = "id:..4 -sort:id&limit=40&fields=id";
+        verify_response(&format!("{QUERY}=id:..4 -sort:id&limit=40{FIELDS}"), "audit_log/list/typical").await?;
+
+        let filter_table = crate::search::audit_log::filter_table();
+        for token in Token::iter() {
+            let filter = filter_table[token];
+            let (sign, filter) = if filter.starts_with('-') {
+                filter.split_at(1)
+            } else {
+                ("", filter)
+            };
+            let query = format!("{QUERY}={sign}{token}:{filter} {PARAMS}");
+            let path = format!("audit_log/list/{token}_filtered");
+            verify_response(&query, &path).await?;
+
+            let query = format!("{QUERY}=sort:{token} {PARAMS}");
+            let path = format!("audit_log/list/{token}_sorted");
+            verify_response(&query, &path).await?;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn record() -> ApiResult<()> {
+        const QUERY: &str = "GET /audit-log/?query=user:audit_intruder&fields=event,user,target,details";
+
+        let credentials = Some(header::basic_credentials_for("audit_intruder", TEST_PASSWORD));
+        verify_response_with_credentials(credentials, "GET /comment/1", "audit_log/record/failed_authentication")
+            .await?;
+        verify_response(QUERY, "audit_log/record/typical").await
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn unauthorized() -> ApiResult<()> {
+        verify_response_with_user(UserRank::Moderator, "GET /audit-log?limit=1", "audit_log/list/unauthorized").await
+    }
+}
@@ -4,20 +4,22 @@ use crate::api::{self, error};
 use crate::app::{AppState, Context};
 use crate::config::Action;
 use crate::extract::{Ctx, DeleteBody, Json, PageParams, PagedResponse, Path, Query, RatingBody, ResourceParams};
-use crate::model::comment::{NewComment, NewCommentScore};
+use crate::model::comment::{Comment, NewComment, NewCommentScore};
 use crate::model::enums::{ResourceType, Score};
 use crate::resource::comment::{CommentInfo, Field};
 use crate::schema::{comment, comment_score};
 use crate::search::Builder;
 use crate::search::comment::QueryBuilder;
 use crate::time::DateTime;
-use diesel::dsl::exists;
+use crate::{notification, snapshot, update};
 use diesel::{ExpressionMethods, Insertable, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
 use serde::Deserialize;
 use utoipa::ToSchema;
 use utoipa_axum::router::OpenApiRouter;
 use utoipa_axum::routes;
 
+const MAX_TEXT_LENGTH: usize = 10_000;
+
 pub fn routes() -> OpenApiRouter<AppState> {
     OpenApiRouter::new()
         .routes(routes!(list, create))
@@ -25,22 +27,30 @@ pub fn routes() -> OpenApiRouter<AppState> {
         .routes(routes!(rate))
 }
 
-fn verify_visibility(conn: &mut PgConnection, ctx: &Context, comment_id: i64) -> ApiResult<()> {
-    let comment_exists: bool = diesel::select(exists(comment::table.find(comment_id))).first(conn)?;
-    if !comment_exists {
-        return Err(ApiError::NotFound(ResourceType::Comment));
-    }
-
-    if let Some(hidden_posts) = ctx.preferences().hidden_posts(comment::post_id) {
-        let comment_lookup = comment::table.find(comment_id).filter(exists(hidden_posts));
-        let comment_hidden: bool = diesel::select(exists(comment_lookup)).first(conn)?;
-        if comment_hidden {
-            return Err(ApiError::Hidden(ResourceType::Comment));
-        }
+fn verify_text_length(text: &str) -> ApiResult<()> {
+    let text_length = text.trim().chars().count();
+    if text_length == 0 || text_length > MAX_TEXT_LENGTH {
+        return Err(ApiError::InvalidTextLength("Comment text", MAX_TEXT_LENGTH));
     }
     Ok(())
 }
 
+pub fn verify_visibility(conn: &mut PgConnection, ctx: &Context, comment_id: i64) -> ApiResult<()> {
+    let post_id: i64 = comment::table
+        .find(comment_id)
+        .select(comment::post_id)
+        .first(conn)
+        .optional()?
+        .ok_or(ApiError::NotFound(ResourceType::Comment))?;
+
+    // Comments are only visible if the post they are on is
+    api::post::verify_visibility(conn, ctx, post_id).map_err(|err| match err {
+        ApiError::NotFound(ResourceType::Post) => ApiError::NotFound(ResourceType::Comment),
+        ApiError::Hidden(ResourceType::Post) => ApiError::Hidden(ResourceType::Comment),
+        err => err,
+    })
+}
+
 /// Lists comments.
 ///
 /// **Anonymous tokens**
@@ -54,6 +64,7 @@ fn verify_visibility(conn: &mut PgConnection, ctx: &Context, comment_id: i64) ->
 /// | `id`                                                         | specific comment ID                            |
 /// | `post`                                                       | specific post ID                               |
 /// | `user`, `author`                                             | created by given user (accepts wildcards)      |
+/// | `mentions`                                                   | mentioning given user (accepts wildcards)      |
 /// | `text`                                                       | containing given text (accepts wildcards)      |
 /// | `score`                                                      | having given score                             |
 /// | `creation-date`, `creation-time`                             | created at given date                          |
@@ -149,11 +160,13 @@ async fn get(
 struct CommentCreateBody {
     /// ID of the post to comment on.
     post_id: i64,
-    /// Comment text.
+    /// ID of the comment to reply to, if any. Must be a comment on the same post.
+    parent_id: Option<i64>,
+    /// Comment text. Users can be mentioned with `@name`.
     text: String,
 }
 
-/// Creates a new comment under given post.
+/// Creates a new comment under given post, optionally as a reply to another comment.
 #[utoipa::path(
     post,
     path = "/comments",
@@ -164,6 +177,9 @@ struct CommentCreateBody {
         (status = 200, body = CommentInfo),
         (status = 403, description = "Privileges are too low"),
         (status = 404, description = "Post does not exist"),
+        (status = 404, description = "Parent comment does not exist"),
+        (status = 422, description = "Parent comment is on a different post"),
+        (status = 422, description = "Text is empty or too long"),
     ),
 )]
 async fn create(
@@ -172,17 +188,35 @@ async fn create(
     Json(body): Json<CommentCreateBody>,
 ) -> ApiResult<Json<CommentInfo>> {
     ctx.verify_privilege(Action::CommentCreate)?;
+    verify_text_length(&body.text)?;
 
     let comment = connection_pool
         .transaction(move |conn| {
+            if let Some(parent_id) = body.parent_id {
+                let parent_post_id: i64 = comment::table
+                    .find(parent_id)
+                    .select(comment::post_id)
+                    .first(conn)
+                    .optional()?
+                    .ok_or(ApiError::NotFound(ResourceType::Comment))?;
+                if parent_post_id != body.post_id {
+                    return Err(ApiError::InvalidParentComment);
+                }
+            }
+
             let insert_result = NewComment {
                 user_id: ctx.client.id,
                 post_id: body.post_id,
                 text: &body.text,
+                parent_comment_id: body.parent_id,
             }
             .insert_into(comment::table)
-            .get_result(conn);
-            error::map_foreign_key_violation(insert_result, ResourceType::Post)
+            .get_result::<Comment>(conn);
+            let comment = error::map_foreign_key_violation(insert_result, ResourceType::Post)?;
+            let mentioned_users = update::comment::set_mentions(conn, comment.id, &comment.text)?;
+            notification::notify_reply_and_mentions(conn, ctx.client, &comment, &mentioned_users)?;
+            snapshot::comment::creation_snapshot(conn, ctx.client, &comment)?;
+            Ok(comment)
         })
         .await?;
     connection_pool
@@ -215,6 +249,7 @@ struct CommentUpdateBody {
         (status = 403, description = "Privileges are too low"),
         (status = 404, description = "Comment does not exist"),
         (status = 409, description = "Version is outdated"),
+        (status = 422, description = "Text is empty or too long"),
     ),
 )]
 async fn update(
@@ -225,6 +260,7 @@ async fn update(
 ) -> ApiResult<Json<CommentInfo>> {
     ctx.verify_privilege(Action::CommentView)?;
     ctx.verify_privilege(Action::CommentEditOwn)?;
+    verify_text_length(&body.text)?;
 
     connection_pool
         .transaction({
@@ -232,21 +268,27 @@ async fn update(
             move |conn| {
                 verify_visibility(conn, &ctx, comment_id)?;
 
-                let (comment_owner, comment_version): (Option<_>, _) = comment::table
+                let old_comment: Comment = comment::table
                     .find(comment_id)
-                    .select((comment::user_id, comment::last_edit_time))
                     .first(conn)
                     .optional()?
                     .ok_or(ApiError::NotFound(ResourceType::Comment))?;
 
-                if ctx.client.id.is_none_or(|client_id| comment_owner != Some(client_id)) {
+                if ctx
+                    .client
+                    .id
+                    .is_none_or(|client_id| old_comment.user_id != Some(client_id))
+                {
                     ctx.verify_privilege(Action::CommentEditAny)?;
                 }
-                api::verify_version(comment_version, body.version)?;
-
-                diesel::update(comment::table.find(comment_id))
-                    .set((comment::text.eq(body.text), comment::last_edit_time.eq(DateTime::now())))
-                    .execute(conn)
+                api::verify_version(old_comment.last_edit_time, body.version)?;
+
+                let comment: Comment = diesel::update(comment::table.find(comment_id))
+                    .set((comment::text.eq(&body.text), comment::last_edit_time.eq(DateTime::now())))
+                    .get_result(conn)?;
+                let new_mentions = update::comment::set_mentions(conn, comment_id, &body.text)?;
+                notification::notify_mentions(conn, ctx.client, &comment, &new_mentions)?;
+                snapshot::comment::modification_snapshot(conn, ctx.client, &old_comment, &comment)
                     .map_err(ApiError::from)
             }
         })
@@ -340,19 +382,19 @@ async fn delete(
         .transaction(move |conn| {
             verify_visibility(conn, &ctx, comment_id)?;
 
-            let (comment_owner, comment_version): (Option<_>, _) = comment::table
+            let comment: Comment = comment::table
                 .find(comment_id)
-                .select((comment::user_id, comment::last_edit_time))
                 .first(conn)
                 .optional()?
                 .ok_or(ApiError::NotFound(ResourceType::Comment))?;
 
-            if ctx.client.id.is_none_or(|client_id| comment_owner != Some(client_id)) {
+            if ctx.client.id.is_none_or(|client_id| comment.user_id != Some(client_id)) {
                 ctx.verify_privilege(Action::CommentDeleteAny)?;
             }
-            api::verify_version(comment_version, *client_version)?;
+            api::verify_version(comment.last_edit_time, *client_version)?;
 
             diesel::delete(comment::table.find(comment_id)).execute(conn)?;
+            snapshot::comment::deletion_snapshot(conn, ctx.client, &comment)?;
             Ok::<_, ApiError>(Json(()))
         })
         .await
@@ -363,7 +405,7 @@ mod test {
     use crate::api::error::ApiResult;
     use crate::model::comment::Comment;
     use crate::model::enums::{ResourceType, UserRank};
-    use crate::schema::{comment, comment_statistics, database_statistics, user, user_statistics};
+    use crate::schema::{comment, comment_mention, comment_statistics, database_statistics, user, user_statistics};
     use crate::search::comment::Token;
     use crate::test::*;
     use crate::time::DateTime;
@@ -466,6 +508,35 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn reply() -> ApiResult<()> {
+        const REPLY_FIELDS: &str = "&fields=id,postId,parentId,text,html,user,mentions";
+        let get_mentions = |conn: &mut PgConnection, comment_id: i64| -> QueryResult<Vec<i64>> {
+            comment_mention::table
+                .select(comment_mention::user_id)
+                .filter(comment_mention::comment_id.eq(comment_id))
+                .load(conn)
+        };
+
+        verify_response(&format!("POST /comments/?{REPLY_FIELDS}"), "comment/create/reply").await?;
+
+        let mut conn = get_connection()?;
+        let reply_id: i64 = comment::table
+            .select(comment::id)
+            .order(comment::id.desc())
+            .first(&mut conn)?;
+        assert_eq!(get_mentions(&mut conn, reply_id)?, [2]);
+
+        verify_response(&format!("PUT /comment/{reply_id}/?{REPLY_FIELDS}"), "comment/edit/mentions").await?;
+        assert_eq!(get_mentions(&mut conn, reply_id)?, [3]);
+
+        verify_response(&format!("DELETE /comment/{reply_id}"), "comment/delete/typical").await?;
+        assert!(get_mentions(&mut conn, reply_id)?.is_empty());
+
+        reset_sequence(ResourceType::Comment)
+    }
+
     #[tokio::test]
     #[serial]
     async fn update() -> ApiResult<()> {
@@ -558,6 +629,10 @@ mod test {
     async fn error() -> ApiResult<()> {
         verify_response("GET /comment/99", "comment/get/nonexistent").await?;
         verify_response("POST /comments", "comment/create/on_nonexistent_post").await?;
+        verify_response("POST /comments", "comment/create/reply_to_nonexistent").await?;
+        verify_response("POST /comments", "comment/create/reply_on_other_post").await?;
+        verify_response("POST /comments", "comment/create/empty").await?;
+        verify_response("PUT /comment/1", "comment/edit/too_long").await?;
         verify_response("PUT /comment/99", "comment/edit/nonexistent").await?;
         verify_response("PUT /comment/99/score", "comment/rate/like_nonexistent").await?;
         verify_response("DELETE /comment/99", "comment/delete/nonexistent").await?;
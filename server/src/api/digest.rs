@@ -0,0 +1,281 @@
+use crate::api::doc::DIGEST_TAG;
+use crate::api::error::{ApiError, ApiResult};
+use crate::app::AppState;
+use crate::config::Action;
+use crate::email::digest;
+use crate::extract::{Ctx, Json, Path};
+use crate::model::digest::{NewDigestSubscription, SavedSearch, WatchedTag};
+use crate::model::enums::{DigestFrequency, ResourceType};
+use crate::model::tag::TagName;
+use crate::schema::{digest_subscription, post, saved_search, tag_name, user, watched_tag};
+use crate::search::post::QueryBuilder;
+use crate::string::SmallString;
+use axum::extract::State;
+use diesel::dsl::max;
+use diesel::{
+    ExpressionMethods, Insertable, JoinOnDsl, OptionalExtension, PgConnection, QueryDsl, QueryResult, RunQueryDsl,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(get_settings, update_settings))
+        .routes(routes!(unsubscribe))
+}
+
+/// Email digest settings of a user.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct DigestSettings {
+    /// How often digests are sent.
+    frequency: DigestFrequency,
+    /// Names of the tags whose new posts are included in digests.
+    watched_tags: Vec<SmallString>,
+    /// Search queries whose new matching posts are included in digests.
+    saved_searches: Vec<SmallString>,
+}
+
+impl DigestSettings {
+    fn retrieve(conn: &mut PgConnection, user_id: i64) -> QueryResult<Self> {
+        let frequency = digest_subscription::table
+            .find(user_id)
+            .select(digest_subscription::frequency)
+            .first(conn)
+            .optional()?
+            .unwrap_or(DigestFrequency::Never);
+        let watched_tags = watched_tag::table
+            .inner_join(tag_name::table.on(tag_name::tag_id.eq(watched_tag::tag_id)))
+            .select(tag_name::name)
+            .filter(watched_tag::user_id.eq(user_id))
+            .filter(TagName::is_primary())
+            .order(tag_name::name)
+            .load(conn)?;
+        let saved_searches = saved_search::table
+            .select(saved_search::query)
+            .filter(saved_search::user_id.eq(user_id))
+            .order(saved_search::query)
+            .load(conn)?;
+        Ok(Self {
+            frequency,
+            watched_tags,
+            saved_searches,
+        })
+    }
+}
+
+/// Retrieves email digest settings of the authenticated user.
+#[utoipa::path(
+    get,
+    path = "/digest-settings",
+    tag = DIGEST_TAG,
+    responses(
+        (status = 200, body = DigestSettings),
+        (status = 401, description = "Client is not logged in"),
+        (status = 403, description = "Privileges are too low"),
+    ),
+)]
+async fn get_settings(Ctx(ctx, connection_pool): Ctx) -> ApiResult<Json<DigestSettings>> {
+    ctx.verify_privilege(Action::DigestSubscribe)?;
+    let user_id = ctx.client.id.ok_or(ApiError::NotLoggedIn)?;
+
+    connection_pool
+        .transaction(move |conn| DigestSettings::retrieve(conn, user_id).map(Json))
+        .await
+}
+
+/// Replaces email digest settings of the authenticated user.
+///
+/// Digests include posts with any of the watched tags or matching any of the saved
+/// searches, replies to the user's comments, and moderation outcomes of the user's uploads.
+/// Only activity since the previous digest is included. Changing the frequency restarts
+/// the digest period, so the first digest after a change only covers activity from then on.
+#[utoipa::path(
+    put,
+    path = "/digest-settings",
+    tag = DIGEST_TAG,
+    request_body = DigestSettings,
+    responses(
+        (status = 200, body = DigestSettings),
+        (status = 400, description = "A saved search is invalid"),
+        (status = 401, description = "Client is not logged in"),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "A watched tag does not exist"),
+        (status = 422, description = "User hasn't provided an email address"),
+        (status = 500, description = "Server can't send emails"),
+    ),
+)]
+async fn update_settings(
+    Ctx(ctx, connection_pool): Ctx,
+    Json(body): Json<DigestSettings>,
+) -> ApiResult<Json<DigestSettings>> {
+    ctx.verify_privilege(Action::DigestSubscribe)?;
+    let user_id = ctx.client.id.ok_or(ApiError::NotLoggedIn)?;
+    if body.frequency != DigestFrequency::Never && ctx.config.smtp().is_none() {
+        return Err(ApiError::MissingSmtpInfo);
+    }
+
+    let mut saved_searches = body.saved_searches;
+    saved_searches.sort();
+    saved_searches.dedup();
+
+    connection_pool
+        .transaction(move |conn| {
+            if body.frequency != DigestFrequency::Never {
+                let user_email: Option<SmallString> = user::table.find(user_id).select(user::email).first(conn)?;
+                if user_email.is_none() {
+                    return Err(ApiError::NoEmail);
+                }
+            }
+            for query in &saved_searches {
+                QueryBuilder::new(&ctx, query)?;
+            }
+            let mut watched_tag_ids = Vec::with_capacity(body.watched_tags.len());
+            for name in &body.watched_tags {
+                let tag_id: i64 = tag_name::table
+                    .select(tag_name::tag_id)
+                    .filter(tag_name::name.eq(name))
+                    .first(conn)
+                    .optional()?
+                    .ok_or(ApiError::NotFound(ResourceType::Tag))?;
+                watched_tag_ids.push(tag_id);
+            }
+            watched_tag_ids.sort_unstable();
+            watched_tag_ids.dedup();
+
+            let current_frequency: Option<DigestFrequency> = digest_subscription::table
+                .find(user_id)
+                .select(digest_subscription::frequency)
+                .first(conn)
+                .optional()?;
+            if current_frequency != Some(body.frequency) {
+                diesel::delete(digest_subscription::table.find(user_id)).execute(conn)?;
+                let latest_post_id = post::table.select(max(post::id)).first::<Option<i64>>(conn)?;
+                NewDigestSubscription {
+                    user_id,
+                    frequency: body.frequency,
+                    last_post_id: latest_post_id.unwrap_or(0),
+                }
+                .insert_into(digest_subscription::table)
+                .execute(conn)?;
+            }
+
+            diesel::delete(watched_tag::table)
+                .filter(watched_tag::user_id.eq(user_id))
+                .execute(conn)?;
+            let new_watched_tags: Vec<_> = watched_tag_ids
+                .into_iter()
+                .map(|tag_id| WatchedTag { user_id, tag_id })
+                .collect();
+            new_watched_tags.insert_into(watched_tag::table).execute(conn)?;
+
+            diesel::delete(saved_search::table)
+                .filter(saved_search::user_id.eq(user_id))
+                .execute(conn)?;
+            let new_saved_searches: Vec<_> = saved_searches
+                .into_iter()
+                .map(|query| SavedSearch { user_id, query })
+                .collect();
+            new_saved_searches.insert_into(saved_search::table).execute(conn)?;
+
+            DigestSettings::retrieve(conn, user_id)
+                .map(Json)
+                .map_err(ApiError::from)
+        })
+        .await
+}
+
+/// Token from a digest email.
+#[derive(Deserialize, ToSchema)]
+struct UnsubscribeToken {
+    token: String,
+}
+
+/// Unsubscribes given user from email digests.
+///
+/// Every digest contains a link with a token that allows the recipient to
+/// unsubscribe without logging in. The token stays valid until the user's
+/// password changes.
+#[utoipa::path(
+    post,
+    path = "/digest-unsubscribe/{username}",
+    tag = DIGEST_TAG,
+    params(
+        ("username" = String, Path, description = "Username"),
+    ),
+    request_body = UnsubscribeToken,
+    responses(
+        (status = 200, body = Object),
+        (status = 401, description = "Token is invalid"),
+        (status = 404, description = "User does not exist"),
+    ),
+)]
+async fn unsubscribe(
+    State(state): State<AppState>,
+    Path(username): Path<SmallString>,
+    Json(confirmation): Json<UnsubscribeToken>,
+) -> ApiResult<Json<()>> {
+    state
+        .connection_pool
+        .transaction(move |conn| {
+            let (user_id, password_salt): (i64, String) = user::table
+                .select((user::id, user::password_salt))
+                .filter(user::name.eq(username))
+                .first(conn)
+                .optional()?
+                .ok_or(ApiError::NotFound(ResourceType::User))?;
+            if confirmation.token != digest::unsubscribe_token(&password_salt) {
+                return Err(ApiError::UnauthorizedUnsubscribe);
+            }
+
+            diesel::update(digest_subscription::table.find(user_id))
+                .set(digest_subscription::frequency.eq(DigestFrequency::Never))
+                .execute(conn)?;
+            Ok(Json(()))
+        })
+        .await
+}
+
+#[cfg(test)]
+mod test {
+    use crate::api::error::ApiResult;
+    use crate::model::enums::UserRank;
+    use crate::test::*;
+    use serial_test::{parallel, serial};
+
+    #[tokio::test]
+    #[serial]
+    async fn update() -> ApiResult<()> {
+        const USER: UserRank = UserRank::Regular;
+
+        verify_response_with_user(USER, "PUT /digest-settings", "digest/edit_settings/typical").await?;
+        verify_response_with_user(USER, "GET /digest-settings", "digest/get_settings/typical").await?;
+        verify_response("POST /digest-unsubscribe/regular_user", "digest/unsubscribe/typical").await?;
+        verify_response_with_user(USER, "GET /digest-settings", "digest/get_settings/unsubscribed").await?;
+        verify_response_with_user(USER, "PUT /digest-settings", "digest/edit_settings/clear").await
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn error() -> ApiResult<()> {
+        const USER: UserRank = UserRank::Regular;
+
+        verify_response_with_user(USER, "PUT /digest-settings", "digest/edit_settings/nonexistent_tag").await?;
+        verify_response_with_user(USER, "PUT /digest-settings", "digest/edit_settings/invalid_search").await?;
+        verify_response_with_user(USER, "PUT /digest-settings", "digest/edit_settings/digests_disabled").await?;
+        verify_response_with_user(UserRank::Moderator, "PUT /digest-settings", "digest/edit_settings/no_email").await?;
+        verify_response("POST /digest-unsubscribe/regular_user", "digest/unsubscribe/invalid_token").await?;
+        verify_response("POST /digest-unsubscribe/nobody", "digest/unsubscribe/nonexistent_user").await
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn unauthorized() -> ApiResult<()> {
+        const USER: UserRank = UserRank::Anonymous;
+
+        verify_response_with_user(USER, "GET /digest-settings", "digest/get_settings/unauthorized").await?;
+        verify_response_with_user(USER, "PUT /digest-settings", "digest/edit_settings/unauthorized").await
+    }
+}
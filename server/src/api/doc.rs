@@ -1,35 +1,65 @@
 use crate::error::ErrorName;
 use utoipa::OpenApi;
 
+pub const ACTIVITY_TAG: &str = "Activity";
+pub const AUDIT_LOG_TAG: &str = "Audit-Log";
 pub const COMMENT_TAG: &str = "Comment";
+pub const DIGEST_TAG: &str = "Digest";
+pub const EMAIL_VERIFICATION_TAG: &str = "Email-Verification";
 pub const INFO_TAG: &str = "Info";
+pub const INVITE_CODE_TAG: &str = "Invite-Code";
+pub const METRICS_TAG: &str = "Metrics";
+pub const NOTIFICATION_TAG: &str = "Notification";
+pub const OIDC_TAG: &str = "OIDC";
 pub const PASSWORD_RESET_TAG: &str = "Password-Reset";
 pub const POOL_TAG: &str = "Pool";
 pub const POOL_CATEGORY_TAG: &str = "Pool-Category";
 pub const POST_TAG: &str = "Post";
+pub const REPORT_TAG: &str = "Report";
 pub const SNAPSHOT_TAG: &str = "Snapshot";
 pub const TAG_TAG: &str = "Tag";
 pub const TAG_CATEGORY_TAG: &str = "Tag-Category";
+pub const TOTP_TAG: &str = "TOTP";
 pub const UPLOAD_TAG: &str = "Upload";
 pub const USER_TAG: &str = "User";
+pub const USER_NOTE_TAG: &str = "User-Note";
+pub const USER_SANCTION_TAG: &str = "User-Sanction";
+pub const USER_SESSION_TAG: &str = "User-Session";
 pub const USER_TOKEN_TAG: &str = "User-Token";
+pub const WEBHOOK_DELIVERY_TAG: &str = "Webhook-Delivery";
+pub const WIKI_PAGE_TAG: &str = "Wiki-Page";
 
 #[derive(OpenApi)]
 #[openapi(
     components(schemas(ErrorName)),
     tags(
+        (name = ACTIVITY_TAG, description = "Activity stream API endpoints"),
+        (name = AUDIT_LOG_TAG, description = "Audit log API endpoints"),
         (name = COMMENT_TAG, description = "Comment API endpoints"),
+        (name = DIGEST_TAG, description = "Email digest API endpoints"),
+        (name = EMAIL_VERIFICATION_TAG, description = "Email verification API endpoints"),
         (name = INFO_TAG, description = "Info API endpoints"),
+        (name = INVITE_CODE_TAG, description = "Invite code API endpoints"),
+        (name = METRICS_TAG, description = "Metrics API endpoints"),
+        (name = NOTIFICATION_TAG, description = "Notification API endpoints"),
+        (name = OIDC_TAG, description = "OpenID Connect single sign-on API endpoints"),
         (name = PASSWORD_RESET_TAG, description = "Password reset API endpoints"),
         (name = POOL_TAG, description = "Pool API endpoints"),
         (name = POOL_CATEGORY_TAG, description = "Pool category API endpoints"),
         (name = POST_TAG, description = "Post API endpoints"),
+        (name = REPORT_TAG, description = "Report API endpoints"),
         (name = SNAPSHOT_TAG, description = "Snapshot API endpoints"),
         (name = TAG_TAG, description = "Tag API endpoints"),
         (name = TAG_CATEGORY_TAG, description = "Tag category API endpoints"),
+        (name = TOTP_TAG, description = "Two-factor authentication API endpoints"),
         (name = UPLOAD_TAG, description = "Upload API endpoints"),
         (name = USER_TAG, description = "User API endpoints"),
+        (name = USER_NOTE_TAG, description = "User note API endpoints"),
+        (name = USER_SANCTION_TAG, description = "User sanction API endpoints"),
+        (name = USER_SESSION_TAG, description = "User session API endpoints"),
         (name = USER_TOKEN_TAG, description = "User token API endpoints"),
+        (name = WEBHOOK_DELIVERY_TAG, description = "Webhook delivery API endpoints"),
+        (name = WIKI_PAGE_TAG, description = "Wiki page API endpoints"),
         (name = "Authentication", description = AUTHENTICATION_DESCRIPTION),
         (name = "User-Token-Authentication", description = USER_TOKEN_AUTHENTICATION_DESCRIPTION),
         (name = "Basic-Requests", description = BASIC_REQUESTS_DESCRIPTION),
@@ -151,6 +181,16 @@ Webhook URIs can be configured in `config.toml` (See `config.toml.dist` for
 example). Upon any event, the API will send a `POST` request to the listed
 URIs with a snapshot resource generated with anonymous user privileges as the
 message body, in JSON format.
+
+Webhooks can be limited to snapshots of given resource types and operations.
+Deliveries that fail, either because the webhook couldn't be reached or because
+it responded with an error status, are retried with exponential backoff. Every
+request carries an `X-Oxibooru-Delivery` header with the ID of the delivery,
+which stays the same across retries. If a webhook is configured with a secret,
+the request also carries an `X-Oxibooru-Signature` header (or another header of
+the administrator's choosing) of the form `sha256=<signature>`, where
+`<signature>` is the hex-encoded HMAC-SHA256 of the request body keyed with
+the secret.
 ";
 
 const SEARCH_DESCRIPTION: &str = r#"
@@ -0,0 +1,169 @@
+use crate::api::doc::EMAIL_VERIFICATION_TAG;
+use crate::api::error::{ApiError, ApiResult};
+use crate::app::AppState;
+use crate::email::verification;
+use crate::extract::{Json, Path};
+use crate::model::enums::{ResourceType, UserRank};
+use crate::schema::{email_verification, user};
+use crate::string::SmallString;
+use axum::extract::State;
+use diesel::{BoolExpressionMethods, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new().routes(routes!(resend, verify))
+}
+
+fn get_user_info(
+    conn: &mut PgConnection,
+    identifier: &str,
+) -> ApiResult<(i64, SmallString, Option<SmallString>, String)> {
+    user::table
+        .select((user::id, user::name, user::email, user::password_salt))
+        .filter(user::name.eq(identifier).or(user::email.eq(identifier)))
+        .first(conn)
+        .optional()?
+        .ok_or(ApiError::NotFound(ResourceType::User))
+}
+
+/// Sends the verification email of given user again.
+///
+/// Does nothing if the user's email address is already verified.
+#[utoipa::path(
+    get,
+    path = "/email-verification/{identifier}",
+    tag = EMAIL_VERIFICATION_TAG,
+    params(
+        ("identifier" = SmallString, Path, description = "User email or username"),
+    ),
+    responses(
+        (status = 200, description = "Verification email sent", body = ()),
+        (status = 404, description = "User does not exist"),
+        (status = 422, description = "User hasn't provided an email address"),
+    ),
+)]
+async fn resend(State(state): State<AppState>, Path(identifier): Path<SmallString>) -> ApiResult<Json<()>> {
+    let mut conn = state.connection_pool.get().await?;
+    let (user_id, username, user_email, password_salt) = get_user_info(conn.as_mut(), &identifier)?;
+    let user_email = user_email.ok_or(ApiError::NoEmail)?;
+    let pending: Option<UserRank> = email_verification::table
+        .find(user_id)
+        .select(email_verification::rank)
+        .first(conn.as_mut())
+        .optional()?;
+    drop(conn);
+
+    if pending.is_some() {
+        verification::send(&state, &username, &user_email, &password_salt)?;
+    }
+    Ok(Json(()))
+}
+
+/// Token from verification email.
+#[derive(Deserialize, ToSchema)]
+struct VerificationToken {
+    token: String,
+}
+
+/// Verifies the email address of given user, which lifts the restrictions placed on
+/// accounts registered in `email_verification` mode. Users that are no longer restricted
+/// keep their current rank.
+///
+/// Verifying an address that is already verified succeeds without doing anything.
+#[utoipa::path(
+    post,
+    path = "/email-verification/{username}",
+    tag = EMAIL_VERIFICATION_TAG,
+    params(
+        ("username" = String, Path, description = "Username"),
+    ),
+    request_body = VerificationToken,
+    responses(
+        (status = 200, description = "Email address verified", body = ()),
+        (status = 401, description = "Token is invalid"),
+        (status = 404, description = "User does not exist"),
+    ),
+)]
+async fn verify(
+    State(state): State<AppState>,
+    Path(username): Path<SmallString>,
+    Json(confirmation): Json<VerificationToken>,
+) -> ApiResult<Json<()>> {
+    state
+        .connection_pool
+        .transaction(move |conn| {
+            let (user_id, _name, user_email, password_salt) = get_user_info(conn, &username)?;
+            let expected_token = user_email.map(|email| verification::token(&email, &password_salt));
+            if expected_token.is_none_or(|token| token != confirmation.token) {
+                return Err(ApiError::UnauthorizedEmailVerification);
+            }
+
+            let pending_rank: Option<UserRank> = diesel::delete(email_verification::table.find(user_id))
+                .returning(email_verification::rank)
+                .get_result(conn)
+                .optional()?;
+            // Users whose rank was changed while the verification was pending keep their rank
+            if let Some(rank) = pending_rank {
+                diesel::update(user::table.find(user_id))
+                    .filter(user::rank.eq(UserRank::Restricted))
+                    .set(user::rank.eq(rank))
+                    .execute(conn)?;
+            }
+            Ok(Json(()))
+        })
+        .await
+}
+
+#[cfg(test)]
+mod test {
+    use crate::api::error::ApiResult;
+    use crate::email::verification;
+    use crate::model::enums::UserRank;
+    use crate::model::user::NewEmailVerification;
+    use crate::schema::{email_verification, user};
+    use crate::test::*;
+    use diesel::dsl::exists;
+    use diesel::{Insertable, QueryDsl, RunQueryDsl};
+    use serde_json::json;
+    use serial_test::parallel;
+
+    #[tokio::test]
+    #[parallel]
+    async fn verify_unrestricted() -> ApiResult<()> {
+        const USER_ID: i64 = 3;
+
+        let mut conn = get_connection()?;
+        NewEmailVerification {
+            user_id: USER_ID,
+            rank: UserRank::Regular,
+        }
+        .insert_into(email_verification::table)
+        .execute(&mut conn)?;
+
+        let token = verification::token("example@hotmail.com", TEST_SALT);
+        let response = test_server(get_state())
+            .post("/email-verification/power_user")
+            .json(&json!({ "token": token }))
+            .await;
+        response.assert_status_ok();
+
+        let rank: UserRank = user::table.find(USER_ID).select(user::rank).first(&mut conn)?;
+        let pending: bool = diesel::select(exists(email_verification::table.find(USER_ID))).first(&mut conn)?;
+        assert_eq!(rank, UserRank::Power);
+        assert!(!pending);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn error() -> ApiResult<()> {
+        verify_response("GET /email-verification/no_one", "email_verification/resend_for_nonexistent_user").await?;
+        verify_response("POST /email-verification/nobody", "email_verification/verify_nonexistent_user").await?;
+
+        verify_response("GET /email-verification/moderator", "email_verification/no_email").await?;
+        verify_response("POST /email-verification/regular_user", "email_verification/invalid_token").await
+    }
+}
@@ -22,6 +22,8 @@ pub type ApiResult<T> = Result<T, ApiError>;
 pub enum ApiError {
     #[error("{0} already exists")]
     AlreadyExists(ResourceProperty),
+    #[error("Identical content belongs to post {0}, which is in the trash. Restore that post instead.")]
+    ContentInTrash(i64),
     #[error("Content exceeds maximum allowed size")]
     ContentTooLarge,
     #[error("Cyclic dependency detected in {0}s")]
@@ -51,14 +53,26 @@ pub enum ApiError {
     InvalidEmailAddress(#[from] lettre::address::AddressError),
     InvalidEmail(#[from] lettre::error::Error),
     InvalidHeader(#[from] reqwest::header::InvalidHeaderValue),
+    #[error("Invite code is missing, invalid, expired, or used up")]
+    InvalidInviteCode,
     InvalidMime(#[from] mime::FromStrError),
+    #[error("Identity provider response is invalid: {0}")]
+    InvalidOidcResponse(&'static str),
+    #[error("Single sign-on state is invalid or has expired")]
+    InvalidOidcState,
+    #[error("Reply must be on the same post as the comment it replies to")]
+    InvalidParentComment,
     #[error("Invalid sort token")]
     InvalidSort,
+    #[error("{0} must be between 1 and {1} characters long")]
+    InvalidTextLength(&'static str, usize),
     InvalidTime(#[from] crate::search::TimeParsingError),
     #[error("Upload token is invalid")]
     InvalidUploadToken,
     #[error("Cannot create an anonymous user")]
     InvalidUserRank,
+    #[error("Wiki page must have either a title or a tag, but not both")]
+    InvalidWikiPageTarget,
     Image(#[from] image::ImageError),
     JsonRejection(#[from] axum::extract::rejection::JsonRejection),
     JsonSerialization(#[from] serde_json::Error),
@@ -70,6 +84,8 @@ pub enum ApiError {
     MissingFormData,
     #[error("Missing metadata form")]
     MissingMetadata,
+    #[error("Missing OIDC info")]
+    MissingOidcInfo,
     #[error("Missing smtp info")]
     MissingSmtpInfo,
     Multipart(#[from] axum::extract::multipart::MultipartError),
@@ -84,18 +100,38 @@ pub enum ApiError {
     #[error("This action requires you to be logged in")]
     NotLoggedIn,
     Password(#[from] argon2::password_hash::Error),
+    #[error("Expiration time must be in the future")]
+    PastExpirationTime,
     PathRejection(#[from] axum::extract::rejection::PathRejection),
     QueryRejection(#[from] axum::extract::rejection::QueryRejection),
     Request(#[from] reqwest::Error),
     #[error("Someone else modified this in the meantime. Please try again.")]
     ResourceModified,
+    #[error("Revision {0} not found")]
+    RevisionNotFound(usize),
     #[error("Cannot merge {0} with itself")]
     SelfMerge(ResourceType),
+    #[error("Cannot sanction yourself")]
+    SelfSanction,
     StdIo(#[from] std::io::Error),
     SwfDecoding(#[from] swf::error::Error),
     TaskJoin(#[from] tokio::task::JoinError),
+    #[error("Too many activity streams are open at once")]
+    TooManyActivityStreams,
+    #[error("Two-factor authentication is already enabled")]
+    TotpAlreadyEnabled,
+    #[error("Two-factor authentication is not enabled")]
+    TotpNotEnabled,
+    #[error("Email verification token is invalid")]
+    UnauthorizedEmailVerification,
     #[error("Password reset token is invalid")]
     UnauthorizedPasswordReset,
+    #[error("Unsubscribe token is invalid")]
+    UnauthorizedUnsubscribe,
+    #[error("Only failed webhook deliveries can be replayed")]
+    UnreplayableWebhookDelivery,
+    #[error("Resources of type {0} cannot be reported")]
+    UnreportableResource(ResourceType),
     #[error("Content type `{0}` not supported")]
     UnsupportedContentType(Cow<'static, str>),
     UnsupportedExtension(#[from] crate::model::enums::ParseExtensionError),
@@ -116,14 +152,21 @@ impl ApiError {
             Self::QueryRejection(err) => err.status(),
             Self::HeaderDeserialization(_)
             | Self::InvalidMime(_)
+            | Self::InvalidOidcState
             | Self::MissingContent(_)
             | Self::MissingContentType
             | Self::MissingFormData
             | Self::MissingMetadata => StatusCode::BAD_REQUEST,
-            Self::NotLoggedIn | Self::Password(_) | Self::UnauthorizedPasswordReset => StatusCode::UNAUTHORIZED,
-            Self::Hidden(_) | Self::InsufficientPrivileges => StatusCode::FORBIDDEN,
-            Self::NotFound(_) => StatusCode::NOT_FOUND,
-            Self::AlreadyExists(_) | Self::ResourceModified => StatusCode::CONFLICT,
+            Self::NotLoggedIn
+            | Self::Password(_)
+            | Self::UnauthorizedEmailVerification
+            | Self::UnauthorizedPasswordReset
+            | Self::UnauthorizedUnsubscribe => StatusCode::UNAUTHORIZED,
+            Self::Hidden(_) | Self::InsufficientPrivileges | Self::InvalidInviteCode => StatusCode::FORBIDDEN,
+            Self::NotFound(_) | Self::RevisionNotFound(_) => StatusCode::NOT_FOUND,
+            Self::AlreadyExists(_) | Self::ContentInTrash(_) | Self::ResourceModified | Self::TotpAlreadyEnabled => {
+                StatusCode::CONFLICT
+            }
             Self::ContentTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
             Self::UnsupportedContentType(_) | Self::UnsupportedExtension(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
             Self::CyclicDependency(_)
@@ -137,26 +180,39 @@ impl ApiError {
             | Self::Image(_)
             | Self::InvalidEmail(_)
             | Self::InvalidEmailAddress(_)
+            | Self::InvalidParentComment
             | Self::InvalidSort
+            | Self::InvalidTextLength(..)
             | Self::InvalidTime(_)
             | Self::InvalidUploadToken
             | Self::InvalidUserRank
+            | Self::InvalidWikiPageTarget
             | Self::NoEmail
             | Self::NoNamesGiven(_)
             | Self::NotAnInteger(_)
+            | Self::PastExpirationTime
             | Self::SelfMerge(_)
+            | Self::SelfSanction
             | Self::SwfDecoding(_)
+            | Self::TotpNotEnabled
+            | Self::UnreplayableWebhookDelivery
+            | Self::UnreportableResource(_)
             | Self::UrlValidation(_) => StatusCode::UNPROCESSABLE_ENTITY,
             Self::FailedEmailTransport(_)
             | Self::FailedQuery(_)
             | Self::InvalidHeader(_)
+            | Self::MissingOidcInfo
             | Self::MissingSmtpInfo
             | Self::StdIo(_)
             | Self::TaskJoin(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::InvalidOidcResponse(_) => StatusCode::BAD_GATEWAY,
             Self::FailedConnection(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::TooManyActivityStreams => StatusCode::TOO_MANY_REQUESTS,
             Self::FailedAuthentication(err) => match err {
+                AuthenticationError::Banned | AuthenticationError::InvalidCsrfToken => StatusCode::FORBIDDEN,
                 AuthenticationError::FailedConnection(_) => StatusCode::SERVICE_UNAVAILABLE,
                 AuthenticationError::FailedQuery(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                AuthenticationError::TotpLockedOut => StatusCode::TOO_MANY_REQUESTS,
                 _ => StatusCode::UNAUTHORIZED,
             },
             Self::JsonSerialization(err) => match err.classify() {
@@ -169,6 +225,7 @@ impl ApiError {
     fn category(&self) -> &'static str {
         match self {
             Self::AlreadyExists(_) => "Already Exists",
+            Self::ContentInTrash(_) => "Content In Trash",
             Self::ContentTooLarge => "Content Too Large",
             Self::CyclicDependency(_) => "Cyclic Dependency",
             Self::DeleteDefault(_) => "Delete Default",
@@ -190,10 +247,16 @@ impl ApiError {
             Self::InvalidEmail(_) => "Invalid Email",
             Self::InvalidHeader(_) => "Invalid Header",
             Self::InvalidMime(_) => "Invalid MIME",
+            Self::InvalidOidcResponse(_) => "Invalid OIDC Response",
+            Self::InvalidOidcState => "Invalid OIDC State",
+            Self::InvalidInviteCode => "Invalid Invite Code",
+            Self::InvalidParentComment => "Invalid Parent Comment",
             Self::InvalidSort => "Invalid Sort",
+            Self::InvalidTextLength(..) => "Invalid Text Length",
             Self::InvalidTime(_) => "Invalid Time",
             Self::InvalidUploadToken => "Invalid Upload Token",
             Self::InvalidUserRank => "Invalid User Rank",
+            Self::InvalidWikiPageTarget => "Invalid Wiki Page Target",
             Self::Image(_) => "Image Error",
             Self::JsonRejection(_) => "JSON Rejection",
             Self::JsonSerialization(_) => "JSON Serialization Error",
@@ -201,6 +264,7 @@ impl ApiError {
             Self::MissingContentType => "Missing Content Type",
             Self::MissingFormData => "Missing Form Data",
             Self::MissingMetadata => "Missing Metadata",
+            Self::MissingOidcInfo => "Missing OIDC Info",
             Self::MissingSmtpInfo => "Missing SMTP Info",
             Self::Multipart(_) => "Multipart/Form-Data Error",
             Self::MultipartRejection(_) => "Multipart Rejection",
@@ -210,15 +274,25 @@ impl ApiError {
             Self::NotFound(_) => "Resource Not Found",
             Self::NotLoggedIn => "Not Logged In",
             Self::Password(_) => "Password Error",
+            Self::PastExpirationTime => "Past Expiration Time",
             Self::PathRejection(_) => "Path Rejection",
             Self::QueryRejection(_) => "Query Rejection",
             Self::Request(_) => "Request Error",
             Self::ResourceModified => "Resource Modified",
+            Self::RevisionNotFound(_) => "Revision Not Found",
             Self::SelfMerge(_) => "Self Merge",
+            Self::SelfSanction => "Self Sanction",
             Self::StdIo(_) => "IO Error",
             Self::SwfDecoding(_) => "SWF Decoding Error",
             Self::TaskJoin(_) => "Task Join Error",
+            Self::TooManyActivityStreams => "Too Many Activity Streams",
+            Self::TotpAlreadyEnabled => "TOTP Already Enabled",
+            Self::TotpNotEnabled => "TOTP Not Enabled",
+            Self::UnauthorizedEmailVerification => "Unauthorized Email Verification",
             Self::UnauthorizedPasswordReset => "Unauthorized Password Reset",
+            Self::UnauthorizedUnsubscribe => "Unauthorized Unsubscribe",
+            Self::UnreplayableWebhookDelivery => "Unreplayable Webhook Delivery",
+            Self::UnreportableResource(_) => "Unreportable Resource",
             Self::UnsupportedContentType(_) => "Unsupported Content Type",
             Self::UnsupportedExtension(_) => "Unsupported extension",
             Self::UrlValidation(_) => "URL Validation Error",
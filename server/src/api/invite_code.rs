@@ -0,0 +1,198 @@
+use crate::api::doc::INVITE_CODE_TAG;
+use crate::api::error::{ApiError, ApiResult};
+use crate::app::AppState;
+use crate::config::Action;
+use crate::extract::{Ctx, Json, Path, Query, ResourceParams, UnpagedResponse};
+use crate::model::enums::{AvatarStyle, ResourceType};
+use crate::model::invite_code::{InviteCode, NewInviteCode};
+use crate::resource::invite_code::{Field, InviteCodeInfo};
+use crate::resource::user::MicroUser;
+use crate::schema::{invite_code, user};
+use crate::string::{SmallString, lower};
+use crate::time::DateTime;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::Engine;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use diesel::dsl::sql;
+use diesel::sql_types::Integer;
+use diesel::{
+    ExpressionMethods, Insertable, JoinOnDsl, NullableExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+    QueryResult, RunQueryDsl, SelectableHelper,
+};
+use serde::Deserialize;
+use std::num::NonZeroU16;
+use utoipa::ToSchema;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list, create))
+        .routes(routes!(delete))
+}
+
+const CODE_LENGTH: usize = 12;
+
+type CreatorInfo = Option<(SmallString, SmallString, AvatarStyle)>;
+
+/// Lists all invite codes, including expired and used up ones.
+#[utoipa::path(
+    get,
+    path = "/invite-codes",
+    tag = INVITE_CODE_TAG,
+    params(ResourceParams),
+    responses(
+        (status = 200, body = UnpagedResponse<InviteCodeInfo>),
+        (status = 403, description = "Privileges are too low"),
+    ),
+)]
+async fn list(
+    Ctx(ctx, connection_pool): Ctx,
+    Query(params): Query<ResourceParams<Field>>,
+) -> ApiResult<Json<UnpagedResponse<InviteCodeInfo>>> {
+    ctx.verify_privilege(Action::InviteCodeList)?;
+
+    let invite_codes: Vec<(InviteCode, CreatorInfo)> = connection_pool
+        .transaction(|conn| {
+            invite_code::table
+                .left_join(user::table.on(invite_code::creator_id.eq(user::id.nullable())))
+                .select((InviteCode::as_select(), (user::name, lower(user::name), user::avatar_style).nullable()))
+                .order(invite_code::creation_time.desc())
+                .load(conn)
+        })
+        .await?;
+
+    let results = invite_codes
+        .into_iter()
+        .map(|(invite_code, creator)| {
+            let creator = creator.map(|(name, lowercase_name, avatar_style)| {
+                MicroUser::new(&ctx.config, name, &lowercase_name, avatar_style)
+            });
+            InviteCodeInfo::new(creator, invite_code, params.fields)
+        })
+        .collect();
+    Ok(Json(UnpagedResponse { results }))
+}
+
+/// Request body for creating an invite code.
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct InviteCodeCreateBody {
+    /// How many users can register with the code. Defaults to 1.
+    #[schema(value_type = Option<u16>, minimum = 1)]
+    max_uses: Option<NonZeroU16>,
+    /// Time the code expires. Codes without an expiration time never expire.
+    expiration_time: Option<DateTime>,
+}
+
+/// Creates a new random invite code.
+///
+/// Invite codes are only needed when `registration_mode` is `invite_code` in the server's
+/// configuration. Each code can be used to register up to `maxUses` accounts.
+#[utoipa::path(
+    post,
+    path = "/invite-codes",
+    tag = INVITE_CODE_TAG,
+    params(ResourceParams),
+    request_body = InviteCodeCreateBody,
+    responses(
+        (status = 200, body = InviteCodeInfo),
+        (status = 403, description = "Privileges are too low"),
+    ),
+)]
+async fn create(
+    Ctx(ctx, connection_pool): Ctx,
+    Query(params): Query<ResourceParams<Field>>,
+    Json(body): Json<InviteCodeCreateBody>,
+) -> ApiResult<Json<InviteCodeInfo>> {
+    ctx.verify_privilege(Action::InviteCodeCreate)?;
+
+    let mut code = [0; CODE_LENGTH];
+    OsRng.fill_bytes(&mut code);
+    let code = BASE64_URL_SAFE_NO_PAD.encode(code);
+
+    let creator_id = ctx.client.id;
+    let (invite_code, creator) = connection_pool
+        .transaction(move |conn| {
+            let invite_code = NewInviteCode {
+                code: &code,
+                creator_id,
+                max_uses: body.max_uses.map_or(1, |max_uses| max_uses.get().into()),
+                expiration_time: body.expiration_time,
+            }
+            .insert_into(invite_code::table)
+            .returning(InviteCode::as_returning())
+            .get_result(conn)?;
+            let creator = get_creator(conn, creator_id)?;
+            Ok::<_, ApiError>((invite_code, creator))
+        })
+        .await?;
+
+    let creator = creator
+        .map(|(name, lowercase_name, avatar_style)| MicroUser::new(&ctx.config, name, &lowercase_name, avatar_style));
+    Ok(Json(InviteCodeInfo::new(creator, invite_code, params.fields)))
+}
+
+/// Deletes an invite code. Users who already registered with the code are unaffected.
+#[utoipa::path(
+    delete,
+    path = "/invite-code/{code}",
+    tag = INVITE_CODE_TAG,
+    params(
+        ("code" = String, Path, description = "Invite code"),
+    ),
+    responses(
+        (status = 200, body = Object),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Invite code does not exist"),
+    ),
+)]
+async fn delete(Ctx(ctx, connection_pool): Ctx, Path(code): Path<String>) -> ApiResult<Json<()>> {
+    ctx.verify_privilege(Action::InviteCodeDelete)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let _: i32 = diesel::delete(invite_code::table.find(code))
+                .returning(sql::<Integer>("0"))
+                .get_result(conn)
+                .optional()?
+                .ok_or(ApiError::NotFound(ResourceType::InviteCode))?;
+            Ok::<_, ApiError>(Json(()))
+        })
+        .await
+}
+
+fn get_creator(conn: &mut PgConnection, creator_id: Option<i64>) -> QueryResult<CreatorInfo> {
+    let Some(creator_id) = creator_id else {
+        return Ok(None);
+    };
+    user::table
+        .find(creator_id)
+        .select((user::name, lower(user::name), user::avatar_style))
+        .first(conn)
+        .optional()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::api::error::ApiResult;
+    use crate::model::enums::UserRank;
+    use crate::test::*;
+    use serial_test::parallel;
+
+    #[tokio::test]
+    #[parallel]
+    async fn error() -> ApiResult<()> {
+        verify_response("DELETE /invite-code/nonexistent", "invite_code/delete/nonexistent_code").await
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn unauthorized() -> ApiResult<()> {
+        const USER: UserRank = UserRank::Power;
+
+        verify_response_with_user(USER, "GET /invite-codes", "invite_code/list/unauthorized").await?;
+        verify_response_with_user(USER, "POST /invite-codes", "invite_code/create/unauthorized").await?;
+        verify_response_with_user(USER, "DELETE /invite-code/nonexistent", "invite_code/delete/unauthorized").await
+    }
+}
@@ -0,0 +1,53 @@
+use crate::api::doc::METRICS_TAG;
+use crate::api::error::ApiResult;
+use crate::app::AppState;
+use crate::config::Action;
+use crate::extract::Ctx;
+use crate::metrics;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new().routes(routes!(get))
+}
+
+/// Retrieves server metrics in the Prometheus text format.
+///
+/// Includes request durations by route and status, database connection wait
+/// times, content cache lookups, FFmpeg durations and timeouts, reverse search
+/// candidate counts, webhook failures, and database statistics. Metrics are
+/// tracked per server instance.
+///
+/// If `METRICS_PORT` is set, metrics are also served without authentication
+/// on that port, so that they can be scraped without exposing them publicly.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = METRICS_TAG,
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", content_type = "text/plain"),
+        (status = 403, description = "Privileges are too low"),
+    ),
+)]
+async fn get(Ctx(ctx, connection_pool): Ctx) -> ApiResult<impl IntoResponse> {
+    ctx.verify_privilege(Action::MetricsView)?;
+
+    let metrics = metrics::gather(&connection_pool).await?;
+    Ok(([(CONTENT_TYPE, metrics::TEXT_FORMAT)], metrics))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::api::error::ApiResult;
+    use crate::model::enums::UserRank;
+    use crate::test::*;
+    use serial_test::parallel;
+
+    #[tokio::test]
+    #[parallel]
+    async fn unauthorized() -> ApiResult<()> {
+        verify_response_with_user(UserRank::Moderator, "GET /metrics", "metrics/unauthorized").await
+    }
+}
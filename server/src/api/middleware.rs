@@ -1,23 +1,17 @@
 use crate::api::error::{ApiError, ApiResult};
 use crate::app::AppState;
-use crate::auth::{Client as AuthClient, header};
-use crate::model::enums::UserRank;
-use crate::resource::field::Mask;
-use crate::resource::snapshot::{Field, SnapshotInfo};
-use crate::schema::snapshot;
-use crate::update;
-use axum::extract::{Request, State};
-use axum::http::Method;
-use axum::http::header::AUTHORIZATION;
+use crate::audit::RequestInfo;
+use crate::auth::{Client as AuthClient, header, session, totp};
+use crate::model::enums::{AuditEvent, UserRank};
+use crate::{activity, audit, metrics, update, webhook};
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::header::{AUTHORIZATION, SET_COOKIE};
+use axum::http::{HeaderValue, Method};
 use axum::middleware::Next;
 use axum::response::Response;
-use diesel::{ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
-use reqwest::Client;
-use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderValue, REFERER};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicI64, Ordering};
-use tracing::{error, warn};
-use url::Url;
+use std::time::Instant;
+use tracing::error;
 
 /// Logs errors from handlers.
 pub async fn log_error(req: Request, next: Next) -> Response {
@@ -33,12 +27,60 @@ pub async fn log_error(req: Request, next: Next) -> Response {
     response
 }
 
-/// Attempts to authorizes user by either username/password or user token.
+/// Records how long requests take, labeled by their method, route, and response status.
+pub async fn record_metrics(request: Request, next: Next) -> Response {
+    let start_time = Instant::now();
+    let method = request.method().clone();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| String::from("unmatched"), |path| path.as_str().to_owned());
+    let response = next.run(request).await;
+
+    let status = response.status();
+    metrics::HTTP_REQUEST_DURATION.observe_duration([method.as_str(), &route, status.as_str()], start_time.elapsed());
+    response
+}
+
+/// Attempts to authorizes user by either username/password, user token, or session cookie.
+/// Session cookies are only considered if the request has no authorization header.
 pub async fn auth(State(state): State<AppState>, mut request: Request, next: Next) -> ApiResult<Response> {
+    let request_info = RequestInfo::new(&state.config, request.headers(), request.extensions());
     let auth_header = request.headers().get(AUTHORIZATION);
+    let session_secret = session::find_cookie(request.headers(), session::SESSION_COOKIE)
+        .filter(|_| auth_header.is_none())
+        .map(str::to_owned);
+    let mut is_session_expired = false;
     let client = if let Some(auth_value) = auth_header {
         let auth_str = auth_value.to_str()?;
-        header::authenticate_user(&state, auth_str).await
+        let totp_code = request
+            .headers()
+            .get(totp::TOTP_HEADER)
+            .map(HeaderValue::to_str)
+            .transpose()?;
+        let result = header::authenticate_user(&state, auth_str, totp_code).await;
+        if let Err(err) = &result {
+            let username = header::claimed_username(auth_str);
+            audit::record_failed_authentication(&state, &request_info, username.as_deref(), err).await;
+        }
+        result
+    } else if let Some(secret) = &session_secret {
+        let csrf_token = request
+            .headers()
+            .get(session::CSRF_HEADER)
+            .map(HeaderValue::to_str)
+            .transpose()?;
+        // Clients can't clear HttpOnly cookies themselves, so an expired session is treated as
+        // anonymous and the browser is told to forget it
+        let client = match session::authenticate(&state, secret, request.method(), csrf_token).await {
+            Ok(client) => client,
+            Err(err) => {
+                audit::record_failed_authentication(&state, &request_info, None, &err).await;
+                return Err(ApiError::from(err));
+            }
+        };
+        is_session_expired = client.is_none();
+        Ok(client.unwrap_or(AuthClient::new(None, UserRank::Anonymous)))
     } else {
         Ok(AuthClient::new(None, UserRank::Anonymous))
     }?;
@@ -50,95 +92,51 @@ pub async fn auth(State(state): State<AppState>, mut request: Request, next: Nex
     {
         let mut conn = state.connection_pool.get().await?;
         update::user::last_login_time(conn.as_mut(), user_id)?;
+        if let Some(secret) = &session_secret {
+            update::user::session_usage_time(conn.as_mut(), &session::hash_secret(secret))?;
+        }
+        audit::record(conn.as_mut(), &request_info, AuditEvent::Login, Some(user_id), None, None)?;
     }
 
     request.extensions_mut().insert(client);
-    Ok(next.run(request).await)
-}
-
-/// Sends snapshot data to webhook URLs after modifying requests.
-pub async fn post_to_webhooks(State(state): State<AppState>, request: Request, next: Next) -> ApiResult<Response> {
-    const FIELDS: Mask<Field> = Mask::<Field>::all();
-
-    let can_modify_database = matches!(request.method(), &Method::POST | &Method::PUT | &Method::DELETE);
-    let response = next.run(request).await;
-
-    if can_modify_database {
-        let new_snapshots = state
-            .connection_pool
-            .transaction({
-                let config = Arc::clone(&state.config);
-                move |conn| {
-                    let last_posted_snapshot = LAST_POSTED_SNAPSHOT.load(Ordering::SeqCst);
-                    let new_snapshots = snapshot::table
-                        .filter(snapshot::id.gt(last_posted_snapshot))
-                        .order(snapshot::id)
-                        .load(conn)?;
-                    SnapshotInfo::new_batch(conn, &config, new_snapshots, FIELDS)
-                }
-            })
-            .await?;
-
-        for snapshot in new_snapshots {
-            post_snapshot(&state, snapshot);
+    let mut response = next.run(request).await;
+    if is_session_expired {
+        for cookie in session::expired_cookies(&state.config) {
+            response.headers_mut().append(SET_COOKIE, cookie);
         }
     }
-
     Ok(response)
 }
 
-/// Initializes last posted snapshot counter using highest ID snapshot
-pub fn initialize_snapshot_counter(conn: &mut PgConnection) -> QueryResult<()> {
-    let latest_snapshot_id = snapshot::table
-        .select(snapshot::id)
-        .order(snapshot::id.desc())
-        .first(conn)
-        .optional()
-        .map(Option::unwrap_or_default)?;
-    LAST_POSTED_SNAPSHOT.store(latest_snapshot_id, Ordering::SeqCst);
-    Ok(())
-}
-
-static LAST_POSTED_SNAPSHOT: AtomicI64 = AtomicI64::new(i64::MAX);
-
-/// Sends `snapshot` data to webhooks if it hasn't already been posted by another thread.
-fn post_snapshot(state: &AppState, snapshot: SnapshotInfo) {
-    loop {
-        let last_posted_snapshot = LAST_POSTED_SNAPSHOT.load(Ordering::SeqCst);
-        if snapshot.id <= last_posted_snapshot {
-            return;
-        }
-
-        if LAST_POSTED_SNAPSHOT
-            .compare_exchange(last_posted_snapshot, snapshot.id, Ordering::SeqCst, Ordering::SeqCst)
-            .is_ok()
-        {
-            break;
-        }
-    }
+/// Records requests that were refused because the client's privileges are too low.
+/// Must be layered inside of [`auth`], as it relies on the client it extracts.
+pub async fn audit_privilege_denials(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let request_info = RequestInfo::new(&state.config, request.headers(), request.extensions());
+    let client = request.extensions().get::<AuthClient>().copied();
+    let target = format!("{} {}", request.method(), request.uri().path());
+    let response = next.run(request).await;
 
-    let snapshot = Arc::new(snapshot);
-    for url in &state.config.webhooks {
-        tokio::spawn(post_to_webhook(url.clone(), Arc::clone(&snapshot)));
+    let is_denied = response
+        .extensions()
+        .get::<Arc<ApiError>>()
+        .is_some_and(|err| matches!(**err, ApiError::InsufficientPrivileges));
+    if is_denied {
+        let user_id = client.and_then(|client| client.id);
+        audit::record_detached(&state, &request_info, AuditEvent::PrivilegeDenial, user_id, Some(&target), None).await;
     }
+    response
 }
 
-/// Sends `snapshot` data to given `url`.
-async fn post_to_webhook(url: Url, snapshot: Arc<SnapshotInfo>) {
-    const APPLICATION_JSON: HeaderValue = HeaderValue::from_static("application/json");
-    let post = async || {
-        let mut headers = HeaderMap::new();
-        headers.insert(REFERER, HeaderValue::from_str(url.as_str())?);
-        headers.insert(CONTENT_TYPE, APPLICATION_JSON);
-
-        let client = Client::builder().default_headers(headers).build()?;
-        let response = client.post(url.clone()).json(&snapshot).send().await?;
-        response.error_for_status().map(|_| ()).map_err(ApiError::from)
-    };
-
-    if let Err(err) = post().await {
-        warn!("Could not post snapshot to {url}. Details:\n{err}");
+/// Wakes up webhook deliveries and the activity stream after modifying requests, so that any
+/// snapshots they created are sent out without waiting for the next scheduled check.
+pub async fn wake_up_snapshot_listeners(request: Request, next: Next) -> Response {
+    let can_modify_database = matches!(request.method(), &Method::POST | &Method::PUT | &Method::DELETE);
+    let response = next.run(request).await;
+    if can_modify_database {
+        webhook::wake_up();
+        activity::wake_up();
     }
+    response
 }
 
 #[cfg(test)]
@@ -1,13 +1,15 @@
 use crate::api::doc::ApiDoc;
 use crate::api::error::{ApiError, ApiResult};
 use crate::app::AppState;
-use crate::auth::Client;
+use crate::auth::{Client, totp};
 use crate::config::{Config, RegexType};
 use crate::model::enums::UserRank;
 use crate::string::SmallString;
+use crate::telemetry;
 use crate::time::DateTime;
 use axum::extract::DefaultBodyLimit;
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use diesel::PgConnection;
 use serde::{Deserialize, Deserializer};
 use std::time::Duration;
 use tower_http::timeout::TimeoutLayer;
@@ -15,22 +17,37 @@ use tower_http::trace::TraceLayer;
 use utoipa::OpenApi;
 use utoipa_axum::router::OpenApiRouter;
 
+mod activity;
+mod audit_log;
 mod comment;
+mod digest;
 mod doc;
+mod email_verification;
 pub mod error;
 mod info;
+mod invite_code;
 mod legacy;
+mod metrics;
 pub mod middleware;
+mod notification;
+mod oidc;
 mod password_reset;
 mod pool;
 mod pool_category;
 mod post;
+mod report;
 mod snapshot;
 mod tag;
 mod tag_category;
+mod totp;
 mod upload;
 mod user;
+mod user_note;
+mod user_sanction;
+mod user_session;
 mod user_token;
+mod webhook_delivery;
+mod wiki_page;
 
 pub fn routes(state: AppState) -> OpenApiRouter {
     let max_upload_size = usize::try_from(state.config.limits.max_upload_size).unwrap_or(usize::MAX);
@@ -38,26 +55,45 @@ pub fn routes(state: AppState) -> OpenApiRouter {
     let request_timeout = Duration::from_secs(state.config.limits.request_timeout_seconds);
 
     OpenApiRouter::with_openapi(ApiDoc::openapi())
+        .merge(activity::routes())
+        .merge(audit_log::routes())
         .merge(comment::routes())
+        .merge(digest::routes())
+        .merge(email_verification::routes())
         .merge(info::routes())
+        .merge(invite_code::routes())
         .merge(legacy::routes())
+        .merge(metrics::routes())
+        .merge(notification::routes())
+        .merge(oidc::routes())
         .merge(password_reset::routes())
         .merge(pool::routes())
         .merge(pool_category::routes())
         .merge(post::routes(upload_limit))
+        .merge(report::routes())
         .merge(snapshot::routes())
         .merge(tag::routes())
         .merge(tag_category::routes())
+        .merge(totp::routes())
         .merge(upload::routes(upload_limit))
         .merge(user::routes(upload_limit))
+        .merge(user_note::routes())
+        .merge(user_sanction::routes())
+        .merge(user_session::routes())
         .merge(user_token::routes())
+        .merge(webhook_delivery::routes())
+        .merge(wiki_page::routes())
         .layer((
-            TraceLayer::new_for_http(),
+            TraceLayer::new_for_http()
+                .make_span_with(telemetry::request_span)
+                .on_response(telemetry::record_response),
             TimeoutLayer::with_status_code(StatusCode::REQUEST_TIMEOUT, request_timeout),
         ))
+        .route_layer(axum::middleware::from_fn_with_state(state.clone(), middleware::audit_privilege_denials))
         .route_layer(axum::middleware::from_fn_with_state(state.clone(), middleware::auth))
-        .route_layer(axum::middleware::from_fn_with_state(state.clone(), middleware::post_to_webhooks))
+        .route_layer(axum::middleware::from_fn(middleware::wake_up_snapshot_listeners))
         .route_layer(axum::middleware::from_fn(middleware::log_error))
+        .route_layer(axum::middleware::from_fn(middleware::record_metrics))
         .with_state(state)
         .fallback(|| async { (StatusCode::NOT_FOUND, "Route not found") })
 }
@@ -107,6 +143,22 @@ fn verify_rank(client: Client, required_rank: UserRank) -> ApiResult<()> {
         .ok_or(ApiError::InsufficientPrivileges)
 }
 
+/// Checks that the `client` has passed two-factor authentication if they have enabled it.
+/// Clients that didn't authenticate with a password must send a code in the TOTP header.
+fn verify_second_factor(
+    conn: &mut PgConnection,
+    config: &Config,
+    client: Client,
+    headers: &HeaderMap,
+) -> ApiResult<()> {
+    let Some(user_id) = client.id.filter(|_| !client.verified_second_factor) else {
+        return Ok(());
+    };
+    let code = headers.get(totp::TOTP_HEADER).map(HeaderValue::to_str).transpose()?;
+    totp::verify_second_factor(conn, config, user_id, code, false)?;
+    Ok(())
+}
+
 // Any value that is present is considered Some value, including null.
 fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
 where
@@ -0,0 +1,292 @@
+use crate::api::doc::NOTIFICATION_TAG;
+use crate::api::error::{ApiError, ApiResult};
+use crate::app::AppState;
+use crate::config::Action;
+use crate::extract::{Ctx, Json, PageParams, Path, Query, ResourceParams};
+use crate::model::enums::{NotificationKind, ResourceType};
+use crate::model::notification::{MutedNotification, Notification};
+use crate::resource::notification::{Field, NotificationInfo};
+use crate::schema::{muted_notification, notification};
+use diesel::{ExpressionMethods, Insertable, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list))
+        .routes(routes!(read))
+        .routes(routes!(read_all))
+        .routes(routes!(get_settings, update_settings))
+}
+
+/// A page of the authenticated user's notifications.
+#[derive(Serialize, ToSchema)]
+struct NotificationPage {
+    /// The record starting offset, passed in the original request.
+    #[schema(examples(0))]
+    offset: u64,
+    /// Number of records on one page.
+    #[schema(examples(40))]
+    limit: u64,
+    /// How many notifications the user has in total.
+    #[schema(examples(12))]
+    total: u64,
+    /// How many of the user's notifications are unread.
+    #[schema(examples(3))]
+    unread: u64,
+    results: Vec<NotificationInfo>,
+}
+
+/// Lists notifications of the authenticated user, newest first.
+#[utoipa::path(
+    get,
+    path = "/notifications",
+    tag = NOTIFICATION_TAG,
+    params(ResourceParams, PageParams),
+    responses(
+        (status = 200, body = NotificationPage),
+        (status = 401, description = "Client is not logged in"),
+        (status = 403, description = "Privileges are too low"),
+    ),
+)]
+async fn list(
+    Ctx(ctx, connection_pool): Ctx,
+    Query(resource): Query<ResourceParams<Field>>,
+    Query(page): Query<PageParams>,
+) -> ApiResult<Json<NotificationPage>> {
+    ctx.verify_privilege(Action::NotificationView)?;
+    let user_id = ctx.client.id.ok_or(ApiError::NotLoggedIn)?;
+
+    let offset = page.offset.unwrap_or(0);
+    let limit = page.limit();
+    connection_pool
+        .transaction(move |conn| {
+            let user_notifications = notification::table.filter(notification::user_id.eq(user_id));
+            let total: i64 = user_notifications.count().first(conn)?;
+            let unread: i64 = user_notifications
+                .filter(notification::is_read.eq(false))
+                .count()
+                .first(conn)?;
+            let selected_notifications: Vec<Notification> = user_notifications
+                .select(Notification::as_select())
+                .order(notification::id.desc())
+                .offset(offset.try_into().unwrap_or(i64::MAX))
+                .limit(limit.try_into().unwrap_or(i64::MAX))
+                .load(conn)?;
+            Ok::<_, ApiError>(Json(NotificationPage {
+                offset,
+                limit,
+                total: u64::try_from(total).unwrap_or(0),
+                unread: u64::try_from(unread).unwrap_or(0),
+                results: NotificationInfo::new_batch(conn, &ctx.config, selected_notifications, resource.fields)?,
+            }))
+        })
+        .await
+}
+
+/// Marks a notification of the authenticated user as read.
+#[utoipa::path(
+    post,
+    path = "/notification/{id}/read",
+    tag = NOTIFICATION_TAG,
+    params(
+        ("id" = i64, Path, description = "Notification ID"),
+        ResourceParams,
+    ),
+    responses(
+        (status = 200, body = NotificationInfo),
+        (status = 401, description = "Client is not logged in"),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Notification does not exist"),
+    ),
+)]
+async fn read(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(notification_id): Path<i64>,
+    Query(params): Query<ResourceParams<Field>>,
+) -> ApiResult<Json<NotificationInfo>> {
+    ctx.verify_privilege(Action::NotificationView)?;
+    let user_id = ctx.client.id.ok_or(ApiError::NotLoggedIn)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            // Notifications of other users are reported as nonexistent
+            let user_notification = notification::table
+                .find(notification_id)
+                .filter(notification::user_id.eq(user_id));
+            let notification: Notification = diesel::update(user_notification)
+                .set(notification::is_read.eq(true))
+                .get_result(conn)
+                .optional()?
+                .ok_or(ApiError::NotFound(ResourceType::Notification))?;
+            NotificationInfo::new(conn, &ctx.config, notification, params.fields)
+                .map(Json)
+                .map_err(ApiError::from)
+        })
+        .await
+}
+
+/// Marks all notifications of the authenticated user as read.
+#[utoipa::path(
+    post,
+    path = "/notifications/read",
+    tag = NOTIFICATION_TAG,
+    responses(
+        (status = 200, body = Object),
+        (status = 401, description = "Client is not logged in"),
+        (status = 403, description = "Privileges are too low"),
+    ),
+)]
+async fn read_all(Ctx(ctx, connection_pool): Ctx) -> ApiResult<Json<()>> {
+    ctx.verify_privilege(Action::NotificationView)?;
+    let user_id = ctx.client.id.ok_or(ApiError::NotLoggedIn)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let unread_notifications = notification::table
+                .filter(notification::user_id.eq(user_id))
+                .filter(notification::is_read.eq(false));
+            diesel::update(unread_notifications)
+                .set(notification::is_read.eq(true))
+                .execute(conn)?;
+            Ok::<_, ApiError>(Json(()))
+        })
+        .await
+}
+
+/// Notification settings of a user.
+#[derive(Serialize, Deserialize, ToSchema)]
+struct NotificationSettings {
+    /// Kinds of events the user doesn't want to be notified about.
+    muted: Vec<NotificationKind>,
+}
+
+/// Retrieves notification settings of the authenticated user.
+#[utoipa::path(
+    get,
+    path = "/notification-settings",
+    tag = NOTIFICATION_TAG,
+    responses(
+        (status = 200, body = NotificationSettings),
+        (status = 401, description = "Client is not logged in"),
+        (status = 403, description = "Privileges are too low"),
+    ),
+)]
+async fn get_settings(Ctx(ctx, connection_pool): Ctx) -> ApiResult<Json<NotificationSettings>> {
+    ctx.verify_privilege(Action::NotificationView)?;
+    let user_id = ctx.client.id.ok_or(ApiError::NotLoggedIn)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            muted_notification::table
+                .select(muted_notification::kind)
+                .filter(muted_notification::user_id.eq(user_id))
+                .order(muted_notification::kind)
+                .load(conn)
+                .map(|muted| Json(NotificationSettings { muted }))
+        })
+        .await
+}
+
+/// Replaces notification settings of the authenticated user.
+///
+/// Notifications of muted kinds are no longer created for the user,
+/// but existing notifications are kept.
+#[utoipa::path(
+    put,
+    path = "/notification-settings",
+    tag = NOTIFICATION_TAG,
+    request_body = NotificationSettings,
+    responses(
+        (status = 200, body = NotificationSettings),
+        (status = 401, description = "Client is not logged in"),
+        (status = 403, description = "Privileges are too low"),
+    ),
+)]
+async fn update_settings(
+    Ctx(ctx, connection_pool): Ctx,
+    Json(body): Json<NotificationSettings>,
+) -> ApiResult<Json<NotificationSettings>> {
+    ctx.verify_privilege(Action::NotificationView)?;
+    let user_id = ctx.client.id.ok_or(ApiError::NotLoggedIn)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            diesel::delete(muted_notification::table)
+                .filter(muted_notification::user_id.eq(user_id))
+                .execute(conn)?;
+
+            let mut muted = body.muted;
+            muted.sort_by_key(|&kind| kind as i16);
+            muted.dedup();
+            let new_muted_notifications: Vec<_> =
+                muted.iter().map(|&kind| MutedNotification { user_id, kind }).collect();
+            new_muted_notifications
+                .insert_into(muted_notification::table)
+                .execute(conn)?;
+            Ok::<_, ApiError>(Json(NotificationSettings { muted }))
+        })
+        .await
+}
+
+#[cfg(test)]
+mod test {
+    use crate::api::error::ApiResult;
+    use crate::model::enums::UserRank;
+    use crate::test::*;
+    use serial_test::{parallel, serial};
+
+    // Exclude fields that involve creation_time
+    const FIELDS: &str = "&fields=id,kind,actor,post,commentId,isRead";
+
+    #[tokio::test]
+    #[serial]
+    async fn notify() -> ApiResult<()> {
+        const USER: UserRank = UserRank::Regular;
+
+        // Other tests may leave notifications behind, so start from a fresh database
+        reset_database();
+
+        verify_response("POST /comments/?fields=id", "notification/events/reply").await?;
+        verify_response("POST /post/2/favorite/?fields=id", "notification/events/favorite").await?;
+        verify_response_with_user(USER, "PUT /notification-settings", "notification/edit_settings/typical").await?;
+        verify_response("POST /post/3/favorite/?fields=id", "notification/events/muted_favorite").await?;
+
+        verify_response_with_user(USER, &format!("GET /notifications/?{FIELDS}"), "notification/list/typical").await?;
+        verify_response_with_user(
+            UserRank::Power,
+            &format!("GET /notifications/?{FIELDS}"),
+            "notification/list/mention",
+        )
+        .await?;
+        verify_response_with_user(USER, &format!("POST /notification/1/read/?{FIELDS}"), "notification/read/typical")
+            .await?;
+        verify_response_with_user(USER, "POST /notification/2/read", "notification/read/other_user").await?;
+        verify_response_with_user(USER, "POST /notifications/read", "notification/read_all/typical").await?;
+        verify_response_with_user(USER, "GET /notifications/?fields=id,isRead", "notification/list/read").await?;
+        verify_response_with_user(USER, "GET /notification-settings", "notification/get_settings/typical").await?;
+
+        reset_database();
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn error() -> ApiResult<()> {
+        verify_response("POST /notification/99/read", "notification/read/nonexistent").await?;
+        verify_response("PUT /notification-settings", "notification/edit_settings/invalid_kind").await
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn unauthorized() -> ApiResult<()> {
+        const USER: UserRank = UserRank::Anonymous;
+
+        verify_response_with_user(USER, "GET /notifications", "notification/list/unauthorized").await?;
+        verify_response_with_user(USER, "GET /notifications", "notification/list/anonymous").await?;
+        verify_response_with_user(USER, "POST /notifications/read", "notification/read_all/unauthorized").await?;
+        verify_response_with_user(USER, "GET /notification-settings", "notification/get_settings/unauthorized").await
+    }
+}
@@ -0,0 +1,479 @@
+use crate::api;
+use crate::api::doc::OIDC_TAG;
+use crate::api::error::{ApiError, ApiResult};
+use crate::app::AppState;
+use crate::audit::{self, RequestInfo};
+use crate::auth::oidc::{self, Identity};
+use crate::auth::{Client, header, password, session};
+use crate::config::{Config, RegexType};
+use crate::extract::{Ctx, Json, Query, ResourceParams};
+use crate::model::enums::{AuditEvent, AvatarStyle, ResourceProperty, UserRank};
+use crate::model::user::{NewUser, NewUserIdentity, NewUserToken};
+use crate::resource::user::MicroUser;
+use crate::resource::user_token::{Field, UserTokenInfo};
+use crate::schema::{user, user_identity, user_token};
+use crate::string::{SecretString, SmallString, lower};
+use crate::time::DateTime;
+use crate::update;
+use axum::http::header::SET_COOKIE;
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use axum::response::{AppendHeaders, Redirect};
+use diesel::dsl::exists;
+use diesel::{ExpressionMethods, Insertable, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+use uuid::Uuid;
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new().routes(routes!(login)).routes(routes!(callback))
+}
+
+/// Maximum number of suffixes tried when looking for a free name for a provisioned user.
+const MAX_NAME_ATTEMPTS: u32 = 100;
+
+type SetCookie = AppendHeaders<[(HeaderName, HeaderValue); 1]>;
+
+/// Redirects to the login page of the identity provider.
+///
+/// After logging in, the provider redirects the user to the configured `redirect_url`
+/// with `code` and `state` query parameters, which the client should pass on to the
+/// callback endpoint. The state expires after 10 minutes.
+///
+/// The response sets an `HttpOnly` cookie that binds the state to the browser, so the
+/// login can only be completed by the same browser. If the client is logged in, the state
+/// is also bound to the client, so the identity can only be linked to the client's account.
+#[utoipa::path(
+    get,
+    path = "/oidc/login",
+    tag = OIDC_TAG,
+    responses(
+        (status = 303, description = "Redirect to the identity provider"),
+        (status = 500, description = "Single sign-on is not configured"),
+        (status = 502, description = "Identity provider response is invalid"),
+    ),
+)]
+async fn login(Ctx(ctx, _): Ctx) -> ApiResult<(SetCookie, Redirect)> {
+    let oidc_config = ctx.config.oidc().ok_or(ApiError::MissingOidcInfo)?;
+    let metadata = oidc::discover(oidc_config).await?;
+    let login = oidc::create_state(&ctx.config, ctx.client.id, DateTime::now());
+    let url = oidc::authorization_url(oidc_config, &metadata, &login.state, &login.nonce);
+    let binding_cookie = oidc::binding_cookie(&ctx.config, &login.binding);
+    Ok((AppendHeaders([(SET_COOKIE, binding_cookie)]), Redirect::to(url.as_str())))
+}
+
+/// Request body for completing a single sign-on login.
+#[derive(Deserialize, ToSchema)]
+struct CallbackBody {
+    /// Authorization code issued by the identity provider.
+    code: String,
+    /// State returned by the identity provider.
+    state: String,
+}
+
+/// Completes a single sign-on login and creates a user token for the identified user.
+///
+/// If the client is logged in, the external identity is linked to the client's account.
+/// Otherwise, the user the identity is linked to is logged in. Identities that aren't
+/// linked to any user yet are given a new account with the default user rank. Its name
+/// is derived from the identity's preferred username or email address, and its email is
+/// taken from the identity if verified and not already in use.
+///
+/// If `group_ranks` are configured and the identity's groups map to a rank, the user's
+/// rank is set to the highest such rank on every login.
+///
+/// The request must carry the binding cookie set by the login endpoint and be made by the
+/// same client that started the login. Users that have enabled two-factor authentication
+/// must send a code in the `X-Totp-Code` header, as logging in through the provider
+/// doesn't pass their second factor.
+#[utoipa::path(
+    post,
+    path = "/oidc/callback",
+    tag = OIDC_TAG,
+    params(ResourceParams),
+    request_body = CallbackBody,
+    responses(
+        (status = 200, body = UserTokenInfo),
+        (status = 400, description = "State is invalid or has expired"),
+        (status = 401, description = "User has been banned"),
+        (status = 401, description = "TOTP code is missing or invalid"),
+        (status = 409, description = "Identity is linked to another user"),
+        (status = 500, description = "Single sign-on is not configured"),
+        (status = 502, description = "Identity provider response is invalid"),
+    ),
+)]
+async fn callback(
+    Ctx(ctx, connection_pool): Ctx,
+    Query(params): Query<ResourceParams<Field>>,
+    request_info: RequestInfo,
+    headers: HeaderMap,
+    Json(body): Json<CallbackBody>,
+) -> ApiResult<(SetCookie, Json<UserTokenInfo>)> {
+    let oidc_config = ctx.config.oidc().ok_or(ApiError::MissingOidcInfo)?;
+    let binding = session::find_cookie(&headers, oidc::BINDING_COOKIE);
+    let nonce = oidc::verify_state(&ctx.config, &body.state, binding, ctx.client.id, DateTime::now())?;
+    let metadata = oidc::discover(oidc_config).await?;
+    let identity = oidc::exchange_code(oidc_config, &metadata, &body.code, &nonce).await?;
+    let group_rank = identity
+        .groups
+        .as_ref()
+        .and_then(|groups| oidc_config.group_rank(groups.iter().map(String::as_str)));
+
+    let linked_user_id: Option<i64> = connection_pool
+        .transaction({
+            let issuer = identity.issuer.clone();
+            let subject = identity.subject.clone();
+            move |conn| {
+                user_identity::table
+                    .find((issuer, subject))
+                    .select(user_identity::user_id)
+                    .first(conn)
+                    .optional()
+            }
+        })
+        .await?;
+
+    if let (Some(linked_user_id), Some(client_id)) = (linked_user_id, ctx.client.id)
+        && linked_user_id != client_id
+    {
+        return Err(ApiError::AlreadyExists(ResourceProperty::UserIdentity));
+    }
+
+    let (user_token, name, lowercase_name, avatar_style) = connection_pool
+        .transaction({
+            let ctx = ctx.clone();
+            move |conn| {
+                let user_id = match (linked_user_id, ctx.client.id) {
+                    (Some(user_id), _) | (None, Some(user_id)) => user_id,
+                    (None, None) => provision_user(conn, &ctx.config, &identity)?,
+                };
+                if linked_user_id.is_none() {
+                    let new_identity = NewUserIdentity {
+                        issuer: &identity.issuer,
+                        subject: &identity.subject,
+                        user_id,
+                    };
+                    let insert_result = new_identity.insert_into(user_identity::table).execute(conn);
+                    api::error::map_unique_violation(insert_result, ResourceProperty::UserIdentity)?;
+                }
+                let mut rank_change = None;
+                if let Some(rank) = group_rank {
+                    let old_rank: UserRank = user::table.find(user_id).select(user::rank).first(conn)?;
+                    diesel::update(user::table.find(user_id))
+                        .set(user::rank.eq(rank))
+                        .execute(conn)?;
+                    rank_change = (rank != old_rank).then(|| audit::rank_change_details(old_rank, rank));
+                }
+
+                let (name, lowercase_name, rank, avatar_style): (SmallString, SmallString, UserRank, AvatarStyle) =
+                    user::table
+                        .find(user_id)
+                        .select((user::name, lower(user::name), user::rank, user::avatar_style))
+                        .first(conn)?;
+
+                // Logging in through the provider doesn't pass the second factor of the user
+                let client = match ctx.client.id {
+                    Some(_) => ctx.client,
+                    None => Client::new(Some(user_id), rank),
+                };
+                api::verify_second_factor(conn, &ctx.config, client, &headers)?;
+                header::apply_sanctions(conn, user_id, rank)?;
+                update::user::last_login_time(conn, user_id)?;
+
+                let user_token = NewUserToken {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    note: Some("Single sign-on"),
+                    enabled: true,
+                    expiration_time: None,
+                }
+                .insert_into(user_token::table)
+                .get_result(conn)?;
+                if let Some(details) = rank_change {
+                    let event = AuditEvent::RankChange;
+                    audit::record(conn, &request_info, event, Some(user_id), Some(&name), Some(&details))?;
+                }
+                audit::record(conn, &request_info, AuditEvent::Login, Some(user_id), None, None)?;
+                audit::record(conn, &request_info, AuditEvent::TokenCreation, Some(user_id), Some(&name), None)?;
+                Ok::<_, ApiError>((user_token, name, lowercase_name, avatar_style))
+            }
+        })
+        .await?;
+    let user_token_info =
+        UserTokenInfo::new(MicroUser::new(&ctx.config, name, &lowercase_name, avatar_style), user_token, params.fields);
+    let expired_cookie = oidc::expired_binding_cookie(&ctx.config);
+    Ok((AppendHeaders([(SET_COOKIE, expired_cookie)]), Json(user_token_info)))
+}
+
+/// Creates a new user for `identity` with the default user rank. Returns the id of the new user.
+fn provision_user(conn: &mut PgConnection, config: &Config, identity: &Identity) -> ApiResult<i64> {
+    // Provisioned users can only log in through the provider, so they are given a random password
+    let random_password = SecretString::from(Uuid::new_v4().to_string());
+    let (password_hash, password_salt) = password::hash_password(config, &random_password)?;
+
+    let name = available_name(conn, config, identity)?;
+    let email_taken = match identity.email.as_deref() {
+        Some(email) => {
+            diesel::select(exists(user::table.select(user::id).filter(user::email.eq(email)))).first(conn)?
+        }
+        None => false,
+    };
+    NewUser {
+        name: &name,
+        password_hash: password_hash.read(),
+        password_salt: password_salt.as_str(),
+        email: identity.email.as_deref().filter(|_| !email_taken),
+        rank: config.public_info.default_user_rank,
+        avatar_style: AvatarStyle::default(),
+    }
+    .insert_into(user::table)
+    .returning(user::id)
+    .get_result(conn)
+    .map_err(ApiError::from)
+}
+
+/// Returns a name for a user provisioned for `identity` that matches the username regex
+/// and isn't taken. Characters that usernames can't contain are dropped and a numeric
+/// suffix is appended if the name is taken.
+fn available_name(conn: &mut PgConnection, config: &Config, identity: &Identity) -> ApiResult<String> {
+    let base_name: String = identity
+        .username_hint()
+        .chars()
+        .filter(|&c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        .take(28)
+        .collect();
+    let base_name = if base_name.is_empty() {
+        String::from("user")
+    } else {
+        base_name
+    };
+
+    for attempt in 1..=MAX_NAME_ATTEMPTS {
+        let name = match attempt {
+            1 => base_name.clone(),
+            _ => format!("{base_name}_{attempt}"),
+        };
+        api::verify_matches_regex(config, &name, RegexType::Username)?;
+
+        let name_taken: bool =
+            diesel::select(exists(user::table.select(user::id).filter(user::name.eq(&name)))).first(conn)?;
+        if !name_taken {
+            return Ok(name);
+        }
+    }
+    Err(ApiError::AlreadyExists(ResourceProperty::UserName))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::api::error::ApiResult;
+    use crate::auth::{header, oidc};
+    use crate::config;
+    use crate::model::enums::UserRank;
+    use crate::model::user::NewUserTotp;
+    use crate::schema::{user, user_identity, user_totp};
+    use crate::test::*;
+    use crate::time::DateTime;
+    use axum::extract::Form;
+    use axum::http::StatusCode;
+    use axum::http::header::{AUTHORIZATION, COOKIE, LOCATION, SET_COOKIE};
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use axum_test::{TestResponse, TestServer};
+    use base64::Engine;
+    use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+    use diesel::{ExpressionMethods, Insertable, QueryDsl, RunQueryDsl};
+    use serde_json::{Value, json};
+    use serial_test::{parallel, serial};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+    use url::Url;
+
+    #[tokio::test]
+    #[serial]
+    async fn login_flow() -> ApiResult<()> {
+        let issuer = mock_provider().await;
+        let server = test_server_with_provider(&issuer);
+        let regular_user = header::basic_credentials_for("regular_user", TEST_PASSWORD);
+        let power_user = header::basic_credentials_for("power_user", TEST_PASSWORD);
+        let mut conn = get_connection()?;
+
+        // Unknown identities get a new account whose rank is synced with the identity's groups
+        let claims = json!({"sub": "new", "preferred_username": "sso_user", "groups": ["booru-mods"]});
+        let response = login(&server, &issuer, None, &claims).await;
+        response.assert_status_ok();
+        let body: Value = response.json();
+        assert_eq!(body["user"]["name"], "sso_user");
+        assert!(response.headers().get_all(SET_COOKIE).iter().any(|cookie| {
+            cookie
+                .to_str()
+                .unwrap()
+                .starts_with(&format!("{}=;", oidc::BINDING_COOKIE))
+        }));
+        let (provisioned_user_id, rank): (i64, UserRank) = user::table
+            .select((user::id, user::rank))
+            .filter(user::name.eq("sso_user"))
+            .first(&mut conn)?;
+        assert_eq!(rank, UserRank::Moderator);
+        let linked_user_id: i64 = user_identity::table
+            .find((&issuer, "new"))
+            .select(user_identity::user_id)
+            .first(&mut conn)?;
+        assert_eq!(linked_user_id, provisioned_user_id);
+
+        // Logging in again doesn't provision another user
+        let response = login(&server, &issuer, None, &claims).await;
+        response.assert_status_ok();
+        let body: Value = response.json();
+        assert_eq!(body["user"]["name"], "sso_user");
+
+        // Logged in clients link the identity to their account
+        let claims = json!({"sub": "linked"});
+        let response = login(&server, &issuer, Some(&regular_user), &claims).await;
+        response.assert_status_ok();
+        let body: Value = response.json();
+        assert_eq!(body["user"]["name"], "regular_user");
+        let linked_user_id: i64 = user_identity::table
+            .find((&issuer, "linked"))
+            .select(user_identity::user_id)
+            .first(&mut conn)?;
+        assert_eq!(linked_user_id, 2);
+
+        // Identities can't be linked to more than one user
+        let response = login(&server, &issuer, Some(&power_user), &claims).await;
+        response.assert_status(StatusCode::CONFLICT);
+
+        // States are bound to the client that started the login
+        let (state, nonce, cookie) = start_login(&server, None).await;
+        let response = complete_login(&server, &issuer, Some(&regular_user), &claims, &state, &nonce, &cookie).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        // States are bound to the browser that started the login
+        let (state, nonce, _) = start_login(&server, None).await;
+        let (_, _, other_cookie) = start_login(&server, None).await;
+        let response = complete_login(&server, &issuer, None, &claims, &state, &nonce, &other_cookie).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        // Users with two-factor authentication must pass their second factor
+        NewUserTotp {
+            user_id: provisioned_user_id,
+            secret: b"12345678901234567890",
+        }
+        .insert_into(user_totp::table)
+        .execute(&mut conn)?;
+        diesel::update(user_totp::table.find(provisioned_user_id))
+            .set(user_totp::enabled.eq(true))
+            .execute(&mut conn)?;
+        let claims = json!({"sub": "new"});
+        let response = login(&server, &issuer, None, &claims).await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+        let body: Value = response.json();
+        assert_eq!(body["name"], "MissingTotpCode");
+
+        reset_database();
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn error() -> ApiResult<()> {
+        verify_response("GET /oidc/login", "oidc/login/not_configured").await?;
+        verify_response("POST /oidc/callback", "oidc/callback/not_configured").await?;
+        verify_response("POST /oidc/callback", "oidc/callback/invalid_state").await
+    }
+
+    /// Serves a mock identity provider on a random local port. Returns its issuer URL.
+    /// Its token endpoint treats the authorization code as the ID token to issue.
+    async fn mock_provider() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        let discovery_document = json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{issuer}/authorize"),
+            "token_endpoint": format!("{issuer}/token"),
+        });
+        let router = Router::new()
+            .route("/.well-known/openid-configuration", get(move || async move { Json(discovery_document) }))
+            .route(
+                "/token",
+                post(
+                    |Form(form): Form<HashMap<String, String>>| async move { Json(json!({"id_token": form["code"]})) },
+                ),
+            );
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        issuer
+    }
+
+    fn test_server_with_provider(issuer: &str) -> TestServer {
+        let mut app_state = get_state();
+        let mut config = config::test_config(None);
+        config.data_dir = app_state.config.data_dir.clone();
+        config.oidc = Some(
+            serde_json::from_value(json!({
+                "issuer": issuer,
+                "client_id": "oxibooru",
+                "client_secret": "secret",
+                "redirect_url": "https://booru.example.com/oidc-callback",
+                "group_ranks": [{ "group": "booru-mods", "rank": "moderator" }],
+            }))
+            .unwrap(),
+        );
+        app_state.config = Arc::new(config);
+        test_server(app_state)
+    }
+
+    /// Logs in through the mock provider as the identity with the given `claims`.
+    async fn login(server: &TestServer, issuer: &str, credentials: Option<&str>, claims: &Value) -> TestResponse {
+        let (state, nonce, cookie) = start_login(server, credentials).await;
+        complete_login(server, issuer, credentials, claims, &state, &nonce, &cookie).await
+    }
+
+    /// Starts a login. Returns the state, the nonce, and the binding cookie.
+    async fn start_login(server: &TestServer, credentials: Option<&str>) -> (String, String, String) {
+        let mut request = server.get("/oidc/login");
+        if let Some(credentials) = credentials {
+            request = request.add_header(AUTHORIZATION, credentials);
+        }
+        let response = request.await;
+        response.assert_status(StatusCode::SEE_OTHER);
+
+        let location = Url::parse(response.headers()[LOCATION].to_str().unwrap()).unwrap();
+        let query_param = |name: &str| {
+            location
+                .query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+                .unwrap()
+        };
+        let set_cookie = response.headers()[SET_COOKIE].to_str().unwrap();
+        let cookie = set_cookie.split(';').next().unwrap().to_owned();
+        (query_param("state"), query_param("nonce"), cookie)
+    }
+
+    async fn complete_login(
+        server: &TestServer,
+        issuer: &str,
+        credentials: Option<&str>,
+        claims: &Value,
+        state: &str,
+        nonce: &str,
+        cookie: &str,
+    ) -> TestResponse {
+        let mut claims = claims.clone();
+        claims["iss"] = json!(issuer);
+        claims["aud"] = json!("oxibooru");
+        claims["exp"] = json!(DateTime::now().unix_timestamp() + 60);
+        claims["nonce"] = json!(nonce);
+        let header = BASE64_URL_SAFE_NO_PAD.encode(br#"{"alg":"RS256"}"#);
+        let payload = BASE64_URL_SAFE_NO_PAD.encode(claims.to_string());
+        let id_token = format!("{header}.{payload}.signature");
+
+        let mut request = server.post("/oidc/callback").add_header(COOKIE, cookie);
+        if let Some(credentials) = credentials {
+            request = request.add_header(AUTHORIZATION, credentials);
+        }
+        request.json(&json!({"code": id_token, "state": state})).await
+    }
+}
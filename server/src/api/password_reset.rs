@@ -1,20 +1,20 @@
 use crate::api::doc::PASSWORD_RESET_TAG;
 use crate::api::error::{ApiError, ApiResult};
 use crate::app::AppState;
+use crate::audit::{self, RequestInfo};
 use crate::auth::password;
 use crate::content::hash;
+use crate::email;
 use crate::extract::{Json, Path};
-use crate::model::enums::ResourceType;
+use crate::model::enums::{AuditEvent, ResourceType};
 use crate::schema::user;
 use crate::string::{SecretString, SmallString};
 use argon2::password_hash::rand_core::{OsRng, RngCore};
 use axum::extract::State;
 use diesel::{BoolExpressionMethods, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
-use lettre::Address;
 use lettre::message::Mailbox;
 use lettre::message::header::ContentType;
-use lettre::transport::smtp::authentication::Credentials;
-use lettre::{Message, SmtpTransport, Transport};
+use lettre::{Address, Message};
 use percent_encoding::NON_ALPHANUMERIC;
 use serde::ser::SerializeStruct;
 use serde::{Deserialize, Serialize, Serializer};
@@ -58,7 +58,11 @@ fn get_user_info(
         (status = 422, description = "User hasn't provided an email address"),
     ),
 )]
-async fn request_reset(State(state): State<AppState>, Path(identifier): Path<SmallString>) -> ApiResult<Json<()>> {
+async fn request_reset(
+    State(state): State<AppState>,
+    Path(identifier): Path<SmallString>,
+    request_info: RequestInfo,
+) -> ApiResult<Json<()>> {
     let smtp_info = state.config.smtp().ok_or(ApiError::MissingSmtpInfo)?;
 
     let mut conn = state.connection_pool.get().await?;
@@ -66,26 +70,15 @@ async fn request_reset(State(state): State<AppState>, Path(identifier): Path<Sma
     let user_email = user_email.ok_or(ApiError::NoEmail)?;
     let user_mailbox = Mailbox::new(None, Address::from_str(&user_email)?);
 
-    let domain = if let Some(domain) = state.config.domain.as_deref() {
-        domain
-    } else if let Some(domain) = state.env.http_origin.as_deref() {
-        domain
-    } else if let Some(domain) = state.env.http_referer.as_deref() {
-        domain
-    } else if let Some(port) = state.env.domain_port {
-        &format!("http://localhost:{port}")
-    } else {
-        ""
-    };
-    let domain = domain.trim_end_matches('/');
+    let domain = email::site_url(&state);
 
     let site_name = &state.config.public_info.name;
-    let username = percent_encoding::utf8_percent_encode(&username, NON_ALPHANUMERIC);
+    let encoded_username = percent_encoding::utf8_percent_encode(&username, NON_ALPHANUMERIC);
     let separator = percent_encoding::percent_encode_byte(b':');
     let reset_token = hash::compute_url_safe_hash(password_salt.as_bytes());
-    let url = format!("{domain}/password-reset/{username}{separator}{reset_token}");
+    let url = format!("{domain}/password-reset/{encoded_username}{separator}{reset_token}");
 
-    let email = Message::builder()
+    let reset_email = Message::builder()
         .from(smtp_info.from.clone())
         .to(user_mailbox)
         .subject(format!("Password reset for {site_name}"))
@@ -101,18 +94,11 @@ async fn request_reset(State(state): State<AppState>, Path(identifier): Path<Sma
             </html>"
         ))?;
 
-    // Open a remote connection to SMTP relay
-    let mut smtp_builder = SmtpTransport::relay(smtp_info.host.read())?;
-    if let (Some(smtp_username), Some(smtp_password)) = (smtp_info.username.as_ref(), smtp_info.password.as_ref()) {
-        let credentials = Credentials::new(smtp_username.read().to_owned(), smtp_password.read().to_owned());
-        smtp_builder = smtp_builder.credentials(credentials);
-    }
-    if let Some(port) = smtp_info.port {
-        smtp_builder = smtp_builder.port(port);
-    }
-    let mailer = smtp_builder.build();
+    email::send(smtp_info, &reset_email)?;
 
-    mailer.send(&email).map(|_| Json(())).map_err(ApiError::from)
+    let event = AuditEvent::PasswordResetRequest;
+    audit::record(conn.as_mut(), &request_info, event, None, Some(&username), None)?;
+    Ok(Json(()))
 }
 
 /// Token from password reset email.
@@ -166,6 +152,7 @@ fn generate_temporary_password(length: u8) -> SecretString {
 async fn reset_password(
     State(state): State<AppState>,
     Path(username): Path<SmallString>,
+    request_info: RequestInfo,
     Json(confirmation): Json<ResetToken>,
 ) -> ApiResult<Json<NewPassword>> {
     const TEMPORARY_PASSWORD_LENGTH: u8 = 16;
@@ -173,7 +160,7 @@ async fn reset_password(
     state
         .connection_pool
         .transaction(move |conn| {
-            let (user_id, _name, _email, password_salt) = get_user_info(conn, &username)?;
+            let (user_id, name, _email, password_salt) = get_user_info(conn, &username)?;
             if confirmation.token != hash::compute_url_safe_hash(password_salt.as_bytes()) {
                 return Err(ApiError::UnauthorizedPasswordReset);
             }
@@ -184,6 +171,7 @@ async fn reset_password(
             diesel::update(user::table.find(user_id))
                 .set((user::password_salt.eq(salt.as_str()), user::password_hash.eq(hash)))
                 .execute(conn)?;
+            audit::record(conn, &request_info, AuditEvent::PasswordReset, None, Some(&name), None)?;
 
             Ok(Json(NewPassword {
                 password: temporary_password,
@@ -1,9 +1,10 @@
 use crate::api::doc::POST_TAG;
 use crate::api::error::{self, ApiError, ApiResult};
 use crate::app::{AppState, Context};
-use crate::config::Action;
-use crate::content::hash::PostHash;
-use crate::content::signature::SignatureCache;
+use crate::config::{Action, AutoTagMode};
+use crate::content::auto_tag::Prediction;
+use crate::content::hash::{PostFileHash, PostHash};
+use crate::content::signature::{COMPRESSED_SIGNATURE_LEN, SignatureCache};
 use crate::content::thumbnail::{ThumbnailCategory, ThumbnailType};
 use crate::content::upload::{PartName, UploadToken};
 use crate::content::{Content, signature, upload};
@@ -12,29 +13,41 @@ use crate::extract::{
     Ctx, DeleteBody, Json, JsonOrMultipart, MergeBody, PageParams, PagedResponse, Path, Query, RatingBody,
     ResourceParams,
 };
-use crate::model::enums::{PostFlag, PostFlags, PostSafety, ResourceProperty, ResourceType, Score};
+use crate::model::enums::{
+    NotificationKind, PostFlag, PostFlags, PostSafety, PostStatus, ResourceProperty, ResourceType, Score,
+};
 use crate::model::post::{
-    NewPost, NewPostFavorite, NewPostFeature, NewPostScore, NewPostSignature, Post, PostSignature,
+    CompressedSignature, NewPost, NewPostFavorite, NewPostFeature, NewPostScore, NewPostSignature, Post, PostSignature,
+    PostTag,
 };
+use crate::model::tag::TagImplication;
+use crate::resource::field::Mask;
 use crate::resource::post::{Field, Note, PostInfo};
-use crate::schema::{post, post_favorite, post_feature, post_score, post_signature, post_statistics};
+use crate::resource::tag::{Field as TagField, TagInfo};
+use crate::schema::{
+    post, post_favorite, post_feature, post_file, post_score, post_signature, post_statistics, post_tag, tag_name,
+    tag_suggestion,
+};
 use crate::search::Builder;
 use crate::search::post::QueryBuilder;
-use crate::snapshot::post::SnapshotData;
+use crate::snapshot::post::{FileSnapshotData, SnapshotData};
 use crate::string::{LargeString, SmallString};
 use crate::time::DateTime;
 use crate::update::tag::FetchMode;
-use crate::{api, db, filesystem, snapshot, update};
+use crate::{api, db, filesystem, metrics, notification, snapshot, update};
 use axum::extract::DefaultBodyLimit;
+use diesel::deserialize::QueryableByName;
 use diesel::dsl::{exists, not};
+use diesel::sql_types::{Array, BigInt, Double, SmallInt};
 use diesel::{
-    ExpressionMethods, Insertable, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl, SaveChangesDsl,
-    SelectableHelper,
+    BoolExpressionMethods, ExpressionMethods, Insertable, NullableExpressionMethods, OptionalExtension, PgConnection,
+    QueryDsl, QueryResult, RunQueryDsl, SaveChangesDsl, SelectableHelper,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::sync::LazyLock;
 use tokio::sync::Mutex as AsyncMutex;
-use tracing::info;
+use tracing::{error, info};
 use url::Url;
 use utoipa::ToSchema;
 use utoipa_axum::router::OpenApiRouter;
@@ -45,18 +58,29 @@ pub fn routes(upload_limit: DefaultBodyLimit) -> OpenApiRouter<AppState> {
         .routes(routes!(reverse_search))
         .routes(routes!(create))
         .routes(routes!(update))
+        .routes(routes!(add_file, reorder_files))
         .route_layer(upload_limit);
     OpenApiRouter::new()
         .routes(routes!(list))
+        .routes(routes!(list_deleted))
         .routes(routes!(get, delete))
+        .routes(routes!(restore))
+        .routes(routes!(approve))
+        .routes(routes!(reject))
         .routes(routes!(get_neighbors))
+        .routes(routes!(get_tag_suggestions))
+        .routes(routes!(suggest_tags))
         .routes(routes!(get_featured, feature))
         .routes(routes!(merge))
         .routes(routes!(favorite, unfavorite))
         .routes(routes!(rate))
+        .routes(routes!(remove_file))
         .merge(upload_capable_routes)
 }
 
+const MAX_TAG_RECOMMENDATIONS: usize = 50;
+const MAX_CO_OCCURRENCE_POSTS: i64 = 1000;
+
 static POST_TAG_MUTEX: LazyLock<AsyncMutex<()>> = LazyLock::new(|| AsyncMutex::new(()));
 
 #[allow(dead_code)]
@@ -72,9 +96,18 @@ struct Multipart<T> {
     thumbnail: Option<String>,
 }
 
-fn verify_visibility(conn: &mut PgConnection, ctx: &Context, post_id: i64) -> ApiResult<()> {
-    let post_exists: bool = diesel::select(exists(post::table.find(post_id))).first(conn)?;
-    if !post_exists {
+pub fn verify_visibility(conn: &mut PgConnection, ctx: &Context, post_id: i64) -> ApiResult<()> {
+    let (status, uploader_id): (PostStatus, Option<i64>) = post::table
+        .find(post_id)
+        .select((post::status, post::user_id))
+        .filter(post::deletion_time.is_null())
+        .first(conn)
+        .optional()?
+        .ok_or(ApiError::NotFound(ResourceType::Post))?;
+
+    // Pending posts are only visible to their uploader and those who can approve them
+    let is_uploader = ctx.client.id.is_some() && ctx.client.id == uploader_id;
+    if status == PostStatus::Pending && !is_uploader && !ctx.has_privilege(Action::PostApprove) {
         return Err(ApiError::NotFound(ResourceType::Post));
     }
 
@@ -113,6 +146,9 @@ where
 
 /// Searches for posts.
 ///
+/// Posts awaiting approval are excluded unless the client is allowed to approve posts
+/// and explicitly searches for them with the `status` token.
+///
 /// **Anonymous tokens**
 ///
 /// Same as `tag` token.
@@ -136,8 +172,11 @@ where
 /// | `note-count`                                                 | having given number of annotations                                      |
 /// | `note-text`                                                  | having given note text (accepts wildcards)                              |
 /// | `relation-count`                                             | having given number of relations                                        |
+/// | `parent`                                                     | having parent post with given ID                                        |
+/// | `child`                                                      | having child post with given ID                                         |
 /// | `feature-count`                                              | having been featured given number of times                              |
 /// | `type`                                                       | type of posts (can be either `image`, `animation`, `flash`, or `video`) |
+/// | `status`                                                     | having given status (can be `approved`, `pending`, or `rejected`)       |
 /// | `content-checksum`                                           | having given BLAKE3 checksum                                            |
 /// | `flag`                                                       | having given flag (can be either `loop` or `sound`)                     |
 /// | `source`                                                     | having given source                                                     |
@@ -169,6 +208,7 @@ where
 /// | `relation-count`                                             | with most relations                              |
 /// | `feature-count`                                              | most often featured                              |
 /// | `type`                                                       | grouped by content type                          |
+/// | `status`                                                     | grouped by approval status                       |
 /// | `flag`                                                       | grouped by flags                                 |
 /// | `source`                                                     | sorted by source                                 |
 /// | `file-size`                                                  | largest files first                              |
@@ -191,6 +231,7 @@ where
 /// | `disliked`   | posts disliked by currently logged in user           |
 /// | `fav`        | posts added to favorites by currently logged in user |
 /// | `tumbleweed` | posts without ratings, comments, or favorites        |
+/// | `has-parent` | posts that have a parent post                        |
 #[utoipa::path(
     get,
     path = "/posts",
@@ -228,6 +269,48 @@ async fn list(
         .await
 }
 
+/// Searches for deleted posts.
+///
+/// Accepts the same query syntax as post search. Results are restricted to posts
+/// that are currently in the trash.
+#[utoipa::path(
+    get,
+    path = "/deleted-posts",
+    tag = POST_TAG,
+    params(ResourceParams, PageParams),
+    responses(
+        (status = 200, body = PagedResponse<PostInfo>),
+        (status = 403, description = "Privileges are too low"),
+    ),
+)]
+async fn list_deleted(
+    Ctx(ctx, connection_pool): Ctx,
+    Query(resource): Query<ResourceParams<Field>>,
+    Query(page): Query<PageParams>,
+) -> ApiResult<Json<PagedResponse<PostInfo>>> {
+    ctx.verify_privilege(Action::PostView)?;
+    ctx.verify_privilege(Action::PostListDeleted)?;
+
+    let offset = page.offset.unwrap_or(0);
+    let limit = page.limit();
+    connection_pool
+        .transaction(move |conn| {
+            let mut query_builder = QueryBuilder::new(&ctx, resource.criteria())?;
+            query_builder.set_deleted(true);
+            query_builder.set_offset_and_limit(offset, limit);
+
+            let (total, selected_posts) = query_builder.list(conn)?;
+            Ok::<_, ApiError>(Json(PagedResponse {
+                query: resource.query,
+                offset,
+                limit,
+                total,
+                results: PostInfo::new_batch_from_ids(conn, &ctx, &selected_posts, resource.fields)?,
+            }))
+        })
+        .await
+}
+
 /// Retrieves information about an existing post.
 #[utoipa::path(
     get,
@@ -396,6 +479,9 @@ async fn get_featured(
         .transaction(move |conn| {
             let mut featured = post_feature::table
                 .select(post_feature::post_id)
+                .inner_join(post::table)
+                .filter(post::deletion_time.is_null())
+                .filter(post::status.eq(PostStatus::Approved))
                 .order(post_feature::time.desc())
                 .into_boxed();
 
@@ -476,6 +562,44 @@ async fn feature(
         .map(Json)
 }
 
+/// Finds posts whose content or files look like the image with the given `signature`.
+/// Returns the id of each post within the configured similarity threshold along with its distance,
+/// sorted from most to least similar. Deleted and unapproved posts are excluded, as is `excluded_post`.
+fn find_similar_posts(
+    conn: &mut PgConnection,
+    ctx: &Context,
+    signature: &[i64; COMPRESSED_SIGNATURE_LEN],
+    excluded_post: Option<i64>,
+) -> QueryResult<Vec<(i64, f64)>> {
+    // Search for similar images candidates among both post contents and post files
+    let indexes = signature::generate_indexes(signature);
+    let mut similar_signature_candidates = PostSignature::find_similar_candidates(conn, &indexes)?;
+    similar_signature_candidates.extend(PostSignature::find_similar_file_candidates(conn, &indexes)?);
+    info!("Found {} similar signatures", similar_signature_candidates.len());
+    let candidate_count = u32::try_from(similar_signature_candidates.len()).unwrap_or(u32::MAX);
+    metrics::REVERSE_SEARCH_CANDIDATES.observe([], f64::from(candidate_count));
+
+    // Filter candidates based on similarity score, keeping only the closest match for each post
+    let content_signature_cache = SignatureCache::new(signature);
+    let mut closest_matches: HashMap<i64, f64> = HashMap::new();
+    for post_signature in similar_signature_candidates
+        .into_iter()
+        .filter(|post_signature| Some(post_signature.post_id) != excluded_post)
+    {
+        let distance = signature::distance(&content_signature_cache, &post_signature.signature);
+        let distance_threshold = 1.0 - ctx.config.post_similarity_threshold;
+        if distance < distance_threshold {
+            closest_matches
+                .entry(post_signature.post_id)
+                .and_modify(|closest_distance| *closest_distance = closest_distance.min(distance))
+                .or_insert(distance);
+        }
+    }
+    let mut similar_posts: Vec<_> = closest_matches.into_iter().collect();
+    similar_posts.sort_unstable_by(|(id_a, dist_a), (id_b, dist_b)| dist_a.total_cmp(dist_b).then(id_a.cmp(id_b)));
+    Ok(similar_posts)
+}
+
 async fn reverse_search_impl(
     ctx: Ctx,
     params: ResourceParams<Field>,
@@ -490,33 +614,25 @@ async fn reverse_search_impl(
         .transaction(move |conn| {
             let _timer = crate::time::Timer::new("reverse search");
 
-            // Check for exact match
+            // Check for exact match, which can be either the content of a post or one of its files
+            let exact_file_owner = post_file::table
+                .select(post_file::post_id)
+                .filter(post_file::checksum.eq(content_properties.checksum))
+                .single_value();
             let exact_post: Option<Post> = post::table
-                .filter(post::checksum.eq(content_properties.checksum))
+                .filter(
+                    post::checksum
+                        .eq(content_properties.checksum)
+                        .or(post::id.nullable().eq(exact_file_owner)),
+                )
+                .filter(post::deletion_time.is_null())
+                .filter(post::status.eq(PostStatus::Approved))
                 .first(conn)
                 .optional()?;
+            let exact_post_id = exact_post.as_ref().map(|post| post.id);
+            let similar_posts = find_similar_posts(conn, &ctx, &content_properties.signature, exact_post_id)?;
 
-            // Search for similar images candidates
-            let similar_signature_candidates = PostSignature::find_similar_candidates(
-                conn,
-                &signature::generate_indexes(&content_properties.signature),
-            )?;
-            info!("Found {} similar signatures", similar_signature_candidates.len());
-
-            // Filter candidates based on similarity score
-            let content_signature_cache = SignatureCache::new(&content_properties.signature);
-            let mut similar_signatures: Vec<_> = similar_signature_candidates
-                .into_iter()
-                .filter(|post_signature| Some(post_signature.post_id) != exact_post.as_ref().map(|post| post.id))
-                .filter_map(|post_signature| {
-                    let distance = signature::distance(&content_signature_cache, &post_signature.signature);
-                    let distance_threshold = 1.0 - ctx.config.post_similarity_threshold;
-                    (distance < distance_threshold).then_some((post_signature.post_id, distance))
-                })
-                .collect();
-            similar_signatures.sort_unstable_by(|(_, dist_a), (_, dist_b)| dist_a.total_cmp(dist_b));
-
-            let (post_ids, distances): (Vec<_>, Vec<_>) = similar_signatures.into_iter().unzip();
+            let (post_ids, distances): (Vec<_>, Vec<_>) = similar_posts.into_iter().unzip();
             Ok::<_, ApiError>(ReverseSearchResponse {
                 exact_post: exact_post
                     .map(|post| PostInfo::new(conn, &ctx, post, params.fields))
@@ -564,6 +680,8 @@ struct ReverseSearchResponse {
 }
 
 /// Retrieves posts that look like the input image.
+///
+/// The content of a post and each of its files are compared separately.
 #[utoipa::path(
     post,
     path = "/posts/reverse-search",
@@ -608,6 +726,241 @@ async fn reverse_search(
     }
 }
 
+/// A tag recommended for a post along with its relevance score.
+#[derive(Serialize, ToSchema)]
+struct TagRecommendation {
+    /// The recommended tag.
+    tag: TagInfo,
+    /// Relevance score. Higher is more relevant.
+    score: f64,
+}
+
+/// Response containing recommended tags.
+#[derive(Serialize, ToSchema)]
+struct TagRecommendations {
+    /// Recommended tags sorted by score.
+    results: Vec<TagRecommendation>,
+}
+
+/// Ranks candidate tags for a post that has the tags `tag_ids` and looks like `similar_posts`.
+///
+/// Each candidate accumulates score from three sources. For each of `tag_ids`, it receives the
+/// fraction of recent listed posts with that tag which also have the candidate. It receives one point for each
+/// of `tag_ids` that explicitly suggests it. Finally, each similar post tagged with the candidate
+/// contributes its similarity relative to the similarity threshold. Tags that are already present,
+/// implied by present tags or hidden by the client's preferences are never recommended.
+fn recommend_tags(
+    conn: &mut PgConnection,
+    ctx: &Context,
+    tag_ids: &[i64],
+    similar_posts: &[(i64, f64)],
+    predicted_tags: &[Prediction],
+    fields: Mask<TagField>,
+) -> ApiResult<TagRecommendations> {
+    let mut scores: HashMap<i64, f64> = HashMap::new();
+
+    // Only the most recent listed posts of each tag are sampled, so that common tags stay cheap
+    let co_occurrences: Vec<TagScore> = diesel::sql_query(
+        "SELECT sibling.tag_id, SUM(1.0 / LEAST(tag_statistics.usage_count, $3))::FLOAT8 AS score
+        FROM tag_statistics
+        CROSS JOIN LATERAL (
+            SELECT post_tag.post_id
+            FROM post_tag
+            INNER JOIN post ON post.id = post_tag.post_id
+            WHERE post_tag.tag_id = tag_statistics.tag_id AND post.deletion_time IS NULL AND post.status = $2
+            ORDER BY post_tag.post_id DESC
+            LIMIT $3
+        ) AS sampled_post
+        INNER JOIN post_tag AS sibling ON sibling.post_id = sampled_post.post_id
+        WHERE tag_statistics.tag_id = ANY($1) AND sibling.tag_id <> ALL($1)
+        GROUP BY sibling.tag_id",
+    )
+    .bind::<Array<BigInt>, _>(tag_ids)
+    .bind::<SmallInt, _>(PostStatus::Approved)
+    .bind::<BigInt, _>(MAX_CO_OCCURRENCE_POSTS)
+    .load(conn)?;
+    for TagScore { tag_id, score } in co_occurrences {
+        *scores.entry(tag_id).or_default() += score;
+    }
+
+    let suggested_ids: Vec<i64> = tag_suggestion::table
+        .select(tag_suggestion::child_id)
+        .filter(tag_suggestion::parent_id.eq_any(tag_ids))
+        .load(conn)?;
+    for tag_id in suggested_ids {
+        *scores.entry(tag_id).or_default() += 1.0;
+    }
+
+    let distance_threshold = 1.0 - ctx.config.post_similarity_threshold;
+    let similarities: HashMap<i64, f64> = similar_posts
+        .iter()
+        .map(|&(post_id, distance)| (post_id, 1.0 - distance / distance_threshold))
+        .collect();
+    let similar_post_ids: Vec<i64> = similarities.keys().copied().collect();
+    let similar_post_tags: Vec<PostTag> = post_tag::table
+        .filter(post_tag::post_id.eq_any(&similar_post_ids))
+        .load(conn)?;
+    for PostTag { post_id, tag_id } in similar_post_tags {
+        *scores.entry(tag_id).or_default() += similarities[&post_id];
+    }
+
+    for prediction in predicted_tags {
+        let tag_id: Option<i64> = tag_name::table
+            .select(tag_name::tag_id)
+            .filter(tag_name::name.eq(&prediction.tag))
+            .first(conn)
+            .optional()?;
+        if let Some(tag_id) = tag_id {
+            *scores.entry(tag_id).or_default() += f64::from(prediction.confidence);
+        }
+    }
+
+    let mut excluded_ids: HashSet<i64> = tag_ids.iter().copied().collect();
+    excluded_ids.extend(
+        TagImplication::descendants(conn, tag_ids)?
+            .into_iter()
+            .map(|implication| implication.child_id),
+    );
+    if let Some(hidden_tags) = ctx.preferences().hidden_tags() {
+        excluded_ids.extend(hidden_tags.load::<i64>(conn)?);
+    }
+
+    let mut ranked: Vec<_> = scores
+        .into_iter()
+        .filter(|(tag_id, _)| !excluded_ids.contains(tag_id))
+        .collect();
+    ranked.sort_unstable_by(|(id_a, score_a), (id_b, score_b)| score_b.total_cmp(score_a).then(id_a.cmp(id_b)));
+    ranked.truncate(MAX_TAG_RECOMMENDATIONS);
+
+    let (recommended_ids, scores): (Vec<_>, Vec<_>) = ranked.into_iter().unzip();
+    let results = TagInfo::new_batch_from_ids(conn, &recommended_ids, fields)?
+        .into_iter()
+        .zip(scores)
+        .map(|(tag, score)| TagRecommendation { tag, score })
+        .collect();
+    Ok(TagRecommendations { results })
+}
+
+#[derive(QueryableByName)]
+struct TagScore {
+    #[diesel(sql_type = BigInt)]
+    tag_id: i64,
+    #[diesel(sql_type = Double)]
+    score: f64,
+}
+
+/// Recommends tags for an existing post.
+///
+/// Candidates are ranked by how often they appear together with the post's
+/// current tags, by explicit suggestions of the post's current tags, and by
+/// the tags of visually similar posts. Tags the post already has, tags implied
+/// by them and hidden tags are excluded. The list is truncated to the first 50
+/// elements. Doesn't use paging.
+#[utoipa::path(
+    get,
+    path = "/post/{id}/tag-suggestions",
+    tag = POST_TAG,
+    params(
+        ("id" = i64, Path, description = "Post ID"),
+        ResourceParams,
+    ),
+    responses(
+        (status = 200, body = TagRecommendations),
+        (status = 403, description = "Privileges are too low"),
+        (status = 403, description = "Post is hidden"),
+        (status = 404, description = "Post does not exist"),
+    ),
+)]
+async fn get_tag_suggestions(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(post_id): Path<i64>,
+    Query(params): Query<ResourceParams<TagField>>,
+) -> ApiResult<Json<TagRecommendations>> {
+    ctx.verify_privilege(Action::PostView)?;
+    ctx.verify_privilege(Action::TagList)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            verify_visibility(conn, &ctx, post_id)?;
+
+            let tag_ids: Vec<i64> = post_tag::table
+                .select(post_tag::tag_id)
+                .filter(post_tag::post_id.eq(post_id))
+                .load(conn)?;
+            let signature: Option<CompressedSignature> = post_signature::table
+                .find(post_id)
+                .select(post_signature::signature)
+                .first(conn)
+                .optional()?;
+            let similar_posts = match signature {
+                Some(signature) => find_similar_posts(conn, &ctx, &signature, Some(post_id))?,
+                None => Vec::new(),
+            };
+            recommend_tags(conn, &ctx, &tag_ids, &similar_posts, &[], params.fields).map(Json)
+        })
+        .await
+}
+
+/// Request body for recommending tags for new content.
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct TagSuggestionBody {
+    /// Token referencing previously uploaded content.
+    #[schema(value_type = String)]
+    content_token: UploadToken,
+    /// Names of tags already chosen for the content.
+    #[serde(default)]
+    tags: Vec<SmallString>,
+}
+
+/// Recommends tags for previously uploaded content.
+///
+/// Works like retrieving tag suggestions for an existing post, except that
+/// the post's current tags are given by `tags` and visually similar posts are
+/// found by comparing against the uploaded content. Meant to speed up tagging
+/// new uploads before they are turned into posts.
+#[utoipa::path(
+    post,
+    path = "/posts/tag-suggestions",
+    tag = POST_TAG,
+    params(ResourceParams),
+    request_body = TagSuggestionBody,
+    responses(
+        (status = 200, body = TagRecommendations),
+        (status = 403, description = "Privileges are too low"),
+        (status = 422, description = "Upload token is invalid"),
+    ),
+)]
+async fn suggest_tags(
+    ctx: Ctx,
+    Query(params): Query<ResourceParams<TagField>>,
+    Json(body): Json<TagSuggestionBody>,
+) -> ApiResult<Json<TagRecommendations>> {
+    ctx.verify_privilege(Action::PostView)?;
+    ctx.verify_privilege(Action::PostReverseSearch)?;
+    ctx.verify_privilege(Action::TagList)?;
+
+    let content = Content::Token(body.content_token);
+    let content_properties = content.compute_properties(ctx.clone()).await?;
+
+    let Ctx(ctx, connection_pool) = ctx;
+    connection_pool
+        .transaction(move |conn| {
+            let tag_ids: Vec<i64> = tag_name::table
+                .select(tag_name::tag_id)
+                .filter(tag_name::name.eq_any(&body.tags))
+                .distinct()
+                .load(conn)?;
+            let similar_posts = find_similar_posts(conn, &ctx, &content_properties.signature, None)?;
+            let predicted_tags: &[Prediction] = match ctx.config.auto_tag() {
+                Some(config) if config.mode == AutoTagMode::Suggest => content_properties.predicted_tags.as_slice(),
+                _ => &[],
+            };
+            recommend_tags(conn, &ctx, &tag_ids, &similar_posts, predicted_tags, params.fields).map(Json)
+        })
+        .await
+}
+
 async fn create_impl(ctx: Ctx, params: ResourceParams<Field>, body: PostCreateBody) -> ApiResult<Json<PostInfo>> {
     let identified_upload = !body.anonymous.unwrap_or(false);
     let action = if identified_upload {
@@ -626,16 +979,42 @@ async fn create_impl(ctx: Ctx, params: ResourceParams<Field>, body: PostCreateBo
         None => None,
     };
     let flags = content_properties.flags | PostFlags::from_slice(&body.flags.unwrap_or_default());
+    let status = if ctx.has_privilege(Action::PostCreateUnmoderated) {
+        PostStatus::Approved
+    } else {
+        PostStatus::Pending
+    };
+
+    let predicted_tags: Vec<SmallString> = match ctx.config.auto_tag() {
+        Some(config) if config.mode == AutoTagMode::Apply => content_properties
+            .predicted_tags
+            .iter()
+            .map(|prediction| prediction.tag.clone())
+            .collect(),
+        _ => Vec::new(),
+    };
+    let tags_updated = body.tags.is_some() || !predicted_tags.is_empty();
 
     let Ctx(ctx, connection_pool) = ctx;
-    let post_id = tagging_update(&connection_pool, body.tags.is_some(), {
+    let post_id = tagging_update(&connection_pool, tags_updated, {
         let ctx = ctx.clone();
         move |conn| {
+            // Predicted tags are only applied if they already exist
+            let mut tag_names = body.tags.unwrap_or_default();
+            if !predicted_tags.is_empty() {
+                let predicted_names: Vec<SmallString> = tag_name::table
+                    .select(tag_name::name)
+                    .filter(tag_name::name.eq_any(&predicted_tags))
+                    .load(conn)?;
+                tag_names.extend(predicted_names);
+            }
+
             // We do this before post insertion so that the post sequence isn't incremented if it fails
-            let (tag_ids, tags) =
-                update::tag::get_or_create_tags(conn, &ctx, body.tags.unwrap_or_default(), FetchMode::Deep)?;
+            let (tag_ids, tags) = update::tag::get_or_create_tags(conn, &ctx, tag_names, FetchMode::Deep)?;
             let relations = body.relations.unwrap_or_default();
             let notes = body.notes.unwrap_or_default();
+            update::post::verify_unique_checksum(conn, content_properties.checksum)?;
+            update::post::verify_unique_file_checksum(conn, content_properties.checksum)?;
 
             let post: Post = NewPost {
                 user_id: (identified_upload).then_some(ctx.client.id).flatten(),
@@ -650,6 +1029,7 @@ async fn create_impl(ctx: Ctx, params: ResourceParams<Field>, body: PostCreateBo
                 flags,
                 source: body.source.as_deref().unwrap_or(""),
                 description: body.description.as_deref().unwrap_or(""),
+                status,
             }
             .insert_into(post::table)
             .on_conflict(post::checksum)
@@ -662,6 +1042,10 @@ async fn create_impl(ctx: Ctx, params: ResourceParams<Field>, body: PostCreateBo
             // Add tags, relations, and notes
             update::post::set_tags(conn, post.id, &tag_ids)?;
             update::post::add_relations(conn, post.id, &relations)?;
+            if let Some(parent_id) = body.parent {
+                verify_visibility(conn, &ctx, parent_id)?;
+            }
+            update::post::set_parent(conn, post.id, body.parent)?;
             update::post::set_notes(conn, post.id, &notes)?;
 
             NewPostSignature {
@@ -701,6 +1085,7 @@ async fn create_impl(ctx: Ctx, params: ResourceParams<Field>, body: PostCreateBo
                 description: post.description,
                 tags,
                 relations,
+                parent: body.parent,
                 notes,
                 featured: false,
             };
@@ -737,6 +1122,8 @@ struct PostCreateBody {
     description: Option<String>,
     /// IDs of related posts.
     relations: Option<Vec<i64>>,
+    /// ID of the parent post.
+    parent: Option<i64>,
     /// If true, the uploader name won't be recorded.
     anonymous: Option<bool>,
     /// Tags to apply. Non-existent tags will be created automatically.
@@ -911,9 +1298,14 @@ async fn favorite(
             move |conn| {
                 verify_visibility(conn, &ctx, post_id)?;
 
-                diesel::delete(post_favorite::table.find((post_id, user_id))).execute(conn)?;
+                let previously_favorited =
+                    diesel::delete(post_favorite::table.find((post_id, user_id))).execute(conn)? > 0;
                 let insert_result = new_post_favorite.insert_into(post_favorite::table).execute(conn);
-                error::map_foreign_key_violation(insert_result, ResourceType::Post)
+                error::map_foreign_key_violation(insert_result, ResourceType::Post)?;
+                if !previously_favorited {
+                    notification::notify_uploader(conn, ctx.client, post_id, NotificationKind::Favorite)?;
+                }
+                Ok::<_, ApiError>(())
             }
         })
         .await?;
@@ -1045,6 +1437,15 @@ async fn update_impl(
                 update::post::set_relations(conn, &ctx, post_id, &mut relations)?;
                 new_snapshot_data.relations = relations;
             }
+            if let Some(parent) = body.parent {
+                ctx.verify_privilege(Action::PostEditRelation)?;
+                if let Some(parent_id) = parent {
+                    verify_visibility(conn, &ctx, parent_id)?;
+                }
+
+                update::post::set_parent(conn, post_id, parent)?;
+                new_snapshot_data.parent = parent;
+            }
             if let Some(tags) = body.tags {
                 ctx.verify_privilege(Action::PostEditTag)?;
 
@@ -1065,6 +1466,10 @@ async fn update_impl(
             }
             if let Some(content_properties) = new_content {
                 ctx.verify_privilege(Action::PostEditContent)?;
+                if content_properties.checksum != new_post.checksum {
+                    update::post::verify_unique_checksum(conn, content_properties.checksum)?;
+                }
+                update::post::verify_unique_file_checksum(conn, content_properties.checksum)?;
 
                 new_snapshot_data.checksum = content_properties.checksum;
 
@@ -1138,6 +1543,9 @@ struct PostUpdateBody {
     description: Option<LargeString>,
     /// IDs of related posts.
     relations: Option<Vec<i64>>,
+    /// ID of the parent post. Set to null to remove.
+    #[serde(default, deserialize_with = "api::deserialize_some")]
+    parent: Option<Option<i64>>,
     /// Tags to apply. Non-existent tags will be created automatically.
     tags: Option<Vec<SmallString>>,
     /// Post annotations.
@@ -1217,8 +1625,233 @@ async fn update(
     }
 }
 
+async fn add_file_impl(
+    ctx: Ctx,
+    post_id: i64,
+    params: ResourceParams<Field>,
+    body: PostFileCreateBody,
+) -> ApiResult<Json<PostInfo>> {
+    let content =
+        Content::new(body.content_token, body.content_url).ok_or(ApiError::MissingContent(ResourceType::PostFile))?;
+    let content_properties = content.remove_or_compute_properties(ctx.clone()).await?;
+
+    let Ctx(ctx, connection_pool) = ctx;
+    connection_pool
+        .transaction({
+            let ctx = ctx.clone();
+            move |conn| {
+                verify_visibility(conn, &ctx, post_id)?;
+
+                let last_edit_time: DateTime = post::table.find(post_id).select(post::last_edit_time).first(conn)?;
+                api::verify_version(last_edit_time, body.version)?;
+
+                let old_files = FileSnapshotData::retrieve(conn, post_id)?;
+                update::post::add_file(conn, &ctx.config, post_id, content_properties)?;
+                let new_files = FileSnapshotData::retrieve(conn, post_id)?;
+                snapshot::post::files_snapshot(conn, ctx.client, post_id, old_files, new_files)
+            }
+        })
+        .await?;
+    connection_pool
+        .transaction(move |conn| PostInfo::new_from_id(conn, &ctx, post_id, params.fields))
+        .await
+        .map(Json)
+}
+
+/// Request body for adding a file to a post.
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct PostFileCreateBody {
+    /// Resource version. See [versioning](#Versioning).
+    version: DateTime,
+    /// Token referencing previously uploaded content.
+    #[schema(value_type = Option<String>)]
+    content_token: Option<UploadToken>,
+    /// URL to fetch content from.
+    content_url: Option<Url>,
+}
+
+/// Appends a content file to an existing post.
+///
+/// Each file has its own checksum, dimensions, and thumbnail and can be found
+/// through reverse search. The post content itself stays first and is used as
+/// the representative thumbnail of the post, unless another file is made
+/// representative when reordering files. For details on how to pass
+/// `content`, see [file uploads](#Upload).
+#[utoipa::path(
+    post,
+    path = "/post/{id}/files",
+    tag = POST_TAG,
+    params(
+        ("id" = i64, Path, description = "Post ID"),
+        ResourceParams,
+    ),
+    request_body(
+        content(
+            (PostFileCreateBody = "application/json"),
+            (Multipart<PostFileCreateBody> = "multipart/form-data"),
+        )
+    ),
+    responses(
+        (status = 200, body = PostInfo),
+        (status = 400, description = "File content is missing"),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Post does not exist"),
+        (status = 409, description = "Version is outdated"),
+        (status = 409, description = "Post content already exists"),
+    ),
+)]
+async fn add_file(
+    ctx: Ctx,
+    Path(post_id): Path<i64>,
+    Query(params): Query<ResourceParams<Field>>,
+    body: JsonOrMultipart<PostFileCreateBody>,
+) -> ApiResult<Json<PostInfo>> {
+    ctx.verify_privilege(Action::PostView)?;
+    ctx.verify_privilege(Action::PostEditContent)?;
+
+    match body {
+        JsonOrMultipart::Json(payload) => add_file_impl(ctx, post_id, params, payload).await,
+        JsonOrMultipart::Multipart(payload) => {
+            let decoded_body = upload::extract(&ctx.config, payload, [PartName::Content]).await?;
+            let metadata = decoded_body.metadata.ok_or(ApiError::MissingMetadata)?;
+            let mut new_file: PostFileCreateBody = serde_json::from_slice(&metadata)?;
+            let [content_token] = decoded_body.files;
+
+            new_file.content_token = content_token;
+            add_file_impl(ctx, post_id, params, new_file).await
+        }
+    }
+}
+
+/// Request body for reordering the files of a post.
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct PostFileOrderBody {
+    /// Resource version. See [versioning](#Versioning).
+    version: DateTime,
+    /// IDs of the post files in their new order.
+    files: Vec<i64>,
+    /// ID of a post file to swap with the post content, making it the representative content of the post.
+    representative: Option<i64>,
+}
+
+/// Reorders the files of an existing post.
+///
+/// Files are placed in the order given by `files`. Files that are not listed
+/// keep their relative order and are placed after the listed ones. If
+/// `representative` is given, the content of that file is swapped with the
+/// post content, making it the representative content and thumbnail of the
+/// post. The previous post content takes the place of that file.
+#[utoipa::path(
+    put,
+    path = "/post/{id}/files",
+    tag = POST_TAG,
+    params(
+        ("id" = i64, Path, description = "Post ID"),
+        ResourceParams,
+    ),
+    request_body = PostFileOrderBody,
+    responses(
+        (status = 200, body = PostInfo),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Post does not exist"),
+        (status = 404, description = "Files refer to files not owned by the post"),
+        (status = 409, description = "Version is outdated"),
+    ),
+)]
+async fn reorder_files(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(post_id): Path<i64>,
+    Query(params): Query<ResourceParams<Field>>,
+    Json(body): Json<PostFileOrderBody>,
+) -> ApiResult<Json<PostInfo>> {
+    ctx.verify_privilege(Action::PostView)?;
+    ctx.verify_privilege(Action::PostEditContent)?;
+
+    connection_pool
+        .transaction({
+            let ctx = ctx.clone();
+            move |conn| {
+                verify_visibility(conn, &ctx, post_id)?;
+
+                let last_edit_time: DateTime = post::table.find(post_id).select(post::last_edit_time).first(conn)?;
+                api::verify_version(last_edit_time, body.version)?;
+
+                let old_files = FileSnapshotData::retrieve(conn, post_id)?;
+                update::post::reorder_files(conn, post_id, &body.files)?;
+                if let Some(post_file_id) = body.representative {
+                    update::post::set_representative_file(conn, &ctx.config, post_id, post_file_id)?;
+                }
+                let new_files = FileSnapshotData::retrieve(conn, post_id)?;
+                snapshot::post::files_snapshot(conn, ctx.client, post_id, old_files, new_files)
+            }
+        })
+        .await?;
+    connection_pool
+        .transaction(move |conn| PostInfo::new_from_id(conn, &ctx, post_id, params.fields))
+        .await
+        .map(Json)
+}
+
+/// Removes a file from an existing post.
+#[utoipa::path(
+    delete,
+    path = "/post/{id}/files/{file_id}",
+    tag = POST_TAG,
+    params(
+        ("id" = i64, Path, description = "Post ID"),
+        ("file_id" = i64, Path, description = "Post file ID"),
+    ),
+    request_body = DeleteBody,
+    responses(
+        (status = 200, body = Object),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Post does not exist"),
+        (status = 404, description = "Post file does not exist"),
+        (status = 409, description = "Version is outdated"),
+    ),
+)]
+async fn remove_file(
+    Ctx(ctx, connection_pool): Ctx,
+    Path((post_id, post_file_id)): Path<(i64, i64)>,
+    Json(client_version): Json<DeleteBody>,
+) -> ApiResult<Json<()>> {
+    ctx.verify_privilege(Action::PostView)?;
+    ctx.verify_privilege(Action::PostEditContent)?;
+
+    let post_file = connection_pool
+        .transaction({
+            let ctx = ctx.clone();
+            move |conn| {
+                verify_visibility(conn, &ctx, post_id)?;
+
+                let last_edit_time: DateTime = post::table.find(post_id).select(post::last_edit_time).first(conn)?;
+                api::verify_version(last_edit_time, *client_version)?;
+
+                let old_files = FileSnapshotData::retrieve(conn, post_id)?;
+                let post_file = update::post::remove_file(conn, post_id, post_file_id)?;
+                let new_files = FileSnapshotData::retrieve(conn, post_id)?;
+                snapshot::post::files_snapshot(conn, ctx.client, post_id, old_files, new_files)?;
+                Ok::<_, ApiError>(post_file)
+            }
+        })
+        .await?;
+
+    // The file is already gone from the database, so a failure to delete its content is only logged
+    if ctx.config.delete_source_files {
+        let post_file_hash = PostFileHash::new(&ctx.config, post_file.id);
+        if let Err(err) = filesystem::delete_post_file(&post_file_hash, post_file.mime_type) {
+            error!("Could not delete content of removed post file {} for reason: {err}", post_file.id);
+        }
+    }
+    Ok(Json(()))
+}
+
 /// Deletes existing post.
 ///
+/// Deleted posts are moved to the trash, where they can be restored by moderators. They are
+/// permanently purged once they have been in the trash for longer than the configured retention period.
 /// Related posts and tags are kept.
 #[utoipa::path(
     delete,
@@ -1240,52 +1873,168 @@ async fn delete(
     Path(post_id): Path<i64>,
     Json(client_version): Json<DeleteBody>,
 ) -> ApiResult<Json<()>> {
-    // Post relation cascade deletion can cause deadlocks when deleting related posts in quick
-    // succession, so we lock an aysnchronous mutex when deleting if the post has any relations.
-    static ANTI_DEADLOCK_MUTEX: LazyLock<AsyncMutex<()>> = LazyLock::new(|| AsyncMutex::new(()));
-
     ctx.verify_privilege(Action::PostDelete)?;
 
-    let relation_count: i64 = post_statistics::table
-        .find(post_id)
-        .select(post_statistics::relation_count)
-        .first(connection_pool.get().await?.as_mut())
-        .optional()?
-        .ok_or(ApiError::NotFound(ResourceType::Post))?;
+    connection_pool
+        .transaction(move |conn| {
+            verify_visibility(conn, &ctx, post_id)?;
 
-    let _lock;
-    if relation_count > 0 {
-        _lock = ANTI_DEADLOCK_MUTEX.lock().await;
-    }
+            let post: Post = post::table.find(post_id).first(conn)?;
+            api::verify_version(post.last_edit_time, *client_version)?;
+
+            let post_data = SnapshotData::retrieve(conn, post)?;
+            snapshot::post::deletion_snapshot(conn, ctx.client, post_id, post_data)?;
+
+            diesel::update(post::table.find(post_id))
+                .set(post::deletion_time.eq(DateTime::now()))
+                .execute(conn)?;
+            Ok::<_, ApiError>(Json(()))
+        })
+        .await
+}
+
+/// Restores a deleted post from the trash.
+#[utoipa::path(
+    post,
+    path = "/post/{id}/restore",
+    tag = POST_TAG,
+    params(
+        ("id" = i64, Path, description = "Post ID"),
+        ResourceParams,
+    ),
+    responses(
+        (status = 200, body = PostInfo),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Deleted post does not exist"),
+    ),
+)]
+async fn restore(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(post_id): Path<i64>,
+    Query(params): Query<ResourceParams<Field>>,
+) -> ApiResult<Json<PostInfo>> {
+    ctx.verify_privilege(Action::PostView)?;
+    ctx.verify_privilege(Action::PostRestore)?;
 
-    let (mime_type, custom_thumbnail_size) = connection_pool
+    connection_pool
         .transaction({
             let ctx = ctx.clone();
             move |conn| {
-                verify_visibility(conn, &ctx, post_id)?;
-
                 let post: Post = post::table
                     .find(post_id)
+                    .filter(post::deletion_time.is_not_null())
                     .first(conn)
                     .optional()?
                     .ok_or(ApiError::NotFound(ResourceType::Post))?;
-                api::verify_version(post.last_edit_time, *client_version)?;
 
-                let mime_type = post.mime_type;
-                let custom_thumbnail_size = post.custom_thumbnail_size;
-                let post_data = SnapshotData::retrieve(conn, post)?;
-                snapshot::post::deletion_snapshot(conn, ctx.client, post_id, post_data)?;
+                // Restored rejected posts return to the approval queue
+                let status = match post.status {
+                    PostStatus::Rejected => PostStatus::Pending,
+                    status => status,
+                };
+                diesel::update(post::table.find(post_id))
+                    .set((post::status.eq(status), post::deletion_time.eq(None::<DateTime>)))
+                    .execute(conn)?;
 
-                diesel::delete(post::table.find(post_id)).execute(conn)?;
-                Ok::<_, ApiError>((mime_type, custom_thumbnail_size))
+                let old_status = post.status;
+                let post_data = SnapshotData::retrieve(conn, post)?;
+                snapshot::post::restoration_snapshot(conn, ctx.client, post_id, post_data)?;
+                snapshot::post::status_snapshot(conn, ctx.client, post_id, old_status, status).map_err(ApiError::from)
             }
         })
         .await?;
-    if ctx.config.delete_source_files {
-        let post_hash = PostHash::new(&ctx.config, post_id, Some(custom_thumbnail_size));
-        filesystem::delete_post(&post_hash, mime_type)?;
-    }
-    Ok(Json(()))
+    connection_pool
+        .transaction(move |conn| PostInfo::new_from_id(conn, &ctx, post_id, params.fields))
+        .await
+        .map(Json)
+}
+
+/// Approves a post that is awaiting moderation, making it publicly visible.
+#[utoipa::path(
+    post,
+    path = "/post/{id}/approve",
+    tag = POST_TAG,
+    params(
+        ("id" = i64, Path, description = "Post ID"),
+        ResourceParams,
+    ),
+    responses(
+        (status = 200, body = PostInfo),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Pending post does not exist"),
+    ),
+)]
+async fn approve(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(post_id): Path<i64>,
+    Query(params): Query<ResourceParams<Field>>,
+) -> ApiResult<Json<PostInfo>> {
+    ctx.verify_privilege(Action::PostView)?;
+    ctx.verify_privilege(Action::PostApprove)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let pending_post = post::table
+                .find(post_id)
+                .filter(post::deletion_time.is_null())
+                .filter(post::status.eq(PostStatus::Pending));
+            let updated_rows = diesel::update(pending_post)
+                .set(post::status.eq(PostStatus::Approved))
+                .execute(conn)?;
+            if updated_rows == 0 {
+                return Err(ApiError::NotFound(ResourceType::Post));
+            }
+            snapshot::post::status_snapshot(conn, ctx.client, post_id, PostStatus::Pending, PostStatus::Approved)?;
+            notification::notify_uploader(conn, ctx.client, post_id, NotificationKind::PostApproved)?;
+            PostInfo::new_from_id(conn, &ctx, post_id, params.fields)
+                .map(Json)
+                .map_err(ApiError::from)
+        })
+        .await
+}
+
+/// Rejects a post that is awaiting moderation.
+///
+/// Rejected posts are moved to the trash in the same way as deleted posts.
+/// Restoring a rejected post returns it to the approval queue.
+#[utoipa::path(
+    post,
+    path = "/post/{id}/reject",
+    tag = POST_TAG,
+    params(
+        ("id" = i64, Path, description = "Post ID"),
+    ),
+    responses(
+        (status = 200, body = Object),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Pending post does not exist"),
+    ),
+)]
+async fn reject(Ctx(ctx, connection_pool): Ctx, Path(post_id): Path<i64>) -> ApiResult<Json<()>> {
+    ctx.verify_privilege(Action::PostView)?;
+    ctx.verify_privilege(Action::PostReject)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let post: Post = post::table
+                .find(post_id)
+                .filter(post::deletion_time.is_null())
+                .filter(post::status.eq(PostStatus::Pending))
+                .first(conn)
+                .optional()?
+                .ok_or(ApiError::NotFound(ResourceType::Post))?;
+
+            let post_data = SnapshotData::retrieve(conn, post)?;
+            snapshot::post::status_snapshot(conn, ctx.client, post_id, PostStatus::Pending, PostStatus::Rejected)?;
+            snapshot::post::deletion_snapshot(conn, ctx.client, post_id, post_data)?;
+
+            diesel::update(post::table.find(post_id))
+                .set((post::status.eq(PostStatus::Rejected), post::deletion_time.eq(DateTime::now())))
+                .execute(conn)?;
+            notification::notify_uploader(conn, ctx.client, post_id, NotificationKind::PostRejected)?;
+            Ok::<_, ApiError>(Json(()))
+        })
+        .await
 }
 
 /// Unmarks the post as favorite for authenticated user.
@@ -1332,13 +2081,17 @@ async fn unfavorite(
 #[cfg(test)]
 mod test {
     use crate::api::error::ApiResult;
+    use crate::content::hash::{Checksum, PostFileHash, PostHash};
     use crate::filesystem::Directory;
-    use crate::model::enums::{ResourceType, UserRank};
+    use crate::model::enums::{MimeType, PostStatus, ResourceType, UserRank};
     use crate::model::post::Post;
-    use crate::schema::{post, post_feature, post_statistics, tag, tag_name, user, user_statistics};
+    use crate::schema::{
+        post, post_feature, post_file, post_statistics, tag, tag_name, tag_statistics, user, user_statistics,
+    };
     use crate::search::post::Token;
     use crate::test::*;
     use crate::time::DateTime;
+    use crate::update;
     use diesel::dsl::exists;
     use diesel::{ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl, SelectableHelper};
     use serial_test::{parallel, serial};
@@ -1376,7 +2129,8 @@ mod test {
         verify_response(&format!("{QUERY}=pool:*a*t* {PARAMS}"), "post/list/pool_name_wildcards_filtered").await?;
         verify_response(&format!("{QUERY}=special:liked {PARAMS}"), "post/list/liked_filtered").await?;
         verify_response(&format!("{QUERY}=special:disliked {PARAMS}"), "post/list/disliked_filtered").await?;
-        verify_response(&format!("{QUERY}=special:tumbleweed {PARAMS}"), "post/list/tumbleweed_filtered").await
+        verify_response(&format!("{QUERY}=special:tumbleweed {PARAMS}"), "post/list/tumbleweed_filtered").await?;
+        verify_response(&format!("{QUERY}=special:has-parent {PARAMS}"), "post/list/has-parent_filtered").await
     }
 
     #[tokio::test]
@@ -1400,6 +2154,12 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    #[parallel]
+    async fn get_hierarchy() -> ApiResult<()> {
+        verify_response("GET /post/3/?fields=id,parent,children", "post/get/hierarchy").await
+    }
+
     #[tokio::test]
     #[parallel]
     async fn get_neighbors() -> ApiResult<()> {
@@ -1454,6 +2214,15 @@ mod test {
         verify_response(&format!("POST /posts/reverse-search/?{FIELDS}"), "post/reverse_search/typical").await
     }
 
+    #[tokio::test]
+    #[parallel]
+    async fn tag_suggestions() -> ApiResult<()> {
+        verify_response("GET /post/3/tag-suggestions/?fields=names", "post/tag_suggestions/typical").await?;
+
+        simulate_upload("1_pixel.png", "upload_for_tag_suggestions.png")?;
+        verify_response("POST /posts/tag-suggestions/?fields=names", "post/suggest_tags/typical").await
+    }
+
     #[tokio::test]
     #[serial]
     async fn create() -> ApiResult<()> {
@@ -1468,6 +2237,18 @@ mod test {
         assert!(thumbnail_path.exists());
 
         verify_response("DELETE /post/6", "post/delete/typical").await?;
+        verify_response(&format!("GET /post/6/?{FIELDS}"), "post/get/deleted").await?;
+        verify_response("GET /deleted-posts/?query=-sort:id&limit=40&fields=id", "post/list_deleted/typical").await?;
+        verify_response("POST /post/6/restore/?fields=id", "post/restore/typical").await?;
+        verify_response("POST /post/6/restore/?fields=id", "post/restore/not_deleted").await?;
+        verify_response("DELETE /post/6", "post/delete/typical").await?;
+
+        simulate_upload("1_pixel.png", "in_trash.png")?;
+        verify_response("POST /posts", "post/create/in_trash").await?;
+
+        // Purge deleted post so that its content can be uploaded again
+        let (purged_posts, _) = update::post::purge_deleted(&mut get_connection()?, DateTime::now())?;
+        assert_eq!(purged_posts.len(), 1);
 
         simulate_upload("1_pixel.png", "cool_post.png")?;
         verify_response(&format!("POST /posts/?{FIELDS}"), "post/create/anonymous").await?;
@@ -1480,6 +2261,94 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn files() -> ApiResult<()> {
+        simulate_upload("1_pixel.png", "post_file.png")?;
+        verify_response("POST /post/1/files/?fields=id,files", "post/add_file/typical").await?;
+        simulate_upload("gradient.png", "second_post_file.png")?;
+        verify_response("POST /post/1/files/?fields=id,files", "post/add_file/second").await?;
+
+        let state = get_state();
+        let post_file_path = PostFileHash::new(&state.config, 1).content_path(MimeType::Png);
+        let post_file_thumbnail_path = PostFileHash::new(&state.config, 1).thumbnail_path();
+        assert!(post_file_path.exists());
+        assert!(post_file_thumbnail_path.exists());
+
+        simulate_upload("1_pixel.png", "duplicate.png")?;
+        verify_response("POST /post/2/files", "post/add_file/duplicate").await?;
+        verify_response("POST /posts", "post/create/duplicate").await?;
+        verify_response("POST /posts/reverse-search/?fields=id", "post/reverse_search/file").await?;
+
+        verify_response("PUT /post/1/files/?fields=id,files", "post/reorder_files/typical").await?;
+        verify_response("PUT /post/1/files", "post/reorder_files/nonexistent").await?;
+        verify_response("DELETE /post/1/files/1", "post/remove_file/typical").await?;
+        verify_response("DELETE /post/1/files/1", "post/remove_file/nonexistent").await?;
+        assert!(!post_file_path.exists());
+        assert!(!post_file_thumbnail_path.exists());
+
+        reset_database();
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn representative_file() -> ApiResult<()> {
+        let get_checksums = |conn: &mut PgConnection| -> QueryResult<(Checksum, Checksum)> {
+            post::table
+                .inner_join(post_file::table)
+                .select((post::checksum, post_file::checksum))
+                .filter(post::id.eq(1))
+                .first(conn)
+        };
+
+        simulate_upload("1_pixel.png", "post_file.png")?;
+        verify_response("POST /post/1/files/?fields=id,files", "post/add_file/typical").await?;
+
+        let mut conn = get_connection()?;
+        let (post_checksum, file_checksum) = get_checksums(&mut conn)?;
+        verify_response("PUT /post/1/files/?fields=id", "post/reorder_files/representative").await?;
+        assert_eq!(get_checksums(&mut conn)?, (file_checksum, post_checksum));
+
+        let state = get_state();
+        let post: Post = post::table.find(1).select(Post::as_select()).first(&mut conn)?;
+        let post_hash = PostHash::new(&state.config, post.id, Some(post.custom_thumbnail_size));
+        assert!(post_hash.content_path(post.mime_type).exists());
+        assert!(post_hash.generated_thumbnail_path().exists());
+        assert!(PostFileHash::new(&state.config, 1).thumbnail_path().exists());
+
+        reset_database();
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn approval() -> ApiResult<()> {
+        const USER: UserRank = UserRank::Regular;
+
+        simulate_upload("1_pixel.png", "pending_post.png")?;
+        verify_response_with_user(USER, "POST /posts/?fields=id,status", "post/create/pending").await?;
+        verify_response("GET /posts/?query=id:6&fields=id", "post/list/pending_excluded").await?;
+        verify_response("GET /posts/?query=status:pending&fields=id,status", "post/list/pending").await?;
+        verify_response_with_user(USER, "GET /post/6/?fields=id,status", "post/get/pending_uploader").await?;
+        verify_response_with_user(UserRank::Power, "GET /post/6/?fields=id", "post/get/pending").await?;
+
+        verify_response("POST /post/6/approve/?fields=id,status", "post/approve/typical").await?;
+        verify_response("POST /post/6/approve", "post/approve/not_pending").await?;
+        verify_response("POST /post/6/reject", "post/reject/not_pending").await?;
+
+        // Return post to the approval queue so that it can be rejected
+        diesel::update(post::table.find(6))
+            .set(post::status.eq(PostStatus::Pending))
+            .execute(&mut get_connection()?)?;
+        verify_response("POST /post/6/reject", "post/reject/typical").await?;
+        verify_response_with_user(UserRank::Power, "GET /post/6/?fields=id", "post/get/pending").await?;
+        verify_response("POST /post/6/restore/?fields=id,status", "post/restore/rejected").await?;
+
+        reset_database();
+        Ok(())
+    }
+
     #[tokio::test]
     #[serial]
     async fn merge() -> ApiResult<()> {
@@ -1656,6 +2525,50 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn delete_unlists() -> ApiResult<()> {
+        let get_usage_count = |conn: &mut PgConnection| -> QueryResult<i64> {
+            tag::table
+                .inner_join(tag_name::table)
+                .inner_join(tag_statistics::table)
+                .select(tag_statistics::usage_count)
+                .filter(tag_name::name.eq("george_lucas"))
+                .first(conn)
+        };
+
+        let mut conn = get_connection()?;
+        assert_eq!(get_usage_count(&mut conn)?, 1);
+
+        verify_response("DELETE /post/2", "post/delete/typical").await?;
+        verify_response("DELETE /post/4", "post/delete/typical").await?;
+        verify_response("GET /post/1/?fields=id,relations", "post/get/unlisted_relations").await?;
+        verify_response("GET /post/3/?fields=id,parent,children", "post/get/unlisted_hierarchy").await?;
+        verify_response("PUT /post/1", "post/edit/deleted_parent").await?;
+        verify_response("GET /pool/4/?fields=id,posts", "pool/get/unlisted_posts").await?;
+        simulate_upload("starry_night.png", "upload_for_unlisted_tag_suggestions.png")?;
+        verify_response("POST /posts/tag-suggestions", "post/suggest_tags/unlisted").await?;
+        assert_eq!(get_usage_count(&mut conn)?, 0);
+
+        verify_response("POST /post/2/restore/?fields=id", "post/restore/unlisted").await?;
+        assert_eq!(get_usage_count(&mut conn)?, 1);
+
+        reset_database();
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn edit_parent() -> ApiResult<()> {
+        const PARAMS: &str = "?fields=id,parent,children";
+        verify_response(&format!("PUT /post/1/{PARAMS}"), "post/edit/parent").await?;
+        verify_response(&format!("GET /post/5/{PARAMS}"), "post/get/children").await?;
+        verify_response(&format!("PUT /post/1/{PARAMS}"), "post/edit/remove_parent").await?;
+
+        reset_database();
+        Ok(())
+    }
+
     #[tokio::test]
     #[serial]
     async fn preferences() -> ApiResult<()> {
@@ -1753,6 +2666,9 @@ mod test {
         verify_response("PUT /post/1", "post/edit/invalid_thumbnail_token").await?;
         verify_response("PUT /post/1", "post/edit/duplicate_relation").await?;
         verify_response("PUT /post/1", "post/edit/nonexistent_relation").await?;
+        verify_response("PUT /post/1", "post/edit/nonexistent_parent").await?;
+        verify_response("PUT /post/1", "post/edit/self_parent").await?;
+        verify_response("PUT /post/5", "post/edit/cyclic_parent").await?;
         verify_response("PUT /post/1", "post/edit/content_too_wide").await?;
         verify_response("PUT /post/1", "post/edit/content_too_tall").await?;
         verify_response("PUT /post/1", "post/edit/content_too_large").await?;
@@ -1774,11 +2690,14 @@ mod test {
 
         simulate_upload("1_pixel.png", "upload.png")?;
         verify_response_with_user(USER, "GET /posts?limit=1", "post/list/unauthorized").await?;
+        verify_response_with_user(USER, "GET /deleted-posts?limit=1", "post/list_deleted/unauthorized").await?;
         verify_response_with_user(USER, "GET /post/1", "post/get/unauthorized").await?;
         verify_response_with_user(USER, "GET /post/1/around", "post/get_around/unauthorized").await?;
         verify_response_with_user(USER, "GET /featured-post", "post/get_featured/unauthorized").await?;
         verify_response_with_user(USER, "POST /featured-post", "post/feature/unauthorized").await?;
         verify_response_with_user(USER, "POST /posts/reverse-search", "post/reverse_search/unauthorized").await?;
+        verify_response_with_user(USER, "GET /post/1/tag-suggestions", "post/tag_suggestions/unauthorized").await?;
+        verify_response_with_user(USER, "POST /posts/tag-suggestions", "post/suggest_tags/unauthorized").await?;
         verify_response_with_user(USER, "POST /posts", "post/create/anonymous_unauthorized").await?;
         verify_response_with_user(USER, "POST /posts", "post/create/identified_unauthorized").await?;
         verify_response_with_user(USER, "POST /post-merge", "post/merge/unauthorized").await?;
@@ -1789,11 +2708,18 @@ mod test {
         verify_response_with_user(USER, "PUT /post/1", "post/edit/flag_unauthorized").await?;
         verify_response_with_user(USER, "PUT /post/1", "post/edit/note_unauthorized").await?;
         verify_response_with_user(USER, "PUT /post/1", "post/edit/relation_unauthorized").await?;
+        verify_response_with_user(USER, "PUT /post/1", "post/edit/parent_unauthorized").await?;
         verify_response_with_user(USER, "PUT /post/1", "post/edit/safety_unauthorized").await?;
         verify_response_with_user(USER, "PUT /post/1", "post/edit/source_unauthorized").await?;
         verify_response_with_user(USER, "PUT /post/1", "post/edit/tag_unauthorized").await?;
         verify_response_with_user(USER, "PUT /post/1", "post/edit/thumbnail_unauthorized").await?;
+        verify_response_with_user(USER, "POST /post/1/files", "post/add_file/unauthorized").await?;
+        verify_response_with_user(USER, "PUT /post/1/files", "post/reorder_files/unauthorized").await?;
+        verify_response_with_user(USER, "DELETE /post/1/files/1", "post/remove_file/unauthorized").await?;
         verify_response_with_user(USER, "DELETE /post/1", "post/delete/unauthorized").await?;
+        verify_response_with_user(USER, "POST /post/1/restore", "post/restore/unauthorized").await?;
+        verify_response_with_user(USER, "POST /post/1/approve", "post/approve/unauthorized").await?;
+        verify_response_with_user(USER, "POST /post/1/reject", "post/reject/unauthorized").await?;
         verify_response_with_user(USER, "DELETE /post/1/favorite", "post/unfavorite/unauthorized").await?;
 
         // Ensure users can't get around lack of view privileges via other actions
@@ -0,0 +1,427 @@
+use crate::api;
+use crate::api::doc::REPORT_TAG;
+use crate::api::error::{self, ApiError, ApiResult};
+use crate::app::{AppState, Context};
+use crate::config::Action;
+use crate::db::AsyncConnectionPool;
+use crate::extract::{Ctx, Json, PageParams, PagedResponse, Path, Query, ResourceParams};
+use crate::model::enums::{ReportStatus, ResourceProperty, ResourceType};
+use crate::model::report::{NewReport, Report};
+use crate::resource::field::Mask;
+use crate::resource::report::{Field, ReportInfo};
+use crate::schema::{report, tag_name, user};
+use crate::search::Builder;
+use crate::search::report::QueryBuilder;
+use crate::snapshot;
+use crate::string::SmallString;
+use crate::time::DateTime;
+use diesel::dsl::exists;
+use diesel::{ExpressionMethods, Insertable, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+const MAX_REASON_LENGTH: usize = 1000;
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list, create))
+        .routes(routes!(get))
+        .routes(routes!(resolve))
+        .routes(routes!(dismiss))
+}
+
+/// Lists reports.
+///
+/// **Anonymous tokens**
+///
+/// Same as `reason` token.
+///
+/// **Named tokens**
+///
+/// | Key                              | Description                                          |
+/// | -------------------------------- | ---------------------------------------------------- |
+/// | `id`                             | specific report ID                                   |
+/// | `user`                           | filed by given user (accepts wildcards)              |
+/// | `type`                           | `post`, `comment`, `tag` or `user`                   |
+/// | `resource`                       | reported resource ID (accepts wildcards)             |
+/// | `reason`                         | containing given reason (accepts wildcards)          |
+/// | `status`                         | `open`, `resolved` or `dismissed`                    |
+/// | `creation-date`, `creation-time` | filed at given date                                  |
+///
+/// **Sort style tokens**
+///
+/// | Value                            | Description               |
+/// | -------------------------------- | ------------------------- |
+/// | `random`                         | as random as it can get   |
+/// | `id`                             | newest to oldest          |
+/// | `user`                           | reporter name, Z to A     |
+/// | `type`                           | resource type             |
+/// | `resource`                       | resource ID               |
+/// | `reason`                         | reason, Z to A            |
+/// | `status`                         | handled reports first     |
+/// | `creation-date`, `creation-time` | newest to oldest          |
+///
+/// **Special tokens**
+///
+/// None.
+#[utoipa::path(
+    get,
+    path = "/reports",
+    tag = REPORT_TAG,
+    params(ResourceParams, PageParams),
+    responses(
+        (status = 200, body = PagedResponse<ReportInfo>),
+        (status = 403, description = "Privileges are too low"),
+    ),
+)]
+async fn list(
+    Ctx(ctx, connection_pool): Ctx,
+    Query(resource): Query<ResourceParams<Field>>,
+    Query(page): Query<PageParams>,
+) -> ApiResult<Json<PagedResponse<ReportInfo>>> {
+    ctx.verify_privilege(Action::ReportView)?;
+    ctx.verify_privilege(Action::ReportList)?;
+
+    let offset = page.offset.unwrap_or(0);
+    let limit = page.limit();
+    connection_pool
+        .transaction(move |conn| {
+            let mut query_builder = QueryBuilder::new(&ctx, resource.criteria())?;
+            query_builder.set_offset_and_limit(offset, limit);
+
+            let (total, selected_reports) = query_builder.list(conn)?;
+            Ok::<_, ApiError>(Json(PagedResponse {
+                query: resource.query,
+                offset,
+                limit,
+                total,
+                results: ReportInfo::new_batch_from_ids(conn, &ctx.config, &selected_reports, resource.fields)?,
+            }))
+        })
+        .await
+}
+
+/// Retrieves information about an existing report.
+#[utoipa::path(
+    get,
+    path = "/report/{id}",
+    tag = REPORT_TAG,
+    params(
+        ("id" = i64, Path, description = "Report ID", example = 1),
+        ResourceParams,
+    ),
+    responses(
+        (status = 200, body = ReportInfo),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Report does not exist"),
+    ),
+)]
+async fn get(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(report_id): Path<i64>,
+    Query(params): Query<ResourceParams<Field>>,
+) -> ApiResult<Json<ReportInfo>> {
+    ctx.verify_privilege(Action::ReportView)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let report_exists: bool = diesel::select(exists(report::table.find(report_id))).first(conn)?;
+            if !report_exists {
+                return Err(ApiError::NotFound(ResourceType::Report));
+            }
+            ReportInfo::new_from_id(conn, &ctx.config, report_id, params.fields)
+                .map(Json)
+                .map_err(ApiError::from)
+        })
+        .await
+}
+
+/// Request body for creating a report.
+#[derive(Deserialize, ToSchema)]
+struct ReportCreateBody {
+    /// Type of the reported resource. Can be `post`, `comment`, `tag`, or `user`.
+    #[serde(rename = "type")]
+    resource_type: ResourceType,
+    /// ID of the reported post or comment, or name of the reported tag or user.
+    /// Reports are always stored with the ID of the resource.
+    #[serde(rename = "id")]
+    resource_id: SmallString,
+    /// Why the resource is being reported. Must be between 1 and 1000 characters long.
+    reason: String,
+}
+
+/// Reports a post, comment, tag, or user to the moderators.
+#[utoipa::path(
+    post,
+    path = "/reports",
+    tag = REPORT_TAG,
+    params(ResourceParams),
+    request_body = ReportCreateBody,
+    responses(
+        (status = 200, body = ReportInfo),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Reported resource does not exist"),
+        (status = 409, description = "User already has an open report on the resource"),
+        (status = 422, description = "Resource type cannot be reported or reason is empty or too long"),
+    ),
+)]
+async fn create(
+    Ctx(ctx, connection_pool): Ctx,
+    Query(params): Query<ResourceParams<Field>>,
+    Json(body): Json<ReportCreateBody>,
+) -> ApiResult<Json<ReportInfo>> {
+    ctx.verify_privilege(Action::ReportCreate)?;
+
+    let reason_length = body.reason.trim().chars().count();
+    if reason_length == 0 || reason_length > MAX_REASON_LENGTH {
+        return Err(ApiError::InvalidTextLength("Report reason", MAX_REASON_LENGTH));
+    }
+
+    let report_id = connection_pool
+        .transaction({
+            let ctx = ctx.clone();
+            move |conn| {
+                let resource_id = reported_resource_id(conn, &ctx, body.resource_type, &body.resource_id)?;
+                let insert_result = NewReport {
+                    user_id: ctx.client.id,
+                    resource_type: body.resource_type,
+                    resource_id,
+                    reason: body.reason.trim(),
+                }
+                .insert_into(report::table)
+                .returning(report::id)
+                .get_result::<i64>(conn);
+                error::map_unique_violation(insert_result, ResourceProperty::OpenReport)
+            }
+        })
+        .await?;
+    connection_pool
+        .transaction(move |conn| ReportInfo::new_from_id(conn, &ctx.config, report_id, params.fields))
+        .await
+        .map(Json)
+}
+
+/// Marks an open report as resolved, meaning that action was taken on the reported resource.
+#[utoipa::path(
+    post,
+    path = "/report/{id}/resolve",
+    tag = REPORT_TAG,
+    params(
+        ("id" = i64, Path, description = "Report ID"),
+        ResourceParams,
+    ),
+    responses(
+        (status = 200, body = ReportInfo),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Report does not exist or is not open"),
+    ),
+)]
+async fn resolve(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(report_id): Path<i64>,
+    Query(params): Query<ResourceParams<Field>>,
+) -> ApiResult<Json<ReportInfo>> {
+    close(ctx, connection_pool, report_id, ReportStatus::Resolved, params.fields).await
+}
+
+/// Marks an open report as dismissed, meaning that no action was needed.
+#[utoipa::path(
+    post,
+    path = "/report/{id}/dismiss",
+    tag = REPORT_TAG,
+    params(
+        ("id" = i64, Path, description = "Report ID"),
+        ResourceParams,
+    ),
+    responses(
+        (status = 200, body = ReportInfo),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Report does not exist or is not open"),
+    ),
+)]
+async fn dismiss(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(report_id): Path<i64>,
+    Query(params): Query<ResourceParams<Field>>,
+) -> ApiResult<Json<ReportInfo>> {
+    close(ctx, connection_pool, report_id, ReportStatus::Dismissed, params.fields).await
+}
+
+/// Closes an open report with the given `status` and records the change as a snapshot.
+async fn close(
+    ctx: Context,
+    connection_pool: AsyncConnectionPool,
+    report_id: i64,
+    status: ReportStatus,
+    fields: Mask<Field>,
+) -> ApiResult<Json<ReportInfo>> {
+    ctx.verify_privilege(Action::ReportView)?;
+    ctx.verify_privilege(Action::ReportResolve)?;
+
+    connection_pool
+        .transaction({
+            let ctx = ctx.clone();
+            move |conn| {
+                let report: Report = report::table
+                    .find(report_id)
+                    .filter(report::status.eq(ReportStatus::Open))
+                    .first(conn)
+                    .optional()?
+                    .ok_or(ApiError::NotFound(ResourceType::Report))?;
+
+                diesel::update(report::table.find(report_id))
+                    .set((
+                        report::status.eq(status),
+                        report::resolver_id.eq(ctx.client.id),
+                        report::resolution_time.eq(DateTime::now()),
+                    ))
+                    .execute(conn)?;
+                snapshot::report::resolution_snapshot(conn, ctx.client, &report, status).map_err(ApiError::from)
+            }
+        })
+        .await?;
+    connection_pool
+        .transaction(move |conn| ReportInfo::new_from_id(conn, &ctx.config, report_id, fields))
+        .await
+        .map(Json)
+}
+
+/// Verifies that the resource being reported exists and is visible to the client, and returns the ID it
+/// should be stored under. Tags and users can be given by name, but are stored by ID so that renames don't
+/// orphan their reports.
+fn reported_resource_id(
+    conn: &mut PgConnection,
+    ctx: &Context,
+    resource_type: ResourceType,
+    resource_id: &str,
+) -> ApiResult<SmallString> {
+    match resource_type {
+        ResourceType::Post => {
+            let post_id: i64 = resource_id.parse()?;
+            api::post::verify_visibility(conn, ctx, post_id)?;
+            Ok(SmallString::from(post_id))
+        }
+        ResourceType::Comment => {
+            let comment_id: i64 = resource_id.parse()?;
+            api::comment::verify_visibility(conn, ctx, comment_id)?;
+            Ok(SmallString::from(comment_id))
+        }
+        ResourceType::Tag => tag_name::table
+            .select(tag_name::tag_id)
+            .filter(tag_name::name.eq(resource_id))
+            .first::<i64>(conn)
+            .optional()?
+            .map(SmallString::from)
+            .ok_or(ApiError::NotFound(ResourceType::Tag)),
+        ResourceType::User => user::table
+            .select(user::id)
+            .filter(user::name.eq(resource_id))
+            .first::<i64>(conn)
+            .optional()?
+            .map(SmallString::from)
+            .ok_or(ApiError::NotFound(ResourceType::User)),
+        _ => Err(ApiError::UnreportableResource(resource_type)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::api::error::ApiResult;
+    use crate::model::enums::{PostStatus, ResourceType, UserRank};
+    use crate::schema::{post, report};
+    use crate::search::report::Token;
+    use crate::test::*;
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+    use serial_test::{parallel, serial};
+    use strum::IntoEnumIterator;
+
+    // Exclude fields that involve creation_time or resolution_time
+    const FIELDS: &str = "&fields=id,user,type,resourceId,reason,status,resolver";
+
+    #[tokio::test]
+    #[parallel]
+    async fn list() -> ApiResult<()> {
+        const QUERY: &str = "GET /reports/?query";
+        const PARAMS: &str = "-sort:id&limit=40&fields=id";
+        verify_response(&format!("{QUERY}=-sort:id&limit=40{FIELDS}"), "report/list/typical").await?;
+
+        let filter_table = crate::search::report::filter_table();
+        for token in Token::iter() {
+            let filter = filter_table[token];
+            let (sign, filter) = if filter.starts_with('-') {
+                filter.split_at(1)
+            } else {
+                ("", filter)
+            };
+            let query = format!("{QUERY}={sign}{token}:{filter} {PARAMS}");
+            let path = format!("report/list/{token}_filtered");
+            verify_response(&query, &path).await?;
+
+            let query = format!("{QUERY}=sort:{token} {PARAMS}");
+            let path = format!("report/list/{token}_sorted");
+            verify_response(&query, &path).await?;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn get() -> ApiResult<()> {
+        verify_response(&format!("GET /report/3/?{FIELDS}"), "report/get/typical").await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn create() -> ApiResult<()> {
+        verify_response_with_user(UserRank::Regular, &format!("POST /reports/?{FIELDS}"), "report/create/typical")
+            .await?;
+        verify_response_with_user(UserRank::Regular, "POST /reports", "report/create/duplicate").await?;
+        verify_response(&format!("POST /report/4/resolve/?{FIELDS}"), "report/resolve/typical").await?;
+        verify_response("POST /report/4/dismiss", "report/dismiss/closed").await?;
+
+        diesel::delete(report::table.find(4)).execute(&mut get_connection()?)?;
+        reset_sequence(ResourceType::Report)
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn pending_post() -> ApiResult<()> {
+        const USER: UserRank = UserRank::Regular;
+        let set_status = |status: PostStatus| -> ApiResult<()> {
+            diesel::update(post::table.find(1))
+                .set(post::status.eq(status))
+                .execute(&mut get_connection()?)?;
+            Ok(())
+        };
+
+        // Post 1 wasn't uploaded by the regular user, so it and its comments can't be seen by them
+        set_status(PostStatus::Pending)?;
+        verify_response_with_user(USER, "POST /reports", "report/create/pending_post").await?;
+        verify_response_with_user(USER, "POST /reports", "report/create/pending_post_comment").await?;
+        set_status(PostStatus::Approved)
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn error() -> ApiResult<()> {
+        verify_response("GET /report/99", "report/get/nonexistent").await?;
+        verify_response("POST /reports", "report/create/unreportable").await?;
+        verify_response("POST /reports", "report/create/nonexistent_post").await?;
+        verify_response("POST /reports", "report/create/empty_reason").await?;
+        verify_response("POST /report/99/resolve", "report/resolve/nonexistent").await
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn unauthorized() -> ApiResult<()> {
+        const USER: UserRank = UserRank::Regular;
+
+        verify_response_with_user(USER, "GET /reports?limit=1", "report/list/unauthorized").await?;
+        verify_response_with_user(USER, "GET /report/1", "report/get/unauthorized").await?;
+        verify_response_with_user(USER, "POST /reports", "report/create/unauthorized").await?;
+        verify_response_with_user(USER, "POST /report/1/resolve", "report/resolve/unauthorized").await?;
+        verify_response_with_user(USER, "POST /report/1/dismiss", "report/dismiss/unauthorized").await
+    }
+}
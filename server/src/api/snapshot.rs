@@ -26,7 +26,7 @@ pub fn routes() -> OpenApiRouter<AppState> {
 /// | `type`         | involving given resource type                                    |
 /// | `id`           | involving given resource id                                      |
 /// | `date`, `time` | created at given date                                            |
-/// | `operation`    | `modified`, `created`, `deleted` or `merged`                     |
+/// | `operation`    | `modified`, `created`, `deleted`, `merged` or `restored`         |
 /// | `user`         | name of the user that created given snapshot (accepts wildcards) |
 ///
 /// **Sort style tokens**
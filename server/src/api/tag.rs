@@ -4,20 +4,25 @@ use crate::app::{AppState, Context};
 use crate::config::Action;
 use crate::extract::{Ctx, DeleteBody, Json, MergeBody, PageParams, PagedResponse, Path, Query, ResourceParams};
 use crate::model::enums::ResourceType;
-use crate::model::tag::{NewTag, Tag};
+use crate::model::tag::{NewTag, Tag, TagImplication, TagName};
 use crate::resource::tag::{Field, TagInfo};
 use crate::schema::{post_tag, tag, tag_category, tag_name};
 use crate::search::Builder;
+use crate::search::post::QueryBuilder as PostQueryBuilder;
 use crate::search::tag::QueryBuilder;
 use crate::snapshot::tag::SnapshotData;
 use crate::string::{LargeString, SmallString};
 use crate::time::DateTime;
 use crate::update::tag::FetchMode;
 use crate::{api, snapshot, update};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
 use diesel::dsl::count_star;
 use diesel::{ExpressionMethods, Insertable, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl, SaveChangesDsl};
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use std::collections::HashSet;
+use std::fmt::Write;
+use utoipa::{IntoParams, ToSchema};
 use utoipa_axum::router::OpenApiRouter;
 use utoipa_axum::routes;
 
@@ -26,12 +31,15 @@ pub fn routes() -> OpenApiRouter<AppState> {
         .routes(routes!(list, create))
         .routes(routes!(get, update, delete))
         .routes(routes!(get_siblings))
+        .routes(routes!(get_implications))
+        .routes(routes!(get_effective))
+        .routes(routes!(apply_implications))
         .routes(routes!(merge))
 }
 
 const MAX_TAG_SIBLINGS: i64 = 50;
 
-fn verify_visibility(conn: &mut PgConnection, ctx: &Context, tag_name: &str) -> ApiResult<i64> {
+pub fn verify_visibility(conn: &mut PgConnection, ctx: &Context, tag_name: &str) -> ApiResult<i64> {
     if ctx.preferences().is_empty() {
         tag_name::table
             .select(tag_name::tag_id)
@@ -65,32 +73,34 @@ fn verify_visibility(conn: &mut PgConnection, ctx: &Context, tag_name: &str) ->
 ///
 /// **Named tokens**
 ///
-/// | Key                                                          | Description                                                   |
-/// | -------------------                                          | ------------------------------------------------------------- |
-/// | `name`                                                       | having given name (accepts wildcards)                         |
-/// | `category`                                                   | having given category (accepts wildcards)                     |
-/// | `description`                                                | having given description (accepts wildcards)                  |
-/// | `creation-date`, `creation-time`                             | created at given date                                         |
-/// | `last-edit-date`, `last-edit-time`, `edit-date`, `edit-time` | edited at given date                                          |
-/// | `usages`, `usage-count`, `post-count`                        | used in given number of posts                                 |
-/// | `suggestion-count`                                           | with given number of suggestions                              |
-/// | `implication-count`                                          | with given number of implications                             |
-/// | `implies`                                                    | having an implication with the given name (accepts wildcards) |
-/// | `suggests`                                                   | having a suggestion with the given name (accepts wildcards)   |
+/// | Key                                                          | Description                                                       |
+/// | -------------------                                          | ----------------------------------------------------------------- |
+/// | `name`                                                       | having given name (accepts wildcards)                             |
+/// | `category`                                                   | having given category (accepts wildcards)                         |
+/// | `description`                                                | having given description (accepts wildcards)                      |
+/// | `creation-date`, `creation-time`                             | created at given date                                             |
+/// | `last-edit-date`, `last-edit-time`, `edit-date`, `edit-time` | edited at given date                                              |
+/// | `usages`, `usage-count`, `post-count`                        | used in given number of posts                                     |
+/// | `suggestion-count`                                           | with given number of suggestions                                  |
+/// | `implication-count`                                          | with given number of implications                                 |
+/// | `implies`                                                    | having an implication with the given name (accepts wildcards)     |
+/// | `suggests`                                                   | having a suggestion with the given name (accepts wildcards)       |
+/// | `implied-by`                                                 | implied by given name, directly or indirectly (accepts wildcards) |
+/// | `implies-transitive`                                         | implying given name, directly or indirectly (accepts wildcards)   |
 ///
 /// **Sort style tokens**
 ///
-/// | Value                                                        | Description                  |
-/// | -------------------                                          | ---------------------------- |
-/// | `random`                                                     | as random as it can get      |
-/// | `name`                                                       | A to Z                       |
-/// | `category`                                                   | category (A to Z)            |
-/// | `description`                                                | description (A to Z)         |
-/// | `creation-date`, `creation-time`                             | recently created first       |
-/// | `last-edit-date`, `last-edit-time`, `edit-date`, `edit-time` | recently edited first        |
-/// | `usages`, `usage-count`, `post-count`                        | used in most posts first     |
-/// | `implication-count`, `implies`                               | with most implications first |
-/// | `suggestion-count`, `suggests`                               | with most suggestions first  |
+/// | Value                                                              | Description                  |
+/// | -------------------                                                | ---------------------------- |
+/// | `random`                                                           | as random as it can get      |
+/// | `name`                                                             | A to Z                       |
+/// | `category`                                                         | category (A to Z)            |
+/// | `description`                                                      | description (A to Z)         |
+/// | `creation-date`, `creation-time`                                   | recently created first       |
+/// | `last-edit-date`, `last-edit-time`, `edit-date`, `edit-time`       | recently edited first        |
+/// | `usages`, `usage-count`, `post-count`                              | used in most posts first     |
+/// | `implication-count`, `implies`, `implied-by`, `implies-transitive` | with most implications first |
+/// | `suggestion-count`, `suggests`                                     | with most suggestions first  |
 ///
 /// **Special tokens**
 ///
@@ -243,6 +253,366 @@ async fn get_siblings(
         .await
 }
 
+/// Output format of an implication graph.
+#[derive(Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+enum GraphFormat {
+    /// JSON object with nodes and edges.
+    #[default]
+    Json,
+    /// Graphviz DOT source.
+    Dot,
+}
+
+/// Represents parameters of a request to retrieve an implication graph.
+#[derive(Clone, Copy, Deserialize, IntoParams)]
+struct GraphParams {
+    /// Output format of the graph. Defaults to `json`.
+    format: Option<GraphFormat>,
+}
+
+/// An implication between two tags, identified by their primary names.
+#[derive(Serialize, ToSchema)]
+struct ImplicationEdge {
+    /// Name of the implying tag.
+    parent: SmallString,
+    /// Name of the implied tag.
+    child: SmallString,
+}
+
+/// Transitive closure of implications around a tag.
+#[derive(Serialize, ToSchema)]
+struct ImplicationGraph {
+    /// Primary name of the queried tag.
+    tag: SmallString,
+    /// The queried tag followed by its ancestors and descendants.
+    nodes: Vec<TagInfo>,
+    /// Implications between the nodes of the graph.
+    edges: Vec<ImplicationEdge>,
+    /// Names of tags that imply the queried tag, directly or indirectly.
+    ancestors: Vec<SmallString>,
+    /// Names of tags implied by the queried tag, directly or indirectly.
+    descendants: Vec<SmallString>,
+}
+
+impl ImplicationGraph {
+    /// Renders the graph as Graphviz DOT source.
+    fn to_dot(&self) -> String {
+        let quote = |name: &str| format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut dot = String::from("digraph implications {\n");
+        let names = std::iter::once(&self.tag)
+            .chain(&self.ancestors)
+            .chain(&self.descendants);
+        for name in names {
+            let _ = writeln!(dot, "    {};", quote(name));
+        }
+        for edge in &self.edges {
+            let _ = writeln!(dot, "    {} -> {};", quote(&edge.parent), quote(&edge.child));
+        }
+        dot.push('}');
+        dot
+    }
+}
+
+/// Retrieves the transitive closure of implications of given tag.
+///
+/// Ancestors are tags which imply the given tag, either directly or through a
+/// chain of implications. Descendants are tags which the given tag implies,
+/// either directly or through a chain of implications. Tags hidden by the
+/// client's preferences are omitted. With `format=dot` the graph is returned
+/// as Graphviz DOT source instead of JSON.
+#[utoipa::path(
+    get,
+    path = "/tag/{name}/implications",
+    tag = TAG_TAG,
+    params(
+        ("name" = String, Path, description = "Tag name"),
+        ResourceParams,
+        GraphParams,
+    ),
+    responses(
+        (
+            status = 200,
+            content(
+                (ImplicationGraph = "application/json"),
+                (String = "text/vnd.graphviz"),
+            )
+        ),
+        (status = 403, description = "Privileges are too low"),
+        (status = 403, description = "Tag is hidden"),
+        (status = 404, description = "Tag does not exist"),
+    ),
+)]
+async fn get_implications(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(name): Path<SmallString>,
+    Query(params): Query<ResourceParams<Field>>,
+    Query(graph_params): Query<GraphParams>,
+) -> ApiResult<Response> {
+    ctx.verify_privilege(Action::TagView)?;
+
+    let graph = connection_pool
+        .transaction(move |conn| {
+            let tag_id = verify_visibility(conn, &ctx, &name)?;
+            let hidden_tags: HashSet<i64> = match ctx.preferences().hidden_tags() {
+                Some(hidden_tags) => hidden_tags.load::<i64>(conn)?.into_iter().collect(),
+                None => HashSet::new(),
+            };
+
+            let ancestor_implications = TagImplication::ancestors(conn, &[tag_id])?;
+            let descendant_implications = TagImplication::descendants(conn, &[tag_id])?;
+            let implications: HashSet<_> = ancestor_implications
+                .iter()
+                .chain(&descendant_implications)
+                .copied()
+                .filter(|implication| {
+                    !hidden_tags.contains(&implication.parent_id) && !hidden_tags.contains(&implication.child_id)
+                })
+                .collect();
+
+            let node_ids: HashSet<i64> = implications
+                .iter()
+                .flat_map(|implication| [implication.parent_id, implication.child_id])
+                .chain(std::iter::once(tag_id))
+                .collect();
+            let node_ids: Vec<_> = node_ids.into_iter().collect();
+            let primary_names = TagName::primary_names(conn, &node_ids)?;
+
+            let sorted_by_name = |ids: HashSet<i64>| {
+                let mut ids: Vec<_> = ids.into_iter().filter(|id| !hidden_tags.contains(id)).collect();
+                ids.sort_unstable_by(|a, b| primary_names[a].cmp(&primary_names[b]));
+                ids
+            };
+            let ancestor_ids = sorted_by_name(
+                ancestor_implications
+                    .iter()
+                    .map(|implication| implication.parent_id)
+                    .collect(),
+            );
+            let descendant_ids = sorted_by_name(
+                descendant_implications
+                    .iter()
+                    .map(|implication| implication.child_id)
+                    .collect(),
+            );
+
+            let mut edges: Vec<_> = implications
+                .into_iter()
+                .map(|implication| ImplicationEdge {
+                    parent: primary_names[&implication.parent_id].clone(),
+                    child: primary_names[&implication.child_id].clone(),
+                })
+                .collect();
+            edges.sort_unstable_by(|a, b| (&a.parent, &a.child).cmp(&(&b.parent, &b.child)));
+
+            // A tag can be both an ancestor and a descendant if implications form a cycle
+            let mut seen = HashSet::new();
+            let ordered_node_ids: Vec<_> = std::iter::once(tag_id)
+                .chain(ancestor_ids.iter().copied())
+                .chain(descendant_ids.iter().copied())
+                .filter(|&id| seen.insert(id))
+                .collect();
+            let names_of = |ids: &[i64]| ids.iter().map(|id| primary_names[id].clone()).collect();
+            Ok::<_, ApiError>(ImplicationGraph {
+                tag: primary_names[&tag_id].clone(),
+                nodes: TagInfo::new_batch_from_ids(conn, &ordered_node_ids, params.fields)?,
+                edges,
+                ancestors: names_of(&ancestor_ids),
+                descendants: names_of(&descendant_ids),
+            })
+        })
+        .await?;
+
+    Ok(match graph_params.format.unwrap_or_default() {
+        GraphFormat::Json => Json(graph).into_response(),
+        GraphFormat::Dot => ([(header::CONTENT_TYPE, "text/vnd.graphviz")], graph.to_dot()).into_response(),
+    })
+}
+
+/// Request body for computing an effective tag set.
+#[derive(Deserialize, ToSchema)]
+struct EffectiveTagsBody {
+    /// Names of tags to be assigned to a post.
+    names: Vec<SmallString>,
+}
+
+/// Tags a post would receive.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct EffectiveTags {
+    /// Existing tags, including all tags transitively implied by them, sorted by name.
+    results: Vec<TagInfo>,
+    /// Names that don't belong to any tag. Assigning them to a post would create new tags.
+    new_names: Vec<SmallString>,
+}
+
+/// Computes the effective set of tags a post would get if tagged with given names.
+///
+/// Implications are followed recursively, so every tag implied directly or
+/// indirectly by a given name is included. Nothing is modified.
+#[utoipa::path(
+    post,
+    path = "/effective-tags",
+    tag = TAG_TAG,
+    params(ResourceParams),
+    request_body = EffectiveTagsBody,
+    responses(
+        (status = 200, body = EffectiveTags),
+        (status = 403, description = "Privileges are too low"),
+    ),
+)]
+async fn get_effective(
+    Ctx(ctx, connection_pool): Ctx,
+    Query(params): Query<ResourceParams<Field>>,
+    Json(body): Json<EffectiveTagsBody>,
+) -> ApiResult<Json<EffectiveTags>> {
+    ctx.verify_privilege(Action::TagView)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let named_ids: Vec<i64> = tag_name::table
+                .select(tag_name::tag_id)
+                .filter(tag_name::name.eq_any(&body.names))
+                .distinct()
+                .load(conn)?;
+            let implied_ids = TagImplication::descendants(conn, &named_ids)?
+                .into_iter()
+                .map(|implication| implication.child_id);
+            let effective_ids: HashSet<_> = named_ids.iter().copied().chain(implied_ids).collect();
+            let effective_ids: Vec<_> = effective_ids.into_iter().collect();
+
+            let sorted_ids: Vec<i64> = tag_name::table
+                .select(tag_name::tag_id)
+                .filter(tag_name::tag_id.eq_any(&effective_ids))
+                .filter(TagName::is_primary())
+                .order(tag_name::name)
+                .load(conn)?;
+            Ok::<_, ApiError>(Json(EffectiveTags {
+                results: TagInfo::new_batch_from_ids(conn, &sorted_ids, params.fields)?,
+                new_names: update::tag::find_new_names(conn, body.names)?,
+            }))
+        })
+        .await
+}
+
+/// Request body for applying tag implications to existing posts.
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct ApplyImplicationsBody {
+    /// Post search query. Only matching posts are considered. Defaults to all posts.
+    query: Option<String>,
+    /// Also remove tags that the post history shows were added by a since-deleted implication.
+    #[serde(default)]
+    remove_stale: bool,
+    /// Report the changes without applying them.
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Tag changes of a single post.
+#[derive(Serialize, ToSchema)]
+struct PostTagDiff {
+    /// Post ID.
+    id: i64,
+    /// Primary names of tags added to the post, sorted by name.
+    added: Vec<SmallString>,
+    /// Primary names of tags removed from the post, sorted by name.
+    removed: Vec<SmallString>,
+}
+
+/// Tag changes made to existing posts.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct ImplicationDiffs {
+    /// Whether the changes were actually applied.
+    applied: bool,
+    /// The starting offset of the considered posts within the matching posts.
+    offset: u64,
+    /// Maximum number of posts considered.
+    limit: u64,
+    /// How many posts match the query.
+    total: u64,
+    /// Changed posts, sorted by ID.
+    results: Vec<PostTagDiff>,
+}
+
+/// Retroactively applies tag implications to existing posts.
+///
+/// Implications are normally only applied when a post is edited, so posts
+/// tagged before an implication was added can lack implied tags. This computes
+/// which of the matching posts on the requested page are missing tags implied
+/// directly or indirectly by their current tags and adds them, creating a
+/// snapshot for each changed post. If `removeStale` is set, tags that the post
+/// history shows were added by an implication that has since been deleted are
+/// removed as well. If `dryRun` is set, nothing is modified and the changes that
+/// would be made are reported instead. Large collections are processed one page
+/// at a time by passing increasing offsets.
+#[utoipa::path(
+    post,
+    path = "/apply-tag-implications",
+    tag = TAG_TAG,
+    params(PageParams),
+    request_body = ApplyImplicationsBody,
+    responses(
+        (status = 200, body = ImplicationDiffs),
+        (status = 403, description = "Privileges are too low"),
+    ),
+)]
+async fn apply_implications(
+    Ctx(ctx, connection_pool): Ctx,
+    Query(page): Query<PageParams>,
+    Json(body): Json<ApplyImplicationsBody>,
+) -> ApiResult<Json<ImplicationDiffs>> {
+    ctx.verify_privilege(Action::TagApplyImplications)?;
+
+    let offset = page.offset.unwrap_or(0);
+    let limit = page.limit();
+    connection_pool
+        .transaction(move |conn| {
+            let query = body.query.unwrap_or_default();
+            let mut query_builder = PostQueryBuilder::new(&ctx, &query)?;
+            query_builder.set_offset_and_limit(offset, limit);
+            let (total, mut post_ids) = query_builder.list(conn)?;
+            post_ids.sort_unstable();
+
+            // Diffs are applied in the same transaction they are computed in, so they can't go stale
+            let diffs = update::tag::implication_diffs(conn, &post_ids, body.remove_stale)?;
+            let tag_ids: HashSet<i64> = diffs
+                .iter()
+                .flat_map(|diff| diff.added.iter().chain(&diff.removed))
+                .copied()
+                .collect();
+            let tag_ids: Vec<_> = tag_ids.into_iter().collect();
+            let primary_names = TagName::primary_names(conn, &tag_ids)?;
+
+            let names_of = |ids: &[i64]| {
+                let mut names: Vec<_> = ids.iter().map(|id| primary_names[id].clone()).collect();
+                names.sort_unstable();
+                names
+            };
+            let results = diffs
+                .iter()
+                .map(|diff| PostTagDiff {
+                    id: diff.post_id,
+                    added: names_of(&diff.added),
+                    removed: names_of(&diff.removed),
+                })
+                .collect();
+
+            if !body.dry_run {
+                update::tag::apply_tag_diffs(conn, ctx.client, &diffs)?;
+            }
+            Ok::<_, ApiError>(Json(ImplicationDiffs {
+                applied: !body.dry_run,
+                offset,
+                limit,
+                total,
+                results,
+            }))
+        })
+        .await
+}
+
 /// Request body for creating a tag.
 #[derive(Deserialize, ToSchema)]
 struct TagCreateBody {
@@ -571,15 +941,18 @@ async fn delete(
 #[cfg(test)]
 mod test {
     use crate::api::error::ApiResult;
-    use crate::model::enums::{ResourceType, UserRank};
-    use crate::model::tag::Tag;
-    use crate::schema::{database_statistics, tag, tag_name, tag_statistics};
+    use crate::model::enums::{ResourceOperation, ResourceType, UserRank};
+    use crate::model::post::PostTag;
+    use crate::model::snapshot::NewSnapshot;
+    use crate::model::tag::{Tag, TagImplication};
+    use crate::schema::{database_statistics, post_tag, tag, tag_implication, tag_name, tag_statistics};
     use crate::search::tag::Token;
     use crate::string::SmallString;
     use crate::test::*;
     use crate::time::DateTime;
     use diesel::dsl::exists;
-    use diesel::{ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl, SelectableHelper};
+    use diesel::{ExpressionMethods, Insertable, PgConnection, QueryDsl, QueryResult, RunQueryDsl, SelectableHelper};
+    use serde_json::{Value, json};
     use serial_test::{parallel, serial};
     use strum::IntoEnumIterator;
 
@@ -656,6 +1029,125 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    #[parallel]
+    async fn get_implications() -> ApiResult<()> {
+        verify_response("GET /tag/tree/implications/?fields=names", "tag/get_implications/typical").await?;
+        verify_response("GET /tag/none/implications", "tag/get_implications/of_nonexistent").await
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn get_effective() -> ApiResult<()> {
+        verify_response("POST /effective-tags/?fields=names", "tag/get_effective/typical").await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn apply_implications() -> ApiResult<()> {
+        let get_tag_id = |conn: &mut PgConnection, name: &str| -> QueryResult<i64> {
+            tag_name::table
+                .select(tag_name::tag_id)
+                .filter(tag_name::name.eq(name))
+                .first(conn)
+        };
+        let get_usages = |conn: &mut PgConnection, tag_id: i64| -> QueryResult<Vec<i64>> {
+            post_tag::table
+                .select(post_tag::post_id)
+                .filter(post_tag::tag_id.eq(tag_id))
+                .order(post_tag::post_id)
+                .load(conn)
+        };
+
+        let mut conn = get_connection()?;
+        let parent_id = get_tag_id(&mut conn, "rock")?;
+        let child_id = get_tag_id(&mut conn, "sand")?;
+        let usages = get_usages(&mut conn, child_id)?;
+        TagImplication { parent_id, child_id }
+            .insert_into(tag_implication::table)
+            .execute(&mut conn)?;
+
+        verify_response("POST /apply-tag-implications", "tag/apply_implications/dry_run").await?;
+
+        let new_usages = get_usages(&mut conn, child_id)?;
+        assert_eq!(new_usages, usages);
+
+        verify_response("POST /apply-tag-implications", "tag/apply_implications/typical").await?;
+
+        let new_usages = get_usages(&mut conn, child_id)?;
+        assert_eq!(new_usages, [1, 2]);
+
+        reset_database();
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn remove_stale_implications() -> ApiResult<()> {
+        let get_tag_id = |conn: &mut PgConnection, name: &str| -> QueryResult<i64> {
+            tag_name::table
+                .select(tag_name::tag_id)
+                .filter(tag_name::name.eq(name))
+                .first(conn)
+        };
+        let list_change = |field: &str, added: &[&str]| {
+            json!({
+                "type": "object change",
+                "value": {
+                    field: {"type": "list change", "added": added, "removed": []}
+                }
+            })
+        };
+        let modification = |resource_type: ResourceType, resource_id: &str, data: Value| NewSnapshot {
+            user_id: None,
+            operation: ResourceOperation::Modified,
+            resource_type,
+            resource_id: resource_id.into(),
+            data,
+        };
+
+        // A since-deleted implication added sand alongside rock to post 3, while post 4 was tagged sand by hand
+        let mut conn = get_connection()?;
+        let parent_id = get_tag_id(&mut conn, "rock")?;
+        let child_id = get_tag_id(&mut conn, "sand")?;
+        [(3, parent_id), (3, child_id), (4, child_id), (4, parent_id)]
+            .map(|(post_id, tag_id)| PostTag { post_id, tag_id })
+            .insert_into(post_tag::table)
+            .execute(&mut conn)?;
+        modification(ResourceType::Tag, "rock", list_change("implications", &["sand"])).insert(&mut conn)?;
+        modification(ResourceType::Post, "3", list_change("tags", &["rock", "sand"])).insert(&mut conn)?;
+        modification(ResourceType::Post, "4", list_change("tags", &["sand"])).insert(&mut conn)?;
+        modification(ResourceType::Post, "4", list_change("tags", &["rock"])).insert(&mut conn)?;
+
+        verify_response("POST /apply-tag-implications", "tag/apply_implications/remove_stale").await?;
+
+        let usages: Vec<i64> = post_tag::table
+            .select(post_tag::post_id)
+            .filter(post_tag::tag_id.eq(child_id))
+            .load(&mut conn)?;
+        assert_eq!(usages, [4]);
+
+        reset_database();
+        Ok(())
+    }
+
+    #[test]
+    fn implication_graph_dot() {
+        let graph = super::ImplicationGraph {
+            tag: SmallString::from("tree"),
+            nodes: Vec::new(),
+            edges: vec![super::ImplicationEdge {
+                parent: SmallString::from("\"quoted\""),
+                child: SmallString::from("tree"),
+            }],
+            ancestors: vec![SmallString::from("\"quoted\"")],
+            descendants: Vec::new(),
+        };
+        let expected =
+            "digraph implications {\n    \"tree\";\n    \"\\\"quoted\\\"\";\n    \"\\\"quoted\\\"\" -> \"tree\";\n}";
+        assert_eq!(graph.to_dot(), expected);
+    }
+
     #[tokio::test]
     #[serial]
     async fn create() -> ApiResult<()> {
@@ -865,6 +1357,9 @@ mod test {
         verify_response_with_user(USER, "GET /tags?limit=1", "tag/list/unauthorized").await?;
         verify_response_with_user(USER, "GET /tag/sky", "tag/get/unauthorized").await?;
         verify_response_with_user(USER, "GET /tag-siblings/sky", "tag/get_siblings/unauthorized").await?;
+        verify_response_with_user(USER, "GET /tag/sky/implications", "tag/get_implications/unauthorized").await?;
+        verify_response_with_user(USER, "POST /effective-tags", "tag/get_effective/unauthorized").await?;
+        verify_response_with_user(USER, "POST /apply-tag-implications", "tag/apply_implications/unauthorized").await?;
         verify_response_with_user(USER, "POST /tags", "tag/create/unauthorized").await?;
         verify_response_with_user(USER, "POST /tag-merge", "tag/merge/unauthorized").await?;
         verify_response_with_user(USER, "PUT /tag/sky", "tag/edit/name_unauthorized").await?;
@@ -23,7 +23,11 @@ pub fn routes() -> OpenApiRouter<AppState> {
         .routes(routes!(set_default))
 }
 
-fn verify_visibility(conn: &mut PgConnection, ctx: &Context, category_name: &SmallString) -> ApiResult<TagCategory> {
+pub fn verify_visibility(
+    conn: &mut PgConnection,
+    ctx: &Context,
+    category_name: &SmallString,
+) -> ApiResult<TagCategory> {
     let category = tag_category::table
         .filter(tag_category::name.eq(category_name))
         .first(conn)
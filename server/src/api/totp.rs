@@ -0,0 +1,385 @@
+use crate::api;
+use crate::api::doc::TOTP_TAG;
+use crate::api::error::{ApiError, ApiResult};
+use crate::app::AppState;
+use crate::auth::header::AuthenticationError;
+use crate::auth::totp;
+use crate::config::{Action, Config};
+use crate::extract::{Ctx, Json, Path};
+use crate::model::enums::ResourceType;
+use crate::model::user::{NewTotpRecoveryCode, NewUserTotp};
+use crate::schema::{totp_recovery_code, user, user_totp};
+use crate::string::SmallString;
+use axum::http::HeaderMap;
+use diesel::{ExpressionMethods, Insertable, OptionalExtension, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(get_status, enroll))
+        .routes(routes!(verify))
+        .routes(routes!(regenerate_recovery_codes))
+        .routes(routes!(disable))
+}
+
+/// Two-factor authentication status of a user.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct TotpStatus {
+    /// Whether two-factor authentication is enabled.
+    enabled: bool,
+    /// Number of unused recovery codes.
+    recovery_codes_left: i64,
+}
+
+/// A new shared secret for an authenticator app.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct TotpEnrollment {
+    /// Base32 encoded secret, for manual entry into an authenticator app.
+    secret: String,
+    /// `otpauth` URI of the secret, usually displayed as a QR code.
+    provisioning_uri: String,
+}
+
+/// Single-use codes that can be used in place of TOTP codes.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct RecoveryCodes {
+    recovery_codes: Vec<String>,
+}
+
+/// Request body for verifying a new secret.
+#[derive(Deserialize, ToSchema)]
+struct VerifyBody {
+    /// Code currently displayed by the authenticator app.
+    code: String,
+}
+
+/// Retrieves the two-factor authentication status of the authenticated user.
+#[utoipa::path(
+    get,
+    path = "/totp",
+    tag = TOTP_TAG,
+    responses(
+        (status = 200, body = TotpStatus),
+        (status = 401, description = "Client is not logged in"),
+        (status = 403, description = "Privileges are too low"),
+    ),
+)]
+async fn get_status(Ctx(ctx, connection_pool): Ctx) -> ApiResult<Json<TotpStatus>> {
+    ctx.verify_privilege(Action::UserTotpEditSelf)?;
+    let user_id = ctx.client.id.ok_or(ApiError::NotLoggedIn)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let enabled = user_totp::table
+                .find(user_id)
+                .select(user_totp::enabled)
+                .first(conn)
+                .optional()?
+                .unwrap_or(false);
+            let recovery_codes_left = totp_recovery_code::table
+                .filter(totp_recovery_code::user_id.eq(user_id))
+                .count()
+                .first(conn)?;
+            Ok(Json(TotpStatus {
+                enabled,
+                recovery_codes_left,
+            }))
+        })
+        .await
+}
+
+/// Generates a new TOTP secret for the authenticated user.
+///
+/// Two-factor authentication isn't enabled until the secret is [verified](#Verify).
+/// Enrolling again before verifying replaces the previous secret.
+#[utoipa::path(
+    post,
+    path = "/totp",
+    tag = TOTP_TAG,
+    responses(
+        (status = 200, body = TotpEnrollment),
+        (status = 401, description = "Client is not logged in"),
+        (status = 403, description = "Privileges are too low"),
+        (status = 409, description = "Two-factor authentication is already enabled"),
+    ),
+)]
+async fn enroll(Ctx(ctx, connection_pool): Ctx) -> ApiResult<Json<TotpEnrollment>> {
+    ctx.verify_privilege(Action::UserTotpEditSelf)?;
+    let user_id = ctx.client.id.ok_or(ApiError::NotLoggedIn)?;
+
+    let secret = totp::generate_secret();
+    connection_pool
+        .transaction(move |conn| {
+            let enabled: Option<bool> = user_totp::table
+                .find(user_id)
+                .select(user_totp::enabled)
+                .first(conn)
+                .optional()?;
+            if enabled == Some(true) {
+                return Err(ApiError::TotpAlreadyEnabled);
+            }
+
+            diesel::delete(user_totp::table.find(user_id)).execute(conn)?;
+            NewUserTotp {
+                user_id,
+                secret: &secret,
+            }
+            .insert_into(user_totp::table)
+            .execute(conn)?;
+
+            let username: SmallString = user::table.find(user_id).select(user::name).first(conn)?;
+            Ok(Json(TotpEnrollment {
+                secret: totp::base32_encode(&secret),
+                provisioning_uri: totp::provisioning_uri(&ctx.config.public_info.name, &username, &secret),
+            }))
+        })
+        .await
+}
+
+/// Enables two-factor authentication for the authenticated user.
+///
+/// The `code` must be generated from the secret of the latest enrollment. Returns a set
+/// of recovery codes, which are only shown once.
+#[utoipa::path(
+    post,
+    path = "/totp/verify",
+    tag = TOTP_TAG,
+    request_body = VerifyBody,
+    responses(
+        (status = 200, body = RecoveryCodes),
+        (status = 401, description = "Code is invalid"),
+        (status = 403, description = "Privileges are too low"),
+        (status = 409, description = "Two-factor authentication is already enabled"),
+        (status = 422, description = "User hasn't enrolled a secret"),
+    ),
+)]
+async fn verify(Ctx(ctx, connection_pool): Ctx, Json(body): Json<VerifyBody>) -> ApiResult<Json<RecoveryCodes>> {
+    ctx.verify_privilege(Action::UserTotpEditSelf)?;
+    let user_id = ctx.client.id.ok_or(ApiError::NotLoggedIn)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let (secret, enabled): (Vec<u8>, bool) = user_totp::table
+                .find(user_id)
+                .select((user_totp::secret, user_totp::enabled))
+                .first(conn)
+                .optional()?
+                .ok_or(ApiError::TotpNotEnabled)?;
+            if enabled {
+                return Err(ApiError::TotpAlreadyEnabled);
+            }
+            let step = totp::code_step(&secret, &body.code, totp::unix_time())
+                .ok_or(ApiError::from(AuthenticationError::InvalidTotpCode))?;
+
+            // The code used to enable two-factor authentication can't be used again
+            diesel::update(user_totp::table.find(user_id))
+                .set((user_totp::enabled.eq(true), user_totp::last_used_step.eq(step)))
+                .execute(conn)?;
+            replace_recovery_codes(conn, &ctx.config, user_id)
+                .map(|recovery_codes| Json(RecoveryCodes { recovery_codes }))
+                .map_err(ApiError::from)
+        })
+        .await
+}
+
+/// Replaces the recovery codes of the authenticated user.
+///
+/// Clients that didn't authenticate with a password must send a TOTP code
+/// or an unused recovery code in the `X-Totp-Code` header.
+#[utoipa::path(
+    post,
+    path = "/totp/recovery-codes",
+    tag = TOTP_TAG,
+    responses(
+        (status = 200, body = RecoveryCodes),
+        (status = 401, description = "TOTP code is missing or invalid"),
+        (status = 403, description = "Privileges are too low"),
+        (status = 422, description = "Two-factor authentication is not enabled"),
+    ),
+)]
+async fn regenerate_recovery_codes(
+    Ctx(ctx, connection_pool): Ctx,
+    headers: HeaderMap,
+) -> ApiResult<Json<RecoveryCodes>> {
+    ctx.verify_privilege(Action::UserTotpEditSelf)?;
+    let user_id = ctx.client.id.ok_or(ApiError::NotLoggedIn)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let enabled: Option<bool> = user_totp::table
+                .find(user_id)
+                .select(user_totp::enabled)
+                .first(conn)
+                .optional()?;
+            if enabled != Some(true) {
+                return Err(ApiError::TotpNotEnabled);
+            }
+            api::verify_second_factor(conn, &ctx.config, ctx.client, &headers)?;
+
+            replace_recovery_codes(conn, &ctx.config, user_id)
+                .map(|recovery_codes| Json(RecoveryCodes { recovery_codes }))
+                .map_err(ApiError::from)
+        })
+        .await
+}
+
+/// Disables two-factor authentication for the given user.
+///
+/// Users disabling their own two-factor authentication must send a TOTP code or an
+/// unused recovery code in the `X-Totp-Code` header if they didn't authenticate with
+/// a password. Users who have lost access to both can ask an administrator to disable
+/// two-factor authentication for them.
+#[utoipa::path(
+    delete,
+    path = "/totp/{username}",
+    tag = TOTP_TAG,
+    params(
+        ("username" = String, Path, description = "Username"),
+    ),
+    responses(
+        (status = 200, body = Object),
+        (status = 401, description = "TOTP code is missing or invalid"),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "User does not exist"),
+        (status = 422, description = "Two-factor authentication is not enabled"),
+    ),
+)]
+async fn disable(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(username): Path<SmallString>,
+    headers: HeaderMap,
+) -> ApiResult<Json<()>> {
+    ctx.verify_privilege(Action::UserTotpEditSelf)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let (user_id, target_rank) = user::table
+                .select((user::id, user::rank))
+                .filter(user::name.eq(username))
+                .first(conn)
+                .optional()?
+                .ok_or(ApiError::NotFound(ResourceType::User))?;
+            let enabled: Option<bool> = user_totp::table
+                .find(user_id)
+                .select(user_totp::enabled)
+                .first(conn)
+                .optional()?;
+            if enabled != Some(true) {
+                return Err(ApiError::TotpNotEnabled);
+            }
+
+            if ctx.client.id == Some(user_id) {
+                api::verify_second_factor(conn, &ctx.config, ctx.client, &headers)?;
+            } else {
+                ctx.verify_privilege(Action::UserTotpDisableAny)?;
+                api::verify_rank(ctx.client, target_rank)?;
+            }
+
+            diesel::delete(user_totp::table.find(user_id)).execute(conn)?;
+            diesel::delete(totp_recovery_code::table)
+                .filter(totp_recovery_code::user_id.eq(user_id))
+                .execute(conn)?;
+            Ok(Json(()))
+        })
+        .await
+}
+
+/// Replaces recovery codes of user with given `user_id` with new ones. Returns the new codes.
+fn replace_recovery_codes(conn: &mut PgConnection, config: &Config, user_id: i64) -> QueryResult<Vec<String>> {
+    diesel::delete(totp_recovery_code::table)
+        .filter(totp_recovery_code::user_id.eq(user_id))
+        .execute(conn)?;
+
+    let recovery_codes = totp::generate_recovery_codes();
+    let code_hashes: Vec<_> = recovery_codes
+        .iter()
+        .map(|code| totp::hash_recovery_code(config, code))
+        .collect();
+    let new_recovery_codes: Vec<_> = code_hashes
+        .iter()
+        .map(|code_hash| NewTotpRecoveryCode { user_id, code_hash })
+        .collect();
+    new_recovery_codes
+        .insert_into(totp_recovery_code::table)
+        .execute(conn)?;
+    Ok(recovery_codes)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::api::error::ApiResult;
+    use crate::auth::totp;
+    use crate::model::enums::UserRank;
+    use crate::model::user::{NewTotpRecoveryCode, NewUserTotp};
+    use crate::schema::{totp_recovery_code, user, user_totp};
+    use crate::test::*;
+    use diesel::{ExpressionMethods, Insertable, QueryDsl, RunQueryDsl};
+    use serial_test::{parallel, serial};
+
+    #[tokio::test]
+    #[serial]
+    async fn disable() -> ApiResult<()> {
+        const USER: UserRank = UserRank::Power;
+
+        let mut conn = get_connection()?;
+        let user_id: i64 = user::table
+            .select(user::id)
+            .filter(user::name.eq("power_user"))
+            .first(&mut conn)?;
+        let secret = totp::generate_secret();
+        let code_hash = totp::hash_recovery_code(&get_state().config, "abcde-fghij");
+        NewUserTotp {
+            user_id,
+            secret: &secret,
+        }
+        .insert_into(user_totp::table)
+        .execute(&mut conn)?;
+        diesel::update(user_totp::table.find(user_id))
+            .set(user_totp::enabled.eq(true))
+            .execute(&mut conn)?;
+        NewTotpRecoveryCode {
+            user_id,
+            code_hash: &code_hash,
+        }
+        .insert_into(totp_recovery_code::table)
+        .execute(&mut conn)?;
+
+        verify_response_with_user(USER, "GET /totp", "totp/get_status/missing_code").await?;
+        verify_response("DELETE /totp/power_user", "totp/disable/typical").await?;
+        verify_response_with_user(USER, "GET /totp", "totp/get_status/typical").await?;
+
+        let recovery_code_count: i64 = totp_recovery_code::table
+            .filter(totp_recovery_code::user_id.eq(user_id))
+            .count()
+            .first(&mut conn)?;
+        assert_eq!(recovery_code_count, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn error() -> ApiResult<()> {
+        const USER: UserRank = UserRank::Moderator;
+
+        verify_response_with_user(USER, "POST /totp/verify", "totp/verify/not_enrolled").await?;
+        verify_response_with_user(USER, "POST /totp/recovery-codes", "totp/regenerate_recovery_codes/not_enabled")
+            .await?;
+        verify_response("DELETE /totp/moderator", "totp/disable/not_enabled").await?;
+        verify_response("DELETE /totp/nobody", "totp/disable/nonexistent_user").await
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn unauthorized() -> ApiResult<()> {
+        const USER: UserRank = UserRank::Anonymous;
+
+        verify_response_with_user(USER, "GET /totp", "totp/get_status/unauthorized").await?;
+        verify_response_with_user(USER, "POST /totp", "totp/enroll/unauthorized").await
+    }
+}
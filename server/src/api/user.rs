@@ -1,25 +1,28 @@
 use crate::api::doc::USER_TAG;
 use crate::api::error::{self, ApiError, ApiResult};
 use crate::app::AppState;
+use crate::audit::{self, RequestInfo};
 use crate::auth::password;
-use crate::config::{Action, RegexType};
+use crate::config::{Action, RegexType, RegistrationMode};
 use crate::content::thumbnail::ThumbnailType;
 use crate::content::upload::{PartName, UploadToken};
 use crate::content::{Content, upload};
+use crate::email::verification;
 use crate::extract::{Ctx, DeleteBody, Json, JsonOrMultipart, PageParams, PagedResponse, Path, Query, ResourceParams};
-use crate::model::enums::{AvatarStyle, ResourceProperty, ResourceType, UserRank};
-use crate::model::user::NewUser;
+use crate::model::enums::{AuditEvent, AvatarStyle, ResourceProperty, ResourceType, UserRank};
+use crate::model::user::{NewEmailVerification, NewUser};
 use crate::resource::user::{Field, UserInfo, Visibility};
-use crate::schema::{database_statistics, user};
+use crate::schema::{database_statistics, email_verification, invite_code, user};
 use crate::search::Builder;
 use crate::search::user::QueryBuilder;
 use crate::string::{SecretString, SmallString, lower};
 use crate::time::DateTime;
 use crate::{api, filesystem, update};
-use axum::extract::DefaultBodyLimit;
+use axum::extract::{DefaultBodyLimit, State};
 use diesel::dsl::exists;
-use diesel::{ExpressionMethods, Insertable, OptionalExtension, QueryDsl, RunQueryDsl};
+use diesel::{BoolExpressionMethods, ExpressionMethods, Insertable, OptionalExtension, QueryDsl, RunQueryDsl};
 use serde::Deserialize;
+use tracing::error;
 use url::Url;
 use utoipa::ToSchema;
 use utoipa_axum::router::OpenApiRouter;
@@ -161,7 +164,12 @@ async fn get(
         .await
 }
 
-async fn create_impl(ctx: Ctx, params: ResourceParams<Field>, body: UserCreateBody) -> ApiResult<Json<UserInfo>> {
+async fn create_impl(
+    state: AppState,
+    ctx: Ctx,
+    params: ResourceParams<Field>,
+    body: UserCreateBody,
+) -> ApiResult<Json<UserInfo>> {
     ctx.verify_privilege(Action::UserCreateSelf)?;
 
     let creating_self = ctx.client.id.is_none();
@@ -195,7 +203,7 @@ async fn create_impl(ctx: Ctx, params: ResourceParams<Field>, body: UserCreateBo
     };
 
     let Ctx(ctx, connection_pool) = ctx;
-    let user_id = connection_pool
+    let (user_id, pending_verification) = connection_pool
         .transaction({
             let ctx = ctx.clone();
             move |conn| {
@@ -215,12 +223,41 @@ async fn create_impl(ctx: Ctx, params: ResourceParams<Field>, body: UserCreateBo
                     UserRank::Administrator
                 };
 
+                // The registration mode only restricts anonymous users, and never the first user
+                let registration_mode = if creating_self && user_count > 0 {
+                    ctx.config.public_info.registration_mode
+                } else {
+                    RegistrationMode::Open
+                };
+                if registration_mode == RegistrationMode::InviteCode {
+                    let code = body.invite_code.as_deref().ok_or(ApiError::InvalidInviteCode)?;
+                    let updated_rows = diesel::update(invite_code::table.find(code))
+                        .filter(invite_code::use_count.lt(invite_code::max_uses))
+                        .filter(
+                            invite_code::expiration_time
+                                .is_null()
+                                .or(invite_code::expiration_time.gt(DateTime::now())),
+                        )
+                        .set(invite_code::use_count.eq(invite_code::use_count + 1))
+                        .execute(conn)?;
+                    if updated_rows == 0 {
+                        return Err(ApiError::InvalidInviteCode);
+                    }
+                }
+                let verifying_email = registration_mode == RegistrationMode::EmailVerification;
+                if verifying_email && body.email.is_none() {
+                    return Err(ApiError::NoEmail);
+                }
+                if verifying_email {
+                    ctx.config.smtp().ok_or(ApiError::MissingSmtpInfo)?;
+                }
+
                 let (user_id, lowercase_name): (_, SmallString) = NewUser {
                     name: &body.name,
                     password_hash: hash.read(),
                     password_salt: salt.as_str(),
                     email: body.email.as_ref().map(SecretString::read),
-                    rank,
+                    rank: if verifying_email { UserRank::Restricted } else { rank },
                     avatar_style,
                 }
                 .insert_into(user::table)
@@ -240,10 +277,26 @@ async fn create_impl(ctx: Ctx, params: ResourceParams<Field>, body: UserCreateBo
                     update::user::avatar(conn, &ctx.config, user_id, &lowercase_name, avatar)?;
                 }
 
-                Ok::<_, ApiError>(user_id)
+                let pending_verification = match body.email.filter(|_| verifying_email) {
+                    Some(user_email) => {
+                        NewEmailVerification { user_id, rank }
+                            .insert_into(email_verification::table)
+                            .execute(conn)?;
+                        Some((body.name, user_email, salt))
+                    }
+                    None => None,
+                };
+                Ok::<_, ApiError>((user_id, pending_verification))
             }
         })
         .await?;
+
+    // The email is only sent once the user exists. If sending fails, the user can request it again.
+    if let Some((name, user_email, salt)) = pending_verification
+        && let Err(err) = verification::send(&state, &name, user_email.read(), salt.as_str())
+    {
+        error!("Could not send verification email to user {user_id}. Details:\n{err}");
+    }
     connection_pool
         .transaction(move |conn| UserInfo::new_from_id(conn, &ctx.config, user_id, params.fields, Visibility::Full))
         .await
@@ -269,6 +322,8 @@ struct UserCreateBody {
     avatar_token: Option<UploadToken>,
     /// URL to fetch avatar from.
     avatar_url: Option<Url>,
+    /// Invite code. Required for registering when `registration_mode` is `invite_code`.
+    invite_code: Option<String>,
 }
 
 /// Creates a new user using specified parameters.
@@ -281,6 +336,11 @@ struct UserCreateBody {
 /// user happens to be the first user ever created, become an administrator,
 /// whereas subsequent users will be given the rank indicated by `default_rank`
 /// in the server's configuration.
+///
+/// Users registering themselves are subject to `registration_mode` from the
+/// server's configuration. In `invite_code` mode, they must provide a valid
+/// invite code. In `email_verification` mode, they must provide an email
+/// address, and stay restricted until they follow the link sent to it.
 #[utoipa::path(
     post,
     path = "/users",
@@ -297,27 +357,30 @@ struct UserCreateBody {
         (status = 400, description = "Avatar is missing for manual avatar style"),
         (status = 403, description = "Privileges are too low"),
         (status = 403, description = "Trying to set rank higher than own rank"),
+        (status = 403, description = "Invite code is missing or invalid"),
         (status = 409, description = "A user with such name already exists"),
         (status = 422, description = "User name is missing or invalid"),
         (status = 422, description = "Password is missing or invalid"),
         (status = 422, description = "Email is invalid"),
+        (status = 422, description = "Email is missing in email verification mode"),
         (status = 422, description = "Rank is invalid"),
     ),
 )]
 async fn create(
+    State(state): State<AppState>,
     ctx: Ctx,
     Query(params): Query<ResourceParams<Field>>,
     body: JsonOrMultipart<UserCreateBody>,
 ) -> ApiResult<Json<UserInfo>> {
     match body {
-        JsonOrMultipart::Json(payload) => create_impl(ctx, params, payload).await,
+        JsonOrMultipart::Json(payload) => create_impl(state, ctx, params, payload).await,
         JsonOrMultipart::Multipart(payload) => {
             let decoded_body = upload::extract(&ctx.config, payload, [PartName::Avatar]).await?;
             let metadata = decoded_body.metadata.ok_or(ApiError::MissingMetadata)?;
             let mut new_user: UserCreateBody = serde_json::from_slice(&metadata)?;
             if let [Some(avatar_token)] = decoded_body.files {
                 new_user.avatar_token = Some(avatar_token);
-                create_impl(ctx, params, new_user).await
+                create_impl(state, ctx, params, new_user).await
             } else {
                 Err(ApiError::MissingFormData)
             }
@@ -329,6 +392,7 @@ async fn update_impl(
     ctx: Ctx,
     username: SmallString,
     params: ResourceParams<Field>,
+    request_info: RequestInfo,
     body: UserUpdateBody,
 ) -> ApiResult<Json<UserInfo>> {
     let custom_avatar = match Content::new(body.avatar_token, body.avatar_url) {
@@ -398,6 +462,11 @@ async fn update_impl(
                     diesel::update(user::table.find(user_id))
                         .set(user::rank.eq(rank))
                         .execute(conn)?;
+                    if rank != target_rank {
+                        let details = audit::rank_change_details(target_rank, rank);
+                        let event = AuditEvent::RankChange;
+                        audit::record(conn, &request_info, event, ctx.client.id, Some(&username), Some(&details))?;
+                    }
                 }
                 if let Some(avatar_style) = body.avatar_style {
                     ctx.verify_privilege(Action::UserEditSelfAvatar)?;
@@ -515,19 +584,20 @@ async fn update(
     ctx: Ctx,
     Path(username): Path<SmallString>,
     Query(params): Query<ResourceParams<Field>>,
+    request_info: RequestInfo,
     body: JsonOrMultipart<UserUpdateBody>,
 ) -> ApiResult<Json<UserInfo>> {
     ctx.verify_privilege(Action::UserView)?;
 
     match body {
-        JsonOrMultipart::Json(payload) => update_impl(ctx, username, params, payload).await,
+        JsonOrMultipart::Json(payload) => update_impl(ctx, username, params, request_info, payload).await,
         JsonOrMultipart::Multipart(payload) => {
             let decoded_body = upload::extract(&ctx.config, payload, [PartName::Avatar]).await?;
             let metadata = decoded_body.metadata.ok_or(ApiError::MissingMetadata)?;
             let mut user_update: UserUpdateBody = serde_json::from_slice(&metadata)?;
             if let [Some(avatar_token)] = decoded_body.files {
                 user_update.avatar_token = Some(avatar_token);
-                update_impl(ctx, username, params, user_update).await
+                update_impl(ctx, username, params, request_info, user_update).await
             } else {
                 Err(ApiError::MissingFormData)
             }
@@ -772,6 +842,12 @@ mod test {
         verify_response("PUT /user/regular_user", "user/edit/avatar_too_tall").await?;
         verify_response("PUT /user/regular_user", "user/edit/avatar_too_large").await?;
 
+        // Check registration modes restrict anonymous users
+        const ANONYMOUS: UserRank = UserRank::Anonymous;
+        verify_response_with_user(ANONYMOUS, "POST /users", "user/create/missing_invite_code").await?;
+        verify_response_with_user(ANONYMOUS, "POST /users", "user/create/invalid_invite_code").await?;
+        verify_response_with_user(ANONYMOUS, "POST /users", "user/create/verification_without_email").await?;
+
         // Check user can't create or promote another user to higher rank
         verify_response_with_user(UserRank::Regular, "POST /users", "user/create/higher_rank").await?;
         verify_response_with_user(UserRank::Regular, "PUT /user/restricted_user", "user/edit/higher_rank").await?;
@@ -0,0 +1,200 @@
+use crate::api::doc::USER_NOTE_TAG;
+use crate::api::error::{ApiError, ApiResult};
+use crate::app::AppState;
+use crate::config::Action;
+use crate::extract::{Ctx, Json, Path, Query, ResourceParams, UnpagedResponse};
+use crate::model::enums::ResourceType;
+use crate::model::user::NewUserNote;
+use crate::resource::user_note::{Field, UserNoteInfo};
+use crate::schema::{user, user_note};
+use crate::snapshot;
+use crate::string::SmallString;
+use diesel::{ExpressionMethods, Insertable, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list))
+        .routes(routes!(create))
+        .routes(routes!(delete))
+}
+
+/// Lists all moderator notes attached to the given user.
+#[utoipa::path(
+    get,
+    path = "/user-notes/{username}",
+    tag = USER_NOTE_TAG,
+    params(
+        ("username" = String, Path, description = "Username"),
+        ResourceParams,
+    ),
+    responses(
+        (status = 200, body = UnpagedResponse<UserNoteInfo>),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "User does not exist"),
+    ),
+)]
+async fn list(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(username): Path<SmallString>,
+    Query(params): Query<ResourceParams<Field>>,
+) -> ApiResult<Json<UnpagedResponse<UserNoteInfo>>> {
+    ctx.verify_privilege(Action::UserNoteList)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let (user_id, _) = get_user(conn, &username)?;
+            let notes = user_note::table
+                .filter(user_note::user_id.eq(user_id))
+                .order(user_note::id.desc())
+                .load(conn)?;
+            let results = UserNoteInfo::new_batch(conn, &ctx.config, notes, params.fields)?;
+            Ok::<_, ApiError>(Json(UnpagedResponse { results }))
+        })
+        .await
+}
+
+/// Request body for attaching a note to a user.
+#[derive(Deserialize, ToSchema)]
+struct UserNoteCreateBody {
+    /// The note contents.
+    text: String,
+}
+
+/// Attaches a moderator note to a user.
+#[utoipa::path(
+    post,
+    path = "/user-note/{username}",
+    tag = USER_NOTE_TAG,
+    params(
+        ("username" = String, Path, description = "Username"),
+        ResourceParams,
+    ),
+    request_body = UserNoteCreateBody,
+    responses(
+        (status = 200, body = UserNoteInfo),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "User does not exist"),
+    ),
+)]
+async fn create(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(username): Path<SmallString>,
+    Query(params): Query<ResourceParams<Field>>,
+    Json(body): Json<UserNoteCreateBody>,
+) -> ApiResult<Json<UserNoteInfo>> {
+    ctx.verify_privilege(Action::UserNoteCreate)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let (user_id, username) = get_user(conn, &username)?;
+            let note_id = NewUserNote {
+                user_id,
+                author_id: ctx.client.id,
+                text: &body.text,
+            }
+            .insert_into(user_note::table)
+            .returning(user_note::id)
+            .get_result(conn)?;
+            snapshot::user::note_creation_snapshot(conn, ctx.client, username, note_id)?;
+
+            UserNoteInfo::new_from_id(conn, &ctx.config, note_id, params.fields)
+                .map(Json)
+                .map_err(ApiError::from)
+        })
+        .await
+}
+
+/// Deletes an existing user note.
+#[utoipa::path(
+    delete,
+    path = "/user-note/{username}/{id}",
+    tag = USER_NOTE_TAG,
+    params(
+        ("username" = String, Path, description = "Username"),
+        ("id" = i64, Path, description = "Note ID"),
+    ),
+    request_body = Object,
+    responses(
+        (status = 200, body = Object),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "User does not exist"),
+        (status = 404, description = "Note does not exist"),
+    ),
+)]
+async fn delete(
+    Ctx(ctx, connection_pool): Ctx,
+    Path((username, note_id)): Path<(SmallString, i64)>,
+) -> ApiResult<Json<()>> {
+    ctx.verify_privilege(Action::UserNoteDelete)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let (user_id, username) = get_user(conn, &username)?;
+            let note_id: i64 = diesel::delete(user_note::table.find(note_id).filter(user_note::user_id.eq(user_id)))
+                .returning(user_note::id)
+                .get_result(conn)
+                .optional()?
+                .ok_or(ApiError::NotFound(ResourceType::UserNote))?;
+            snapshot::user::note_deletion_snapshot(conn, ctx.client, username, note_id)?;
+            Ok::<_, ApiError>(Json(()))
+        })
+        .await
+}
+
+/// Retrieves the id and canonical name of the user called `username`.
+fn get_user(conn: &mut PgConnection, username: &str) -> ApiResult<(i64, SmallString)> {
+    user::table
+        .select((user::id, user::name))
+        .filter(user::name.eq(username))
+        .first(conn)
+        .optional()?
+        .ok_or(ApiError::NotFound(ResourceType::User))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::api::error::ApiResult;
+    use crate::model::enums::{ResourceType, UserRank};
+    use crate::test::*;
+    use serial_test::{parallel, serial};
+
+    // Exclude fields that involve creation_time
+    const FIELDS: &str = "&fields=id,user,author,text";
+
+    #[tokio::test]
+    #[parallel]
+    async fn list() -> ApiResult<()> {
+        verify_response(&format!("GET /user-notes/regular_user/?{FIELDS}"), "user_note/list/typical").await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn create() -> ApiResult<()> {
+        const USER: &str = "power_user";
+        verify_response(&format!("POST /user-note/{USER}/?{FIELDS}"), "user_note/create/typical").await?;
+        verify_response(&format!("DELETE /user-note/{USER}/3"), "user_note/delete/typical").await?;
+        reset_sequence(ResourceType::UserNote)
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn error() -> ApiResult<()> {
+        verify_response("GET /user-notes/fake_user", "user_note/list/nonexistent_user").await?;
+        verify_response("POST /user-note/fake_user", "user_note/create/nonexistent_user").await?;
+        verify_response("DELETE /user-note/fake_user/1", "user_note/delete/nonexistent_user").await?;
+        verify_response("DELETE /user-note/power_user/1", "user_note/delete/nonexistent_note").await
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn unauthorized() -> ApiResult<()> {
+        const USER: UserRank = UserRank::Power;
+        verify_response_with_user(USER, "GET /user-notes/regular_user", "user_note/list/unauthorized").await?;
+        verify_response_with_user(USER, "POST /user-note/regular_user", "user_note/create/unauthorized").await?;
+        verify_response_with_user(USER, "DELETE /user-note/regular_user/1", "user_note/delete/unauthorized").await
+    }
+}
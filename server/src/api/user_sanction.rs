@@ -0,0 +1,271 @@
+use crate::api::doc::USER_SANCTION_TAG;
+use crate::api::error::{ApiError, ApiResult};
+use crate::app::AppState;
+use crate::auth::Client;
+use crate::config::Action;
+use crate::extract::{Ctx, Json, Path, Query, ResourceParams, UnpagedResponse};
+use crate::model::enums::{ResourceType, SanctionKind, UserRank};
+use crate::model::user::{NewUserSanction, UserSanction};
+use crate::resource::user_sanction::{Field, UserSanctionInfo};
+use crate::schema::{user, user_sanction};
+use crate::snapshot;
+use crate::string::SmallString;
+use crate::time::DateTime;
+use diesel::{ExpressionMethods, Insertable, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list))
+        .routes(routes!(create))
+        .routes(routes!(delete))
+}
+
+/// Lists all sanctions issued against the given user, including expired ones.
+#[utoipa::path(
+    get,
+    path = "/user-sanctions/{username}",
+    tag = USER_SANCTION_TAG,
+    params(
+        ("username" = String, Path, description = "Username"),
+        ResourceParams,
+    ),
+    responses(
+        (status = 200, body = UnpagedResponse<UserSanctionInfo>),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "User does not exist"),
+    ),
+)]
+async fn list(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(username): Path<SmallString>,
+    Query(params): Query<ResourceParams<Field>>,
+) -> ApiResult<Json<UnpagedResponse<UserSanctionInfo>>> {
+    ctx.verify_privilege(Action::UserSanctionList)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let (user_id, ..) = get_user(conn, &username)?;
+            let sanctions = user_sanction::table
+                .filter(user_sanction::user_id.eq(user_id))
+                .order(user_sanction::id.desc())
+                .load(conn)?;
+            let results = UserSanctionInfo::new_batch(conn, &ctx.config, sanctions, params.fields)?;
+            Ok::<_, ApiError>(Json(UnpagedResponse { results }))
+        })
+        .await
+}
+
+/// Request body for sanctioning a user.
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct UserSanctionCreateBody {
+    /// Whether to suspend or ban the user.
+    #[serde(rename = "type")]
+    kind: SanctionKind,
+    /// Why the user is being sanctioned.
+    reason: String,
+    /// Time the sanction expires. Sanctions without an expiration time are permanent.
+    expiration_time: Option<DateTime>,
+}
+
+/// Suspends or bans a user.
+///
+/// Suspended users are treated as restricted users until the suspension expires.
+/// Banned users cannot authenticate at all until the ban expires.
+#[utoipa::path(
+    post,
+    path = "/user-sanction/{username}",
+    tag = USER_SANCTION_TAG,
+    params(
+        ("username" = String, Path, description = "Username"),
+        ResourceParams,
+    ),
+    request_body = UserSanctionCreateBody,
+    responses(
+        (status = 200, body = UserSanctionInfo),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "User does not exist"),
+    ),
+)]
+async fn create(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(username): Path<SmallString>,
+    Query(params): Query<ResourceParams<Field>>,
+    Json(body): Json<UserSanctionCreateBody>,
+) -> ApiResult<Json<UserSanctionInfo>> {
+    ctx.verify_privilege(Action::UserSanctionCreate)?;
+    if body
+        .expiration_time
+        .as_ref()
+        .is_some_and(|time| *time <= DateTime::now())
+    {
+        return Err(ApiError::PastExpirationTime);
+    }
+
+    connection_pool
+        .transaction(move |conn| {
+            let (user_id, username, target_rank) = get_user(conn, &username)?;
+            verify_sanctionable(ctx.client, user_id, target_rank)?;
+
+            let sanction: UserSanction = NewUserSanction {
+                user_id,
+                issuer_id: ctx.client.id,
+                kind: body.kind,
+                reason: &body.reason,
+                expiration_time: body.expiration_time,
+            }
+            .insert_into(user_sanction::table)
+            .get_result(conn)?;
+            snapshot::user::sanction_creation_snapshot(conn, ctx.client, username, &sanction)?;
+
+            UserSanctionInfo::new_from_id(conn, &ctx.config, sanction.id, params.fields)
+                .map(Json)
+                .map_err(ApiError::from)
+        })
+        .await
+}
+
+/// Lifts an existing sanction.
+#[utoipa::path(
+    delete,
+    path = "/user-sanction/{username}/{id}",
+    tag = USER_SANCTION_TAG,
+    params(
+        ("username" = String, Path, description = "Username"),
+        ("id" = i64, Path, description = "Sanction ID"),
+    ),
+    request_body = Object,
+    responses(
+        (status = 200, body = Object),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "User does not exist"),
+        (status = 404, description = "Sanction does not exist"),
+    ),
+)]
+async fn delete(
+    Ctx(ctx, connection_pool): Ctx,
+    Path((username, sanction_id)): Path<(SmallString, i64)>,
+) -> ApiResult<Json<()>> {
+    ctx.verify_privilege(Action::UserSanctionDelete)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let (user_id, username, target_rank) = get_user(conn, &username)?;
+            verify_sanctionable(ctx.client, user_id, target_rank)?;
+
+            let sanction: UserSanction = diesel::delete(
+                user_sanction::table
+                    .find(sanction_id)
+                    .filter(user_sanction::user_id.eq(user_id)),
+            )
+            .get_result(conn)
+            .optional()?
+            .ok_or(ApiError::NotFound(ResourceType::UserSanction))?;
+            snapshot::user::sanction_deletion_snapshot(conn, ctx.client, username, &sanction)?;
+            Ok::<_, ApiError>(Json(()))
+        })
+        .await
+}
+
+/// Checks that the `client` may sanction the user with the given id and rank, or lift their sanctions.
+/// Users can only sanction users of strictly lower rank than themselves, and never themselves.
+fn verify_sanctionable(client: Client, user_id: i64, user_rank: UserRank) -> ApiResult<()> {
+    if client.id == Some(user_id) {
+        return Err(ApiError::SelfSanction);
+    }
+    (client.rank > user_rank)
+        .then_some(())
+        .ok_or(ApiError::InsufficientPrivileges)
+}
+
+/// Retrieves the id, canonical name, and rank of the user called `username`.
+fn get_user(conn: &mut PgConnection, username: &str) -> ApiResult<(i64, SmallString, UserRank)> {
+    user::table
+        .select((user::id, user::name, user::rank))
+        .filter(user::name.eq(username))
+        .first(conn)
+        .optional()?
+        .ok_or(ApiError::NotFound(ResourceType::User))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::api::error::ApiResult;
+    use crate::model::enums::{ResourceType, UserRank};
+    use crate::test::*;
+    use serial_test::{parallel, serial};
+
+    // Exclude fields that involve creation_time
+    const FIELDS: &str = "&fields=id,user,issuer,type,reason,expirationTime";
+
+    #[tokio::test]
+    #[parallel]
+    async fn list() -> ApiResult<()> {
+        verify_response(&format!("GET /user-sanctions/regular_user/?{FIELDS}"), "user_sanction/list/typical").await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn suspend() -> ApiResult<()> {
+        const USER: &str = "power_user";
+        const QUERY: &str = "GET /user/power_user/?fields=name";
+        verify_response(&format!("POST /user-sanction/{USER}/?{FIELDS}"), "user_sanction/create/suspension").await?;
+        verify_response_with_user(UserRank::Power, QUERY, "user_sanction/create/suspended_user").await?;
+
+        verify_response(&format!("DELETE /user-sanction/{USER}/3"), "user_sanction/delete/suspension").await?;
+        verify_response_with_user(UserRank::Power, QUERY, "user_sanction/delete/unsuspended_user").await?;
+        reset_sequence(ResourceType::UserSanction)
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn ban() -> ApiResult<()> {
+        const USER: &str = "regular_user";
+        const QUERY: &str = "GET /user/regular_user/?fields=name";
+        verify_response(&format!("POST /user-sanction/{USER}/?{FIELDS}"), "user_sanction/create/ban").await?;
+        verify_response_with_user(UserRank::Regular, QUERY, "user_sanction/create/banned_user").await?;
+
+        verify_response(&format!("DELETE /user-sanction/{USER}/3"), "user_sanction/delete/ban").await?;
+        verify_response_with_user(UserRank::Regular, QUERY, "user_sanction/delete/unbanned_user").await?;
+        reset_sequence(ResourceType::UserSanction)
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn error() -> ApiResult<()> {
+        verify_response("GET /user-sanctions/fake_user", "user_sanction/list/nonexistent_user").await?;
+        verify_response("POST /user-sanction/fake_user", "user_sanction/create/nonexistent_user").await?;
+        verify_response("DELETE /user-sanction/fake_user/1", "user_sanction/delete/nonexistent_user").await?;
+        verify_response("DELETE /user-sanction/power_user/1", "user_sanction/delete/nonexistent_sanction").await?;
+        verify_response("POST /user-sanction/administrator", "user_sanction/create/self").await?;
+        verify_response("POST /user-sanction/regular_user", "user_sanction/create/past_expiration").await
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn unauthorized() -> ApiResult<()> {
+        const USER: UserRank = UserRank::Power;
+        verify_response_with_user(USER, "GET /user-sanctions/regular_user", "user_sanction/list/unauthorized").await?;
+        verify_response_with_user(USER, "POST /user-sanction/regular_user", "user_sanction/create/unauthorized")
+            .await?;
+        verify_response_with_user(USER, "DELETE /user-sanction/regular_user/2", "user_sanction/delete/unauthorized")
+            .await?;
+
+        verify_response_with_user(
+            UserRank::Moderator,
+            "POST /user-sanction/administrator",
+            "user_sanction/create/higher_rank",
+        )
+        .await?;
+        verify_response_with_user(
+            UserRank::Administrator,
+            "DELETE /user-sanction/administrator/1",
+            "user_sanction/delete/self",
+        )
+        .await
+    }
+}
@@ -0,0 +1,364 @@
+use crate::api;
+use crate::api::doc::USER_SESSION_TAG;
+use crate::api::error::{ApiError, ApiResult};
+use crate::app::AppState;
+use crate::audit::{self, RequestInfo};
+use crate::auth::session;
+use crate::config::Action;
+use crate::extract::{Ctx, Json, Path, Query, ResourceParams, UnpagedResponse};
+use crate::model::enums::{AuditEvent, ResourceType};
+use crate::model::user::{NewUserSession, UserSession};
+use crate::resource::user::MicroUser;
+use crate::resource::user_session::{Field, UserSessionInfo};
+use crate::schema::{user, user_session};
+use crate::string::{SmallString, lower};
+use crate::time::DateTime;
+use axum::extract::State;
+use axum::http::header::SET_COOKIE;
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use axum::response::AppendHeaders;
+use diesel::dsl::sql;
+use diesel::sql_types::Integer;
+use diesel::{ExpressionMethods, Insertable, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
+use serde::Serialize;
+use utoipa::ToSchema;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list))
+        .routes(routes!(create, end))
+        .routes(routes!(delete))
+}
+
+type SetCookies = AppendHeaders<[(HeaderName, HeaderValue); 2]>;
+
+/// A newly created browser session.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct NewSessionInfo {
+    #[serde(flatten)]
+    session: UserSessionInfo,
+    /// Token that must be sent in the `X-CSRF-Token` header of requests that can modify state.
+    csrf_token: String,
+}
+
+/// Searches for sessions of the given user.
+#[utoipa::path(
+    get,
+    path = "/user-sessions/{username}",
+    tag = USER_SESSION_TAG,
+    params(
+        ("username" = String, Path, description = "Username"),
+        ResourceParams,
+    ),
+    responses(
+        (status = 200, body = UnpagedResponse<UserSessionInfo>),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "User does not exist"),
+    ),
+)]
+async fn list(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(username): Path<SmallString>,
+    Query(params): Query<ResourceParams<Field>>,
+) -> ApiResult<Json<UnpagedResponse<UserSessionInfo>>> {
+    ctx.verify_privilege(Action::UserSessionListSelf)?;
+
+    let (lowercase_name, avatar_style, user_sessions) = connection_pool
+        .transaction({
+            let ctx = ctx.clone();
+            let username = username.clone();
+            move |conn| {
+                let (user_id, lowercase_name, avatar_style, target_rank): (_, SmallString, _, _) = user::table
+                    .select((user::id, lower(user::name), user::avatar_style, user::rank))
+                    .filter(user::name.eq(&username))
+                    .first(conn)
+                    .optional()?
+                    .ok_or(ApiError::NotFound(ResourceType::User))?;
+                if ctx.client.id != Some(user_id) {
+                    ctx.verify_privilege(Action::UserSessionListAny)?;
+                    api::verify_rank(ctx.client, target_rank)?;
+                }
+
+                user_session::table
+                    .select(UserSession::as_select())
+                    .filter(user_session::user_id.eq(user_id))
+                    .filter(user_session::expiration_time.gt(DateTime::now()))
+                    .order(user_session::creation_time.desc())
+                    .load(conn)
+                    .map(|sessions| (lowercase_name, avatar_style, sessions))
+                    .map_err(ApiError::from)
+            }
+        })
+        .await?;
+
+    let results = user_sessions
+        .into_iter()
+        .map(|user_session| {
+            UserSessionInfo::new(
+                MicroUser::new(&ctx.config, username.clone(), &lowercase_name, avatar_style),
+                user_session,
+                params.fields,
+            )
+        })
+        .collect();
+    Ok(Json(UnpagedResponse { results }))
+}
+
+/// Starts a browser session for the authenticated user.
+///
+/// The response sets an `HttpOnly` session cookie, which authenticates subsequent requests
+/// that don't have an `Authorization` header, and a CSRF cookie. Requests authenticated by
+/// the session cookie that can modify state must copy the CSRF token into the `X-CSRF-Token`
+/// header. Users with two-factor authentication enabled who are authenticated by a token or
+/// another session must send a TOTP code in the `X-Totp-Code` header.
+#[utoipa::path(
+    post,
+    path = "/user-session",
+    tag = USER_SESSION_TAG,
+    params(ResourceParams),
+    responses(
+        (status = 200, body = NewSessionInfo),
+        (status = 401, description = "Client is not logged in"),
+        (status = 401, description = "TOTP code is missing or invalid"),
+        (status = 403, description = "Privileges are too low"),
+    ),
+)]
+async fn create(
+    Ctx(ctx, connection_pool): Ctx,
+    Query(params): Query<ResourceParams<Field>>,
+    request_info: RequestInfo,
+    headers: HeaderMap,
+) -> ApiResult<(SetCookies, Json<NewSessionInfo>)> {
+    ctx.verify_privilege(Action::UserSessionCreate)?;
+    let user_id = ctx.client.id.ok_or(ApiError::NotLoggedIn)?;
+
+    let secret = session::generate_secret();
+    let expiration_time =
+        DateTime::from(*DateTime::now() + time::Duration::days(ctx.config.session_lifetime_days.into()));
+    let (user_session, name, lowercase_name, avatar_style) = connection_pool
+        .transaction({
+            let ctx = ctx.clone();
+            let secret_hash = session::hash_secret(&secret);
+            move |conn| {
+                api::verify_second_factor(conn, &ctx.config, ctx.client, &headers)?;
+
+                // Delete any expired sessions owned by user
+                diesel::delete(user_session::table)
+                    .filter(user_session::user_id.eq(user_id))
+                    .filter(user_session::expiration_time.lt(DateTime::now()))
+                    .execute(conn)?;
+
+                let user_session = NewUserSession {
+                    user_id,
+                    secret_hash: &secret_hash,
+                    expiration_time,
+                }
+                .insert_into(user_session::table)
+                .returning(UserSession::as_returning())
+                .get_result(conn)?;
+                audit::record(conn, &request_info, AuditEvent::Login, Some(user_id), None, None)?;
+
+                let (name, lowercase_name, avatar_style): (SmallString, SmallString, _) = user::table
+                    .find(user_id)
+                    .select((user::name, lower(user::name), user::avatar_style))
+                    .first(conn)?;
+                Ok::<_, ApiError>((user_session, name, lowercase_name, avatar_style))
+            }
+        })
+        .await?;
+
+    let session_info = NewSessionInfo {
+        session: UserSessionInfo::new(
+            MicroUser::new(&ctx.config, name, &lowercase_name, avatar_style),
+            user_session,
+            params.fields,
+        ),
+        csrf_token: session::csrf_token(&ctx.config, &secret),
+    };
+    Ok((set_cookies(session::session_cookies(&ctx.config, &secret)), Json(session_info)))
+}
+
+/// Ends the browser session the request is authenticated by.
+///
+/// The response clears the session and CSRF cookies. Succeeds even if the request doesn't
+/// carry a session cookie.
+#[utoipa::path(
+    delete,
+    path = "/user-session",
+    tag = USER_SESSION_TAG,
+    responses(
+        (status = 200, body = Object),
+        (status = 403, description = "CSRF token is missing or invalid"),
+    ),
+)]
+async fn end(State(state): State<AppState>, headers: HeaderMap) -> ApiResult<(SetCookies, Json<()>)> {
+    if let Some(secret) = session::find_cookie(&headers, session::SESSION_COOKIE) {
+        let secret_hash = session::hash_secret(secret);
+        state
+            .connection_pool
+            .transaction(move |conn| {
+                diesel::delete(user_session::table)
+                    .filter(user_session::secret_hash.eq(secret_hash))
+                    .execute(conn)
+            })
+            .await?;
+    }
+    Ok((set_cookies(session::expired_cookies(&state.config)), Json(())))
+}
+
+/// Revokes an existing session.
+#[utoipa::path(
+    delete,
+    path = "/user-session/{username}/{id}",
+    tag = USER_SESSION_TAG,
+    params(
+        ("username" = String, Path, description = "Username"),
+        ("id" = i64, Path, description = "Session ID"),
+    ),
+    responses(
+        (status = 200, body = Object),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "User does not exist"),
+        (status = 404, description = "Session does not exist"),
+    ),
+)]
+async fn delete(Ctx(ctx, connection_pool): Ctx, Path((username, id)): Path<(String, i64)>) -> ApiResult<Json<()>> {
+    ctx.verify_privilege(Action::UserSessionDeleteSelf)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let (session_owner, target_rank) = user::table
+                .select((user::id, user::rank))
+                .filter(user::name.eq(username))
+                .first(conn)
+                .optional()?
+                .ok_or(ApiError::NotFound(ResourceType::User))?;
+            if ctx.client.id != Some(session_owner) {
+                ctx.verify_privilege(Action::UserSessionDeleteAny)?;
+                api::verify_rank(ctx.client, target_rank)?;
+            }
+
+            let _: i32 = diesel::delete(
+                user_session::table
+                    .find(id)
+                    .filter(user_session::user_id.eq(session_owner)),
+            )
+            .returning(sql::<Integer>("0"))
+            .get_result(conn)
+            .optional()?
+            .ok_or(ApiError::NotFound(ResourceType::UserSession))?;
+            Ok::<_, ApiError>(Json(()))
+        })
+        .await
+}
+
+fn set_cookies(cookies: [HeaderValue; 2]) -> SetCookies {
+    AppendHeaders(cookies.map(|cookie| (SET_COOKIE, cookie)))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::api::error::ApiResult;
+    use crate::auth::{header, session};
+    use crate::model::enums::UserRank;
+    use crate::test::*;
+    use axum::http::StatusCode;
+    use axum::http::header::{AUTHORIZATION, COOKIE, SET_COOKIE};
+    use serde_json::Value;
+    use serial_test::{parallel, serial};
+
+    #[tokio::test]
+    #[serial]
+    async fn session_lifecycle() -> ApiResult<()> {
+        let server = test_server(get_state());
+        let credentials = header::basic_credentials_for("regular_user", TEST_PASSWORD);
+        let response = server
+            .post("/user-session")
+            .add_header(AUTHORIZATION, credentials)
+            .await;
+        response.assert_status_ok();
+
+        let set_cookies: Vec<&str> = response
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .map(|value| value.to_str().unwrap())
+            .collect();
+        let cookie_value = |name: &str| {
+            set_cookies
+                .iter()
+                .find_map(|cookie| cookie.strip_prefix(&format!("{name}=")))
+                .and_then(|cookie| cookie.split(';').next())
+                .unwrap()
+                .to_owned()
+        };
+        let secret = cookie_value(session::SESSION_COOKIE);
+        let csrf_token = cookie_value(session::CSRF_COOKIE);
+        let body: Value = response.json();
+        assert_eq!(body["csrfToken"], csrf_token.as_str());
+        assert_eq!(body["user"]["name"], "regular_user");
+
+        let cookies = format!("{}={secret}; {}={csrf_token}", session::SESSION_COOKIE, session::CSRF_COOKIE);
+        let response = server
+            .get("/user-sessions/regular_user?fields=id")
+            .add_header(COOKIE, &cookies)
+            .await;
+        response.assert_status_ok();
+        let sessions: Value = response.json();
+        assert!(
+            sessions["results"]
+                .as_array()
+                .is_some_and(|results| !results.is_empty())
+        );
+
+        // Requests that can modify state must carry the CSRF token
+        let response = server.delete("/user-session").add_header(COOKIE, &cookies).await;
+        response.assert_status(StatusCode::FORBIDDEN);
+        let response = server
+            .delete("/user-session")
+            .add_header(COOKIE, &cookies)
+            .add_header(session::CSRF_HEADER, "garbage")
+            .await;
+        response.assert_status(StatusCode::FORBIDDEN);
+        let response = server
+            .delete("/user-session")
+            .add_header(COOKIE, &cookies)
+            .add_header(session::CSRF_HEADER, &csrf_token)
+            .await;
+        response.assert_status_ok();
+
+        // Ended sessions are treated as anonymous and the browser is told to forget them
+        let response = server
+            .get("/user-sessions/regular_user")
+            .add_header(COOKIE, &cookies)
+            .await;
+        response.assert_status(StatusCode::FORBIDDEN);
+        assert!(
+            response
+                .headers()
+                .get_all(SET_COOKIE)
+                .iter()
+                .any(|cookie| cookie.to_str().unwrap().contains("Max-Age=0"))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn error() -> ApiResult<()> {
+        verify_response("GET /user-sessions/nobody", "user_session/list/nonexistent_user").await?;
+        verify_response("DELETE /user-session/administrator/0", "user_session/delete/nonexistent_session").await
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn unauthorized() -> ApiResult<()> {
+        const USER: UserRank = UserRank::Anonymous;
+
+        verify_response_with_user(USER, "GET /user-sessions/regular_user", "user_session/list/unauthorized").await?;
+        verify_response_with_user(USER, "POST /user-session", "user_session/create/unauthorized").await
+    }
+}
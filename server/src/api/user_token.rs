@@ -2,15 +2,17 @@ use crate::api;
 use crate::api::doc::USER_TOKEN_TAG;
 use crate::api::error::{ApiError, ApiResult};
 use crate::app::AppState;
+use crate::audit::{self, RequestInfo};
 use crate::config::Action;
 use crate::extract::{Ctx, Json, Path, Query, ResourceParams, UnpagedResponse};
-use crate::model::enums::ResourceType;
+use crate::model::enums::{AuditEvent, ResourceType};
 use crate::model::user::{NewUserToken, UserToken};
 use crate::resource::user::MicroUser;
 use crate::resource::user_token::{Field, UserTokenInfo};
 use crate::schema::{user, user_token};
 use crate::string::{LargeString, SmallString, lower};
 use crate::time::DateTime;
+use axum::http::HeaderMap;
 use diesel::dsl::sql;
 use diesel::sql_types::Integer;
 use diesel::{
@@ -105,6 +107,8 @@ struct UserTokenCreateBody {
 /// Creates a new user token for authentication.
 ///
 /// The token can be used for authentication of API endpoints instead of a password.
+/// Users with two-factor authentication enabled who create a token for themselves
+/// must either authenticate with a password or send a TOTP code in the `X-Totp-Code` header.
 #[utoipa::path(
     post,
     path = "/user-token/{username}",
@@ -116,6 +120,7 @@ struct UserTokenCreateBody {
     request_body = UserTokenCreateBody,
     responses(
         (status = 200, body = UserTokenInfo),
+        (status = 401, description = "TOTP code is missing or invalid"),
         (status = 403, description = "Privileges are too low"),
         (status = 404, description = "User does not exist"),
     ),
@@ -124,6 +129,8 @@ async fn create(
     Ctx(ctx, connection_pool): Ctx,
     Path(username): Path<SmallString>,
     Query(params): Query<ResourceParams<Field>>,
+    request_info: RequestInfo,
+    headers: HeaderMap,
     Json(body): Json<UserTokenCreateBody>,
 ) -> ApiResult<Json<UserTokenInfo>> {
     ctx.verify_privilege(Action::UserTokenCreateSelf)?;
@@ -139,7 +146,9 @@ async fn create(
                     .first(conn)
                     .optional()?
                     .ok_or(ApiError::NotFound(ResourceType::User))?;
-                if ctx.client.id != Some(user_id) {
+                if ctx.client.id == Some(user_id) {
+                    api::verify_second_factor(conn, &ctx.config, ctx.client, &headers)?;
+                } else {
                     ctx.verify_privilege(Action::UserTokenCreateAny)?;
                     api::verify_rank(ctx.client, target_rank)?;
                 }
@@ -164,6 +173,9 @@ async fn create(
                 }
                 .insert_into(user_token::table)
                 .get_result(conn)?;
+
+                let event = AuditEvent::TokenCreation;
+                audit::record(conn, &request_info, event, ctx.client.id, Some(&username), None)?;
                 Ok::<_, ApiError>((user_token, lowercase_name, avatar_style))
             }
         })
@@ -284,14 +296,18 @@ async fn update(
         (status = 404, description = "Token does not exist"),
     ),
 )]
-async fn delete(Ctx(ctx, connection_pool): Ctx, Path((username, token)): Path<(String, Uuid)>) -> ApiResult<Json<()>> {
+async fn delete(
+    Ctx(ctx, connection_pool): Ctx,
+    Path((username, token)): Path<(String, Uuid)>,
+    request_info: RequestInfo,
+) -> ApiResult<Json<()>> {
     ctx.verify_privilege(Action::UserTokenDeleteSelf)?;
 
     connection_pool
         .transaction(move |conn| {
             let (user_token_owner, target_rank) = user::table
                 .select((user::id, user::rank))
-                .filter(user::name.eq(username))
+                .filter(user::name.eq(&username))
                 .first(conn)
                 .optional()?
                 .ok_or(ApiError::NotFound(ResourceType::User))?;
@@ -309,6 +325,9 @@ async fn delete(Ctx(ctx, connection_pool): Ctx, Path((username, token)): Path<(S
             .get_result(conn)
             .optional()?
             .ok_or(ApiError::NotFound(ResourceType::UserToken))?;
+
+            let event = AuditEvent::TokenDeletion;
+            audit::record(conn, &request_info, event, ctx.client.id, Some(&username), None)?;
             Ok::<_, ApiError>(Json(()))
         })
         .await
@@ -0,0 +1,246 @@
+use crate::api::doc::WEBHOOK_DELIVERY_TAG;
+use crate::api::error::{ApiError, ApiResult};
+use crate::app::AppState;
+use crate::config::Action;
+use crate::extract::{Ctx, Json, PageParams, PagedResponse, Path, Query, ResourceParams};
+use crate::model::enums::ResourceType;
+use crate::resource::webhook_delivery::{Field, WebhookDeliveryInfo};
+use crate::schema::webhook_delivery;
+use crate::search::Builder;
+use crate::search::webhook_delivery::QueryBuilder;
+use crate::webhook;
+use diesel::dsl::exists;
+use diesel::{QueryDsl, RunQueryDsl};
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list))
+        .routes(routes!(get))
+        .routes(routes!(replay))
+}
+
+/// Searches deliveries of snapshots to webhooks.
+///
+/// A delivery is created for every snapshot that a configured webhook is interested in.
+/// Deliveries that fail are retried with exponential backoff. Once every attempt has
+/// failed, the delivery is marked as `failed` and can be replayed.
+///
+/// **Anonymous tokens**
+///
+/// Same as `url` token.
+///
+/// **Named tokens**
+///
+/// | Key                              | Description                                          |
+/// | -------------------------------- | ---------------------------------------------------- |
+/// | `id`                             | specific delivery ID                                 |
+/// | `snapshot`                       | delivering given snapshot ID                         |
+/// | `url`                            | posted to given webhook URL (accepts wildcards)      |
+/// | `status`                         | `pending`, `delivered` or `failed`                   |
+/// | `attempts`                       | having given number of attempts                      |
+/// | `creation-date`, `creation-time` | created at given date                                |
+///
+/// **Sort style tokens**
+///
+/// | Value                            | Description               |
+/// | -------------------------------- | ------------------------- |
+/// | `random`                         | as random as it can get   |
+/// | `id`                             | newest to oldest          |
+/// | `snapshot`                       | newest snapshot first     |
+/// | `url`                            | webhook URL, Z to A       |
+/// | `status`                         | failed deliveries first   |
+/// | `attempts`                       | most attempts first       |
+/// | `creation-date`, `creation-time` | newest to oldest          |
+///
+/// **Special tokens**
+///
+/// None.
+#[utoipa::path(
+    get,
+    path = "/webhook-deliveries",
+    tag = WEBHOOK_DELIVERY_TAG,
+    params(ResourceParams, PageParams),
+    responses(
+        (status = 200, body = PagedResponse<WebhookDeliveryInfo>),
+        (status = 403, description = "Privileges are too low"),
+    ),
+)]
+async fn list(
+    Ctx(ctx, connection_pool): Ctx,
+    Query(resource): Query<ResourceParams<Field>>,
+    Query(page): Query<PageParams>,
+) -> ApiResult<Json<PagedResponse<WebhookDeliveryInfo>>> {
+    ctx.verify_privilege(Action::WebhookDeliveryList)?;
+
+    let offset = page.offset.unwrap_or(0);
+    let limit = page.limit();
+    connection_pool
+        .transaction(move |conn| {
+            let mut query_builder = QueryBuilder::new(&ctx, resource.criteria())?;
+            query_builder.set_offset_and_limit(offset, limit);
+
+            let (total, selected_deliveries) = query_builder.list(conn)?;
+            Ok::<_, ApiError>(Json(PagedResponse {
+                query: resource.query,
+                offset,
+                limit,
+                total,
+                results: WebhookDeliveryInfo::new_batch_from_ids(conn, &selected_deliveries, resource.fields)?,
+            }))
+        })
+        .await
+}
+
+/// Retrieves information about a webhook delivery.
+#[utoipa::path(
+    get,
+    path = "/webhook-delivery/{id}",
+    tag = WEBHOOK_DELIVERY_TAG,
+    params(
+        ("id" = i64, Path, description = "Webhook delivery ID", example = 1),
+        ResourceParams,
+    ),
+    responses(
+        (status = 200, body = WebhookDeliveryInfo),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Webhook delivery does not exist"),
+    ),
+)]
+async fn get(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(delivery_id): Path<i64>,
+    Query(params): Query<ResourceParams<Field>>,
+) -> ApiResult<Json<WebhookDeliveryInfo>> {
+    ctx.verify_privilege(Action::WebhookDeliveryList)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let delivery_exists: bool =
+                diesel::select(exists(webhook_delivery::table.find(delivery_id))).first(conn)?;
+            if !delivery_exists {
+                return Err(ApiError::NotFound(ResourceType::WebhookDelivery));
+            }
+            WebhookDeliveryInfo::new_from_id(conn, delivery_id, params.fields)
+                .map(Json)
+                .map_err(ApiError::from)
+        })
+        .await
+}
+
+/// Attempts a failed webhook delivery again.
+///
+/// The delivery is attempted as soon as possible, with the same number of retries as a new delivery.
+/// The snapshot is posted as it is now, so the user associated with it may have changed since the
+/// original attempts.
+#[utoipa::path(
+    post,
+    path = "/webhook-delivery/{id}/replay",
+    tag = WEBHOOK_DELIVERY_TAG,
+    params(
+        ("id" = i64, Path, description = "Webhook delivery ID"),
+        ResourceParams,
+    ),
+    responses(
+        (status = 200, body = WebhookDeliveryInfo),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Webhook delivery does not exist"),
+        (status = 422, description = "Webhook delivery has not failed"),
+    ),
+)]
+async fn replay(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(delivery_id): Path<i64>,
+    Query(params): Query<ResourceParams<Field>>,
+) -> ApiResult<Json<WebhookDeliveryInfo>> {
+    ctx.verify_privilege(Action::WebhookDeliveryReplay)?;
+
+    let delivery = connection_pool
+        .transaction(move |conn| {
+            webhook::replay(conn, delivery_id)?;
+            WebhookDeliveryInfo::new_from_id(conn, delivery_id, params.fields).map_err(ApiError::from)
+        })
+        .await?;
+    webhook::wake_up();
+    Ok(Json(delivery))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::api::error::ApiResult;
+    use crate::model::enums::{UserRank, WebhookDeliveryStatus};
+    use crate::schema::webhook_delivery;
+    use crate::search::webhook_delivery::Token;
+    use crate::test::*;
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+    use serial_test::{parallel, serial};
+    use strum::IntoEnumIterator;
+
+    // Exclude fields that involve time
+    const FIELDS: &str = "&fields=id,snapshot,url,status,attemptCount,lastError";
+
+    #[tokio::test]
+    #[parallel]
+    async fn list() -> ApiResult<()> {
+        // The replay test modifies the last delivery, so searches are limited to the others
+        const QUERY: &str = "GET /webhook-deliveries/?query";
+        const PARAMS: &str = "id:..3 -sort:id&limit=40&fields=id";
+        verify_response(&format!("{QUERY}=id:..3 -sort:id&limit=40{FIELDS}"), "webhook_delivery/list/typical").await?;
+
+        let filter_table = crate::search::webhook_delivery::filter_table();
+        for token in Token::iter() {
+            let filter = filter_table[token];
+            let (sign, filter) = if filter.starts_with('-') {
+                filter.split_at(1)
+            } else {
+                ("", filter)
+            };
+            let query = format!("{QUERY}={sign}{token}:{filter} {PARAMS}");
+            let path = format!("webhook_delivery/list/{token}_filtered");
+            verify_response(&query, &path).await?;
+
+            let query = format!("{QUERY}=sort:{token} {PARAMS}");
+            let path = format!("webhook_delivery/list/{token}_sorted");
+            verify_response(&query, &path).await?;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn get() -> ApiResult<()> {
+        verify_response(&format!("GET /webhook-delivery/2/?{FIELDS}"), "webhook_delivery/get/typical").await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn replay() -> ApiResult<()> {
+        verify_response(&format!("POST /webhook-delivery/4/replay/?{FIELDS}"), "webhook_delivery/replay/typical")
+            .await?;
+        verify_response("POST /webhook-delivery/4/replay", "webhook_delivery/replay/pending").await?;
+
+        diesel::update(webhook_delivery::table.find(4))
+            .set((webhook_delivery::status.eq(WebhookDeliveryStatus::Failed), webhook_delivery::attempt_count.eq(10)))
+            .execute(&mut get_connection()?)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn error() -> ApiResult<()> {
+        verify_response("GET /webhook-delivery/99", "webhook_delivery/get/nonexistent").await?;
+        verify_response("POST /webhook-delivery/99/replay", "webhook_delivery/replay/nonexistent").await?;
+        verify_response("POST /webhook-delivery/1/replay", "webhook_delivery/replay/delivered").await
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn unauthorized() -> ApiResult<()> {
+        const USER: UserRank = UserRank::Moderator;
+        verify_response_with_user(USER, "GET /webhook-deliveries?limit=1", "webhook_delivery/list/unauthorized")
+            .await?;
+        verify_response_with_user(USER, "GET /webhook-delivery/1", "webhook_delivery/get/unauthorized").await?;
+        verify_response_with_user(USER, "POST /webhook-delivery/4/replay", "webhook_delivery/replay/unauthorized").await
+    }
+}
@@ -0,0 +1,595 @@
+use crate::api::doc::WIKI_PAGE_TAG;
+use crate::api::error::{self, ApiError, ApiResult};
+use crate::app::{AppState, Context};
+use crate::config::Action;
+use crate::extract::{Ctx, DeleteBody, Json, Path, Query, ResourceParams, UnpagedResponse};
+use crate::model::enums::{ResourceProperty, ResourceType};
+use crate::model::wiki_page::{NewWikiPage, WikiPage};
+use crate::resource::wiki_page::{Field, WikiPageInfo};
+use crate::resource::wiki_revision::{self, WikiPageDiff, WikiRevisionInfo};
+use crate::schema::{tag_name, wiki_page};
+use crate::snapshot::wiki_page::SnapshotData;
+use crate::string::{LargeString, SmallString};
+use crate::time::DateTime;
+use crate::{api, snapshot};
+use diesel::{ExpressionMethods, Insertable, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl, SaveChangesDsl};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(create))
+        .routes(routes!(get, update, delete))
+        .routes(routes!(get_by_title))
+        .routes(routes!(get_by_tag))
+        .routes(routes!(list_revisions))
+        .routes(routes!(diff))
+        .routes(routes!(revert))
+}
+
+/// Retrieves information about an existing wiki page.
+#[utoipa::path(
+    get,
+    path = "/wiki-page/{id}",
+    tag = WIKI_PAGE_TAG,
+    params(
+        ("id" = i64, Path, description = "Wiki page ID"),
+        ResourceParams,
+    ),
+    responses(
+        (status = 200, body = WikiPageInfo),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Wiki page does not exist"),
+    ),
+)]
+async fn get(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(page_id): Path<i64>,
+    Query(params): Query<ResourceParams<Field>>,
+) -> ApiResult<Json<WikiPageInfo>> {
+    ctx.verify_privilege(Action::WikiPageView)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let page = find_page(conn, page_id)?;
+            WikiPageInfo::new(conn, &ctx, page, params.fields)
+                .map(Json)
+                .map_err(ApiError::from)
+        })
+        .await
+}
+
+/// Retrieves information about an existing standalone wiki page by its title.
+///
+/// Titles are case insensitive.
+#[utoipa::path(
+    get,
+    path = "/wiki/{title}",
+    tag = WIKI_PAGE_TAG,
+    params(
+        ("title" = String, Path, description = "Wiki page title"),
+        ResourceParams,
+    ),
+    responses(
+        (status = 200, body = WikiPageInfo),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Wiki page does not exist"),
+    ),
+)]
+async fn get_by_title(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(title): Path<SmallString>,
+    Query(params): Query<ResourceParams<Field>>,
+) -> ApiResult<Json<WikiPageInfo>> {
+    ctx.verify_privilege(Action::WikiPageView)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let page = wiki_page::table
+                .filter(wiki_page::title.eq(title))
+                .first(conn)
+                .optional()?
+                .ok_or(ApiError::NotFound(ResourceType::WikiPage))?;
+            WikiPageInfo::new(conn, &ctx, page, params.fields)
+                .map(Json)
+                .map_err(ApiError::from)
+        })
+        .await
+}
+
+/// Retrieves the wiki page of an existing tag.
+///
+/// Any of the tag's names may be used.
+#[utoipa::path(
+    get,
+    path = "/tag/{name}/wiki",
+    tag = WIKI_PAGE_TAG,
+    params(
+        ("name" = String, Path, description = "Tag name"),
+        ResourceParams,
+    ),
+    responses(
+        (status = 200, body = WikiPageInfo),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Tag does not exist"),
+        (status = 404, description = "Tag has no wiki page"),
+    ),
+)]
+async fn get_by_tag(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(name): Path<SmallString>,
+    Query(params): Query<ResourceParams<Field>>,
+) -> ApiResult<Json<WikiPageInfo>> {
+    ctx.verify_privilege(Action::TagView)?;
+    ctx.verify_privilege(Action::WikiPageView)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let tag_id = find_tag_id(conn, &name)?;
+            let page = wiki_page::table
+                .filter(wiki_page::tag_id.eq(tag_id))
+                .first(conn)
+                .optional()?
+                .ok_or(ApiError::NotFound(ResourceType::WikiPage))?;
+            WikiPageInfo::new(conn, &ctx, page, params.fields)
+                .map(Json)
+                .map_err(ApiError::from)
+        })
+        .await
+}
+
+/// Request body for creating a wiki page.
+#[derive(Deserialize, ToSchema)]
+struct WikiPageCreateBody {
+    /// Title of a standalone page. Cannot be given together with `tag`.
+    title: Option<SmallString>,
+    /// Name of the tag the page describes. Cannot be given together with `title`.
+    tag: Option<SmallString>,
+    /// Page contents.
+    body: LargeString,
+}
+
+/// Creates a new wiki page.
+///
+/// A page either describes a tag, in which case `tag` must be given, or stands on
+/// its own, in which case `title` must be given. Each tag can have at most one page,
+/// and standalone page titles are unique and case insensitive.
+#[utoipa::path(
+    post,
+    path = "/wiki-pages",
+    tag = WIKI_PAGE_TAG,
+    params(ResourceParams),
+    request_body = WikiPageCreateBody,
+    responses(
+        (status = 200, body = WikiPageInfo),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Tag does not exist"),
+        (status = 409, description = "Tag already has a wiki page"),
+        (status = 409, description = "Title is used by an existing wiki page"),
+        (status = 422, description = "Neither or both of title and tag were given"),
+    ),
+)]
+async fn create(
+    Ctx(ctx, connection_pool): Ctx,
+    Query(params): Query<ResourceParams<Field>>,
+    Json(body): Json<WikiPageCreateBody>,
+) -> ApiResult<Json<WikiPageInfo>> {
+    ctx.verify_privilege(Action::WikiPageCreate)?;
+
+    let title = body.title.filter(|title| !title.is_empty());
+    let page = connection_pool
+        .transaction({
+            let ctx = ctx.clone();
+            move |conn| {
+                let (tag_id, unique_property) = match (&title, &body.tag) {
+                    (Some(_), None) => (None, ResourceProperty::WikiPageTitle),
+                    (None, Some(tag)) => (Some(find_tag_id(conn, tag)?), ResourceProperty::WikiPageTag),
+                    _ => return Err(ApiError::InvalidWikiPageTarget),
+                };
+                let new_page = NewWikiPage {
+                    title: title.as_deref(),
+                    tag_id,
+                    body: &body.body,
+                };
+                let page: WikiPage = error::map_unique_violation(
+                    new_page.insert_into(wiki_page::table).get_result(conn),
+                    unique_property,
+                )?;
+
+                let page_data = SnapshotData::retrieve(conn, page.clone())?;
+                snapshot::wiki_page::creation_snapshot(conn, ctx.client, page.id, page_data)?;
+                Ok::<_, ApiError>(page)
+            }
+        })
+        .await?;
+    connection_pool
+        .transaction(move |conn| WikiPageInfo::new(conn, &ctx, page, params.fields))
+        .await
+        .map(Json)
+}
+
+/// Request body for updating a wiki page.
+#[derive(Deserialize, ToSchema)]
+struct WikiPageUpdateBody {
+    /// Resource version. See [versioning](#Versioning).
+    version: DateTime,
+    /// New title. Only standalone pages have titles.
+    title: Option<SmallString>,
+    /// New page contents.
+    body: Option<LargeString>,
+}
+
+/// Updates an existing wiki page using specified parameters.
+///
+/// All fields except `version` are optional - update concerns only provided fields.
+/// Every update that changes the page is recorded as a new revision.
+#[utoipa::path(
+    put,
+    path = "/wiki-page/{id}",
+    tag = WIKI_PAGE_TAG,
+    params(
+        ("id" = i64, Path, description = "Wiki page ID"),
+        ResourceParams,
+    ),
+    request_body = WikiPageUpdateBody,
+    responses(
+        (status = 200, body = WikiPageInfo),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Wiki page does not exist"),
+        (status = 409, description = "Version is outdated"),
+        (status = 409, description = "Title is used by an existing wiki page"),
+        (status = 422, description = "Title is empty or was given for a tag page"),
+    ),
+)]
+async fn update(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(page_id): Path<i64>,
+    Query(params): Query<ResourceParams<Field>>,
+    Json(body): Json<WikiPageUpdateBody>,
+) -> ApiResult<Json<WikiPageInfo>> {
+    ctx.verify_privilege(Action::WikiPageView)?;
+    ctx.verify_privilege(Action::WikiPageEdit)?;
+
+    let page = connection_pool
+        .transaction({
+            let ctx = ctx.clone();
+            move |conn| {
+                let old_page = find_page(conn, page_id)?;
+                api::verify_version(old_page.last_edit_time, body.version)?;
+                edit_page(conn, &ctx, old_page, body.title, body.body)
+            }
+        })
+        .await?;
+    connection_pool
+        .transaction(move |conn| WikiPageInfo::new(conn, &ctx, page, params.fields))
+        .await
+        .map(Json)
+}
+
+/// Deletes an existing wiki page.
+///
+/// The revision history of the page is kept in its snapshots.
+#[utoipa::path(
+    delete,
+    path = "/wiki-page/{id}",
+    tag = WIKI_PAGE_TAG,
+    params(
+        ("id" = i64, Path, description = "Wiki page ID"),
+    ),
+    request_body = DeleteBody,
+    responses(
+        (status = 200, body = ()),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Wiki page does not exist"),
+        (status = 409, description = "Version is outdated"),
+    ),
+)]
+async fn delete(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(page_id): Path<i64>,
+    Json(client_version): Json<DeleteBody>,
+) -> ApiResult<Json<()>> {
+    ctx.verify_privilege(Action::WikiPageDelete)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            let page = find_page(conn, page_id)?;
+            api::verify_version(page.last_edit_time, *client_version)?;
+
+            diesel::delete(wiki_page::table.find(page_id)).execute(conn)?;
+            let page_data = SnapshotData::retrieve(conn, page)?;
+            snapshot::wiki_page::deletion_snapshot(conn, ctx.client, page_id, page_data)?;
+            Ok(Json(()))
+        })
+        .await
+}
+
+/// Lists every revision of an existing wiki page, from oldest to newest.
+///
+/// Doesn't use paging.
+#[utoipa::path(
+    get,
+    path = "/wiki-page/{id}/revisions",
+    tag = WIKI_PAGE_TAG,
+    params(
+        ("id" = i64, Path, description = "Wiki page ID"),
+        ResourceParams,
+    ),
+    responses(
+        (status = 200, body = UnpagedResponse<WikiRevisionInfo>),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Wiki page does not exist"),
+    ),
+)]
+async fn list_revisions(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(page_id): Path<i64>,
+    Query(params): Query<ResourceParams<wiki_revision::Field>>,
+) -> ApiResult<Json<UnpagedResponse<WikiRevisionInfo>>> {
+    ctx.verify_privilege(Action::WikiPageView)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            find_page(conn, page_id)?;
+            let revisions = snapshot::wiki_page::revisions(conn, page_id)?;
+            WikiRevisionInfo::new_batch(conn, &ctx.config, revisions, params.fields).map_err(ApiError::from)
+        })
+        .await
+        .map(|results| UnpagedResponse { results })
+        .map(Json)
+}
+
+/// Represents parameters of a request to compare two revisions of a wiki page.
+#[derive(Clone, Copy, Deserialize, IntoParams)]
+struct DiffParams {
+    /// Revision to compare from.
+    #[param(example = 1)]
+    from: usize,
+    /// Revision to compare to. Defaults to the latest revision.
+    to: Option<usize>,
+}
+
+/// Compares two revisions of an existing wiki page line by line.
+#[utoipa::path(
+    get,
+    path = "/wiki-page/{id}/diff",
+    tag = WIKI_PAGE_TAG,
+    params(
+        ("id" = i64, Path, description = "Wiki page ID"),
+        DiffParams,
+    ),
+    responses(
+        (status = 200, body = WikiPageDiff),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Wiki page does not exist"),
+        (status = 404, description = "Revision does not exist"),
+    ),
+)]
+async fn diff(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(page_id): Path<i64>,
+    Query(params): Query<DiffParams>,
+) -> ApiResult<Json<WikiPageDiff>> {
+    ctx.verify_privilege(Action::WikiPageView)?;
+
+    connection_pool
+        .transaction(move |conn| {
+            find_page(conn, page_id)?;
+            let revisions = snapshot::wiki_page::revisions(conn, page_id)?;
+            let to = params.to.unwrap_or(revisions.len());
+            let revision_data = |revision: usize| {
+                revision
+                    .checked_sub(1)
+                    .and_then(|index| revisions.get(index))
+                    .map(|revision| revision.data.clone())
+                    .ok_or(ApiError::RevisionNotFound(revision))
+            };
+            let old = revision_data(params.from)?;
+            let new = revision_data(to)?;
+            Ok(Json(WikiPageDiff::new(params.from, old, to, new)))
+        })
+        .await
+}
+
+/// Request body for reverting a wiki page.
+#[derive(Deserialize, ToSchema)]
+struct WikiPageRevertBody {
+    /// Resource version. See [versioning](#Versioning).
+    version: DateTime,
+    /// Revision to restore the page to.
+    revision: usize,
+}
+
+/// Restores the title and contents of an existing wiki page to a previous revision.
+///
+/// The revert is recorded as a new revision, so it can itself be reverted.
+#[utoipa::path(
+    post,
+    path = "/wiki-page/{id}/revert",
+    tag = WIKI_PAGE_TAG,
+    params(
+        ("id" = i64, Path, description = "Wiki page ID"),
+        ResourceParams,
+    ),
+    request_body = WikiPageRevertBody,
+    responses(
+        (status = 200, body = WikiPageInfo),
+        (status = 403, description = "Privileges are too low"),
+        (status = 404, description = "Wiki page does not exist"),
+        (status = 404, description = "Revision does not exist"),
+        (status = 409, description = "Version is outdated"),
+        (status = 409, description = "Title is used by an existing wiki page"),
+    ),
+)]
+async fn revert(
+    Ctx(ctx, connection_pool): Ctx,
+    Path(page_id): Path<i64>,
+    Query(params): Query<ResourceParams<Field>>,
+    Json(body): Json<WikiPageRevertBody>,
+) -> ApiResult<Json<WikiPageInfo>> {
+    ctx.verify_privilege(Action::WikiPageView)?;
+    ctx.verify_privilege(Action::WikiPageEdit)?;
+
+    let page = connection_pool
+        .transaction({
+            let ctx = ctx.clone();
+            move |conn| {
+                let old_page = find_page(conn, page_id)?;
+                api::verify_version(old_page.last_edit_time, body.version)?;
+
+                let revision = snapshot::wiki_page::revision(conn, page_id, body.revision)?;
+                edit_page(conn, &ctx, old_page, revision.data.title, Some(revision.data.body))
+            }
+        })
+        .await?;
+    connection_pool
+        .transaction(move |conn| WikiPageInfo::new(conn, &ctx, page, params.fields))
+        .await
+        .map(Json)
+}
+
+fn find_page(conn: &mut PgConnection, page_id: i64) -> ApiResult<WikiPage> {
+    wiki_page::table
+        .find(page_id)
+        .first(conn)
+        .optional()?
+        .ok_or(ApiError::NotFound(ResourceType::WikiPage))
+}
+
+fn find_tag_id(conn: &mut PgConnection, name: &str) -> ApiResult<i64> {
+    tag_name::table
+        .select(tag_name::tag_id)
+        .filter(tag_name::name.eq(name))
+        .first(conn)
+        .optional()?
+        .ok_or(ApiError::NotFound(ResourceType::Tag))
+}
+
+/// Replaces the title and body of `old_page` with the given ones, if any,
+/// and records the change as a new revision.
+fn edit_page(
+    conn: &mut PgConnection,
+    ctx: &Context,
+    old_page: WikiPage,
+    title: Option<SmallString>,
+    body: Option<LargeString>,
+) -> ApiResult<WikiPage> {
+    let mut new_page = old_page.clone();
+    if let Some(title) = title {
+        if old_page.title.is_none() || title.is_empty() {
+            return Err(ApiError::InvalidWikiPageTarget);
+        }
+        new_page.title = Some(title);
+    }
+    if let Some(body) = body {
+        new_page.body = body;
+    }
+
+    new_page.last_edit_time = DateTime::now();
+    let saved_page: WikiPage =
+        error::map_unique_violation(new_page.save_changes(conn), ResourceProperty::WikiPageTitle)?;
+
+    let old_data = SnapshotData::retrieve(conn, old_page)?;
+    let new_data = SnapshotData::retrieve(conn, new_page)?;
+    snapshot::wiki_page::modification_snapshot(conn, ctx.client, saved_page.id, old_data, new_data)?;
+    Ok(saved_page)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::api::error::ApiResult;
+    use crate::model::enums::{PostStatus, UserRank};
+    use crate::schema::post;
+    use crate::test::*;
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+    use serial_test::{parallel, serial};
+
+    // Exclude fields that involve creation_time or last_edit_time
+    const FIELDS: &str = "&fields=id,title,tag,body,linkedTags,linkedPages,linkedPosts";
+
+    #[tokio::test]
+    #[serial]
+    async fn history() -> ApiResult<()> {
+        verify_response(&format!("POST /wiki-pages/?{FIELDS}"), "wiki_page/create/tag").await?;
+        verify_response_with_user(
+            UserRank::Regular,
+            &format!("POST /wiki-pages/?{FIELDS}"),
+            "wiki_page/create/standalone",
+        )
+        .await?;
+        verify_response(&format!("GET /tag/annakin_skywalker/wiki/?{FIELDS}"), "wiki_page/get_by_tag/typical").await?;
+        verify_response(&format!("GET /wiki/star%20wars%20characters/?{FIELDS}"), "wiki_page/get_by_title/typical")
+            .await?;
+
+        verify_response(&format!("PUT /wiki-page/2/?{FIELDS}"), "wiki_page/edit/typical").await?;
+        verify_response(
+            "GET /wiki-page/2/revisions/?fields=revision,user,title,body",
+            "wiki_page/list_revisions/typical",
+        )
+        .await?;
+        verify_response("GET /wiki-page/2/diff/?from=1&to=2", "wiki_page/diff/typical").await?;
+        verify_response(&format!("POST /wiki-page/2/revert/?{FIELDS}"), "wiki_page/revert/typical").await?;
+        verify_response("GET /wiki-page/2/diff/?from=1", "wiki_page/diff/latest").await?;
+
+        verify_response("POST /wiki-pages", "wiki_page/create/tag_clash").await?;
+        verify_response("POST /wiki-pages", "wiki_page/create/title_clash").await?;
+        verify_response("PUT /wiki-page/1", "wiki_page/edit/tag_title").await?;
+        verify_response("GET /wiki-page/2/diff/?from=9", "wiki_page/diff/nonexistent_revision").await?;
+        verify_response("POST /wiki-page/2/revert", "wiki_page/revert/nonexistent_revision").await?;
+
+        verify_response("DELETE /wiki-page/2", "wiki_page/delete/typical").await?;
+        verify_response("GET /wiki-page/2", "wiki_page/get/nonexistent").await?;
+
+        reset_database();
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn pending_linked_post() -> ApiResult<()> {
+        const LINK_FIELDS: &str = "fields=title,linkedPosts";
+        verify_response(&format!("POST /wiki-pages/?{LINK_FIELDS}"), "wiki_page/create/pending_post").await?;
+
+        // Post 1 wasn't uploaded by the regular user, so it shouldn't be linked to for them
+        diesel::update(post::table.find(1))
+            .set(post::status.eq(PostStatus::Pending))
+            .execute(&mut get_connection()?)?;
+        verify_response_with_user(
+            UserRank::Regular,
+            &format!("GET /wiki/awaiting%20approval/?{LINK_FIELDS}"),
+            "wiki_page/get_by_title/pending_post",
+        )
+        .await?;
+
+        reset_database();
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn error() -> ApiResult<()> {
+        verify_response("GET /wiki-page/99", "wiki_page/get/nonexistent").await?;
+        verify_response("GET /tag/tagme/wiki", "wiki_page/get_by_tag/no_page").await?;
+        verify_response("GET /tag/none/wiki", "wiki_page/get_by_tag/nonexistent_tag").await?;
+        verify_response("GET /wiki/none", "wiki_page/get_by_title/nonexistent").await?;
+        verify_response("GET /wiki-page/99/revisions", "wiki_page/list_revisions/nonexistent").await?;
+        verify_response("POST /wiki-pages", "wiki_page/create/invalid_target").await?;
+        verify_response("POST /wiki-pages", "wiki_page/create/nonexistent_tag").await?;
+        verify_response("PUT /wiki-page/99", "wiki_page/edit/nonexistent").await?;
+        verify_response("DELETE /wiki-page/99", "wiki_page/delete/nonexistent").await
+    }
+
+    #[tokio::test]
+    #[parallel]
+    async fn unauthorized() -> ApiResult<()> {
+        const USER: UserRank = UserRank::Regular;
+
+        verify_response_with_user(USER, "GET /wiki-page/1", "wiki_page/get/unauthorized").await?;
+        verify_response_with_user(USER, "POST /wiki-pages", "wiki_page/create/unauthorized").await?;
+        verify_response_with_user(USER, "PUT /wiki-page/1", "wiki_page/edit/unauthorized").await?;
+        verify_response_with_user(USER, "POST /wiki-page/1/revert", "wiki_page/revert/unauthorized").await?;
+        verify_response_with_user(USER, "DELETE /wiki-page/1", "wiki_page/delete/unauthorized").await
+    }
+}
@@ -1,22 +1,26 @@
 use crate::api::error::{ApiError, ApiResult};
-use crate::api::middleware;
 use crate::auth::Client;
 use crate::config::{Action, Config, Env};
 use crate::content::cache::RingCache;
+use crate::content::hash::{PostFileHash, PostHash};
 use crate::db::AsyncConnectionPool;
 use crate::extract::Ctx;
 use crate::model::enums::UserRank;
 use crate::search::preferences::Preferences;
-use crate::{admin, api, db, filesystem};
+use crate::time::DateTime;
+use crate::{activity, admin, api, audit, db, email, filesystem, metrics, telemetry, update, webhook};
 use axum::Router;
 use reqwest::Client as HttpClient;
 use std::error::Error;
 use std::fmt::Display;
+use std::net::SocketAddr;
 use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::runtime::Handle;
 #[cfg(unix)]
 use tokio::signal::unix::SignalKind;
+use tokio::time::MissedTickBehavior;
 use tower_http::normalize_path::NormalizePathLayer;
 use tower_layer::Layer;
 use tracing::{debug, error, info, warn};
@@ -112,7 +116,9 @@ pub fn num_rayon_threads() -> usize {
 /// Initializes logging using [`tracing_subscriber`].
 pub fn enable_tracing(config: &Config) {
     let initialize = |filter: EnvFilter| {
+        // Trace export is layered directly on the registry, but is still subject to the filter
         tracing_subscriber::registry()
+            .with(telemetry::layer(config))
             .with(filter)
             .with(tracing_subscriber::fmt::layer().without_time())
             .init();
@@ -126,7 +132,7 @@ pub fn enable_tracing(config: &Config) {
     }
 }
 
-/// Runs database migrations, initializes snapshot counter, and spawns any long-running tasks.
+/// Runs database migrations and spawns any long-running tasks.
 pub fn initialize(state: &AppState) -> Result<(), Box<dyn Error + Send + Sync>> {
     if let Some(migration_range) = db::run_database_migrations(&state.connection_pool)? {
         db::run_server_migrations(state, migration_range)?;
@@ -139,13 +145,94 @@ pub fn initialize(state: &AppState) -> Result<(), Box<dyn Error + Send + Sync>>
 
     let mut conn = state.connection_pool.get_blocking()?;
     db::check_signature_version(&mut conn)?; // We do this after admin mode check so that users can update signatures
-    middleware::initialize_snapshot_counter(&mut conn)?;
     filesystem::spawn_temporary_uploads_cleanup_task(Arc::clone(&state.config));
+    if state.config.deleted_post_retention_days > 0 {
+        spawn_purge_task(state.clone(), "deleted posts", purge_deleted_posts);
+    }
+    if state.config.audit_log_retention_days > 0 {
+        spawn_purge_task(state.clone(), "audit log", purge_audit_log);
+    }
+    if state.config.webhook_delivery_retention_days > 0 {
+        spawn_purge_task(state.clone(), "webhook deliveries", purge_webhook_deliveries);
+    }
+    if state.config.smtp().is_some() {
+        spawn_email_digest_task(state.clone());
+    }
+    spawn_webhook_delivery_task(state.clone());
+    spawn_activity_broadcast_task(state.clone());
+    Ok(())
+}
+
+/// Permanently deletes posts that have been in the trash for longer than the configured retention period.
+async fn purge_deleted_posts(state: AppState) -> ApiResult<()> {
+    let retention_period = time::Duration::days(i64::from(state.config.deleted_post_retention_days));
+    let cutoff = DateTime::from(*DateTime::now() - retention_period);
+    let (purged_posts, purged_files) = state
+        .connection_pool
+        .transaction(move |conn| update::post::purge_deleted(conn, cutoff))
+        .await?;
+
+    // Posts are already gone from the database, so keep deleting the remaining files after a failure
+    if state.config.delete_source_files {
+        for post in &purged_posts {
+            let post_hash = PostHash::new(&state.config, post.id, Some(post.custom_thumbnail_size));
+            if let Err(err) = filesystem::delete_post(&post_hash, post.mime_type) {
+                error!("Could not delete files of purged post {} for reason: {err}", post.id);
+            }
+        }
+        for post_file in &purged_files {
+            let post_file_hash = PostFileHash::new(&state.config, post_file.id);
+            if let Err(err) = filesystem::delete_post_file(&post_file_hash, post_file.mime_type) {
+                error!("Could not delete files of purged post file {} for reason: {err}", post_file.id);
+            }
+        }
+    }
+    if !purged_posts.is_empty() {
+        info!("Purged {} deleted posts", purged_posts.len());
+    }
+    Ok(())
+}
+
+/// Deletes audit log entries that are older than the configured retention period.
+async fn purge_audit_log(state: AppState) -> ApiResult<()> {
+    let retention_period = time::Duration::days(i64::from(state.config.audit_log_retention_days));
+    let cutoff = DateTime::from(*DateTime::now() - retention_period);
+    let purged_entries = state
+        .connection_pool
+        .transaction(move |conn| audit::purge(conn, cutoff))
+        .await?;
+    if purged_entries > 0 {
+        info!("Purged {purged_entries} audit log entries");
+    }
+    Ok(())
+}
+
+/// Deletes successful webhook deliveries that are older than the configured retention period.
+async fn purge_webhook_deliveries(state: AppState) -> ApiResult<()> {
+    let retention_period = time::Duration::days(i64::from(state.config.webhook_delivery_retention_days));
+    let cutoff = DateTime::from(*DateTime::now() - retention_period);
+    let purged_deliveries = state
+        .connection_pool
+        .transaction(move |conn| webhook::purge_delivered(conn, cutoff))
+        .await?;
+    if purged_deliveries > 0 {
+        info!("Purged {purged_deliveries} webhook deliveries");
+    }
     Ok(())
 }
 
 pub async fn run(state: AppState) -> std::io::Result<()> {
     let server_port = state.env.server_port;
+    if let Some(metrics_port) = state.env.metrics_port {
+        let listener = TcpListener::bind(format!("0.0.0.0:{metrics_port}")).await?;
+        debug!("serving metrics on {}", listener.local_addr()?);
+        let metrics_router = metrics::router(state.clone());
+        tokio::spawn(async move {
+            if let Err(err) = axum::serve(listener, metrics_router).await {
+                error!("Metrics server stopped. Details:\n{err}");
+            }
+        });
+    }
 
     let (router, api) = api::routes(state).split_for_parts();
     let normalized_router = NormalizePathLayer::trim_trailing_slash().layer(router);
@@ -157,7 +244,7 @@ pub async fn run(state: AppState) -> std::io::Result<()> {
     let listener = TcpListener::bind(address).await?;
     info!("Oxibooru server running on {} threads", Handle::current().metrics().num_workers());
     debug!("listening on {}", listener.local_addr()?);
-    axum::serve(listener, app)
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .with_graceful_shutdown(shutdown_signal())
         .await
 }
@@ -168,6 +255,67 @@ pub fn shutdown<E: Display>(message: &str, error: E) -> ! {
     std::process::exit(1)
 }
 
+/// Spawns an asynchronous task that calls `purge` every hour to purge expired `name`.
+fn spawn_purge_task<F, Fut>(state: AppState, name: &'static str, purge: F)
+where
+    F: Fn(AppState) -> Fut + Send + 'static,
+    Fut: Future<Output = ApiResult<()>> + Send,
+{
+    const PURGE_INTERVAL: Duration = Duration::from_hours(1);
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if let Err(err) = purge(state.clone()).await {
+                error!("Failed to purge {name}. Details:\n{err}");
+            }
+        }
+    });
+}
+
+/// Spawns an asynchronous task that periodically sends email digests to subscribed users.
+fn spawn_email_digest_task(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(email::digest::CHECK_INTERVAL);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if let Err(err) = email::digest::send_due_digests(&state).await {
+                error!("Failed to send email digests. Details:\n{err}");
+            }
+        }
+    });
+}
+
+/// Spawns an asynchronous task that posts snapshots to webhooks and retries failed deliveries.
+/// The task runs even when no webhooks are configured, so that the queue of new snapshots is drained.
+fn spawn_webhook_delivery_task(state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            if let Err(err) = webhook::deliver_due(&state).await {
+                error!("Failed to deliver webhooks. Details:\n{err}");
+            }
+            webhook::wait_for_work().await;
+        }
+    });
+}
+
+/// Spawns an asynchronous task that broadcasts new snapshots to subscribers of the activity stream.
+fn spawn_activity_broadcast_task(state: AppState) {
+    tokio::spawn(async move {
+        let mut last_seen = None;
+        loop {
+            match activity::broadcast_new(&state, last_seen).await {
+                Ok(event_id) => last_seen = Some(event_id),
+                Err(err) => error!("Failed to broadcast site activity. Details:\n{err}"),
+            }
+            activity::wait_for_work().await;
+        }
+    });
+}
+
 async fn shutdown_signal() {
     let ctrl_c = async {
         tokio::signal::ctrl_c()
@@ -0,0 +1,163 @@
+//! The audit log is an append-only record of security-relevant events, like logins, failed
+//! authentication attempts, and rank changes. Each entry remembers the name of the user
+//! involved at the time, so entries stay meaningful after the user is renamed or deleted.
+
+use crate::api::error::ApiError;
+use crate::app::AppState;
+use crate::auth::header::AuthenticationError;
+use crate::config::Config;
+use crate::model::audit_log::NewAuditLogEntry;
+use crate::model::enums::{AuditEvent, UserRank};
+use crate::schema::{audit_log, user};
+use crate::string::SmallString;
+use crate::time::DateTime;
+use axum::extract::{ConnectInfo, FromRef, FromRequestParts, State};
+use axum::http::header::USER_AGENT;
+use axum::http::request::Parts;
+use axum::http::{Extensions, HeaderMap, HeaderValue};
+use diesel::{ExpressionMethods, Insertable, OptionalExtension, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use tracing::error;
+
+pub const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// Where a request came from.
+#[derive(Clone, Default)]
+pub struct RequestInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl RequestInfo {
+    pub fn new(config: &Config, headers: &HeaderMap, extensions: &Extensions) -> Self {
+        // A reverse proxy appends the address it received the request from, so any
+        // earlier addresses in the header could have been made up by the client
+        let forwarded_address = headers
+            .get(FORWARDED_FOR_HEADER)
+            .filter(|_| config.trust_forwarded_for)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|addresses| addresses.rsplit(',').next())
+            .map(|address| address.trim().to_owned());
+        let ip_address = forwarded_address.or_else(|| {
+            extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(address)| address.ip().to_string())
+        });
+        let user_agent = headers
+            .get(USER_AGENT)
+            .map(HeaderValue::to_str)
+            .and_then(Result::ok)
+            .map(str::to_owned);
+        Self { ip_address, user_agent }
+    }
+}
+
+impl<S> FromRequestParts<S> for RequestInfo
+where
+    S: Send + Sync,
+    AppState: FromRef<S>,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Ok(State(state)) = State::<AppState>::from_request_parts(parts, state).await;
+        Ok(Self::new(&state.config, &parts.headers, &parts.extensions))
+    }
+}
+
+/// Records an `event` involving the user with id `user_id`.
+/// `target` is the name of whatever was acted on, if anything.
+pub fn record(
+    conn: &mut PgConnection,
+    request: &RequestInfo,
+    event: AuditEvent,
+    user_id: Option<i64>,
+    target: Option<&str>,
+    details: Option<&str>,
+) -> QueryResult<()> {
+    let user_name: Option<SmallString> = match user_id {
+        Some(id) => user::table.find(id).select(user::name).first(conn).optional()?,
+        None => None,
+    };
+    NewAuditLogEntry {
+        event,
+        user_id,
+        user_name: user_name.as_deref(),
+        target,
+        details,
+        ip_address: request.ip_address.as_deref(),
+        user_agent: request.user_agent.as_deref(),
+    }
+    .insert_into(audit_log::table)
+    .execute(conn)?;
+    Ok(())
+}
+
+/// Records an `event` outside of any transaction.
+pub async fn record_detached(
+    state: &AppState,
+    request: &RequestInfo,
+    event: AuditEvent,
+    user_id: Option<i64>,
+    target: Option<&str>,
+    details: Option<&str>,
+) {
+    insert_detached(state, event, |conn| record(conn, request, event, user_id, target, details)).await;
+}
+
+/// Records a rejected authentication attempt as `username`, unless the attempt
+/// failed because of a server-side error.
+pub async fn record_failed_authentication(
+    state: &AppState,
+    request: &RequestInfo,
+    username: Option<&str>,
+    err: &AuthenticationError,
+) {
+    const EVENT: AuditEvent = AuditEvent::FailedAuthentication;
+    if matches!(err, AuthenticationError::FailedConnection(_) | AuthenticationError::FailedQuery(_)) {
+        return;
+    }
+
+    let details = err.to_string();
+    let entry = NewAuditLogEntry {
+        event: EVENT,
+        user_id: None,
+        user_name: username,
+        target: None,
+        details: Some(&details),
+        ip_address: request.ip_address.as_deref(),
+        user_agent: request.user_agent.as_deref(),
+    };
+    insert_detached(state, EVENT, |conn| entry.insert_into(audit_log::table).execute(conn).map(|_| ())).await;
+}
+
+/// Describes a rank change from `old_rank` to `new_rank`.
+pub fn rank_change_details(old_rank: UserRank, new_rank: UserRank) -> String {
+    let old_rank: &str = old_rank.into();
+    let new_rank: &str = new_rank.into();
+    format!("{old_rank} -> {new_rank}")
+}
+
+/// Deletes audit log entries older than `cutoff`. Returns the number of deleted entries.
+pub fn purge(conn: &mut PgConnection, cutoff: DateTime) -> QueryResult<usize> {
+    diesel::delete(audit_log::table)
+        .filter(audit_log::creation_time.lt(cutoff))
+        .execute(conn)
+}
+
+/// Runs `insert` on a connection of its own. Failures are logged rather than returned,
+/// so that a request isn't rejected just because the audit log couldn't be written.
+async fn insert_detached<F>(state: &AppState, event: AuditEvent, insert: F)
+where
+    F: FnOnce(&mut PgConnection) -> QueryResult<()>,
+{
+    let result = async {
+        let mut conn = state.connection_pool.get().await?;
+        insert(conn.as_mut())?;
+        Ok::<_, ApiError>(())
+    };
+    if let Err(err) = result.await {
+        error!("Failed to record {event:?} event in audit log. Details:\n{err}");
+    }
+}
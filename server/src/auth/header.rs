@@ -1,18 +1,23 @@
 use crate::app::AppState;
-use crate::auth::{self, Client};
-use crate::schema::{user, user_token};
+use crate::auth::{self, Client, totp};
+use crate::model::enums::{SanctionKind, UserRank};
+use crate::schema::{user, user_sanction, user_token};
+use crate::string::SecretString;
 use crate::time::DateTime;
 use base64::prelude::BASE64_STANDARD;
 use base64::{DecodeError, Engine};
-use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use diesel::{BoolExpressionMethods, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
 use std::str::Utf8Error;
 use std::sync::Arc;
 use thiserror::Error;
+use tracing::warn;
 use uuid::Uuid;
 
 #[derive(Debug, Error)]
 #[error(transparent)]
 pub enum AuthenticationError {
+    #[error("User has been banned")]
+    Banned,
     #[error("Token has been disabled")]
     DisabledToken,
     #[error("Token has expired")]
@@ -21,10 +26,18 @@ pub enum AuthenticationError {
     FailedQuery(#[from] diesel::result::Error),
     #[error("Invalid authentication type")]
     InvalidAuthType,
+    #[error("CSRF token is missing or invalid")]
+    InvalidCsrfToken,
     InvalidEncoding(#[from] DecodeError),
+    #[error("Two-factor authentication code is invalid")]
+    InvalidTotpCode,
     #[error("Authentication credentials are malformed")]
     MalformedCredentials,
     MalformedToken(#[from] uuid::Error),
+    #[error("Two-factor authentication code is required")]
+    MissingTotpCode,
+    #[error("Too many invalid two-factor authentication codes, try again later")]
+    TotpLockedOut,
     #[error("Invalid username and password combination")]
     UsernamePasswordMismatch,
     #[error("Invalid username and token combination")]
@@ -33,16 +46,27 @@ pub enum AuthenticationError {
 }
 
 /// Authentication can either be done by token-based authentication (recommended)
-/// or by sending password as plaintext.
-pub async fn authenticate_user(state: &AppState, auth: &str) -> Result<Client, AuthenticationError> {
+/// or by sending password as plaintext. Users with two-factor authentication enabled
+/// must also send a `totp_code` when authenticating with a password.
+pub async fn authenticate_user(
+    state: &AppState,
+    auth: &str,
+    totp_code: Option<&str>,
+) -> Result<Client, AuthenticationError> {
     let (auth_type, credentials) = auth.split_once(' ').ok_or(AuthenticationError::MalformedCredentials)?;
     match auth_type {
-        "Basic" => basic_access_authentication(state, credentials).await,
+        "Basic" => basic_access_authentication(state, credentials, totp_code).await,
         "Token" => token_authentication(state, credentials).await,
         _ => Err(AuthenticationError::InvalidAuthType),
     }
 }
 
+/// Returns the username that the `auth` header claims to belong to, if it can be decoded.
+pub fn claimed_username(auth: &str) -> Option<String> {
+    let (_, credentials) = auth.split_once(' ')?;
+    decode_credentials(credentials).ok().map(|(username, _)| username)
+}
+
 #[cfg(test)]
 pub fn basic_credentials_for(username: &str, password: &str) -> String {
     let credentials = format!("{username}:{password}");
@@ -66,32 +90,60 @@ fn decode_credentials(credentials: &str) -> Result<(String, String), Authenticat
 }
 
 /// Checks that the given `credentials` are of the form "username:password"
-/// and that the username/password combination is valid.
-async fn basic_access_authentication(state: &AppState, credentials: &str) -> Result<Client, AuthenticationError> {
+/// and that the username/password combination is valid. If the user has enabled
+/// two-factor authentication, `totp_code` must be valid as well.
+async fn basic_access_authentication(
+    state: &AppState,
+    credentials: &str,
+    totp_code: Option<&str>,
+) -> Result<Client, AuthenticationError> {
     let (username, password) = decode_credentials(credentials)?;
-    let (user_id, rank, password_hash): (_, _, String) = {
+    let (user_id, rank, password_hash, password_salt): (_, _, String, String) = {
         let mut conn = state.connection_pool.get().await?;
 
         // For security reasons, don't give any indication to the user if it was the password
         // or the username that was incorrect.
         user::table
-            .select((user::id, user::rank, user::password_hash))
+            .select((user::id, user::rank, user::password_hash, user::password_salt))
             .filter(user::name.eq(username))
             .first(conn.as_mut())
             .optional()?
             .ok_or(AuthenticationError::UsernamePasswordMismatch)
     }?;
-    tokio::task::spawn_blocking({
+    let new_hash = tokio::task::spawn_blocking({
         let config = Arc::clone(&state.config);
         move || {
-            auth::password::is_valid_password(&config, &password_hash, &password)
-                .is_ok()
-                .then_some(Client::new(Some(user_id), rank))
-                .ok_or(AuthenticationError::UsernamePasswordMismatch)
+            let hash_kind = auth::password::is_valid_password(&config, &password_hash, &password_salt, &password)
+                .map_err(|_| AuthenticationError::UsernamePasswordMismatch)?;
+            if !hash_kind.is_outdated() {
+                return Ok(None);
+            }
+
+            // Outdated hashes can only be replaced while the plaintext password is at hand. The salt is
+            // kept, as tokens sent to the user by email are derived from it.
+            match auth::password::rehash_password(&config, &SecretString::from(password), &password_salt) {
+                Ok(new_hash) => Ok(Some(new_hash)),
+                Err(err) => {
+                    warn!("Could not rehash password of user {user_id}. Details:\n{err}");
+                    Ok(None)
+                }
+            }
         }
     })
     .await
-    .unwrap()
+    .unwrap()?;
+
+    let mut conn = state.connection_pool.get().await?;
+    let verified_second_factor = totp::verify_second_factor(conn.as_mut(), &state.config, user_id, totp_code, true)?;
+    let mut client = apply_sanctions(conn.as_mut(), user_id, rank)?;
+    client.verified_second_factor = verified_second_factor;
+
+    if let Some(hash) = new_hash {
+        diesel::update(user::table.find(user_id))
+            .set(user::password_hash.eq(&hash))
+            .execute(conn.as_mut())?;
+    }
+    Ok(client)
 }
 
 /// Checks that the given `credentials` are of the form "username:token"
@@ -113,6 +165,27 @@ async fn token_authentication(state: &AppState, credentials: &str) -> Result<Cli
         Err(AuthenticationError::DisabledToken)
     } else if expiration.is_some_and(|expiration_time| DateTime::now() > expiration_time) {
         Err(AuthenticationError::ExpiredToken)
+    } else {
+        apply_sanctions(conn.as_mut(), user_id, rank)
+    }
+}
+
+/// Rejects users with an active ban. Users with an active suspension are
+/// treated as restricted until the suspension expires.
+pub fn apply_sanctions(conn: &mut PgConnection, user_id: i64, rank: UserRank) -> Result<Client, AuthenticationError> {
+    let active_sanctions: Vec<SanctionKind> = user_sanction::table
+        .select(user_sanction::kind)
+        .filter(user_sanction::user_id.eq(user_id))
+        .filter(
+            user_sanction::expiration_time
+                .is_null()
+                .or(user_sanction::expiration_time.gt(DateTime::now())),
+        )
+        .load(conn)?;
+    if active_sanctions.contains(&SanctionKind::Ban) {
+        Err(AuthenticationError::Banned)
+    } else if active_sanctions.contains(&SanctionKind::Suspension) {
+        Ok(Client::new(Some(user_id), rank.min(UserRank::Restricted)))
     } else {
         Ok(Client::new(Some(user_id), rank))
     }
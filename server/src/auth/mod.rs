@@ -1,16 +1,25 @@
 use crate::model::enums::UserRank;
 
 pub mod header;
+pub mod oidc;
 pub mod password;
+pub mod session;
+pub mod totp;
 
 #[derive(Clone, Copy)]
 pub struct Client {
     pub id: Option<i64>,
     pub rank: UserRank,
+    /// Whether the client has passed two-factor authentication during this request.
+    pub verified_second_factor: bool,
 }
 
 impl Client {
     pub fn new(id: Option<i64>, rank: UserRank) -> Self {
-        Self { id, rank }
+        Self {
+            id,
+            rank,
+            verified_second_factor: false,
+        }
     }
 }
@@ -0,0 +1,395 @@
+//! Single sign-on through an OpenID Connect provider using the authorization code flow.
+//!
+//! The state parameter is stateless: it carries a nonce, an expiry, and the id of the client that
+//! started the login, which are authenticated with a key derived from the password secret, so no
+//! pending logins need to be stored. The MAC also covers a random binding secret that is only kept
+//! in an `HttpOnly` cookie, so a state can only be completed by the browser that started the login
+//! and can't be used to log someone else into an attacker's account.
+//!
+//! The ID token is received directly from the token endpoint of the provider over TLS, so its claims
+//! are validated but its signature is not (see OpenID Connect Core 1.0, 3.1.3.7).
+
+use crate::api::error::{ApiError, ApiResult};
+use crate::auth::session;
+use crate::config::{Config, OidcConfig};
+use crate::time::DateTime;
+use axum::http::HeaderValue;
+use base64::Engine;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::LazyLock;
+use std::time::Duration;
+use url::Url;
+use uuid::Uuid;
+
+/// Name of the cookie that binds a login to the browser that started it.
+pub const BINDING_COOKIE: &str = "oxibooru_oidc";
+
+/// How long a user has to complete a login at the provider.
+const STATE_LIFETIME: Duration = Duration::from_mins(10);
+
+/// Requests to the provider don't go through the downloader, as the provider is
+/// often hosted on the same private network as the server.
+static HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .expect("HTTP client should be constructible with default TLS backend")
+});
+
+/// The subset of the provider's discovery document that is needed for logins.
+#[derive(Deserialize)]
+pub struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: Url,
+    token_endpoint: Url,
+}
+
+/// A login that has been started but not yet completed.
+pub struct PendingLogin {
+    /// State parameter that is passed through the provider.
+    pub state: String,
+    /// Nonce that the ID token must carry.
+    pub nonce: String,
+    /// Secret that must be sent back in the binding cookie to complete the login.
+    pub binding: String,
+}
+
+/// Validated claims of an ID token.
+pub struct Identity {
+    pub issuer: String,
+    pub subject: String,
+    pub preferred_username: Option<String>,
+    pub email: Option<String>,
+    pub groups: Option<Vec<String>>,
+}
+
+impl Identity {
+    /// Returns the name a newly provisioned user should preferably get.
+    pub fn username_hint(&self) -> &str {
+        let email_name = self
+            .email
+            .as_deref()
+            .and_then(|email| email.split_once('@'))
+            .map(|(name, _)| name);
+        self.preferred_username.as_deref().or(email_name).unwrap_or("user")
+    }
+}
+
+/// Fetches the discovery document of the configured provider.
+pub async fn discover(oidc: &OidcConfig) -> ApiResult<ProviderMetadata> {
+    let issuer = oidc.issuer.as_str().trim_end_matches('/');
+    let metadata: ProviderMetadata = HTTP_CLIENT
+        .get(format!("{issuer}/.well-known/openid-configuration"))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    if metadata.issuer.trim_end_matches('/') != issuer {
+        return Err(ApiError::InvalidOidcResponse("issuer of discovery document doesn't match"));
+    }
+    Ok(metadata)
+}
+
+/// Returns the URL of the provider's login page.
+pub fn authorization_url(oidc: &OidcConfig, metadata: &ProviderMetadata, state: &str, nonce: &str) -> Url {
+    let mut url = metadata.authorization_endpoint.clone();
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &oidc.client_id)
+        .append_pair("redirect_uri", oidc.redirect_url.as_str())
+        .append_pair("scope", oidc.scope())
+        .append_pair("state", state)
+        .append_pair("nonce", nonce);
+    url
+}
+
+/// Creates a new state parameter for a login started by the client with the given `client_id`.
+pub fn create_state(config: &Config, client_id: Option<i64>, now: DateTime) -> PendingLogin {
+    let nonce = Uuid::new_v4().simple().to_string();
+    let binding = session::generate_secret();
+    let expiry = (*now + STATE_LIFETIME).unix_timestamp();
+    let client_id = client_id.map(|id| id.to_string()).unwrap_or_default();
+    let payload = format!("{nonce}.{expiry}.{client_id}");
+    let mac = BASE64_URL_SAFE_NO_PAD.encode(state_mac(config, &payload, &binding).as_bytes());
+    PendingLogin {
+        state: format!("{payload}.{mac}"),
+        nonce,
+        binding,
+    }
+}
+
+/// Verifies that `state` was created by this server for the browser holding `binding` and the
+/// client with the given `client_id`, and that it hasn't expired. Returns the nonce it carries.
+pub fn verify_state(
+    config: &Config,
+    state: &str,
+    binding: Option<&str>,
+    client_id: Option<i64>,
+    now: DateTime,
+) -> ApiResult<String> {
+    let binding = binding.ok_or(ApiError::InvalidOidcState)?;
+    let (payload, encoded_mac) = state.rsplit_once('.').ok_or(ApiError::InvalidOidcState)?;
+    let mac: [u8; blake3::OUT_LEN] = BASE64_URL_SAFE_NO_PAD
+        .decode(encoded_mac)
+        .ok()
+        .and_then(|mac| mac.try_into().ok())
+        .ok_or(ApiError::InvalidOidcState)?;
+
+    // Comparison of blake3 hashes is constant-time
+    if state_mac(config, payload, binding) != blake3::Hash::from_bytes(mac) {
+        return Err(ApiError::InvalidOidcState);
+    }
+    let mut fields = payload.split('.');
+    let (Some(nonce), Some(expiry), Some(state_client_id), None) =
+        (fields.next(), fields.next(), fields.next(), fields.next())
+    else {
+        return Err(ApiError::InvalidOidcState);
+    };
+    let expiry: i64 = expiry.parse().map_err(|_| ApiError::InvalidOidcState)?;
+    if now.unix_timestamp() > expiry {
+        return Err(ApiError::InvalidOidcState);
+    }
+
+    // Identities may only be linked to the user that started the login
+    let state_client_id = match state_client_id {
+        "" => None,
+        id => Some(id.parse::<i64>().map_err(|_| ApiError::InvalidOidcState)?),
+    };
+    if state_client_id != client_id {
+        return Err(ApiError::InvalidOidcState);
+    }
+    Ok(nonce.to_owned())
+}
+
+/// Returns a `Set-Cookie` header value that holds the `binding` of a pending login.
+pub fn binding_cookie(config: &Config, binding: &str) -> HeaderValue {
+    session::cookie(config, BINDING_COOKIE, binding, STATE_LIFETIME.as_secs(), true)
+}
+
+/// Returns a `Set-Cookie` header value that makes the browser forget the binding of a completed login.
+pub fn expired_binding_cookie(config: &Config) -> HeaderValue {
+    session::cookie(config, BINDING_COOKIE, "", 0, true)
+}
+
+/// Exchanges an authorization `code` for an ID token and validates its claims.
+pub async fn exchange_code(
+    oidc: &OidcConfig,
+    metadata: &ProviderMetadata,
+    code: &str,
+    nonce: &str,
+) -> ApiResult<Identity> {
+    #[derive(Deserialize)]
+    struct TokenResponse {
+        id_token: String,
+    }
+
+    let response: TokenResponse = HTTP_CLIENT
+        .post(metadata.token_endpoint.clone())
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", oidc.redirect_url.as_str()),
+            ("client_id", &oidc.client_id),
+            ("client_secret", oidc.client_secret.read()),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    validate_id_token(oidc, &metadata.issuer, &response.id_token, nonce, DateTime::now())
+}
+
+/// Either a single string or an array of strings.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    fn contains(&self, value: &str) -> bool {
+        match self {
+            Self::One(one) => one == value,
+            Self::Many(many) => many.iter().any(|item| item == value),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    iss: String,
+    sub: String,
+    aud: OneOrMany,
+    exp: i64,
+    nonce: Option<String>,
+    preferred_username: Option<String>,
+    email: Option<String>,
+    email_verified: Option<bool>,
+    #[serde(flatten)]
+    other: serde_json::Map<String, Value>,
+}
+
+fn validate_id_token(
+    oidc: &OidcConfig,
+    issuer: &str,
+    id_token: &str,
+    nonce: &str,
+    now: DateTime,
+) -> ApiResult<Identity> {
+    let mut parts = id_token.split('.');
+    let (Some(_header), Some(payload), Some(_signature), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(ApiError::InvalidOidcResponse("ID token is malformed"));
+    };
+    let payload = BASE64_URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .map_err(|_| ApiError::InvalidOidcResponse("ID token is malformed"))?;
+    let mut claims: Claims =
+        serde_json::from_slice(&payload).map_err(|_| ApiError::InvalidOidcResponse("ID token is malformed"))?;
+
+    if claims.iss != issuer {
+        return Err(ApiError::InvalidOidcResponse("ID token has wrong issuer"));
+    }
+    if !claims.aud.contains(&oidc.client_id) {
+        return Err(ApiError::InvalidOidcResponse("ID token has wrong audience"));
+    }
+    if now.unix_timestamp() > claims.exp {
+        return Err(ApiError::InvalidOidcResponse("ID token has expired"));
+    }
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err(ApiError::InvalidOidcResponse("ID token has wrong nonce"));
+    }
+
+    let groups = match claims.other.remove(oidc.groups_claim()) {
+        Some(value) => match serde_json::from_value(value) {
+            Ok(OneOrMany::One(group)) => Some(vec![group]),
+            Ok(OneOrMany::Many(groups)) => Some(groups),
+            Err(_) => return Err(ApiError::InvalidOidcResponse("groups claim is malformed")),
+        },
+        None => None,
+    };
+    Ok(Identity {
+        issuer: claims.iss,
+        subject: claims.sub,
+        preferred_username: claims.preferred_username,
+        email: claims.email.filter(|_| claims.email_verified == Some(true)),
+        groups,
+    })
+}
+
+fn state_mac(config: &Config, payload: &str, binding: &str) -> blake3::Hash {
+    let key = blake3::derive_key("Oxibooru OIDC state", config.password_secret.as_bytes());
+    blake3::keyed_hash(&key, format!("{payload}.{binding}").as_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config;
+    use crate::model::enums::UserRank;
+    use serde_json::json;
+
+    fn test_oidc_config() -> OidcConfig {
+        serde_json::from_value(json!({
+            "issuer": "https://sso.example.com",
+            "client_id": "oxibooru",
+            "client_secret": "secret",
+            "redirect_url": "https://booru.example.com/oidc-callback",
+            "groups_claim": "roles",
+            "group_ranks": [
+                { "group": "booru-power", "rank": "power" },
+                { "group": "booru-mods", "rank": "moderator" },
+            ],
+        }))
+        .unwrap()
+    }
+
+    fn encode_id_token(claims: &Value) -> String {
+        let header = BASE64_URL_SAFE_NO_PAD.encode(br#"{"alg":"RS256"}"#);
+        let payload = BASE64_URL_SAFE_NO_PAD.encode(claims.to_string());
+        format!("{header}.{payload}.signature")
+    }
+
+    #[test]
+    fn state() {
+        let config = config::test_config(None);
+        let now = DateTime::now();
+        let login = create_state(&config, None, now);
+        let binding = Some(login.binding.as_str());
+        assert_eq!(verify_state(&config, &login.state, binding, None, now).unwrap(), login.nonce);
+
+        let later = DateTime::from(*now + STATE_LIFETIME + Duration::from_secs(1));
+        assert!(verify_state(&config, &login.state, binding, None, later).is_err());
+
+        // States are bound to the browser and client that started the login
+        let other_binding = session::generate_secret();
+        assert!(verify_state(&config, &login.state, None, None, now).is_err());
+        assert!(verify_state(&config, &login.state, Some(&other_binding), None, now).is_err());
+        assert!(verify_state(&config, &login.state, binding, Some(2), now).is_err());
+
+        let linking_login = create_state(&config, Some(2), now);
+        let linking_binding = Some(linking_login.binding.as_str());
+        assert!(verify_state(&config, &linking_login.state, linking_binding, Some(2), now).is_ok());
+        assert!(verify_state(&config, &linking_login.state, linking_binding, Some(3), now).is_err());
+        assert!(verify_state(&config, &linking_login.state, linking_binding, None, now).is_err());
+
+        let (payload, mac) = login.state.rsplit_once('.').unwrap();
+        let (_, expiry_and_client) = payload.split_once('.').unwrap();
+        let tampered_state = format!("{}.{expiry_and_client}.{mac}", Uuid::new_v4().simple());
+        assert!(verify_state(&config, &tampered_state, binding, None, now).is_err());
+        assert!(verify_state(&config, "garbage", binding, None, now).is_err());
+    }
+
+    #[test]
+    fn id_token() {
+        let oidc = test_oidc_config();
+        let now = DateTime::now();
+        let valid_claims = json!({
+            "iss": "https://sso.example.com",
+            "sub": "1234",
+            "aud": ["oxibooru", "other"],
+            "exp": now.unix_timestamp() + 60,
+            "nonce": "abc",
+            "email": "user@example.com",
+            "email_verified": true,
+            "roles": ["booru-mods"],
+        });
+        let identity =
+            validate_id_token(&oidc, "https://sso.example.com", &encode_id_token(&valid_claims), "abc", now).unwrap();
+        assert_eq!(identity.subject, "1234");
+        assert_eq!(identity.username_hint(), "user");
+        assert_eq!(identity.email.as_deref(), Some("user@example.com"));
+        assert_eq!(identity.groups, Some(vec![String::from("booru-mods")]));
+
+        let validate =
+            |claims: &Value| validate_id_token(&oidc, "https://sso.example.com", &encode_id_token(claims), "abc", now);
+        let with = |key: &str, value: Value| {
+            let mut claims = valid_claims.clone();
+            claims[key] = value;
+            claims
+        };
+        assert!(validate(&with("iss", json!("https://evil.example.com"))).is_err());
+        assert!(validate(&with("aud", json!("other"))).is_err());
+        assert!(validate(&with("exp", json!(now.unix_timestamp() - 1))).is_err());
+        assert!(validate(&with("nonce", json!("xyz"))).is_err());
+        assert!(validate(&with("roles", json!(42))).is_err());
+        assert!(validate_id_token(&oidc, "https://sso.example.com", "not.a-token", "abc", now).is_err());
+
+        let unverified = validate(&with("email_verified", json!(false))).unwrap();
+        assert_eq!(unverified.email, None);
+    }
+
+    #[test]
+    fn group_rank() {
+        let oidc = test_oidc_config();
+        assert_eq!(oidc.group_rank(["booru-power", "booru-mods", "other"]), Some(UserRank::Moderator));
+        assert_eq!(oidc.group_rank(["booru-power"]), Some(UserRank::Power));
+        assert_eq!(oidc.group_rank(["other"]), None);
+    }
+}
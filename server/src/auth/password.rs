@@ -4,31 +4,150 @@ use argon2::password_hash::rand_core::OsRng;
 use argon2::password_hash::{Error, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
 use argon2::{Algorithm, Params, Version};
 use argon2::{Argon2, Error as ArgonError};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use strum::{EnumIter, IntoStaticStr};
+
+/// Kinds of password hashes that can be stored in the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum HashKind {
+    /// Argon2id hash computed with the configured parameters.
+    Current,
+    /// Argon2 hash computed with parameters weaker than the configured ones.
+    Weak,
+    /// Argon2id hash imported from szurubooru.
+    SzurubooruArgon2,
+    /// SHA-256 hash imported from szurubooru.
+    SzurubooruSha256,
+    /// SHA-1 hash imported from szurubooru.
+    SzurubooruSha1,
+    /// A hash that no password can match.
+    Unknown,
+}
+
+impl HashKind {
+    /// Returns `true` if hashes of this kind should be replaced the next time the user logs in.
+    pub fn is_outdated(self) -> bool {
+        self != Self::Current
+    }
+}
 
 /// Takes a plaintext `password` and hashes it using a cryptographically secure,
 /// memory-hard hash: Argon2id. A randomly generated `salt` is mixed in with the
 /// hash to protect against rainbow table attacks.
 pub fn hash_password(config: &Config, password: &SecretString) -> Result<(SecretString, SaltString), Error> {
     let salt = SaltString::generate(&mut OsRng);
-    let argon_context = create_argon_context(config)?;
-    let password_hash = argon_context.hash_password(password.as_bytes(), &salt)?;
-    Ok((SecretString::from(password_hash.to_string()), salt))
+    let password_hash = hash_with_salt(config, password, &salt)?;
+    Ok((password_hash, salt))
+}
+
+/// Hashes `password` like [`hash_password`], but keeps the user's existing `password_salt`.
+/// Used to replace outdated hashes, as tokens sent to users by email are derived from the salt.
+/// Fails if the salt is too short or contains characters that Argon2 doesn't allow in salts.
+pub fn rehash_password(config: &Config, password: &SecretString, password_salt: &str) -> Result<SecretString, Error> {
+    let salt = SaltString::from_b64(password_salt)?;
+    hash_with_salt(config, password, &salt)
 }
 
-/// Returns [`Ok`] if the given `password_hash` and `password` match.
-pub fn is_valid_password(config: &Config, password_hash: &str, password: &str) -> Result<(), Error> {
+/// Determines the kind of `password_hash` without verifying any password.
+pub fn hash_kind(config: &Config, password_hash: &str, password_salt: &str) -> HashKind {
+    const SHA256_HEX_LENGTH: usize = 64;
+    const SHA1_HEX_LENGTH: usize = 40;
+
+    let is_hex = password_hash.bytes().all(|byte| byte.is_ascii_hexdigit());
+    match PasswordHash::new(password_hash) {
+        // Szurubooru generated a separate salt for its Argon2 hashes, so it isn't the stored one
+        Ok(parsed_hash) if parsed_hash.salt.is_some_and(|salt| salt.as_str() == password_salt) => {
+            if is_weak(config, &parsed_hash) {
+                HashKind::Weak
+            } else {
+                HashKind::Current
+            }
+        }
+        Ok(_) => HashKind::SzurubooruArgon2,
+        Err(_) if is_hex && password_hash.len() == SHA256_HEX_LENGTH => HashKind::SzurubooruSha256,
+        Err(_) if is_hex && password_hash.len() == SHA1_HEX_LENGTH => HashKind::SzurubooruSha1,
+        Err(_) => HashKind::Unknown,
+    }
+}
+
+/// Returns the kind of `password_hash` if the given `password_hash` and `password` match.
+/// Hashes imported from szurubooru are verified the same way szurubooru did, which requires
+/// `password_secret` to be the `secret` from szurubooru's config.
+pub fn is_valid_password(
+    config: &Config,
+    password_hash: &str,
+    password_salt: &str,
+    password: &str,
+) -> Result<HashKind, Error> {
+    let hash_kind = hash_kind(config, password_hash, password_salt);
+    let secret = config.password_secret.read();
+    match hash_kind {
+        HashKind::Current | HashKind::Weak => {
+            let argon_context = create_argon_context(config)?;
+            PasswordHash::new(password_hash)
+                .and_then(|parsed_hash| argon_context.verify_password(password.as_bytes(), &parsed_hash))?;
+        }
+        HashKind::SzurubooruArgon2 => {
+            let peppered_password = format!("{secret}{password_salt}{password}");
+            PasswordHash::new(password_hash).and_then(|parsed_hash| {
+                Argon2::default().verify_password(peppered_password.as_bytes(), &parsed_hash)
+            })?;
+        }
+        HashKind::SzurubooruSha256 => {
+            let digest = Sha256::new()
+                .chain_update(secret)
+                .chain_update(password_salt)
+                .chain_update(password)
+                .finalize();
+            verify_hex_digest(&digest, password_hash)?;
+        }
+        HashKind::SzurubooruSha1 => {
+            let digest = Sha1::new()
+                .chain_update(b"1A2/$_4xVa")
+                .chain_update(password_salt)
+                .chain_update(password)
+                .finalize();
+            verify_hex_digest(&digest, password_hash)?;
+        }
+        HashKind::Unknown => return Err(Error::Password),
+    }
+    Ok(hash_kind)
+}
+
+fn hash_with_salt(config: &Config, password: &SecretString, salt: &SaltString) -> Result<SecretString, Error> {
     let argon_context = create_argon_context(config)?;
-    PasswordHash::new(password_hash)
-        .and_then(|parsed_hash| argon_context.verify_password(password.as_bytes(), &parsed_hash))
+    let password_hash = argon_context.hash_password(password.as_bytes(), salt)?;
+    Ok(SecretString::from(password_hash.to_string()))
 }
 
 fn create_argon_context(config: &Config) -> Result<Argon2<'_>, ArgonError> {
-    Argon2::new_with_secret(
-        config.password_secret.as_bytes(),
-        Algorithm::default(),
-        Version::default(),
-        Params::default(),
-    )
+    let hashing = &config.password_hashing;
+    let params = Params::new(hashing.memory_cost_kib, hashing.iterations, hashing.parallelism, None)?;
+    Argon2::new_with_secret(config.password_secret.as_bytes(), Algorithm::Argon2id, Version::V0x13, params)
+}
+
+/// Returns `true` if `parsed_hash` was computed with a different algorithm or with
+/// parameters weaker than the configured ones.
+fn is_weak(config: &Config, parsed_hash: &PasswordHash) -> bool {
+    let hashing = &config.password_hashing;
+    let Ok(params) = Params::try_from(parsed_hash) else {
+        return true;
+    };
+    parsed_hash.algorithm != Algorithm::Argon2id.ident()
+        || parsed_hash.version != Some(Version::V0x13.into())
+        || params.m_cost() < hashing.memory_cost_kib
+        || params.t_cost() < hashing.iterations
+        || params.p_cost() < hashing.parallelism
+}
+
+fn verify_hex_digest(digest: &[u8], password_hash: &str) -> Result<(), Error> {
+    if hex::encode(digest).eq_ignore_ascii_case(password_hash) {
+        Ok(())
+    } else {
+        Err(Error::Password)
+    }
 }
 
 #[cfg(test)]
@@ -40,7 +159,57 @@ mod test {
     #[test]
     fn hash_password() {
         let test_config = config::test_config(None);
-        assert!(is_valid_password(&test_config, TEST_HASH, TEST_PASSWORD).is_ok());
-        assert!(is_valid_password(&test_config, TEST_HASH, "wrong_password").is_err());
+        assert!(is_valid_password(&test_config, TEST_HASH, TEST_SALT, TEST_PASSWORD).is_ok());
+        assert!(is_valid_password(&test_config, TEST_HASH, TEST_SALT, "wrong_password").is_err());
+    }
+
+    #[test]
+    fn weak_hash() {
+        let mut test_config = config::test_config(None);
+        let password = SecretString::from(TEST_PASSWORD);
+        let (hash, salt) = super::hash_password(&test_config, &password).unwrap();
+        assert_eq!(hash_kind(&test_config, hash.read(), salt.as_str()), HashKind::Current);
+
+        test_config.password_hashing.iterations += 1;
+        assert_eq!(hash_kind(&test_config, hash.read(), salt.as_str()), HashKind::Weak);
+        assert_eq!(is_valid_password(&test_config, hash.read(), salt.as_str(), TEST_PASSWORD), Ok(HashKind::Weak));
+    }
+
+    #[test]
+    fn szurubooru_hash() {
+        let test_config = config::test_config(None);
+        let secret = test_config.password_secret.read();
+        let sha256_hash = hex::encode(Sha256::digest(format!("{secret}salt{TEST_PASSWORD}")));
+        let sha1_hash = hex::encode(Sha1::digest(format!("1A2/$_4xVasalt{TEST_PASSWORD}")));
+
+        assert_eq!(hash_kind(&test_config, &sha256_hash, "salt"), HashKind::SzurubooruSha256);
+        assert_eq!(hash_kind(&test_config, &sha1_hash, "salt"), HashKind::SzurubooruSha1);
+        assert_eq!(hash_kind(&test_config, "garbage", "salt"), HashKind::Unknown);
+        assert!(is_valid_password(&test_config, &sha256_hash, "salt", TEST_PASSWORD).is_ok());
+        assert!(is_valid_password(&test_config, &sha1_hash, "salt", TEST_PASSWORD).is_ok());
+        assert!(is_valid_password(&test_config, &sha256_hash, "salt", "wrong_password").is_err());
+        assert!(is_valid_password(&test_config, &sha1_hash, "other_salt", TEST_PASSWORD).is_err());
+    }
+
+    #[test]
+    fn rehash_password() {
+        const LEGACY_SALT: &str = "5f3d9a0c2b7e4f18";
+
+        let test_config = config::test_config(None);
+        let password = SecretString::from(TEST_PASSWORD);
+        let sha1_hash = hex::encode(Sha1::digest(format!("1A2/$_4xVa{LEGACY_SALT}{TEST_PASSWORD}")));
+        assert_eq!(hash_kind(&test_config, &sha1_hash, LEGACY_SALT), HashKind::SzurubooruSha1);
+
+        // Rehashed passwords keep their salt
+        let new_hash = super::rehash_password(&test_config, &password, LEGACY_SALT).unwrap();
+        assert_eq!(hash_kind(&test_config, new_hash.read(), LEGACY_SALT), HashKind::Current);
+        assert!(is_valid_password(&test_config, new_hash.read(), LEGACY_SALT, TEST_PASSWORD).is_ok());
+
+        let new_hash = super::rehash_password(&test_config, &password, TEST_SALT).unwrap();
+        assert_eq!(hash_kind(&test_config, new_hash.read(), TEST_SALT), HashKind::Current);
+
+        // Salts that Argon2 can't use are left alone
+        assert!(super::rehash_password(&test_config, &password, "salt").is_err());
+        assert!(super::rehash_password(&test_config, &password, "not a salt").is_err());
     }
 }
@@ -0,0 +1,167 @@
+//! Cookie-based browser sessions.
+//!
+//! The session cookie is `HttpOnly`, so scripts can't read it, and only a hash of its secret
+//! is stored. Because browsers attach cookies to cross-site requests too, requests that can
+//! modify state must also carry a CSRF token in the `X-CSRF-Token` header. The token is derived
+//! from the session secret and handed to the client in a second cookie that scripts can read.
+//! Other sites can't read that cookie, so they can't forge the header (double-submit).
+
+use crate::app::AppState;
+use crate::auth::Client;
+use crate::auth::header::{self, AuthenticationError};
+use crate::config::Config;
+use crate::schema::{user, user_session};
+use crate::time::DateTime;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use axum::http::header::COOKIE;
+use axum::http::{HeaderMap, HeaderValue, Method};
+use base64::Engine;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+
+/// Name of the cookie that carries the session secret.
+pub const SESSION_COOKIE: &str = "oxibooru_session";
+
+/// Name of the cookie that carries the CSRF token.
+pub const CSRF_COOKIE: &str = "oxibooru_csrf";
+
+/// Name of the header that clients must copy the CSRF token into.
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+const SECRET_LENGTH: usize = 32;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Generates a new random session secret.
+pub fn generate_secret() -> String {
+    let mut secret = [0; SECRET_LENGTH];
+    OsRng.fill_bytes(&mut secret);
+    BASE64_URL_SAFE_NO_PAD.encode(secret)
+}
+
+/// Hashes a session secret for storage. Secrets have enough entropy that a fast unkeyed hash suffices.
+pub fn hash_secret(secret: &str) -> String {
+    blake3::hash(secret.as_bytes()).to_hex().to_string()
+}
+
+/// Returns the CSRF token of the session with the given `secret`.
+pub fn csrf_token(config: &Config, secret: &str) -> String {
+    BASE64_URL_SAFE_NO_PAD.encode(csrf_mac(config, secret).as_bytes())
+}
+
+/// Returns the value of the cookie with the given `name`, if the request carries it.
+pub fn find_cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|&(key, _)| key == name)
+        .map(|(_, value)| value)
+}
+
+/// Returns `Set-Cookie` header values that start a session with the given `secret`.
+pub fn session_cookies(config: &Config, secret: &str) -> [HeaderValue; 2] {
+    let max_age = u64::from(config.session_lifetime_days) * SECONDS_PER_DAY;
+    [
+        cookie(config, SESSION_COOKIE, secret, max_age, true),
+        cookie(config, CSRF_COOKIE, &csrf_token(config, secret), max_age, false),
+    ]
+}
+
+/// Returns `Set-Cookie` header values that make the browser forget its session.
+pub fn expired_cookies(config: &Config) -> [HeaderValue; 2] {
+    [
+        cookie(config, SESSION_COOKIE, "", 0, true),
+        cookie(config, CSRF_COOKIE, "", 0, false),
+    ]
+}
+
+/// Authenticates the session with the given `secret`. Returns `None` if the session doesn't
+/// exist or has expired. Requests with a `method` that can modify state must carry the
+/// `csrf_token` of the session.
+pub async fn authenticate(
+    state: &AppState,
+    secret: &str,
+    method: &Method,
+    csrf_token: Option<&str>,
+) -> Result<Option<Client>, AuthenticationError> {
+    let mut conn = state.connection_pool.get().await?;
+    let session: Option<(i64, _, DateTime)> = user_session::table
+        .inner_join(user::table)
+        .select((user::id, user::rank, user_session::expiration_time))
+        .filter(user_session::secret_hash.eq(hash_secret(secret)))
+        .first(conn.as_mut())
+        .optional()?;
+    let Some((user_id, rank, _)) = session.filter(|&(_, _, expiration_time)| DateTime::now() <= expiration_time) else {
+        return Ok(None);
+    };
+
+    if !method.is_safe() && !csrf_token.is_some_and(|token| is_valid_csrf_token(&state.config, secret, token)) {
+        return Err(AuthenticationError::InvalidCsrfToken);
+    }
+    header::apply_sanctions(conn.as_mut(), user_id, rank).map(Some)
+}
+
+fn is_valid_csrf_token(config: &Config, secret: &str, token: &str) -> bool {
+    let mac: Option<[u8; blake3::OUT_LEN]> = BASE64_URL_SAFE_NO_PAD
+        .decode(token)
+        .ok()
+        .and_then(|mac| mac.try_into().ok());
+
+    // Comparison of blake3 hashes is constant-time
+    mac.is_some_and(|mac| csrf_mac(config, secret) == blake3::Hash::from_bytes(mac))
+}
+
+fn csrf_mac(config: &Config, secret: &str) -> blake3::Hash {
+    let key = blake3::derive_key("Oxibooru CSRF token", config.password_secret.as_bytes());
+    blake3::keyed_hash(&key, secret.as_bytes())
+}
+
+/// Returns a `Set-Cookie` header value for a same-site cookie that expires after `max_age` seconds.
+pub fn cookie(config: &Config, name: &str, value: &str, max_age: u64, http_only: bool) -> HeaderValue {
+    let mut cookie = format!("{name}={value}; Path=/; Max-Age={max_age}; SameSite=Strict");
+    if http_only {
+        cookie.push_str("; HttpOnly");
+    }
+    if config.secure_session_cookies {
+        cookie.push_str("; Secure");
+    }
+    HeaderValue::from_str(&cookie).expect("Cookie should only contain visible ASCII characters")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config;
+
+    #[test]
+    fn csrf() {
+        let config = config::test_config(None);
+        let secret = generate_secret();
+        let token = csrf_token(&config, &secret);
+        assert!(is_valid_csrf_token(&config, &secret, &token));
+        assert!(!is_valid_csrf_token(&config, &generate_secret(), &token));
+        assert!(!is_valid_csrf_token(&config, &secret, &token[1..]));
+        assert!(!is_valid_csrf_token(&config, &secret, ""));
+    }
+
+    #[test]
+    fn cookies() {
+        let config = config::test_config(None);
+        let [session_cookie, csrf_cookie] = session_cookies(&config, "secret");
+        let session_cookie = session_cookie.to_str().unwrap();
+        let csrf_cookie = csrf_cookie.to_str().unwrap();
+        assert!(session_cookie.starts_with("oxibooru_session=secret; Path=/; Max-Age="));
+        assert!(session_cookie.contains("; HttpOnly"));
+        assert!(csrf_cookie.starts_with(&format!("oxibooru_csrf={};", csrf_token(&config, "secret"))));
+        assert!(!csrf_cookie.contains("; HttpOnly"));
+
+        let mut headers = HeaderMap::new();
+        headers.append(COOKIE, HeaderValue::from_static("theme=dark; oxibooru_session=abc"));
+        headers.append(COOKIE, HeaderValue::from_static("oxibooru_csrf=def"));
+        assert_eq!(find_cookie(&headers, SESSION_COOKIE), Some("abc"));
+        assert_eq!(find_cookie(&headers, CSRF_COOKIE), Some("def"));
+        assert_eq!(find_cookie(&headers, "missing"), None);
+    }
+}
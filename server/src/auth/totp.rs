@@ -0,0 +1,358 @@
+//! Time-based one-time passwords (RFC 6238) used as a second authentication factor.
+//!
+//! Codes are 6 digits long, change every 30 seconds, and are computed with HMAC-SHA1,
+//! which is the only algorithm all common authenticator apps support. Codes from the
+//! previous and the next time step are also accepted to tolerate clock drift.
+//!
+//! To prevent replays, a code is only accepted for a time step after the last one used by
+//! the user. Clients that also send the user's password can keep using the code of the last
+//! used time step, so that logging in with a password can take more than one request. Users
+//! are locked out for a while after too many consecutive invalid codes, with
+//! the lockout doubling each time. Invalid codes are counted in memory rather than in the
+//! database, as second factors are often verified in transactions that are rolled back when
+//! verification fails.
+
+use crate::auth::header::AuthenticationError;
+use crate::config::Config;
+use crate::schema::{totp_recovery_code, user_totp};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::Engine;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use diesel::{BoolExpressionMethods, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
+use hmac::{Hmac, KeyInit, Mac};
+use percent_encoding::NON_ALPHANUMERIC;
+use sha1::Sha1;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Name of the header that carries a TOTP or recovery code.
+pub const TOTP_HEADER: &str = "x-totp-code";
+
+/// Number of recovery codes generated at once.
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+const SECRET_LENGTH: usize = 20;
+const RECOVERY_CODE_LENGTH: usize = 10;
+const PERIOD_SECONDS: u64 = 30;
+const DIGITS: u32 = 6;
+
+/// Number of consecutive invalid codes after which a user is locked out.
+const MAX_FAILED_ATTEMPTS: u32 = 5;
+const LOCKOUT_DURATION: Duration = Duration::from_secs(30);
+const MAX_LOCKOUT_DURATION: Duration = Duration::from_mins(30);
+
+/// Invalid codes entered by each user since their last valid one.
+static FAILED_ATTEMPTS: LazyLock<Mutex<HashMap<i64, FailedAttempts>>> = LazyLock::new(Mutex::default);
+
+#[derive(Default)]
+struct FailedAttempts {
+    count: u32,
+    locked_until: Option<Instant>,
+}
+
+/// Generates a new random shared secret.
+pub fn generate_secret() -> Vec<u8> {
+    let mut secret = vec![0; SECRET_LENGTH];
+    OsRng.fill_bytes(&mut secret);
+    secret
+}
+
+/// Generates a set of random recovery codes of the form `xxxxx-xxxxx`.
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0; RECOVERY_CODE_LENGTH * 5 / 8];
+            OsRng.fill_bytes(&mut bytes);
+            let code = base32_encode(&bytes).to_ascii_lowercase();
+            let (left, right) = code.split_at(RECOVERY_CODE_LENGTH / 2);
+            format!("{left}-{right}")
+        })
+        .collect()
+}
+
+/// Hashes a recovery code for storage. Recovery codes have enough entropy that a fast
+/// keyed hash suffices.
+pub fn hash_recovery_code(config: &Config, code: &str) -> String {
+    let normalized_code: String = code
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let key = blake3::derive_key("Oxibooru TOTP recovery code", config.password_secret.as_bytes());
+    BASE64_URL_SAFE_NO_PAD.encode(blake3::keyed_hash(&key, normalized_code.as_bytes()).as_bytes())
+}
+
+/// Returns the `otpauth` URI that authenticator apps use to import a `secret`.
+pub fn provisioning_uri(issuer: &str, username: &str, secret: &[u8]) -> String {
+    let issuer = percent_encoding::utf8_percent_encode(issuer, NON_ALPHANUMERIC);
+    let username = percent_encoding::utf8_percent_encode(username, NON_ALPHANUMERIC);
+    let secret = base32_encode(secret);
+    format!(
+        "otpauth://totp/{issuer}:{username}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={DIGITS}&period={PERIOD_SECONDS}"
+    )
+}
+
+/// Encodes `bytes` as unpadded RFC 4648 base32, which is how secrets are shown to users.
+pub fn base32_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut buffer: u16 = 0;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | u16::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(char::from(ALPHABET[usize::from((buffer >> bits) & 0x1F)]));
+        }
+    }
+    if bits > 0 {
+        encoded.push(char::from(ALPHABET[usize::from((buffer << (5 - bits)) & 0x1F)]));
+    }
+    encoded
+}
+
+/// Returns the time step `code` belongs to if it is valid for `secret` at `unix_time` or one time step around it.
+pub fn code_step(secret: &[u8], code: &str, unix_time: u64) -> Option<i64> {
+    let normalized_code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if normalized_code.len() != DIGITS as usize || !normalized_code.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let code = normalized_code.parse::<u32>().ok()?;
+
+    let step = unix_time / PERIOD_SECONDS;
+    [step.saturating_sub(1), step, step + 1]
+        .into_iter()
+        .find(|&step| compute_code(secret, step) == code)
+        .map(|step| step as i64)
+}
+
+/// Checks the second factor of user with given `user_id`, if they have enabled two-factor authentication.
+/// `code` can either be a TOTP code or an unused recovery code, which is consumed. If `with_password` is
+/// set, the client has also proven the user's password and may reuse the code of the last used time step.
+/// Returns `true` if the user has enabled two-factor authentication and `code` is valid.
+pub fn verify_second_factor(
+    conn: &mut PgConnection,
+    config: &Config,
+    user_id: i64,
+    code: Option<&str>,
+    with_password: bool,
+) -> Result<bool, AuthenticationError> {
+    let secret: Option<Vec<u8>> = user_totp::table
+        .find(user_id)
+        .select(user_totp::secret)
+        .filter(user_totp::enabled.eq(true))
+        .first(conn)
+        .optional()?;
+    let Some(secret) = secret else {
+        return Ok(false);
+    };
+    let code = code.ok_or(AuthenticationError::MissingTotpCode)?;
+    check_lockout(user_id, Instant::now())?;
+
+    if let Some(step) = code_step(&secret, code, unix_time()) {
+        // Conditional update so that concurrent requests can't use the same code
+        let first_unusable_step = if with_password { step + 1 } else { step };
+        let usable_step = diesel::update(user_totp::table.find(user_id))
+            .filter(
+                user_totp::last_used_step
+                    .lt(first_unusable_step)
+                    .or(user_totp::last_used_step.is_null()),
+            )
+            .set(user_totp::last_used_step.eq(step))
+            .execute(conn)?;
+        if usable_step > 0 {
+            clear_failed_attempts(user_id);
+            return Ok(true);
+        }
+    }
+
+    let used_recovery_codes = diesel::delete(totp_recovery_code::table)
+        .filter(
+            totp_recovery_code::user_id
+                .eq(user_id)
+                .and(totp_recovery_code::code_hash.eq(hash_recovery_code(config, code))),
+        )
+        .execute(conn)?;
+    if used_recovery_codes > 0 {
+        clear_failed_attempts(user_id);
+        Ok(true)
+    } else {
+        record_failed_attempt(user_id, Instant::now());
+        Err(AuthenticationError::InvalidTotpCode)
+    }
+}
+
+/// Returns the number of seconds since the Unix epoch.
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+fn failed_attempts() -> MutexGuard<'static, HashMap<i64, FailedAttempts>> {
+    FAILED_ATTEMPTS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Returns an error if the user with given `user_id` is locked out at time `now`.
+fn check_lockout(user_id: i64, now: Instant) -> Result<(), AuthenticationError> {
+    let locked_until = failed_attempts()
+        .get(&user_id)
+        .and_then(|attempts| attempts.locked_until);
+    match locked_until {
+        Some(locked_until) if now < locked_until => Err(AuthenticationError::TotpLockedOut),
+        _ => Ok(()),
+    }
+}
+
+/// Records an invalid code entered by user with given `user_id` at time `now`.
+/// Every `MAX_FAILED_ATTEMPTS` invalid codes lock the user out for twice as long as the last time.
+fn record_failed_attempt(user_id: i64, now: Instant) {
+    let mut failed_attempts = failed_attempts();
+    let attempts = failed_attempts.entry(user_id).or_default();
+    attempts.count += 1;
+    if attempts.count % MAX_FAILED_ATTEMPTS == 0 {
+        let previous_lockouts = attempts.count / MAX_FAILED_ATTEMPTS - 1;
+        let lockout_duration = LOCKOUT_DURATION
+            .saturating_mul(2u32.saturating_pow(previous_lockouts))
+            .min(MAX_LOCKOUT_DURATION);
+        attempts.locked_until = Some(now + lockout_duration);
+    }
+}
+
+fn clear_failed_attempts(user_id: i64) {
+    failed_attempts().remove(&user_id);
+}
+
+/// Computes the HOTP value (RFC 4226) of `secret` for a given `counter`.
+fn compute_code(secret: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC should take key of any size");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = usize::from(hash[hash.len() - 1] & 0x0F);
+    let truncated = u32::from_be_bytes([hash[offset], hash[offset + 1], hash[offset + 2], hash[offset + 3]]);
+    (truncated & 0x7FFF_FFFF) % 10u32.pow(DIGITS)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::error::ApiResult;
+    use crate::config;
+    use crate::model::user::NewUserTotp;
+    use crate::test::get_connection;
+    use diesel::Insertable;
+    use serial_test::serial;
+
+    // Test vectors from RFC 6238, truncated to 6 digits
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn rfc_codes() {
+        assert_eq!(compute_code(RFC_SECRET, 59 / PERIOD_SECONDS), 287082);
+        assert_eq!(compute_code(RFC_SECRET, 1111111109 / PERIOD_SECONDS), 81804);
+        assert_eq!(compute_code(RFC_SECRET, 1234567890 / PERIOD_SECONDS), 5924);
+        assert_eq!(compute_code(RFC_SECRET, 2000000000 / PERIOD_SECONDS), 279037);
+    }
+
+    #[test]
+    fn validate_code() {
+        const STEP: i64 = (1111111109 / PERIOD_SECONDS) as i64;
+
+        assert_eq!(code_step(RFC_SECRET, "081804", 1111111109), Some(STEP));
+        assert_eq!(code_step(RFC_SECRET, "081 804", 1111111109), Some(STEP));
+        assert_eq!(code_step(RFC_SECRET, "081804", 1111111109 + PERIOD_SECONDS), Some(STEP));
+        assert_eq!(code_step(RFC_SECRET, "081804", 1111111109 + 3 * PERIOD_SECONDS), None);
+        assert_eq!(code_step(RFC_SECRET, "81804", 1111111109), None);
+        assert_eq!(code_step(RFC_SECRET, "+81804", 1111111109), None);
+        assert_eq!(code_step(RFC_SECRET, "abcdef", 1111111109), None);
+    }
+
+    #[test]
+    fn lockout() {
+        // Negative ids don't belong to any user, so this can't interfere with other tests
+        const USER_ID: i64 = -1;
+
+        let now = Instant::now();
+        for _ in 1..MAX_FAILED_ATTEMPTS {
+            record_failed_attempt(USER_ID, now);
+        }
+        assert!(check_lockout(USER_ID, now).is_ok());
+
+        record_failed_attempt(USER_ID, now);
+        assert!(check_lockout(USER_ID, now).is_err());
+        assert!(check_lockout(USER_ID, now + LOCKOUT_DURATION).is_ok());
+
+        // Lockouts get longer with each further round of invalid codes
+        let later = now + LOCKOUT_DURATION;
+        for _ in 0..MAX_FAILED_ATTEMPTS {
+            record_failed_attempt(USER_ID, later);
+        }
+        assert!(check_lockout(USER_ID, later + LOCKOUT_DURATION).is_err());
+        assert!(check_lockout(USER_ID, later + 2 * LOCKOUT_DURATION).is_ok());
+
+        clear_failed_attempts(USER_ID);
+        assert!(check_lockout(USER_ID, later).is_ok());
+    }
+
+    #[test]
+    #[serial]
+    fn step_reuse() -> ApiResult<()> {
+        const USER_ID: i64 = 4;
+
+        let mut conn = get_connection()?;
+        let config = config::test_config(None);
+        let secret = generate_secret();
+        NewUserTotp {
+            user_id: USER_ID,
+            secret: &secret,
+        }
+        .insert_into(user_totp::table)
+        .execute(&mut conn)?;
+        diesel::update(user_totp::table.find(USER_ID))
+            .set(user_totp::enabled.eq(true))
+            .execute(&mut conn)?;
+
+        let code = format!("{:06}", compute_code(&secret, unix_time() / PERIOD_SECONDS));
+        let mut verify = |with_password| verify_second_factor(&mut conn, &config, USER_ID, Some(&code), with_password);
+
+        // Logging in with a password can take several requests with the same code, but it can't be replayed otherwise
+        assert!(verify(true)?);
+        assert!(verify(true)?);
+        assert!(matches!(verify(false), Err(AuthenticationError::InvalidTotpCode)));
+
+        clear_failed_attempts(USER_ID);
+        diesel::delete(user_totp::table.find(USER_ID)).execute(&mut conn)?;
+        Ok(())
+    }
+
+    #[test]
+    fn base32() {
+        assert_eq!(base32_encode(b""), "");
+        assert_eq!(base32_encode(b"f"), "MY");
+        assert_eq!(base32_encode(b"fooba"), "MZXW6YTB");
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+    }
+
+    #[test]
+    fn recovery_codes() {
+        let config = config::test_config(None);
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert!(codes.iter().all(|code| code.len() == RECOVERY_CODE_LENGTH + 1));
+        assert_eq!(hash_recovery_code(&config, "abcde-fghij"), hash_recovery_code(&config, " ABCDE FGHIJ"));
+        assert_ne!(hash_recovery_code(&config, "abcde-fghij"), hash_recovery_code(&config, "abcde-fghik"));
+    }
+
+    #[test]
+    fn uri() {
+        assert_eq!(
+            provisioning_uri("My Booru", "user", b"foobar"),
+            "otpauth://totp/My%20Booru:user?secret=MZXW6YTBOI&issuer=My%20Booru&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use crate::content::hash;
 use crate::filesystem::Directory;
-use crate::model::enums::{AvatarStyle, UserRank};
+use crate::model::enums::{AvatarStyle, ResourceOperation, ResourceType, UserRank};
 use crate::search::preferences::Preferences;
 use crate::string::{SecretString, SmallString};
 use crate::unit::ByteCount;
@@ -34,6 +34,7 @@ pub struct Env {
     pub http_referer: Option<String>,
     pub domain_port: Option<u16>,
     pub server_port: u16,
+    pub metrics_port: Option<u16>,
     postgres_user: SecretString,
     postgres_password: SecretString,
     postgres_hostname: SecretString,
@@ -54,6 +55,14 @@ pub enum RegexType {
     Password,
 }
 
+/// Argon2id parameters used to hash passwords.
+#[derive(Debug, Deserialize)]
+pub struct PasswordHashingConfig {
+    pub memory_cost_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ThumbnailConfig {
     pub avatar_width: u32,
@@ -81,6 +90,154 @@ pub struct SmtpConfig {
     pub from: Mailbox,
 }
 
+/// A URL that snapshots are posted to as they are created.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: Url,
+    pub secret: Option<SecretString>,
+    pub signature_header: Option<String>,
+    pub resource_types: Option<Vec<ResourceType>>,
+    pub operations: Option<Vec<ResourceOperation>>,
+}
+
+impl WebhookConfig {
+    pub fn signature_header(&self) -> &str {
+        self.signature_header.as_deref().unwrap_or("X-Oxibooru-Signature")
+    }
+
+    /// Returns whether snapshots of `resource_type` created by `operation` should be posted to this webhook.
+    pub fn accepts(&self, resource_type: ResourceType, operation: ResourceOperation) -> bool {
+        self.resource_types
+            .as_ref()
+            .is_none_or(|types| types.contains(&resource_type))
+            && self
+                .operations
+                .as_ref()
+                .is_none_or(|operations| operations.contains(&operation))
+    }
+}
+
+/// Webhooks can be given as just a URL, or as a table with additional settings.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WebhookDefinition {
+    Url(Url),
+    Table(WebhookConfig),
+}
+
+fn deserialize_webhooks<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<WebhookConfig>, D::Error> {
+    let definitions = Vec::<WebhookDefinition>::deserialize(deserializer)?;
+    Ok(definitions
+        .into_iter()
+        .map(|definition| match definition {
+            WebhookDefinition::Url(url) => WebhookConfig {
+                url,
+                secret: None,
+                signature_header: None,
+                resource_types: None,
+                operations: None,
+            },
+            WebhookDefinition::Table(webhook) => webhook,
+        })
+        .collect())
+}
+
+/// Maps members of an identity provider group to a user rank.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OidcGroupRank {
+    pub group: String,
+    pub rank: UserRank,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OidcConfig {
+    pub issuer: Url,
+    pub client_id: String,
+    pub client_secret: SecretString,
+    pub redirect_url: Url,
+    pub scope: Option<String>,
+    pub groups_claim: Option<String>,
+    #[serde(default)]
+    pub group_ranks: Vec<OidcGroupRank>,
+}
+
+impl OidcConfig {
+    pub fn scope(&self) -> &str {
+        self.scope.as_deref().unwrap_or("openid profile email")
+    }
+
+    pub fn groups_claim(&self) -> &str {
+        self.groups_claim.as_deref().unwrap_or("groups")
+    }
+
+    /// Returns the highest rank mapped to any of the given `groups`, if any.
+    pub fn group_rank<'a>(&self, groups: impl IntoIterator<Item = &'a str>) -> Option<UserRank> {
+        groups
+            .into_iter()
+            .filter_map(|group| self.group_ranks.iter().find(|mapping| mapping.group == group))
+            .map(|mapping| mapping.rank)
+            .max()
+    }
+}
+
+/// Memory layout of the image tensor expected by an auto-tagging model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TensorLayout {
+    Nchw,
+    Nhwc,
+}
+
+/// What to do with tags predicted for newly uploaded content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoTagMode {
+    /// Predicted tags are added to the post on upload.
+    Apply,
+    /// Predicted tags are offered as tag suggestions for the upload.
+    Suggest,
+}
+
+/// How anonymous users can register accounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistrationMode {
+    /// Accounts are usable right after registering.
+    Open,
+    /// Accounts stay restricted until the user clicks a link sent to their email address.
+    EmailVerification,
+    /// Registering requires an invite code.
+    InviteCode,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AutoTagConfig {
+    pub model_path: PathBuf,
+    pub label_path: PathBuf,
+    pub input_size: u32,
+    pub input_layout: TensorLayout,
+    pub input_scale: f32,
+    pub threshold: f32,
+    pub mode: AutoTagMode,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TraceExportConfig {
+    pub endpoint: Url,
+    pub service_name: Option<String>,
+}
+
+impl TraceExportConfig {
+    pub fn service_name(&self) -> &str {
+        self.service_name.as_deref().unwrap_or("oxibooru")
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct LimitsConfig {
     pub max_image_width: u32,
@@ -123,8 +280,29 @@ pub enum Action {
     UserTokenDeleteAny,
     UserTokenDeleteSelf,
 
+    UserTotpEditSelf,
+    UserTotpDisableAny,
+
+    UserSessionCreate,
+    UserSessionListAny,
+    UserSessionListSelf,
+    UserSessionDeleteAny,
+    UserSessionDeleteSelf,
+
+    InviteCodeList,
+    InviteCodeCreate,
+    InviteCodeDelete,
+
+    UserSanctionList,
+    UserSanctionCreate,
+    UserSanctionDelete,
+    UserNoteList,
+    UserNoteCreate,
+    UserNoteDelete,
+
     PostCreateAnonymous,
     PostCreateIdentified,
+    PostCreateUnmoderated,
     PostList,
     PostReverseSearch,
     PostView,
@@ -140,6 +318,10 @@ pub enum Action {
     PostEditThumbnail,
     PostFeature,
     PostDelete,
+    PostListDeleted,
+    PostRestore,
+    PostApprove,
+    PostReject,
     PostScore,
     PostMerge,
     PostFavorite,
@@ -157,6 +339,7 @@ pub enum Action {
     TagView,
     TagMerge,
     TagDelete,
+    TagApplyImplications,
 
     TagCategoryCreate,
     TagCategoryEditName,
@@ -195,6 +378,25 @@ pub enum Action {
     CommentScore,
 
     SnapshotList,
+    WebhookDeliveryList,
+    WebhookDeliveryReplay,
+    ActivityStream,
+
+    AuditLogList,
+    MetricsView,
+
+    ReportCreate,
+    ReportList,
+    ReportView,
+    ReportResolve,
+
+    WikiPageCreate,
+    WikiPageEdit,
+    WikiPageView,
+    WikiPageDelete,
+
+    NotificationView,
+    DigestSubscribe,
 
     UploadCreate,
     UploadUseDownloader,
@@ -250,6 +452,7 @@ impl ToSchema for PrivilegeConfig {}
 pub struct PublicConfig {
     pub name: SmallString,
     pub default_user_rank: UserRank,
+    pub registration_mode: RegistrationMode,
     pub enable_safety: bool,
     pub contact_email: Option<SmallString>,
     #[serde(skip)]
@@ -276,11 +479,18 @@ pub struct Config {
     pub args: Args,
     pub data_dir: PathBuf,
     pub data_url: String,
-    pub webhooks: Vec<Url>,
+    #[serde(deserialize_with = "deserialize_webhooks")]
+    pub webhooks: Vec<WebhookConfig>,
     pub password_secret: SecretString,
     pub content_secret: SecretString,
     pub domain: Option<SmallString>,
     pub delete_source_files: bool,
+    pub deleted_post_retention_days: u32,
+    pub session_lifetime_days: u32,
+    pub secure_session_cookies: bool,
+    pub audit_log_retention_days: u32,
+    pub webhook_delivery_retention_days: u32,
+    pub trust_forwarded_for: bool,
     pub append_tag_implications_on_post_edit: bool,
     pub post_similarity_threshold: f64,
     #[serde(with = "serde_regex")]
@@ -289,8 +499,12 @@ pub struct Config {
     pub pool_category_regex: Regex,
     pub log_filter: String,
     pub auto_explain: bool,
+    pub password_hashing: PasswordHashingConfig,
     pub thumbnails: ThumbnailConfig,
     pub smtp: Option<SmtpConfig>,
+    pub oidc: Option<OidcConfig>,
+    pub auto_tag: Option<AutoTagConfig>,
+    pub trace_export: Option<TraceExportConfig>,
     pub limits: LimitsConfig,
     #[serde(default)]
     pub anonymous_preferences: Preferences,
@@ -312,6 +526,20 @@ impl Config {
         self.smtp.as_ref()
     }
 
+    pub fn oidc(&self) -> Option<&OidcConfig> {
+        self.oidc.as_ref()
+    }
+
+    /// Returns auto-tagging configuration if auto-tagging is supported by this build.
+    pub fn auto_tag(&self) -> Option<&AutoTagConfig> {
+        self.auto_tag.as_ref().filter(|_| cfg!(feature = "auto_tag"))
+    }
+
+    /// Returns trace export configuration if trace export is supported by this build.
+    pub fn trace_export(&self) -> Option<&TraceExportConfig> {
+        self.trace_export.as_ref().filter(|_| cfg!(feature = "otel"))
+    }
+
     pub fn privileges(&self) -> &PrivilegeConfig {
         &self.public_info.privileges
     }
@@ -389,6 +617,7 @@ pub fn read_env(config: &Config) -> Result<Arc<Env>, Box<dyn Error>> {
         .ok()
         .and_then(|var| var.parse().ok())
         .unwrap_or(DEFAULT_SERVER_PORT);
+    let metrics_port = std::env::var("METRICS_PORT").ok().and_then(|port| port.parse().ok());
 
     let postgres_user = std::env::var("POSTGRES_USER").map(SecretString::from)?;
     let postgres_password = std::env::var("POSTGRES_PASSWORD").map(SecretString::from)?;
@@ -404,6 +633,7 @@ pub fn read_env(config: &Config) -> Result<Arc<Env>, Box<dyn Error>> {
         http_referer,
         domain_port,
         server_port,
+        metrics_port,
         postgres_user,
         postgres_password,
         postgres_hostname,
@@ -503,6 +733,12 @@ fn create_config(args: Args) -> Config {
     };
     config.args = args;
     config.public_info.can_send_mails = config.smtp.is_some();
+    if config.auto_tag.is_some() && !cfg!(feature = "auto_tag") {
+        eprintln!("Auto-tagging is configured, but server was built without the auto_tag feature. Ignoring.");
+    }
+    if config.trace_export.is_some() && !cfg!(feature = "otel") {
+        eprintln!("Trace export is configured, but server was built without the otel feature. Ignoring.");
+    }
     // Default user rank can't be anonymous
     config.public_info.default_user_rank = std::cmp::max(config.public_info.default_user_rank, UserRank::Restricted);
 
@@ -0,0 +1,218 @@
+use crate::config::Config;
+#[cfg(any(feature = "auto_tag", test))]
+use crate::config::TensorLayout;
+use crate::string::SmallString;
+use image::DynamicImage;
+
+/// A tag predicted for some content by the auto-tagging model.
+#[derive(Debug, Clone)]
+pub struct Prediction {
+    pub tag: SmallString,
+    pub confidence: f32,
+}
+
+/// Predicts tags for the given `image` using the configured auto-tagging model.
+/// Returned predictions are distinct, sorted by confidence and all have confidence
+/// above the configured threshold.
+///
+/// Returns no predictions if auto-tagging isn't configured or fails.
+#[cfg(feature = "auto_tag")]
+pub fn predict(config: &Config, image: &DynamicImage) -> Vec<Prediction> {
+    match config.auto_tag() {
+        Some(auto_tag_config) => tagger::predict(auto_tag_config, image),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(not(feature = "auto_tag"))]
+pub fn predict(_config: &Config, _image: &DynamicImage) -> Vec<Prediction> {
+    Vec::new()
+}
+
+#[cfg(feature = "auto_tag")]
+mod tagger {
+    use crate::config::{AutoTagConfig, TensorLayout};
+    use crate::content::auto_tag::{CHANNELS, Prediction, input_index, parse_label};
+    use crate::string::SmallString;
+    use crate::time::Timer;
+    use image::DynamicImage;
+    use image::imageops::FilterType;
+    use ort::session::Session;
+    use ort::value::Tensor;
+    use std::collections::HashMap;
+    use std::sync::{Mutex, OnceLock, PoisonError};
+    use thiserror::Error;
+    use tracing::{error, info};
+
+    pub fn predict(config: &AutoTagConfig, image: &DynamicImage) -> Vec<Prediction> {
+        let Some(tagger) = get_or_load(config) else {
+            return Vec::new();
+        };
+
+        let _timer = Timer::new("auto_tag::predict");
+        tagger.predict(config, image).unwrap_or_else(|err| {
+            error!("Auto-tagging failed for reason: {err}");
+            Vec::new()
+        })
+    }
+
+    static TAGGER: OnceLock<Tagger> = OnceLock::new();
+    static LOAD_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Returns the loaded tagger, loading it first if necessary.
+    /// A failed load isn't cached, so it is retried on the next prediction.
+    fn get_or_load(config: &AutoTagConfig) -> Option<&'static Tagger> {
+        if let Some(tagger) = TAGGER.get() {
+            return Some(tagger);
+        }
+
+        // Serialize loads so that concurrent predictions don't each load the model
+        let _guard = LOAD_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(tagger) = TAGGER.get() {
+            return Some(tagger);
+        }
+        match Tagger::load(config) {
+            Ok(tagger) => Some(TAGGER.get_or_init(|| tagger)),
+            Err(err) => {
+                error!("Failed to load auto-tagging model for reason: {err}");
+                None
+            }
+        }
+    }
+
+    #[derive(Debug, Error)]
+    #[error(transparent)]
+    enum AutoTagError {
+        Io(#[from] std::io::Error),
+        Ort(#[from] ort::Error),
+    }
+
+    struct Tagger {
+        session: Mutex<Session>,
+        /// Tag for each model output. Outputs without a tag are ignored.
+        tags: Vec<Option<SmallString>>,
+    }
+
+    impl Tagger {
+        fn load(config: &AutoTagConfig) -> Result<Self, AutoTagError> {
+            let labels = std::fs::read_to_string(&config.label_path)?;
+            let tags: Vec<_> = labels.lines().map(parse_label).collect();
+            let session = Session::builder()?.commit_from_file(&config.model_path)?;
+            info!("Loaded auto-tagging model with {} labels", tags.len());
+
+            Ok(Self {
+                session: Mutex::new(session),
+                tags,
+            })
+        }
+
+        fn predict(&self, config: &AutoTagConfig, image: &DynamicImage) -> Result<Vec<Prediction>, AutoTagError> {
+            let image = image
+                .resize_exact(config.input_size, config.input_size, FilterType::Triangle)
+                .into_rgb8();
+            let size = config.input_size as usize;
+            let mut input = vec![0.0; CHANNELS * size * size];
+            for (x, y, pixel) in image.enumerate_pixels() {
+                let (x, y) = (x as usize, y as usize);
+                for (channel, &value) in pixel.0.iter().enumerate() {
+                    let index = input_index(config.input_layout, size, x, y, channel);
+                    input[index] = f32::from(value) * config.input_scale;
+                }
+            }
+            let shape = match config.input_layout {
+                TensorLayout::Nchw => [1, CHANNELS, size, size],
+                TensorLayout::Nhwc => [1, size, size, CHANNELS],
+            };
+            let input = Tensor::from_array((shape, input))?;
+
+            let mut session = self.session.lock().unwrap_or_else(PoisonError::into_inner);
+            let outputs = session.run(ort::inputs![input])?;
+            let (_, confidences) = outputs[0].try_extract_tensor::<f32>()?;
+
+            // Multiple labels can map to the same tag, so keep the most confident one
+            let mut predictions: HashMap<&SmallString, f32> = HashMap::new();
+            for (&confidence, tag) in confidences.iter().zip(&self.tags) {
+                if let Some(tag) = tag.as_ref().filter(|_| confidence >= config.threshold) {
+                    let max_confidence = predictions.entry(tag).or_insert(confidence);
+                    *max_confidence = max_confidence.max(confidence);
+                }
+            }
+            let mut predictions: Vec<_> = predictions
+                .into_iter()
+                .map(|(tag, confidence)| Prediction {
+                    tag: tag.clone(),
+                    confidence,
+                })
+                .collect();
+            predictions.sort_unstable_by(|a, b| b.confidence.total_cmp(&a.confidence).then_with(|| a.tag.cmp(&b.tag)));
+            Ok(predictions)
+        }
+    }
+}
+
+/// Number of color channels in the image tensor.
+#[cfg(any(feature = "auto_tag", test))]
+const CHANNELS: usize = 3;
+
+/// Returns the index of the given `channel` of pixel (`x`, `y`) in an image tensor
+/// of `size`x`size` pixels with the given `layout`.
+#[cfg(any(feature = "auto_tag", test))]
+fn input_index(layout: TensorLayout, size: usize, x: usize, y: usize, channel: usize) -> usize {
+    match layout {
+        TensorLayout::Nchw => (channel * size + y) * size + x,
+        TensorLayout::Nhwc => (y * size + x) * CHANNELS + channel,
+    }
+}
+
+/// Parses a line of the label file, which has the form `<label>,<tag>`.
+/// If the tag is omitted, the label is used as the tag. An empty tag means the label is ignored.
+#[cfg(any(feature = "auto_tag", test))]
+fn parse_label(line: &str) -> Option<SmallString> {
+    let tag = line.split_once(',').map_or(line, |(_, tag)| tag).trim();
+    (!tag.is_empty()).then(|| SmallString::from(tag))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn label_parsing() {
+        assert_eq!(parse_label("1girl").as_deref(), Some("1girl"));
+        assert_eq!(parse_label("1girl,girl").as_deref(), Some("girl"));
+        assert_eq!(parse_label("1girl, girl ").as_deref(), Some("girl"));
+        assert_eq!(parse_label("rating:safe,").as_deref(), None);
+        assert_eq!(parse_label("rating:safe, ").as_deref(), None);
+        assert_eq!(parse_label("").as_deref(), None);
+        assert_eq!(parse_label("a,b,c").as_deref(), Some("b,c"));
+    }
+
+    #[test]
+    fn tensor_indexing() {
+        const SIZE: usize = 4;
+
+        // Both layouts must map every (x, y, channel) to a distinct index within the tensor
+        for layout in [TensorLayout::Nchw, TensorLayout::Nhwc] {
+            let mut visited = [false; CHANNELS * SIZE * SIZE];
+            for y in 0..SIZE {
+                for x in 0..SIZE {
+                    for channel in 0..CHANNELS {
+                        let index = input_index(layout, SIZE, x, y, channel);
+                        assert!(!visited[index], "{layout:?} maps multiple values to index {index}");
+                        visited[index] = true;
+                    }
+                }
+            }
+        }
+
+        // Channel planes are contiguous in NCHW, pixels are contiguous in NHWC
+        assert_eq!(input_index(TensorLayout::Nchw, SIZE, 1, 0, 0), 1);
+        assert_eq!(input_index(TensorLayout::Nchw, SIZE, 0, 1, 0), SIZE);
+        assert_eq!(input_index(TensorLayout::Nchw, SIZE, 0, 0, 1), SIZE * SIZE);
+        assert_eq!(input_index(TensorLayout::Nchw, SIZE, 3, 2, 1), SIZE * SIZE + 2 * SIZE + 3);
+        assert_eq!(input_index(TensorLayout::Nhwc, SIZE, 0, 0, 1), 1);
+        assert_eq!(input_index(TensorLayout::Nhwc, SIZE, 1, 0, 0), CHANNELS);
+        assert_eq!(input_index(TensorLayout::Nhwc, SIZE, 0, 1, 0), SIZE * CHANNELS);
+        assert_eq!(input_index(TensorLayout::Nhwc, SIZE, 3, 2, 1), (2 * SIZE + 3) * CHANNELS + 1);
+    }
+}
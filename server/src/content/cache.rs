@@ -1,15 +1,18 @@
 use crate::api::error::ApiResult;
+use crate::content::auto_tag::Prediction;
 use crate::content::hash::{Checksum, Md5Checksum};
 use crate::content::signature::COMPRESSED_SIGNATURE_LEN;
 use crate::content::thumbnail::ThumbnailType;
 use crate::content::upload::UploadToken;
-use crate::content::{decode, hash, signature, thumbnail};
+use crate::content::{auto_tag, decode, hash, signature, thumbnail};
 use crate::extract::Ctx;
 use crate::model::enums::{MimeType, PostFlag, PostFlags, PostType};
-use crate::{content, filesystem};
+use crate::{content, filesystem, metrics};
 use image::DynamicImage;
 use image::error::LimitErrorKind;
 use std::collections::VecDeque;
+use tracing::field::Empty;
+use tracing::{Span, instrument};
 
 /// Stores properties of content that are costly to compute (usually require reading/decoding entire file).
 #[derive(Clone)]
@@ -25,6 +28,7 @@ pub struct CachedProperties {
     pub post_type: PostType,
     pub file_size: i64,
     pub flags: PostFlags,
+    pub predicted_tags: Vec<Prediction>,
 }
 
 /// A simple ring buffer that stores [`CachedProperties`].
@@ -67,15 +71,25 @@ pub fn compute_properties(ctx: &Ctx, content_token: UploadToken) -> ApiResult<Ca
 }
 
 /// Returns cached properties of content or computes them if not in cache.
+#[instrument(skip_all, fields(cache_hit = Empty))]
 pub fn remove_or_compute_properties(ctx: &Ctx, content_token: UploadToken) -> ApiResult<CachedProperties> {
     let maybe_properties = ctx.get_content_cache().remove(&content_token);
     match maybe_properties {
-        Some(properties) => Ok(properties),
-        None => compute_properties_no_cache(ctx, content_token),
+        Some(properties) => {
+            Span::current().record("cache_hit", true);
+            metrics::CONTENT_CACHE_LOOKUPS.increment(["hit"]);
+            Ok(properties)
+        }
+        None => {
+            Span::current().record("cache_hit", false);
+            metrics::CONTENT_CACHE_LOOKUPS.increment(["miss"]);
+            compute_properties_no_cache(ctx, content_token)
+        }
     }
 }
 
 /// Computes content properties without storing them in cache.
+#[instrument(skip_all, fields(mime_type = %token.mime_type()))]
 fn compute_properties_no_cache(ctx: &Ctx, token: UploadToken) -> ApiResult<CachedProperties> {
     let temp_path = token.path(&ctx.config);
     let file_size = content::map_read_result(filesystem::file_size(&temp_path))?;
@@ -102,6 +116,7 @@ fn compute_properties_no_cache(ctx: &Ctx, token: UploadToken) -> ApiResult<Cache
         checksum,
         md5_checksum,
         signature: signature::compute(&image),
+        predicted_tags: auto_tag::predict(&ctx.config, &image),
         thumbnail: thumbnail::create(&ctx.config, image, ThumbnailType::Post),
         width,
         height,
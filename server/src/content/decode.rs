@@ -1,6 +1,7 @@
 use crate::api::error::{ApiError, ApiResult};
 use crate::config::Config;
 use crate::content::{self, flash};
+use crate::metrics;
 use crate::model::enums::{MimeType, PostType};
 use ffmpeg_sidecar::child::FfmpegChild;
 use ffmpeg_sidecar::command::FfmpegCommand;
@@ -15,14 +16,15 @@ use std::path::Path;
 use std::str::FromStr;
 use std::sync::mpsc::{RecvTimeoutError, SyncSender};
 use std::sync::{Arc, Mutex, PoisonError};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use swf::Tag;
-use tracing::{error, warn};
+use tracing::{error, instrument, warn};
 
 /// Returns a representative image for the given content.
 /// For images, this is simply the decoded image.
 /// For videos, `FFmpeg` determines the thumbnail.
 /// For Flash media, it is the largest image that can be decoded from the Flash tags.
+#[instrument(skip_all)]
 pub fn representative_image(config: &Config, file_path: &Path, mime_type: MimeType) -> ApiResult<DynamicImage> {
     match mime_type {
         MimeType::Bmp => image(config, file_path, ImageFormat::Bmp),
@@ -84,6 +86,7 @@ pub fn swf_has_audio(path: &Path) -> ApiResult<bool> {
 /// Returns the post type based on file content.
 /// For image formats that support animation, it checks the file content for multiple frames.
 /// For everything else, it just checks the mime type.
+#[instrument(skip_all)]
 pub fn detect_post_type(config: &Config, file_path: &Path, mime_type: MimeType) -> ApiResult<PostType> {
     // Shorthand to return PostType::Animation or PostType::Image based on bool input
     let image_type = |animated: bool| -> PostType { if animated { PostType::Animation } else { PostType::Image } };
@@ -120,6 +123,7 @@ struct FfmpegChildState {
 struct FfmpegSubprocess {
     state: Arc<Mutex<FfmpegChildState>>,
     watchdog_disarm: Option<SyncSender<()>>,
+    start_time: Instant,
 }
 
 impl FfmpegSubprocess {
@@ -151,6 +155,7 @@ impl FfmpegSubprocess {
                 // Blocks until disarmed (sender dropped or timeout)
                 if disarm_rx.recv_timeout(ffmpeg_timeout) == Err(RecvTimeoutError::Timeout) {
                     warn!("Killing FFmpeg subprocess after {}s", ffmpeg_timeout.as_secs());
+                    metrics::FFMPEG_TIMEOUTS.increment([]);
 
                     let mut guard = state.lock().unwrap_or_else(PoisonError::into_inner);
                     guard.timed_out = true;
@@ -161,6 +166,7 @@ impl FfmpegSubprocess {
         Ok(Self {
             state,
             watchdog_disarm: Some(disarm_tx),
+            start_time: Instant::now(),
         })
     }
 
@@ -190,6 +196,7 @@ impl Drop for FfmpegSubprocess {
         // Ignore errors as the process may have already exited
         guard.child.kill().ok();
         guard.child.wait().ok();
+        metrics::FFMPEG_DURATION.observe_duration([], self.start_time.elapsed());
     }
 }
 
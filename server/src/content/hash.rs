@@ -18,6 +18,7 @@ use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use tracing::instrument;
 use utoipa::ToSchema;
 
 /// Stores a `post_id` and cached post `hash`.
@@ -103,6 +104,66 @@ impl Display for PostHash<'_> {
     }
 }
 
+/// Stores a `post_file_id` and cached post file `hash`.
+pub struct PostFileHash<'a> {
+    post_file_id: i64,
+    hash: String,
+    config: &'a Config,
+}
+
+impl<'a> PostFileHash<'a> {
+    /// Creates a new [`PostFileHash`]. The hash is domain-separated from [`PostHash`] so
+    /// that post files and posts with the same id don't share a filename.
+    pub fn new(config: &'a Config, post_file_id: i64) -> Self {
+        let key: [u8; KEY_LEN] = std::array::from_fn(|i| config.content_secret.as_bytes().get(i).copied().unwrap_or(0));
+        let hash = blake3::Hasher::new_keyed(&key)
+            .update(b"post_file")
+            .update(&post_file_id.to_le_bytes())
+            .finalize();
+        Self {
+            hash: URL_SAFE_NO_PAD.encode(hash.as_bytes()),
+            post_file_id,
+            config,
+        }
+    }
+
+    /// Returns URL to post file content.
+    pub fn content_url(&self, content_type: MimeType) -> String {
+        const POST_FILES_DIRECTORY: Directory = Directory::PostFiles;
+        format!(
+            "{}/{POST_FILES_DIRECTORY}/{self}.{}",
+            self.config.data_url.trim_end_matches('/'),
+            content_type.extension()
+        )
+    }
+
+    /// Returns URL to post file thumbnail.
+    pub fn thumbnail_url(&self) -> String {
+        const THUMBNAILS_DIRECTORY: Directory = Directory::PostFileThumbnails;
+        format!("{}/{THUMBNAILS_DIRECTORY}/{self}.{THUMBNAIL_EXTENSION}", self.config.data_url.trim_end_matches('/'))
+    }
+
+    /// Returns path to post file content on disk.
+    pub fn content_path(&self, content_type: MimeType) -> PathBuf {
+        let filename = format!("{self}.{}", content_type.extension());
+        self.config.path(Directory::PostFiles).join(filename)
+    }
+
+    /// Returns path to post file thumbnail on disk.
+    pub fn thumbnail_path(&self) -> PathBuf {
+        let filename = format!("{self}.{THUMBNAIL_EXTENSION}");
+        self.config.path(Directory::PostFileThumbnails).join(filename)
+    }
+}
+
+impl Display for PostFileHash<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let outer_bucket = self.post_file_id / 1_000_000;
+        let inner_bucket = (self.post_file_id / 10_000) % 100;
+        write!(f, "{outer_bucket:06}/{inner_bucket:02}/{}_{}", self.post_file_id, self.hash)
+    }
+}
+
 pub type Checksum = GenericChecksum<32>;
 pub type Md5Checksum = GenericChecksum<16>;
 
@@ -188,6 +249,7 @@ pub fn gravatar_url(config: &Config, lowercase_username: &str) -> String {
 ///
 /// BLAKE3 is strongly preferred for duplicate detection. MD5 is vulnerable to collisions
 /// and is only computed for convience for search on other sites.
+#[instrument(skip_all)]
 pub fn compute_checksums(path: &Path) -> std::io::Result<(Checksum, Md5Checksum)> {
     const KB: usize = 1024;
     const MB: usize = 1024 * KB;
@@ -6,8 +6,10 @@ use crate::extract::Ctx;
 use image::DynamicImage;
 use mime::Mime;
 use reqwest::header::{CONTENT_TYPE, HeaderMap, ToStrError};
+use tracing::Span;
 use url::Url;
 
+pub mod auto_tag;
 pub mod cache;
 pub mod decode;
 pub mod download;
@@ -65,13 +67,15 @@ impl Content {
     /// Computes properties for uploaded content.
     pub async fn compute_properties(self, ctx: Ctx) -> ApiResult<CachedProperties> {
         let token = self.save(&ctx).await?;
-        tokio::task::spawn_blocking(move || cache::compute_properties(&ctx, token)).await?
+        let span = Span::current();
+        tokio::task::spawn_blocking(move || span.in_scope(|| cache::compute_properties(&ctx, token))).await?
     }
 
     /// Retrieves content properties from cache or computes them if not present in cache.
     pub async fn remove_or_compute_properties(self, ctx: Ctx) -> ApiResult<CachedProperties> {
         let token = self.save(&ctx).await?;
-        tokio::task::spawn_blocking(move || cache::remove_or_compute_properties(&ctx, token)).await?
+        let span = Span::current();
+        tokio::task::spawn_blocking(move || span.in_scope(|| cache::remove_or_compute_properties(&ctx, token))).await?
     }
 }
 
@@ -5,6 +5,7 @@ use crate::math::rect::{Array2D, IRect};
 use image::{DynamicImage, GrayImage};
 use num_traits::ToPrimitive;
 use std::num::NonZeroU64;
+use tracing::instrument;
 
 pub const NUM_WORDS: usize = 100; // Number indexes to create from signature
 pub const COMPRESSED_SIGNATURE_LEN: usize = SIGNATURE_LEN.div_ceil(SIGNATURE_DIGITS);
@@ -29,6 +30,7 @@ impl SignatureCache {
 /// Calculates a compact "signature" for an image that can be used for similarity search.
 ///
 /// Implementation follows H. Chi Wong, Marshall Bern and David Goldberg with a few tweaks
+#[instrument(skip_all)]
 pub fn compute(image: &DynamicImage) -> [i64; COMPRESSED_SIGNATURE_LEN] {
     let gray_image = image.to_luma8();
     let (grid_points, grid_square_radius) = compute_grid_points(&gray_image);
@@ -1,5 +1,6 @@
 use crate::config::Config;
 use image::{DynamicImage, GenericImageView, RgbImage};
+use tracing::instrument;
 
 #[derive(Clone, Copy)]
 pub enum ThumbnailType {
@@ -16,6 +17,7 @@ pub enum ThumbnailCategory {
 /// Returns a thumbnail of the given `image`. Resizes thumbnail so that it is a small as possible
 /// while containing an area with dimensions `X_width` by `X_height`, where "X" refers to the thumbnail type.
 /// The values of `X_width` and `X_height` are read from the config.toml.
+#[instrument(skip_all)]
 pub fn create(config: &Config, image: DynamicImage, thumbnail_type: ThumbnailType) -> DynamicImage {
     // JPEG doesn't support transparency, so composite any transparent pixels
     // onto a solid background to avoid corruption in the final thumbnail.
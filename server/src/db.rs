@@ -6,7 +6,7 @@ use crate::content::signature::SIGNATURE_VERSION;
 use crate::schema::database_statistics;
 #[cfg(test)]
 use crate::string::SecretString;
-use crate::{admin, app, config};
+use crate::{admin, app, config, metrics};
 use diesel::migration::Migration;
 use diesel::pg::Pg;
 use diesel::r2d2::{ConnectionManager, CustomizeConnection, Pool, PoolError, PooledConnection};
@@ -18,9 +18,9 @@ use std::error::Error;
 use std::num::ParseIntError;
 use std::ops::RangeInclusive;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::{OwnedSemaphorePermit, Semaphore};
-use tracing::{error, info};
+use tracing::{Instrument, error, info, info_span};
 
 pub type Connection = PooledConnection<ConnectionManager<PgConnection>>;
 pub type AsyncConnectionResult = Result<AsyncConnection, PoolError>;
@@ -60,23 +60,28 @@ impl AsyncConnectionPool {
         F: FnOnce(&mut Connection) -> Result<T, E> + Send + 'static,
         ApiError: From<E>,
     {
-        let permit = self.acquire_permit().await;
-        let mut conn = self.pool.get()?;
+        let span = info_span!("transaction");
+        let permit = self.acquire_permit().instrument(span.clone()).await;
+        let mut conn = span.in_scope(|| self.pool.get())?;
         tokio::task::spawn_blocking(move || {
             // Permit is moved inside blocking task so it is held even if future is dropped
             let _permit = permit;
-            conn.transaction(f)
+            span.in_scope(|| conn.transaction(f))
         })
         .await?
         .map_err(ApiError::from)
     }
 
     async fn acquire_permit(&self) -> OwnedSemaphorePermit {
-        self.semaphore
+        let start_time = Instant::now();
+        let permit = self
+            .semaphore
             .clone()
             .acquire_owned()
             .await
-            .expect(SEMAPHORE_PANIC_MESSAGE)
+            .expect(SEMAPHORE_PANIC_MESSAGE);
+        metrics::CONNECTION_WAIT_DURATION.observe_duration([], start_time.elapsed());
+        permit
     }
 }
 
@@ -0,0 +1,253 @@
+//! Email digests summarize activity a user has subscribed to: new posts with watched tags
+//! or matching saved searches, replies to the user's comments, and moderation outcomes of
+//! the user's uploads. Digests are batched by a background task and sent at most once per
+//! period of the frequency the user has chosen.
+
+use crate::api::error::{ApiError, ApiResult};
+use crate::app::AppState;
+use crate::auth::Client;
+use crate::content::hash;
+use crate::extract::Ctx;
+use crate::model::digest::DigestSubscription;
+use crate::model::enums::{DigestFrequency, NotificationKind, PostStatus, UserRank};
+use crate::schema::{digest_subscription, notification, post, post_tag, saved_search, user, watched_tag};
+use crate::search::Builder;
+use crate::search::post::QueryBuilder;
+use crate::string::SmallString;
+use crate::time::DateTime;
+use crate::{email, markdown};
+use diesel::dsl::{exists, max, not};
+use diesel::{
+    ExpressionMethods, JoinOnDsl, NullableExpressionMethods, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper,
+};
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::{Address, Message};
+use percent_encoding::NON_ALPHANUMERIC;
+use std::fmt::Write;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::error;
+
+/// How often the background task checks for digests that are due.
+pub const CHECK_INTERVAL: Duration = Duration::from_hours(1);
+
+/// Maximum number of posts listed in a single section of a digest.
+const MAX_SECTION_POSTS: i64 = 25;
+
+/// Computes the token that allows the owner of an account to unsubscribe from digests without
+/// logging in. Like password reset tokens, it is derived from the user's password salt, so it
+/// is invalidated whenever the password changes.
+pub fn unsubscribe_token(password_salt: &str) -> String {
+    hash::compute_url_safe_hash(format!("digest-unsubscribe:{password_salt}").as_bytes())
+}
+
+/// Sends a digest to every subscribed user whose previous digest is at least one period old.
+/// Failing to send one digest doesn't prevent the others from being sent.
+pub async fn send_due_digests(state: &AppState) -> ApiResult<()> {
+    let now = DateTime::now();
+    let subscriptions: Vec<DigestSubscription> = state
+        .connection_pool
+        .transaction(|conn| {
+            digest_subscription::table
+                .select(DigestSubscription::as_select())
+                .filter(digest_subscription::frequency.ne(DigestFrequency::Never))
+                .load(conn)
+        })
+        .await?;
+
+    // Tolerate some jitter so that digests don't drift later by up to an interval each period
+    let is_due = |subscription: &DigestSubscription| {
+        subscription
+            .frequency
+            .period()
+            .is_some_and(|period| *subscription.last_sent_time + period <= *now + CHECK_INTERVAL / 2)
+    };
+    for subscription in subscriptions.into_iter().filter(is_due) {
+        let user_id = subscription.user_id;
+        let digest_state = state.clone();
+        let result = state
+            .connection_pool
+            .transaction(move |conn| send_digest(conn, &digest_state, &subscription, now))
+            .await;
+        if let Err(err) = result {
+            error!("Failed to send email digest to user {user_id}. Details:\n{err}");
+        }
+    }
+    Ok(())
+}
+
+/// A titled list of HTML entries in a digest.
+struct Section {
+    title: String,
+    entries: Vec<String>,
+}
+
+/// Collects and sends the digest of the user with given `subscription`. Digests with no
+/// entries aren't sent. If sending fails, the transaction is rolled back so that the
+/// same activity is included in the next attempt.
+fn send_digest(
+    conn: &mut PgConnection,
+    state: &AppState,
+    subscription: &DigestSubscription,
+    now: DateTime,
+) -> ApiResult<()> {
+    let user_id = subscription.user_id;
+    let (username, user_email, rank, password_salt): (SmallString, Option<SmallString>, UserRank, String) = user::table
+        .find(user_id)
+        .select((user::name, user::email, user::rank, user::password_salt))
+        .first(conn)?;
+    let latest_post_id = post::table
+        .select(max(post::id))
+        .first::<Option<i64>>(conn)?
+        .unwrap_or(subscription.last_post_id);
+
+    let domain = email::site_url(state);
+    let post_link = |post_id: i64| format!("<a href=\"{domain}/post/{post_id}\">post #{post_id}</a>");
+    let mut sections = Vec::new();
+
+    // Posts are searched for with the privileges and preferences of the subscriber
+    let Ctx(ctx, _) = state.clone().make_context(Client::new(Some(user_id), rank));
+    let mut watched_tag_posts = post::table
+        .select(post::id)
+        .filter(post::id.gt(subscription.last_post_id))
+        .filter(post::id.le(latest_post_id))
+        .filter(post::deletion_time.is_null())
+        .filter(post::status.eq(PostStatus::Approved))
+        .filter(
+            post::id.eq_any(
+                post_tag::table.select(post_tag::post_id).filter(
+                    post_tag::tag_id.eq_any(
+                        watched_tag::table
+                            .select(watched_tag::tag_id)
+                            .filter(watched_tag::user_id.eq(user_id)),
+                    ),
+                ),
+            ),
+        )
+        .into_boxed();
+    if let Some(hidden_posts) = ctx.preferences().hidden_posts(post::id) {
+        watched_tag_posts = watched_tag_posts.filter(not(exists(hidden_posts)));
+    }
+    let watched_tag_posts: Vec<i64> = watched_tag_posts
+        .order(post::id.desc())
+        .limit(MAX_SECTION_POSTS)
+        .load(conn)?;
+    if !watched_tag_posts.is_empty() {
+        sections.push(Section {
+            title: String::from("New posts with watched tags"),
+            entries: watched_tag_posts.into_iter().map(post_link).collect(),
+        });
+    }
+
+    let saved_searches: Vec<SmallString> = saved_search::table
+        .select(saved_search::query)
+        .filter(saved_search::user_id.eq(user_id))
+        .order(saved_search::query)
+        .load(conn)?;
+    for query in saved_searches {
+        let matching_posts: Vec<i64> = QueryBuilder::new(&ctx, &query)?
+            .build_filtered(conn)?
+            .filter(post::id.gt(subscription.last_post_id))
+            .filter(post::id.le(latest_post_id))
+            .order(post::id.desc())
+            .limit(MAX_SECTION_POSTS)
+            .load(conn)?;
+        if !matching_posts.is_empty() {
+            sections.push(Section {
+                title: format!("New posts matching <code>{}</code>", markdown::escape(&query)),
+                entries: matching_posts.into_iter().map(post_link).collect(),
+            });
+        }
+    }
+
+    let events: Vec<(NotificationKind, Option<SmallString>, Option<i64>)> = notification::table
+        .left_join(user::table.on(notification::actor_id.eq(user::id.nullable())))
+        .select((notification::kind, user::name.nullable(), notification::post_id))
+        .filter(notification::user_id.eq(user_id))
+        .filter(notification::kind.eq_any([
+            NotificationKind::Reply,
+            NotificationKind::PostApproved,
+            NotificationKind::PostRejected,
+        ]))
+        .filter(notification::creation_time.gt(subscription.last_sent_time))
+        .filter(notification::creation_time.le(now))
+        .order(notification::id)
+        .load(conn)?;
+    let replies: Vec<_> = events
+        .iter()
+        .filter(|(kind, ..)| *kind == NotificationKind::Reply)
+        .filter_map(|(_, actor, post_id)| {
+            let actor = actor
+                .as_deref()
+                .map_or_else(|| String::from("Someone"), markdown::escape);
+            post_id.map(|post_id| format!("{actor} replied to your comment on {}", post_link(post_id)))
+        })
+        .collect();
+    if !replies.is_empty() {
+        sections.push(Section {
+            title: String::from("Replies to your comments"),
+            entries: replies,
+        });
+    }
+    let moderation_outcomes: Vec<_> = events
+        .iter()
+        .filter_map(|&(kind, _, post_id)| match kind {
+            NotificationKind::PostApproved => post_id.map(|id| format!("Your upload {} was approved", post_link(id))),
+            NotificationKind::PostRejected => post_id.map(|id| format!("Your upload {} was rejected", post_link(id))),
+            _ => None,
+        })
+        .collect();
+    if !moderation_outcomes.is_empty() {
+        sections.push(Section {
+            title: String::from("Moderation of your uploads"),
+            entries: moderation_outcomes,
+        });
+    }
+
+    diesel::update(digest_subscription::table.find(user_id))
+        .set((digest_subscription::last_post_id.eq(latest_post_id), digest_subscription::last_sent_time.eq(now)))
+        .execute(conn)?;
+
+    let Some(user_email) = user_email.filter(|_| !sections.is_empty()) else {
+        return Ok(());
+    };
+    let smtp_info = state.config.smtp().ok_or(ApiError::MissingSmtpInfo)?;
+    let user_mailbox = Mailbox::new(None, Address::from_str(&user_email)?);
+
+    let site_name = &state.config.public_info.name;
+    let escaped_username = markdown::escape(&username);
+    let separator = percent_encoding::percent_encode_byte(b':');
+    let unsubscribe_token = unsubscribe_token(&password_salt);
+    let username = percent_encoding::utf8_percent_encode(&username, NON_ALPHANUMERIC);
+    let unsubscribe_url = format!("{domain}/digest-unsubscribe/{username}{separator}{unsubscribe_token}");
+
+    let mut body = format!(
+        "<html>
+            <body>
+                <p>Hello {escaped_username},</p>
+                <p>Here is what happened on {site_name} since your last digest.</p>"
+    );
+    for section in sections {
+        let _ = write!(body, "<h3>{}</h3><ul>", section.title);
+        for entry in section.entries {
+            let _ = write!(body, "<li>{entry}</li>");
+        }
+        body.push_str("</ul>");
+    }
+    let _ = write!(
+        body,
+        "<p>You are receiving this email because you subscribed to email digests on {site_name}.<br>
+                To unsubscribe, click this link: <a href=\"{unsubscribe_url}\">{unsubscribe_url}</a></p>
+            </body>
+        </html>"
+    );
+
+    let digest_email = Message::builder()
+        .from(smtp_info.from.clone())
+        .to(user_mailbox)
+        .subject(format!("Your {site_name} digest"))
+        .header(ContentType::TEXT_HTML)
+        .body(body)?;
+    email::send(smtp_info, &digest_email)
+}
@@ -0,0 +1,43 @@
+//! Emails are sent through the SMTP relay described by the `smtp` section of the config.
+
+pub mod digest;
+pub mod verification;
+
+use crate::api::error::ApiResult;
+use crate::app::AppState;
+use crate::config::SmtpConfig;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+
+/// Returns the URL of the site, which is used as the base of links sent in emails.
+pub fn site_url(state: &AppState) -> String {
+    let domain = if let Some(domain) = state.config.domain.as_deref() {
+        domain
+    } else if let Some(domain) = state.env.http_origin.as_deref() {
+        domain
+    } else if let Some(domain) = state.env.http_referer.as_deref() {
+        domain
+    } else if let Some(port) = state.env.domain_port {
+        return format!("http://localhost:{port}");
+    } else {
+        ""
+    };
+    domain.trim_end_matches('/').to_owned()
+}
+
+/// Sends `email` through the SMTP relay. Blocks until the relay has accepted the email.
+pub fn send(smtp_info: &SmtpConfig, email: &Message) -> ApiResult<()> {
+    // Open a remote connection to SMTP relay
+    let mut smtp_builder = SmtpTransport::relay(smtp_info.host.read())?;
+    if let (Some(smtp_username), Some(smtp_password)) = (smtp_info.username.as_ref(), smtp_info.password.as_ref()) {
+        let credentials = Credentials::new(smtp_username.read().to_owned(), smtp_password.read().to_owned());
+        smtp_builder = smtp_builder.credentials(credentials);
+    }
+    if let Some(port) = smtp_info.port {
+        smtp_builder = smtp_builder.port(port);
+    }
+    let mailer = smtp_builder.build();
+
+    mailer.send(email)?;
+    Ok(())
+}
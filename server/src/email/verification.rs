@@ -0,0 +1,49 @@
+//! When registering in `email_verification` mode, accounts stay restricted until the owner
+//! proves they can read the email address they registered with.
+
+use crate::api::error::{ApiError, ApiResult};
+use crate::app::AppState;
+use crate::content::hash;
+use crate::email;
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::{Address, Message};
+use percent_encoding::NON_ALPHANUMERIC;
+use std::str::FromStr;
+
+/// Computes the token that verifies the owner of an account can read `email`. Like password
+/// reset tokens, it is derived from the user's password salt, so it can't be guessed.
+pub fn token(email: &str, password_salt: &str) -> String {
+    hash::compute_url_safe_hash(format!("email-verification:{email}:{password_salt}").as_bytes())
+}
+
+/// Sends an email containing a verification link to `user_email`.
+pub fn send(state: &AppState, username: &str, user_email: &str, password_salt: &str) -> ApiResult<()> {
+    let smtp_info = state.config.smtp().ok_or(ApiError::MissingSmtpInfo)?;
+    let user_mailbox = Mailbox::new(None, Address::from_str(user_email)?);
+
+    let domain = email::site_url(state);
+    let site_name = &state.config.public_info.name;
+    let encoded_username = percent_encoding::utf8_percent_encode(username, NON_ALPHANUMERIC);
+    let separator = percent_encoding::percent_encode_byte(b':');
+    let verification_token = token(user_email, password_salt);
+    let url = format!("{domain}/email-verification/{encoded_username}{separator}{verification_token}");
+
+    let verification_email = Message::builder()
+        .from(smtp_info.from.clone())
+        .to(user_mailbox)
+        .subject(format!("Verify your email address for {site_name}"))
+        .header(ContentType::TEXT_HTML)
+        .body(format!(
+            "<html>
+                <body>
+                    <p>Hello {username},</p>
+                    <p>Thanks for registering on {site_name}.<br>
+                    To activate your account, click this link: <a href=\"{url}\">{url}</a></p>
+                    <p>If you didn't register, please ignore this email.</p>
+                </body>
+            </html>"
+        ))?;
+
+    email::send(smtp_info, &verification_email)
+}
@@ -17,6 +17,7 @@ pub enum ErrorName {
     ConnectionAborted,
     ConnectionRefused,
     ConnectionReset,
+    ContentInTrash,
     ContentTooLarge,
     CrossesDevices,
     CryptoError,
@@ -73,6 +74,7 @@ pub enum ErrorName {
     InvalidByte,
     InvalidCharacter,
     InvalidConnectionUrl,
+    InvalidCsrfToken,
     InvalidCString,
     InvalidData,
     InvalidDigit,
@@ -81,17 +83,25 @@ pub enum ErrorName {
     InvalidFormat,
     InvalidHeader,
     InvalidInput,
+    InvalidInviteCode,
     InvalidLastSymbol,
     InvalidLength,
     InvalidMime,
+    InvalidOidcResponse,
+    InvalidOidcState,
     InvalidPadding,
+    InvalidParentComment,
     InvalidPassword,
     InvalidPhcStringField,
     InvalidSort,
+    InvalidTextLength,
+    InvalidTotpCode,
     InvalidUploadToken,
     InvalidUserRank,
     InvalidUtf8InPathParam,
     InvalidVersion,
+    InvalidWikiPageTarget,
+    InviteCodeNotFound,
     IsADirectory,
     JsonDataError,
     JsonInvalidData,
@@ -107,8 +117,10 @@ pub enum ErrorName {
     MissingFormData,
     MissingJsonContentType,
     MissingMetadata,
+    MissingOidcInfo,
     MissingPathParams,
     MissingSmtpInfo,
+    MissingTotpCode,
     MultipartError,
     NegativeOverflow,
     NetworkDown,
@@ -120,8 +132,10 @@ pub enum ErrorName {
     NotConnected,
     NotInTransaction,
     NotLoggedIn,
+    NotificationNotFound,
     NotNullViolation,
     NotSeekable,
+    OpenReportAlreadyExists,
     OtherIoError,
     OtherPathError,
     OutOfMemory,
@@ -129,6 +143,7 @@ pub enum ErrorName {
     ParamNameDuplicated,
     ParamNameInvalid,
     ParamsMaxExceeded,
+    PastExpirationTime,
     PathDeserializeError,
     PathParseError,
     PathParseErrorAtIndex,
@@ -142,19 +157,23 @@ pub enum ErrorName {
     PoolPostAlreadyExists,
     PositiveOverflow,
     PostAlreadyFeatured,
+    PostFileNotFound,
     PostNotFound,
     PostRelationAlreadyExists,
     QueryBuilderError,
     QuotaExceeded,
     ReadOnlyFilesystem,
     ReadOnlyTransaction,
+    ReportNotFound,
     RequestError,
     ResourceBusy,
     ResourceHidden,
     ResourceModified,
+    RevisionNotFound,
     RollbackTransaction,
     RowNotFound,
     SelfMerge,
+    SelfSanction,
     SerializationError,
     SerializationFailure,
     StaleNetworkFileHandle,
@@ -171,10 +190,16 @@ pub enum ErrorName {
     TaskCancelled,
     TaskPanicked,
     TimedOut,
+    TooManyActivityStreams,
     TooManyArgs,
     TooManyLinks,
+    TotpAlreadyEnabled,
+    TotpLockedOut,
+    TotpNotEnabled,
     UnableToSendCommand,
+    UnauthorizedEmailVerification,
     UnauthorizedPasswordReset,
+    UnauthorizedUnsubscribe,
     UnexpectedEof,
     UnexpectedOutputSize,
     UniqueViolation,
@@ -194,6 +219,8 @@ pub enum ErrorName {
     UnknownPathRejectionError,
     UnknownQueryError,
     UnknownQueryRejectionError,
+    UnreplayableWebhookDelivery,
+    UnreportableResource,
     Unsupported,
     UnsupportedAlgorithm,
     UnsupportedContentType,
@@ -204,15 +231,24 @@ pub enum ErrorName {
     UnsupportedImageDimensions,
     UnsupportedPathType,
     UrlValidationError,
+    UserBanned,
     UserEmailAlreadyExists,
+    UserIdentityAlreadyExists,
     UserNameAlreadyExists,
     UsernamePasswordMismatch,
     UsernameTokenMismatch,
+    UserNoteNotFound,
     UserNotFound,
+    UserSanctionNotFound,
+    UserSessionNotFound,
     UserTokenNotFound,
     Utf8ConversionError,
     ValueTooLong,
     ValueTooShort,
+    WebhookDeliveryNotFound,
+    WikiPageNotFound,
+    WikiPageTagAlreadyExists,
+    WikiPageTitleAlreadyExists,
     WouldBlock,
     WriteZero,
     WrongNumberOfPathParameters,
@@ -335,14 +371,19 @@ impl ErrorKind for base64::DecodeError {
 impl ErrorKind for crate::auth::header::AuthenticationError {
     fn kind(&self) -> ErrorName {
         match self {
+            Self::Banned => ErrorName::UserBanned,
             Self::DisabledToken => ErrorName::DisabledToken,
             Self::ExpiredToken => ErrorName::ExpiredToken,
             Self::FailedConnection(_) => ErrorName::FailedConnection,
             Self::FailedQuery(err) => err.kind(),
             Self::InvalidAuthType => ErrorName::InvalidAuthType,
+            Self::InvalidCsrfToken => ErrorName::InvalidCsrfToken,
             Self::InvalidEncoding(err) => err.kind(),
+            Self::InvalidTotpCode => ErrorName::InvalidTotpCode,
             Self::MalformedCredentials => ErrorName::MalformedCredentials,
             Self::MalformedToken(_) => ErrorName::MalformedToken,
+            Self::MissingTotpCode => ErrorName::MissingTotpCode,
+            Self::TotpLockedOut => ErrorName::TotpLockedOut,
             Self::UsernamePasswordMismatch => ErrorName::UsernamePasswordMismatch,
             Self::UsernameTokenMismatch => ErrorName::UsernameTokenMismatch,
             Self::Utf8Conversion(_) => ErrorName::Utf8ConversionError,
@@ -353,6 +394,7 @@ impl ErrorKind for crate::auth::header::AuthenticationError {
 impl ErrorKind for crate::model::enums::ResourceProperty {
     fn kind(&self) -> ErrorName {
         match self {
+            Self::OpenReport => ErrorName::OpenReportAlreadyExists,
             Self::PoolName => ErrorName::PoolNameAlreadyExists,
             Self::PoolPost => ErrorName::PoolPostAlreadyExists,
             Self::PoolCategoryName => ErrorName::PoolCategoryNameAlreadyExists,
@@ -363,6 +405,9 @@ impl ErrorKind for crate::model::enums::ResourceProperty {
             Self::TagCategoryName => ErrorName::TagCategoryNameAlreadyExists,
             Self::UserName => ErrorName::UserNameAlreadyExists,
             Self::UserEmail => ErrorName::UserEmailAlreadyExists,
+            Self::UserIdentity => ErrorName::UserIdentityAlreadyExists,
+            Self::WikiPageTag => ErrorName::WikiPageTagAlreadyExists,
+            Self::WikiPageTitle => ErrorName::WikiPageTitleAlreadyExists,
         }
     }
 }
@@ -378,6 +423,15 @@ impl ErrorKind for crate::model::enums::ResourceType {
             Self::TagCategory => ErrorName::TagCategoryNotFound,
             Self::User => ErrorName::UserNotFound,
             Self::UserToken => ErrorName::UserTokenNotFound,
+            Self::Report => ErrorName::ReportNotFound,
+            Self::UserSanction => ErrorName::UserSanctionNotFound,
+            Self::UserNote => ErrorName::UserNoteNotFound,
+            Self::PostFile => ErrorName::PostFileNotFound,
+            Self::WikiPage => ErrorName::WikiPageNotFound,
+            Self::Notification => ErrorName::NotificationNotFound,
+            Self::UserSession => ErrorName::UserSessionNotFound,
+            Self::InviteCode => ErrorName::InviteCodeNotFound,
+            Self::WebhookDelivery => ErrorName::WebhookDeliveryNotFound,
         }
     }
 }
@@ -612,6 +666,7 @@ impl ErrorKind for crate::api::error::ApiError {
     fn kind(&self) -> ErrorName {
         match self {
             Self::AlreadyExists(err) => err.kind(),
+            Self::ContentInTrash(_) => ErrorName::ContentInTrash,
             Self::ContentTooLarge => ErrorName::ContentTooLarge,
             Self::CyclicDependency(_) => ErrorName::CyclicDependency,
             Self::DeleteDefault(_) => ErrorName::DeleteDefault,
@@ -632,11 +687,17 @@ impl ErrorKind for crate::api::error::ApiError {
             Self::InvalidEmailAddress(err) => err.kind(),
             Self::InvalidEmail(err) => err.kind(),
             Self::InvalidHeader(_) => ErrorName::InvalidHeader,
+            Self::InvalidInviteCode => ErrorName::InvalidInviteCode,
             Self::InvalidMime(_) => ErrorName::InvalidMime,
+            Self::InvalidOidcResponse(_) => ErrorName::InvalidOidcResponse,
+            Self::InvalidOidcState => ErrorName::InvalidOidcState,
+            Self::InvalidParentComment => ErrorName::InvalidParentComment,
             Self::InvalidSort => ErrorName::InvalidSort,
+            Self::InvalidTextLength(..) => ErrorName::InvalidTextLength,
             Self::InvalidTime(err) => err.kind(),
             Self::InvalidUploadToken => ErrorName::InvalidUploadToken,
             Self::InvalidUserRank => ErrorName::InvalidUserRank,
+            Self::InvalidWikiPageTarget => ErrorName::InvalidWikiPageTarget,
             Self::Image(err) => err.kind(),
             Self::JsonRejection(err) => err.kind(),
             Self::JsonSerialization(err) => err.classify().kind(),
@@ -645,6 +706,7 @@ impl ErrorKind for crate::api::error::ApiError {
             Self::MissingContentType => ErrorName::MissingContentType,
             Self::MissingFormData => ErrorName::MissingFormData,
             Self::MissingMetadata => ErrorName::MissingMetadata,
+            Self::MissingOidcInfo => ErrorName::MissingOidcInfo,
             Self::MissingSmtpInfo => ErrorName::MissingSmtpInfo,
             Self::Multipart(_) => ErrorName::MultipartError,
             Self::MultipartRejection(err) => err.kind(),
@@ -653,15 +715,25 @@ impl ErrorKind for crate::api::error::ApiError {
             Self::NotFound(err) => err.kind(),
             Self::NotLoggedIn => ErrorName::NotLoggedIn,
             Self::Password(err) => err.kind(),
+            Self::PastExpirationTime => ErrorName::PastExpirationTime,
             Self::PathRejection(err) => err.kind(),
             Self::QueryRejection(err) => err.kind(),
             Self::Request(_) => ErrorName::RequestError,
             Self::ResourceModified => ErrorName::ResourceModified,
+            Self::RevisionNotFound(_) => ErrorName::RevisionNotFound,
             Self::SelfMerge(_) => ErrorName::SelfMerge,
+            Self::SelfSanction => ErrorName::SelfSanction,
             Self::StdIo(err) => err.kind().kind(),
             Self::SwfDecoding(err) => err.kind(),
             Self::TaskJoin(err) => err.kind(),
+            Self::TooManyActivityStreams => ErrorName::TooManyActivityStreams,
+            Self::TotpAlreadyEnabled => ErrorName::TotpAlreadyEnabled,
+            Self::TotpNotEnabled => ErrorName::TotpNotEnabled,
+            Self::UnauthorizedEmailVerification => ErrorName::UnauthorizedEmailVerification,
             Self::UnauthorizedPasswordReset => ErrorName::UnauthorizedPasswordReset,
+            Self::UnauthorizedUnsubscribe => ErrorName::UnauthorizedUnsubscribe,
+            Self::UnreplayableWebhookDelivery => ErrorName::UnreplayableWebhookDelivery,
+            Self::UnreportableResource(_) => ErrorName::UnreportableResource,
             Self::UnsupportedContentType(_) => ErrorName::UnsupportedContentType,
             Self::UnsupportedExtension(_) => ErrorName::UnsupportedExtension,
             Self::UrlValidation(_) => ErrorName::UrlValidationError,
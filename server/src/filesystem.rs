@@ -1,7 +1,7 @@
 use crate::api::error::{ApiError, ApiResult};
 use crate::config::Config;
 use crate::content::decode;
-use crate::content::hash::PostHash;
+use crate::content::hash::{PostFileHash, PostHash};
 use crate::content::thumbnail::ThumbnailCategory;
 use crate::content::upload::UploadToken;
 use crate::model::enums::MimeType;
@@ -34,6 +34,8 @@ pub enum Directory {
     GeneratedThumbnails,
     CustomThumbnails,
     TemporaryUploads,
+    PostFiles,
+    PostFileThumbnails,
 }
 
 /// Returns the size of the file at `path` in bytes as an i64
@@ -151,6 +153,22 @@ pub fn delete_post(post: &PostHash, mime_type: MimeType) -> std::io::Result<()>
     delete_content(post, mime_type)
 }
 
+/// Saves `post_file` `thumbnail` to disk. Returns size of the thumbnail in bytes.
+pub fn save_post_file_thumbnail(post_file: &PostFileHash, thumbnail: DynamicImage) -> ImageResult<i64> {
+    let thumbnail_path = post_file.thumbnail_path();
+    remove_if_exists(&thumbnail_path)?;
+    std::fs::create_dir_all(thumbnail_path.parent().unwrap_or(Path::new("")))?;
+
+    thumbnail.into_rgb8().save(&thumbnail_path)?;
+    file_size(&thumbnail_path).map_err(ImageError::from)
+}
+
+/// Deletes `post_file` thumbnail and content from disk.
+pub fn delete_post_file(post_file: &PostFileHash, mime_type: MimeType) -> std::io::Result<()> {
+    remove_if_exists(&post_file.thumbnail_path())?;
+    remove_if_exists(&post_file.content_path(mime_type))
+}
+
 /// Renames the contents and thumbnails of two posts as if they had swapped ids.
 pub fn swap_posts(
     post_a: &PostHash,
@@ -182,6 +200,26 @@ pub fn swap_posts(
     }
 }
 
+/// Renames the content and generated thumbnail of `post` and the content and thumbnail of `post_file`
+/// as if the two had swapped contents. Custom thumbnails belong to the post and are left in place.
+pub fn swap_post_content(
+    post: &PostHash,
+    post_mime_type: MimeType,
+    post_file: &PostFileHash,
+    file_mime_type: MimeType,
+) -> std::io::Result<()> {
+    swap_files(&post.generated_thumbnail_path(), &post_file.thumbnail_path())?;
+
+    let post_content_path = post.content_path(post_mime_type);
+    let file_content_path = post_file.content_path(file_mime_type);
+    if post_mime_type == file_mime_type {
+        swap_files(&post_content_path, &file_content_path)
+    } else {
+        move_file(&post_content_path, &post_file.content_path(post_mime_type))?;
+        move_file(&file_content_path, &post.content_path(file_mime_type))
+    }
+}
+
 /// Moves file from `from` to `to`.
 /// Tries simply renaming first and falls back to copy/remove if `from` and `to`
 /// are on different file systems.
@@ -1,25 +1,33 @@
+mod activity;
 mod admin;
 mod api;
 mod app;
+mod audit;
 mod auth;
 mod config;
 mod content;
 mod db;
+mod email;
 mod error;
 mod extract;
 mod filesystem;
+mod markdown;
 mod math;
+mod metrics;
 mod model;
+mod notification;
 mod resource;
 mod schema;
 mod search;
 mod snapshot;
 mod string;
+mod telemetry;
 #[cfg(test)]
 mod test;
 mod time;
 mod unit;
 mod update;
+mod webhook;
 
 /// Avoid musl's default allocator due to lackluster performance
 /// https://nickb.dev/blog/default-musl-allocator-considered-harmful-to-performance
@@ -50,4 +58,5 @@ async fn main() {
     app::run(state)
         .await
         .unwrap_or_else(|err| app::shutdown("Failed to start server", err));
+    telemetry::shutdown();
 }
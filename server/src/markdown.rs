@@ -0,0 +1,295 @@
+//! A renderer for the safe subset of Markdown allowed in comments.
+//!
+//! Supported block elements are paragraphs, fenced code blocks, block quotes, and
+//! unordered lists. Supported inline elements are code spans, strong emphasis (`**`),
+//! emphasis (`*`), strikethrough (`~~`), links, and user mentions (`@name`).
+//! Raw HTML is never passed through; all text is escaped.
+
+/// Maximum nesting depth of block quotes. Deeper quote markers are rendered as text.
+const MAX_QUOTE_DEPTH: usize = 8;
+
+/// Returns the names of all users mentioned in `text`, in order of appearance.
+/// A mention is an `@` followed by a username that doesn't immediately follow
+/// an alphanumeric character, so that email addresses aren't treated as mentions.
+pub fn mentions(text: &str) -> impl Iterator<Item = &str> {
+    text.match_indices('@').filter_map(|(index, _)| mention_at(text, index))
+}
+
+/// Renders `text` as sanitized HTML. Mentions of users for which `is_user` returns
+/// `true` are rendered as links to the user's profile.
+pub fn render(text: &str, is_user: &dyn Fn(&str) -> bool) -> String {
+    let mut html = String::with_capacity(text.len());
+    render_blocks(&mut html, text, is_user, 0);
+    html
+}
+
+/// Escapes `text` so that it can be safely embedded in HTML.
+pub fn escape(text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+    escape_into(&mut html, text);
+    html
+}
+
+/// Returns the username mentioned by the `@` at `index` of `text`, if any.
+fn mention_at(text: &str, index: usize) -> Option<&str> {
+    if text[..index].chars().next_back().is_some_and(char::is_alphanumeric) {
+        return None;
+    }
+    let name_start = index + 1;
+    let name_length = text[name_start..]
+        .find(|c: char| !is_username_char(c))
+        .unwrap_or(text.len() - name_start);
+    (name_length > 0).then(|| &text[name_start..name_start + name_length])
+}
+
+fn is_username_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+#[derive(PartialEq, Eq)]
+enum Block {
+    None,
+    Paragraph,
+    List,
+}
+
+fn render_blocks(html: &mut String, text: &str, is_user: &dyn Fn(&str) -> bool, quote_depth: usize) {
+    let mut lines = text.lines().peekable();
+    let mut current = Block::None;
+    let close = |html: &mut String, current: &mut Block| {
+        match current {
+            Block::None => (),
+            Block::Paragraph => html.push_str("</p>"),
+            Block::List => html.push_str("</ul>"),
+        }
+        *current = Block::None;
+    };
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            close(html, &mut current);
+            html.push_str("<pre><code>");
+            let mut first = true;
+            for code_line in lines.by_ref() {
+                if code_line.trim_start().starts_with("```") {
+                    break;
+                }
+                if !first {
+                    html.push('\n');
+                }
+                escape_into(html, code_line);
+                first = false;
+            }
+            html.push_str("</code></pre>");
+        } else if trimmed.starts_with('>') && quote_depth < MAX_QUOTE_DEPTH {
+            close(html, &mut current);
+            let mut quote = String::from(strip_quote(trimmed));
+            while let Some(next) = lines.next_if(|next| next.trim_start().starts_with('>')) {
+                quote.push('\n');
+                quote.push_str(strip_quote(next.trim_start()));
+            }
+            html.push_str("<blockquote>");
+            render_blocks(html, &quote, is_user, quote_depth + 1);
+            html.push_str("</blockquote>");
+        } else if let Some(item) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) {
+            if current != Block::List {
+                close(html, &mut current);
+                html.push_str("<ul>");
+                current = Block::List;
+            }
+            html.push_str("<li>");
+            render_inline(html, item.trim(), is_user);
+            html.push_str("</li>");
+        } else if trimmed.is_empty() {
+            close(html, &mut current);
+        } else {
+            match current {
+                Block::Paragraph => html.push_str("<br>"),
+                Block::List | Block::None => {
+                    close(html, &mut current);
+                    html.push_str("<p>");
+                    current = Block::Paragraph;
+                }
+            }
+            render_inline(html, line.trim(), is_user);
+        }
+    }
+    close(html, &mut current);
+}
+
+fn strip_quote(line: &str) -> &str {
+    let content = &line[1..];
+    content.strip_prefix(' ').unwrap_or(content)
+}
+
+fn render_inline(html: &mut String, text: &str, is_user: &dyn Fn(&str) -> bool) {
+    let mut index = 0;
+    while index < text.len() {
+        let rest = &text[index..];
+        if let Some((consumed, code)) = delimited(rest, "`") {
+            html.push_str("<code>");
+            escape_into(html, code);
+            html.push_str("</code>");
+            index += consumed;
+        } else if let Some((consumed, content)) = delimited(rest, "**") {
+            html.push_str("<strong>");
+            render_inline(html, content, is_user);
+            html.push_str("</strong>");
+            index += consumed;
+        } else if let Some((consumed, content)) = delimited(rest, "~~") {
+            html.push_str("<del>");
+            render_inline(html, content, is_user);
+            html.push_str("</del>");
+            index += consumed;
+        } else if let Some((consumed, content)) = delimited(rest, "*") {
+            html.push_str("<em>");
+            render_inline(html, content, is_user);
+            html.push_str("</em>");
+            index += consumed;
+        } else if let Some((consumed, label, url)) = link(rest) {
+            html.push_str("<a href=\"");
+            escape_into(html, url);
+            html.push_str("\" rel=\"nofollow noopener\">");
+            render_inline(html, label, is_user);
+            html.push_str("</a>");
+            index += consumed;
+        } else if let Some(name) = mention_at(text, index).filter(|name| is_user(name)) {
+            // Usernames consist only of characters which are safe to use in both HTML and URLs
+            html.push_str("<a href=\"/user/");
+            html.push_str(name);
+            html.push_str("\">@");
+            html.push_str(name);
+            html.push_str("</a>");
+            index += name.len() + 1;
+        } else {
+            let next = rest.chars().next().unwrap_or_default();
+            escape_into(html, &rest[..next.len_utf8()]);
+            index += next.len_utf8();
+        }
+    }
+}
+
+/// If `text` starts with a span enclosed by `delimiter`, returns the length of the
+/// span including the delimiters and the enclosed contents. As in Markdown proper, the
+/// contents must be non-empty and can't begin or end with whitespace.
+fn delimited<'a>(text: &'a str, delimiter: &str) -> Option<(usize, &'a str)> {
+    let rest = text.strip_prefix(delimiter)?;
+    let end = rest.find(delimiter)?;
+    let content = &rest[..end];
+    let flanking = !content.starts_with(char::is_whitespace) && !content.ends_with(char::is_whitespace);
+    (!content.is_empty() && flanking).then_some((2 * delimiter.len() + end, content))
+}
+
+/// If `text` starts with a link of the form `[label](url)` with a safe `url`,
+/// returns the length of the link, its label, and its url.
+fn link(text: &str) -> Option<(usize, &str, &str)> {
+    let rest = text.strip_prefix('[')?;
+    let label_end = rest.find("](")?;
+    let label = &rest[..label_end];
+    let url_start = label_end + 2;
+    let url_length = rest[url_start..].find(')')?;
+    let url = rest[url_start..url_start + url_length].trim();
+    (!label.is_empty() && is_safe_url(url)).then_some((url_start + url_length + 2, label, url))
+}
+
+/// Only absolute http(s) URLs and site-relative paths are allowed as link targets.
+fn is_safe_url(url: &str) -> bool {
+    let lowercase_url = url.to_ascii_lowercase();
+    let safe_scheme = lowercase_url.starts_with("https://") || lowercase_url.starts_with("http://");
+    let relative = url.starts_with('/') && !url.starts_with("//");
+    (safe_scheme || relative) && !url.chars().any(char::is_whitespace)
+}
+
+fn escape_into(html: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            _ => html.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render_text(text: &str) -> String {
+        render(text, &|name| name.eq_ignore_ascii_case("admin"))
+    }
+
+    #[test]
+    fn find_mentions() {
+        let found: Vec<_> = mentions("@admin, ask @regular_user or email me@example.com @").collect();
+        assert_eq!(found, ["admin", "regular_user"]);
+        assert_eq!(mentions("no mentions").count(), 0);
+        assert_eq!(mentions("(@a-b)").collect::<Vec<_>>(), ["a-b"]);
+    }
+
+    #[test]
+    fn escape() {
+        assert_eq!(
+            render_text("<script>alert('hi')</script>"),
+            "<p>&lt;script&gt;alert(&#39;hi&#39;)&lt;/script&gt;</p>"
+        );
+        assert_eq!(render_text("a & \"b\""), "<p>a &amp; &quot;b&quot;</p>");
+        assert_eq!(render_text("`<b>`"), "<p><code>&lt;b&gt;</code></p>");
+    }
+
+    #[test]
+    fn inline() {
+        assert_eq!(
+            render_text("**bold** *italic* ~~gone~~"),
+            "<p><strong>bold</strong> <em>italic</em> <del>gone</del></p>"
+        );
+        assert_eq!(render_text("**bold *and* italic**"), "<p><strong>bold <em>and</em> italic</strong></p>");
+        assert_eq!(render_text("2 * 3 * 4"), "<p>2 * 3 * 4</p>");
+        assert_eq!(render_text("unclosed **bold"), "<p>unclosed **bold</p>");
+        assert_eq!(render_text("hi @Admin and @nobody"), "<p>hi <a href=\"/user/Admin\">@Admin</a> and @nobody</p>");
+    }
+
+    #[test]
+    fn links() {
+        assert_eq!(
+            render_text("[site](https://example.com/?a=1&b=2)"),
+            "<p><a href=\"https://example.com/?a=1&amp;b=2\" rel=\"nofollow noopener\">site</a></p>"
+        );
+        assert_eq!(render_text("[post](/post/1)"), "<p><a href=\"/post/1\" rel=\"nofollow noopener\">post</a></p>");
+        assert_eq!(render_text("[x](javascript:alert(1))"), "<p>[x](javascript:alert(1))</p>");
+        assert_eq!(render_text("[x](//evil.com)"), "<p>[x](//evil.com)</p>");
+        assert_eq!(render_text("[x](\"onclick=\")"), "<p>[x](&quot;onclick=&quot;)</p>");
+    }
+
+    #[test]
+    fn blocks() {
+        assert_eq!(render_text("one\ntwo\n\nthree"), "<p>one<br>two</p><p>three</p>");
+        assert_eq!(render_text("- a\n- *b*\ntext"), "<ul><li>a</li><li><em>b</em></li></ul><p>text</p>");
+        assert_eq!(
+            render_text("> quoted\n> **text**\nreply"),
+            "<blockquote><p>quoted<br><strong>text</strong></p></blockquote><p>reply</p>"
+        );
+        assert_eq!(render_text("```\nlet x = 1 < 2;\n  *y*\n```"), "<pre><code>let x = 1 &lt; 2;\n  *y*</code></pre>");
+        assert_eq!(render_text("```\nunterminated"), "<pre><code>unterminated</code></pre>");
+    }
+
+    #[test]
+    fn nested_quotes() {
+        const DEEP: usize = 100_000;
+
+        let nested = |depth| format!("{}text", ">".repeat(depth));
+        let quoted = |depth, content: &str| {
+            format!("{}<p>{content}</p>{}", "<blockquote>".repeat(depth), "</blockquote>".repeat(depth))
+        };
+        assert_eq!(render_text(&nested(2)), quoted(2, "text"));
+        assert_eq!(render_text(&nested(MAX_QUOTE_DEPTH)), quoted(MAX_QUOTE_DEPTH, "text"));
+        assert_eq!(render_text(&nested(MAX_QUOTE_DEPTH + 1)), quoted(MAX_QUOTE_DEPTH, "&gt;text"));
+
+        // Quote markers past the maximum depth are rendered as text instead of recursing further
+        let excess_markers = "&gt;".repeat(DEEP - MAX_QUOTE_DEPTH);
+        assert_eq!(render_text(&nested(DEEP)), quoted(MAX_QUOTE_DEPTH, &format!("{excess_markers}text")));
+    }
+}
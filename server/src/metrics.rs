@@ -0,0 +1,294 @@
+//! Metrics are recorded in process-wide registries, so that instrumented code doesn't need access
+//! to the application state, and are exposed in the Prometheus text format. Statistics that are
+//! already kept in the database are read when metrics are gathered instead.
+
+use crate::api::error::ApiResult;
+use crate::app::AppState;
+use crate::db::AsyncConnectionPool;
+use crate::schema::database_statistics;
+use axum::Router;
+use axum::extract::State;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use diesel::{QueryDsl, RunQueryDsl};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// Content type of the Prometheus text format.
+pub const TEXT_FORMAT: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Upper bounds of buckets for histograms of durations, in seconds.
+const DURATION_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Upper bounds of buckets for histograms of counts.
+const COUNT_BUCKETS: &[f64] = &[0.0, 1.0, 10.0, 100.0, 1000.0, 10000.0, 100_000.0];
+
+pub static HTTP_REQUEST_DURATION: Histogram<3> = Histogram::new(
+    "oxibooru_http_request_duration_seconds",
+    "Time taken to respond to HTTP requests.",
+    ["method", "route", "status"],
+    DURATION_BUCKETS,
+);
+pub static CONNECTION_WAIT_DURATION: Histogram<0> = Histogram::new(
+    "oxibooru_db_connection_wait_seconds",
+    "Time spent waiting for a database connection to become available.",
+    [],
+    DURATION_BUCKETS,
+);
+pub static CONTENT_CACHE_LOOKUPS: Counter<1> = Counter::new(
+    "oxibooru_content_cache_lookups_total",
+    "Lookups of uploaded content properties in the content cache.",
+    ["result"],
+);
+pub static FFMPEG_DURATION: Histogram<0> =
+    Histogram::new("oxibooru_ffmpeg_duration_seconds", "Time FFmpeg subprocesses took to run.", [], DURATION_BUCKETS);
+pub static FFMPEG_TIMEOUTS: Counter<0> =
+    Counter::new("oxibooru_ffmpeg_timeouts_total", "FFmpeg subprocesses killed for exceeding the timeout.", []);
+pub static REVERSE_SEARCH_CANDIDATES: Histogram<0> = Histogram::new(
+    "oxibooru_reverse_search_candidates",
+    "Number of candidate signatures compared per reverse search.",
+    [],
+    COUNT_BUCKETS,
+);
+pub static WEBHOOK_ATTEMPT_FAILURES: Counter<0> =
+    Counter::new("oxibooru_webhook_attempt_failures_total", "Failed attempts to post snapshots to webhooks.", []);
+pub static WEBHOOK_DELIVERY_FAILURES: Counter<0> = Counter::new(
+    "oxibooru_webhook_delivery_failures_total",
+    "Webhook deliveries that failed after running out of attempts.",
+    [],
+);
+
+/// A value that only ever increases, with one value per combination of labels.
+pub struct Counter<const N: usize> {
+    name: &'static str,
+    help: &'static str,
+    label_names: [&'static str; N],
+    values: Mutex<BTreeMap<[String; N], u64>>,
+}
+
+impl<const N: usize> Counter<N> {
+    pub const fn new(name: &'static str, help: &'static str, label_names: [&'static str; N]) -> Self {
+        Self {
+            name,
+            help,
+            label_names,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn increment(&self, label_values: [&str; N]) {
+        *lock(&self.values).entry(label_values.map(str::to_owned)).or_default() += 1;
+    }
+
+    fn render(&self, output: &mut String) {
+        write_header(output, self.name, self.help, "counter");
+        let values = lock(&self.values);
+        if N == 0 && values.is_empty() {
+            let _ = writeln!(output, "{} 0", self.name);
+        }
+        for (label_values, value) in &*values {
+            let labels = format_labels(&self.label_names, label_values, None);
+            let _ = writeln!(output, "{}{labels} {value}", self.name);
+        }
+    }
+}
+
+/// Counts observed values in buckets, with one set of buckets per combination of labels.
+pub struct Histogram<const N: usize> {
+    name: &'static str,
+    help: &'static str,
+    label_names: [&'static str; N],
+    bucket_bounds: &'static [f64],
+    values: Mutex<BTreeMap<[String; N], Observations>>,
+}
+
+#[derive(Default)]
+struct Observations {
+    bucket_counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl<const N: usize> Histogram<N> {
+    pub const fn new(
+        name: &'static str,
+        help: &'static str,
+        label_names: [&'static str; N],
+        bucket_bounds: &'static [f64],
+    ) -> Self {
+        Self {
+            name,
+            help,
+            label_names,
+            bucket_bounds,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn observe(&self, label_values: [&str; N], value: f64) {
+        let mut values = lock(&self.values);
+        let observations = values.entry(label_values.map(str::to_owned)).or_default();
+        observations.bucket_counts.resize(self.bucket_bounds.len(), 0);
+        if let Some(bucket) = self.bucket_bounds.iter().position(|&bound| value <= bound) {
+            observations.bucket_counts[bucket] += 1;
+        }
+        observations.sum += value;
+        observations.count += 1;
+    }
+
+    pub fn observe_duration(&self, label_values: [&str; N], duration: Duration) {
+        self.observe(label_values, duration.as_secs_f64());
+    }
+
+    fn render(&self, output: &mut String) {
+        write_header(output, self.name, self.help, "histogram");
+        let values = lock(&self.values);
+        for (label_values, observations) in &*values {
+            let mut cumulative_count = 0;
+            for (bound, bucket_count) in self.bucket_bounds.iter().zip(&observations.bucket_counts) {
+                cumulative_count += bucket_count;
+                let labels = format_labels(&self.label_names, label_values, Some(&bound.to_string()));
+                let _ = writeln!(output, "{}_bucket{labels} {cumulative_count}", self.name);
+            }
+            let labels = format_labels(&self.label_names, label_values, Some("+Inf"));
+            let _ = writeln!(output, "{}_bucket{labels} {}", self.name, observations.count);
+
+            let labels = format_labels(&self.label_names, label_values, None);
+            let _ = writeln!(output, "{}_sum{labels} {}", self.name, observations.sum);
+            let _ = writeln!(output, "{}_count{labels} {}", self.name, observations.count);
+        }
+    }
+}
+
+/// Gathers every metric, including statistics read from the database.
+pub async fn gather(connection_pool: &AsyncConnectionPool) -> ApiResult<String> {
+    let statistics = connection_pool
+        .transaction(|conn| {
+            database_statistics::table
+                .select((
+                    database_statistics::disk_usage,
+                    database_statistics::comment_count,
+                    database_statistics::pool_count,
+                    database_statistics::post_count,
+                    database_statistics::tag_count,
+                    database_statistics::user_count,
+                ))
+                .first::<(i64, i64, i64, i64, i64, i64)>(conn)
+        })
+        .await?;
+    let (disk_usage, comment_count, pool_count, post_count, tag_count, user_count) = statistics;
+
+    let mut output = String::new();
+    HTTP_REQUEST_DURATION.render(&mut output);
+    CONNECTION_WAIT_DURATION.render(&mut output);
+    CONTENT_CACHE_LOOKUPS.render(&mut output);
+    FFMPEG_DURATION.render(&mut output);
+    FFMPEG_TIMEOUTS.render(&mut output);
+    REVERSE_SEARCH_CANDIDATES.render(&mut output);
+    WEBHOOK_ATTEMPT_FAILURES.render(&mut output);
+    WEBHOOK_DELIVERY_FAILURES.render(&mut output);
+
+    let gauges = [
+        ("oxibooru_disk_usage_bytes", "Disk space used by post content and thumbnails.", disk_usage),
+        ("oxibooru_comments", "Number of comments.", comment_count),
+        ("oxibooru_pools", "Number of pools.", pool_count),
+        ("oxibooru_posts", "Number of posts.", post_count),
+        ("oxibooru_tags", "Number of tags.", tag_count),
+        ("oxibooru_users", "Number of users.", user_count),
+    ];
+    for (name, help, value) in gauges {
+        write_header(&mut output, name, help, "gauge");
+        let _ = writeln!(output, "{name} {value}");
+    }
+    Ok(output)
+}
+
+/// Creates a router that serves metrics without authentication, for use on a separate port.
+pub fn router(state: AppState) -> Router {
+    Router::new().route("/metrics", get(serve)).with_state(state)
+}
+
+async fn serve(State(state): State<AppState>) -> ApiResult<impl IntoResponse> {
+    let metrics = gather(&state.connection_pool).await?;
+    Ok(([(CONTENT_TYPE, TEXT_FORMAT)], metrics))
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn write_header(output: &mut String, name: &str, help: &str, metric_type: &str) {
+    let _ = writeln!(output, "# HELP {name} {help}");
+    let _ = writeln!(output, "# TYPE {name} {metric_type}");
+}
+
+/// Formats labels as `{name="value",...}`, with an optional `le` label for histogram buckets.
+fn format_labels(names: &[&str], values: &[String], upper_bound: Option<&str>) -> String {
+    let escape = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+    let mut labels: Vec<_> = names
+        .iter()
+        .zip(values)
+        .map(|(name, value)| format!("{name}=\"{}\"", escape(value)))
+        .collect();
+    if let Some(upper_bound) = upper_bound {
+        labels.push(format!("le=\"{upper_bound}\""));
+    }
+
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels.join(","))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Counter, Histogram};
+
+    #[test]
+    fn counter_format() {
+        let counter = Counter::new("test_total", "A test counter.", ["route", "status"]);
+        counter.increment(["/post/{id}", "200"]);
+        counter.increment(["/post/{id}", "200"]);
+        counter.increment(["/\"quoted\"", "404"]);
+
+        let mut output = String::new();
+        counter.render(&mut output);
+        assert_eq!(
+            output,
+            "# HELP test_total A test counter.\n\
+             # TYPE test_total counter\n\
+             test_total{route=\"/\\\"quoted\\\"\",status=\"404\"} 1\n\
+             test_total{route=\"/post/{id}\",status=\"200\"} 2\n"
+        );
+
+        let unlabeled_counter = Counter::new("unused_total", "An unused counter.", []);
+        let mut output = String::new();
+        unlabeled_counter.render(&mut output);
+        assert!(output.ends_with("unused_total 0\n"));
+    }
+
+    #[test]
+    fn histogram_format() {
+        let histogram = Histogram::new("test_seconds", "A test histogram.", [], &[0.5, 1.0]);
+        histogram.observe([], 0.25);
+        histogram.observe([], 0.75);
+        histogram.observe([], 2.0);
+
+        let mut output = String::new();
+        histogram.render(&mut output);
+        assert_eq!(
+            output,
+            "# HELP test_seconds A test histogram.\n\
+             # TYPE test_seconds histogram\n\
+             test_seconds_bucket{le=\"0.5\"} 1\n\
+             test_seconds_bucket{le=\"1\"} 2\n\
+             test_seconds_bucket{le=\"+Inf\"} 3\n\
+             test_seconds_sum 3\n\
+             test_seconds_count 3\n"
+        );
+    }
+}
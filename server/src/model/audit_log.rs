@@ -0,0 +1,33 @@
+use crate::model::enums::AuditEvent;
+use crate::schema::audit_log;
+use crate::time::DateTime;
+use diesel::pg::Pg;
+use diesel::{Identifiable, Insertable, Queryable, Selectable};
+
+#[derive(Insertable)]
+#[diesel(table_name = audit_log)]
+#[diesel(check_for_backend(Pg))]
+pub struct NewAuditLogEntry<'a> {
+    pub event: AuditEvent,
+    pub user_id: Option<i64>,
+    pub user_name: Option<&'a str>,
+    pub target: Option<&'a str>,
+    pub details: Option<&'a str>,
+    pub ip_address: Option<&'a str>,
+    pub user_agent: Option<&'a str>,
+}
+
+#[derive(Identifiable, Queryable, Selectable)]
+#[diesel(table_name = audit_log)]
+#[diesel(check_for_backend(Pg))]
+pub struct AuditLogEntry {
+    pub id: i64,
+    pub event: AuditEvent,
+    pub user_id: Option<i64>,
+    pub user_name: Option<String>,
+    pub target: Option<String>,
+    pub details: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub creation_time: DateTime,
+}
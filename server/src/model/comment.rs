@@ -1,7 +1,7 @@
 use crate::model::enums::Score;
 use crate::model::post::Post;
 use crate::model::user::User;
-use crate::schema::{comment, comment_score};
+use crate::schema::{comment, comment_mention, comment_score};
 use crate::string::LargeString;
 use crate::time::DateTime;
 use diesel::pg::Pg;
@@ -14,6 +14,7 @@ pub struct NewComment<'a> {
     pub user_id: Option<i64>,
     pub post_id: i64,
     pub text: &'a str,
+    pub parent_comment_id: Option<i64>,
 }
 
 #[derive(Insertable)]
@@ -25,6 +26,7 @@ pub struct OrphanedComment {
     pub text: String,
     pub creation_time: DateTime,
     pub last_edit_time: DateTime,
+    pub parent_comment_id: Option<i64>,
 }
 
 #[derive(Associations, Identifiable, Queryable, Selectable)]
@@ -38,6 +40,17 @@ pub struct Comment {
     pub text: LargeString,
     pub creation_time: DateTime,
     pub last_edit_time: DateTime,
+    pub parent_comment_id: Option<i64>,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Selectable)]
+#[diesel(belongs_to(Comment), belongs_to(User))]
+#[diesel(table_name = comment_mention)]
+#[diesel(primary_key(comment_id, user_id))]
+#[diesel(check_for_backend(Pg))]
+pub struct CommentMention {
+    pub comment_id: i64,
+    pub user_id: i64,
 }
 
 #[derive(Insertable)]
@@ -0,0 +1,49 @@
+use crate::model::enums::DigestFrequency;
+use crate::model::tag::Tag;
+use crate::model::user::User;
+use crate::schema::{digest_subscription, saved_search, watched_tag};
+use crate::string::SmallString;
+use crate::time::DateTime;
+use diesel::pg::Pg;
+use diesel::{Associations, Identifiable, Insertable, Queryable, Selectable};
+
+#[derive(Insertable)]
+#[diesel(table_name = digest_subscription)]
+#[diesel(check_for_backend(Pg))]
+pub struct NewDigestSubscription {
+    pub user_id: i64,
+    pub frequency: DigestFrequency,
+    pub last_post_id: i64,
+}
+
+#[derive(Associations, Identifiable, Queryable, Selectable)]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = digest_subscription)]
+#[diesel(primary_key(user_id))]
+#[diesel(check_for_backend(Pg))]
+pub struct DigestSubscription {
+    pub user_id: i64,
+    pub frequency: DigestFrequency,
+    pub last_post_id: i64,
+    pub last_sent_time: DateTime,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Selectable)]
+#[diesel(belongs_to(User), belongs_to(Tag))]
+#[diesel(table_name = watched_tag)]
+#[diesel(primary_key(user_id, tag_id))]
+#[diesel(check_for_backend(Pg))]
+pub struct WatchedTag {
+    pub user_id: i64,
+    pub tag_id: i64,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Selectable)]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = saved_search)]
+#[diesel(primary_key(user_id, query))]
+#[diesel(check_for_backend(Pg))]
+pub struct SavedSearch {
+    pub user_id: i64,
+    pub query: SmallString,
+}
@@ -228,6 +228,33 @@ impl FromSql<SmallInt, Pg> for PostSafety {
     }
 }
 
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumString, FromRepr, AsExpression, FromSqlRow, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+#[diesel(sql_type = SmallInt)]
+#[repr(i16)]
+pub enum PostStatus {
+    Approved,
+    Pending,
+    Rejected,
+}
+
+impl ToSql<SmallInt, Pg> for PostStatus {
+    fn to_sql(&self, out: &mut Output<Pg>) -> serialize::Result {
+        out.write_all(&(*self as i16).to_be_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<SmallInt, Pg> for PostStatus {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        let database_value = i16::from_sql(value)?;
+        Self::from_repr(database_value).ok_or("Failed to deserialize post status".into())
+    }
+}
+
 #[derive(Clone, Copy, EnumCount, EnumIter, EnumString, FromRepr, IntoStaticStr, Deserialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 #[strum(serialize_all = "lowercase")]
@@ -337,6 +364,7 @@ impl Serialize for PostFlags {
     PartialOrd,
     Ord,
     EnumString,
+    IntoStaticStr,
     FromRepr,
     AsExpression,
     FromSqlRow,
@@ -440,7 +468,9 @@ impl FromSql<SmallInt, Pg> for Score {
     }
 }
 
-#[derive(Debug, Clone, Copy, EnumString, FromRepr, AsExpression, FromSqlRow, Serialize, ToSchema)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumString, FromRepr, AsExpression, FromSqlRow, Serialize, Deserialize, ToSchema,
+)]
 #[serde(rename_all = "snake_case")]
 #[diesel(sql_type = SmallInt)]
 #[repr(i16)]
@@ -449,6 +479,7 @@ pub enum ResourceOperation {
     Modified,
     Merged,
     Deleted,
+    Restored,
 }
 
 impl ToSql<SmallInt, Pg> for ResourceOperation {
@@ -465,7 +496,21 @@ impl FromSql<SmallInt, Pg> for ResourceOperation {
     }
 }
 
-#[derive(Debug, Display, Clone, Copy, EnumString, FromRepr, AsExpression, FromSqlRow, Serialize, ToSchema)]
+#[derive(
+    Debug,
+    Display,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumString,
+    FromRepr,
+    AsExpression,
+    FromSqlRow,
+    Serialize,
+    Deserialize,
+    ToSchema,
+)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 #[diesel(sql_type = SmallInt)]
@@ -481,6 +526,15 @@ pub enum ResourceType {
     TagSuggestion,
     User,
     UserToken,
+    Report,
+    UserSanction,
+    UserNote,
+    PostFile,
+    WikiPage,
+    Notification,
+    UserSession,
+    InviteCode,
+    WebhookDelivery,
 }
 
 impl ToSql<SmallInt, Pg> for ResourceType {
@@ -497,9 +551,245 @@ impl FromSql<SmallInt, Pg> for ResourceType {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, FromRepr, AsExpression, FromSqlRow, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+#[diesel(sql_type = SmallInt)]
+#[repr(i16)]
+pub enum ReportStatus {
+    Open,
+    Resolved,
+    Dismissed,
+}
+
+impl ToSql<SmallInt, Pg> for ReportStatus {
+    fn to_sql(&self, out: &mut Output<Pg>) -> serialize::Result {
+        out.write_all(&(*self as i16).to_be_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<SmallInt, Pg> for ReportStatus {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        let database_value = i16::from_sql(value)?;
+        Self::from_repr(database_value).ok_or("Failed to deserialize report status".into())
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumString, FromRepr, AsExpression, FromSqlRow, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+#[diesel(sql_type = SmallInt)]
+#[repr(i16)]
+pub enum SanctionKind {
+    Suspension,
+    Ban,
+}
+
+impl ToSql<SmallInt, Pg> for SanctionKind {
+    fn to_sql(&self, out: &mut Output<Pg>) -> serialize::Result {
+        out.write_all(&(*self as i16).to_be_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<SmallInt, Pg> for SanctionKind {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        let database_value = i16::from_sql(value)?;
+        Self::from_repr(database_value).ok_or("Failed to deserialize sanction kind".into())
+    }
+}
+
+/// Kind of a post relation. Symmetric relations are stored as a pair of rows, while
+/// directed relations are stored as a single parent -> child row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, FromRepr, AsExpression, FromSqlRow)]
+#[diesel(sql_type = SmallInt)]
+#[repr(i16)]
+pub enum RelationKind {
+    Symmetric,
+    Directed,
+}
+
+impl ToSql<SmallInt, Pg> for RelationKind {
+    fn to_sql(&self, out: &mut Output<Pg>) -> serialize::Result {
+        out.write_all(&(*self as i16).to_be_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<SmallInt, Pg> for RelationKind {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        let database_value = i16::from_sql(value)?;
+        Self::from_repr(database_value).ok_or("Failed to deserialize relation kind".into())
+    }
+}
+
+/// Kind of event a user can be notified about.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    EnumString,
+    FromRepr,
+    AsExpression,
+    FromSqlRow,
+    Serialize,
+    Deserialize,
+    ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+#[diesel(sql_type = SmallInt)]
+#[repr(i16)]
+pub enum NotificationKind {
+    /// Someone replied to one of the user's comments.
+    Reply,
+    /// Someone mentioned the user in a comment.
+    Mention,
+    /// Someone favorited one of the user's uploads.
+    Favorite,
+    /// Someone edited one of the user's uploads.
+    PostEdit,
+    /// One of the user's uploads was approved.
+    PostApproved,
+    /// One of the user's uploads was rejected.
+    PostRejected,
+}
+
+impl ToSql<SmallInt, Pg> for NotificationKind {
+    fn to_sql(&self, out: &mut Output<Pg>) -> serialize::Result {
+        out.write_all(&(*self as i16).to_be_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<SmallInt, Pg> for NotificationKind {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        let database_value = i16::from_sql(value)?;
+        Self::from_repr(database_value).ok_or("Failed to deserialize notification kind".into())
+    }
+}
+
+/// How often a user receives email digests.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumString, FromRepr, AsExpression, FromSqlRow, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+#[diesel(sql_type = SmallInt)]
+#[repr(i16)]
+pub enum DigestFrequency {
+    Never,
+    Daily,
+    Weekly,
+}
+
+impl DigestFrequency {
+    /// Returns the time between consecutive digests, or `None` if digests are disabled.
+    pub fn period(self) -> Option<time::Duration> {
+        match self {
+            Self::Never => None,
+            Self::Daily => Some(time::Duration::DAY),
+            Self::Weekly => Some(time::Duration::WEEK),
+        }
+    }
+}
+
+impl ToSql<SmallInt, Pg> for DigestFrequency {
+    fn to_sql(&self, out: &mut Output<Pg>) -> serialize::Result {
+        out.write_all(&(*self as i16).to_be_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<SmallInt, Pg> for DigestFrequency {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        let database_value = i16::from_sql(value)?;
+        Self::from_repr(database_value).ok_or("Failed to deserialize digest frequency".into())
+    }
+}
+
+/// Kind of security-relevant event recorded in the audit log.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumString, FromRepr, AsExpression, FromSqlRow, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+#[diesel(sql_type = SmallInt)]
+#[repr(i16)]
+pub enum AuditEvent {
+    /// A user logged in, either by starting a session or by bumping their login time.
+    Login,
+    /// Someone sent credentials that were rejected.
+    FailedAuthentication,
+    /// A user token was created.
+    TokenCreation,
+    /// A user token was deleted.
+    TokenDeletion,
+    /// Someone requested a password reset email.
+    PasswordResetRequest,
+    /// A password was reset using a token from a password reset email.
+    PasswordReset,
+    /// A user's rank was changed.
+    RankChange,
+    /// A client was refused an action because their privileges are too low.
+    PrivilegeDenial,
+}
+
+impl ToSql<SmallInt, Pg> for AuditEvent {
+    fn to_sql(&self, out: &mut Output<Pg>) -> serialize::Result {
+        out.write_all(&(*self as i16).to_be_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<SmallInt, Pg> for AuditEvent {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        let database_value = i16::from_sql(value)?;
+        Self::from_repr(database_value).ok_or("Failed to deserialize audit event".into())
+    }
+}
+
+/// State of a webhook delivery.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumString, FromRepr, AsExpression, FromSqlRow, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+#[diesel(sql_type = SmallInt)]
+#[repr(i16)]
+pub enum WebhookDeliveryStatus {
+    /// The delivery is waiting for its next attempt.
+    Pending,
+    /// The webhook accepted the payload.
+    Delivered,
+    /// Every attempt failed. The delivery won't be retried unless it is replayed.
+    Failed,
+}
+
+impl ToSql<SmallInt, Pg> for WebhookDeliveryStatus {
+    fn to_sql(&self, out: &mut Output<Pg>) -> serialize::Result {
+        out.write_all(&(*self as i16).to_be_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<SmallInt, Pg> for WebhookDeliveryStatus {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        let database_value = i16::from_sql(value)?;
+        Self::from_repr(database_value).ok_or("Failed to deserialize webhook delivery status".into())
+    }
+}
+
 #[derive(Debug, Display)]
 #[strum(serialize_all = "snake_case")]
 pub enum ResourceProperty {
+    OpenReport,
     PoolName,
     PoolPost,
     PoolCategoryName,
@@ -510,6 +800,9 @@ pub enum ResourceProperty {
     TagCategoryName,
     UserName,
     UserEmail,
+    UserIdentity,
+    WikiPageTag,
+    WikiPageTitle,
 }
 
 #[cfg(test)]
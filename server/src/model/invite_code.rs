@@ -0,0 +1,27 @@
+use crate::schema::invite_code;
+use crate::time::DateTime;
+use diesel::pg::Pg;
+use diesel::{Identifiable, Insertable, Queryable, Selectable};
+
+#[derive(Insertable)]
+#[diesel(table_name = invite_code)]
+#[diesel(check_for_backend(Pg))]
+pub struct NewInviteCode<'a> {
+    pub code: &'a str,
+    pub creator_id: Option<i64>,
+    pub max_uses: i32,
+    pub expiration_time: Option<DateTime>,
+}
+
+#[derive(Identifiable, Queryable, Selectable)]
+#[diesel(table_name = invite_code)]
+#[diesel(primary_key(code))]
+#[diesel(check_for_backend(Pg))]
+pub struct InviteCode {
+    pub code: String,
+    pub creator_id: Option<i64>,
+    pub max_uses: i32,
+    pub use_count: i32,
+    pub expiration_time: Option<DateTime>,
+    pub creation_time: DateTime,
+}
@@ -1,9 +1,16 @@
+pub mod audit_log;
 pub mod comment;
+pub mod digest;
 pub mod enums;
+pub mod invite_code;
+pub mod notification;
 pub mod pool;
 pub mod pool_category;
 pub mod post;
+pub mod report;
 pub mod snapshot;
 pub mod tag;
 pub mod tag_category;
 pub mod user;
+pub mod webhook;
+pub mod wiki_page;
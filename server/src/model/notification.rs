@@ -0,0 +1,42 @@
+use crate::model::enums::NotificationKind;
+use crate::model::user::User;
+use crate::schema::{muted_notification, notification};
+use crate::time::DateTime;
+use diesel::pg::Pg;
+use diesel::{Associations, Identifiable, Insertable, Queryable, Selectable};
+
+#[derive(Insertable)]
+#[diesel(table_name = notification)]
+#[diesel(check_for_backend(Pg))]
+pub struct NewNotification {
+    pub user_id: i64,
+    pub kind: NotificationKind,
+    pub actor_id: Option<i64>,
+    pub post_id: Option<i64>,
+    pub comment_id: Option<i64>,
+}
+
+#[derive(Associations, Identifiable, Queryable, Selectable)]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = notification)]
+#[diesel(check_for_backend(Pg))]
+pub struct Notification {
+    pub id: i64,
+    pub user_id: i64,
+    pub kind: NotificationKind,
+    pub actor_id: Option<i64>,
+    pub post_id: Option<i64>,
+    pub comment_id: Option<i64>,
+    pub is_read: bool,
+    pub creation_time: DateTime,
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable, Selectable)]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = muted_notification)]
+#[diesel(primary_key(user_id, kind))]
+#[diesel(check_for_backend(Pg))]
+pub struct MutedNotification {
+    pub user_id: i64,
+    pub kind: NotificationKind,
+}
@@ -1,10 +1,11 @@
 use crate::content::hash::{Checksum, Md5Checksum};
 use crate::content::signature::{COMPRESSED_SIGNATURE_LEN, NUM_WORDS};
-use crate::model::enums::{MimeType, PostFlags, PostSafety, PostType, Score};
+use crate::model::enums::{MimeType, PostFlags, PostSafety, PostStatus, PostType, RelationKind, Score};
 use crate::model::tag::Tag;
 use crate::model::user::User;
 use crate::schema::{
-    post, post_favorite, post_feature, post_note, post_relation, post_score, post_signature, post_tag,
+    post, post_favorite, post_feature, post_file, post_file_signature, post_note, post_relation, post_score,
+    post_signature, post_tag,
 };
 use crate::string::LargeString;
 use crate::time::DateTime;
@@ -13,8 +14,8 @@ use diesel::pg::{Pg, PgValue};
 use diesel::serialize::{self, Output, ToSql};
 use diesel::sql_types::{Array, BigInt, Integer, Nullable};
 use diesel::{
-    AsChangeset, Associations, Connection, Identifiable, Insertable, PgArrayExpressionMethods, PgConnection, QueryDsl,
-    QueryResult, Queryable, RunQueryDsl, Selectable, SelectableHelper,
+    AsChangeset, Associations, Connection, ExpressionMethods, Identifiable, Insertable, PgArrayExpressionMethods,
+    PgConnection, QueryDsl, QueryResult, Queryable, RunQueryDsl, Selectable, SelectableHelper,
 };
 use diesel::{AsExpression, FromSqlRow};
 use std::ops::Deref;
@@ -35,6 +36,7 @@ pub struct NewPost<'a> {
     pub flags: PostFlags,
     pub source: &'a str,
     pub description: &'a str,
+    pub status: PostStatus,
 }
 
 #[derive(Clone, AsChangeset, Associations, Identifiable, Queryable, Selectable)]
@@ -60,16 +62,19 @@ pub struct Post {
     pub generated_thumbnail_size: i64,
     pub custom_thumbnail_size: i64,
     pub description: LargeString,
+    pub deletion_time: Option<DateTime>,
+    pub status: PostStatus,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Associations, Identifiable, Insertable, Queryable, Selectable)]
 #[diesel(belongs_to(Post, foreign_key = parent_id))]
 #[diesel(table_name = post_relation)]
-#[diesel(primary_key(parent_id, child_id))]
+#[diesel(primary_key(parent_id, child_id, kind))]
 #[diesel(check_for_backend(Pg))]
 pub struct PostRelation {
     pub parent_id: i64,
     pub child_id: i64,
+    pub kind: RelationKind,
 }
 
 diesel::joinable!(post_relation -> post (parent_id));
@@ -77,12 +82,58 @@ diesel::joinable!(post_relation -> post (parent_id));
 impl PostRelation {
     /// Creates a bidirectional pair of [`PostRelation`]s for two posts with ids `id_1` and `id_2`.
     pub fn new_pair(id_1: i64, id_2: i64) -> [Self; 2] {
-        [PostRelation::new(id_1, id_2), PostRelation::new(id_2, id_1)]
+        [
+            PostRelation::new(id_1, id_2, RelationKind::Symmetric),
+            PostRelation::new(id_2, id_1, RelationKind::Symmetric),
+        ]
     }
 
-    fn new(parent_id: i64, child_id: i64) -> Self {
-        Self { parent_id, child_id }
+    /// Creates a directed [`PostRelation`] from the post with id `parent_id` to the post with id `child_id`.
+    pub fn new_directed(parent_id: i64, child_id: i64) -> Self {
+        PostRelation::new(parent_id, child_id, RelationKind::Directed)
     }
+
+    fn new(parent_id: i64, child_id: i64, kind: RelationKind) -> Self {
+        Self {
+            parent_id,
+            child_id,
+            kind,
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = post_file)]
+#[diesel(check_for_backend(Pg))]
+pub struct NewPostFile {
+    pub post_id: i64,
+    pub order: i32,
+    pub file_size: i64,
+    pub width: i32,
+    pub height: i32,
+    pub type_: PostType,
+    pub mime_type: MimeType,
+    pub checksum: Checksum,
+    pub checksum_md5: Md5Checksum,
+}
+
+#[derive(Associations, Identifiable, Queryable, Selectable)]
+#[diesel(belongs_to(Post))]
+#[diesel(table_name = post_file)]
+#[diesel(check_for_backend(Pg))]
+pub struct PostFile {
+    pub id: i64,
+    pub post_id: i64,
+    pub order: i32,
+    pub file_size: i64,
+    pub width: i32,
+    pub height: i32,
+    pub type_: PostType,
+    pub mime_type: MimeType,
+    pub checksum: Checksum,
+    pub checksum_md5: Md5Checksum,
+    pub generated_thumbnail_size: i64,
+    pub creation_time: DateTime,
 }
 
 #[derive(Associations, Identifiable, Insertable, Queryable, Selectable)]
@@ -259,17 +310,47 @@ impl PostSignature {
     /// This is designed avoid false negatives with as few false positives as possible,
     /// but it's still very coarse and the vast majority of candidates will still be false positives.
     /// This should only be used in conjuction with a more fine-grained but slower similarity test.
+    /// Deleted and unapproved posts are never candidates.
     pub fn find_similar_candidates(conn: &mut PgConnection, words: &[i32; NUM_WORDS]) -> QueryResult<Vec<Self>> {
         conn.transaction(|conn| {
             // Postgres really wants to perform a seq scan here, which is much slower than
             // an index scan. We temporarily disable seq scans to force it to use the index scan.
             diesel::sql_query("SET LOCAL enable_seqscan=false").execute(conn)?;
             post_signature::table
+                .inner_join(post::table)
                 .select(PostSignature::as_select())
                 .filter(post_signature::words.overlaps_with(words.as_slice()))
+                .filter(post::deletion_time.is_null())
+                .filter(post::status.eq(PostStatus::Approved))
                 .load(conn)
         })
     }
+
+    /// Retrieves a list of potential candidates for similar post files from the database.
+    /// Each candidate holds the signature of a file together with the id of the post that owns it.
+    ///
+    /// See [`PostSignature::find_similar_candidates`] for details.
+    pub fn find_similar_file_candidates(conn: &mut PgConnection, words: &[i32; NUM_WORDS]) -> QueryResult<Vec<Self>> {
+        conn.transaction(|conn| {
+            diesel::sql_query("SET LOCAL enable_seqscan=false").execute(conn)?;
+            post_file_signature::table
+                .inner_join(post_file::table.inner_join(post::table))
+                .select((post_file::post_id, post_file_signature::signature))
+                .filter(post_file_signature::words.overlaps_with(words.as_slice()))
+                .filter(post::deletion_time.is_null())
+                .filter(post::status.eq(PostStatus::Approved))
+                .load(conn)
+        })
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = post_file_signature)]
+#[diesel(check_for_backend(Pg))]
+pub struct NewPostFileSignature {
+    pub post_file_id: i64,
+    pub signature: CompressedSignature,
+    pub words: SignatureIndexes,
 }
 
 /// Deserializes a database query `value` into a fixed-size array of length `N`.
@@ -0,0 +1,33 @@
+use crate::model::enums::{ReportStatus, ResourceType};
+use crate::model::user::User;
+use crate::schema::report;
+use crate::string::{LargeString, SmallString};
+use crate::time::DateTime;
+use diesel::pg::Pg;
+use diesel::{Associations, Identifiable, Insertable, Queryable, Selectable};
+
+#[derive(Insertable)]
+#[diesel(table_name = report)]
+#[diesel(check_for_backend(Pg))]
+pub struct NewReport<'a> {
+    pub user_id: Option<i64>,
+    pub resource_type: ResourceType,
+    pub resource_id: SmallString,
+    pub reason: &'a str,
+}
+
+#[derive(Associations, Identifiable, Queryable, Selectable)]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = report)]
+#[diesel(check_for_backend(Pg))]
+pub struct Report {
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub resource_type: ResourceType,
+    pub resource_id: SmallString,
+    pub reason: LargeString,
+    pub status: ReportStatus,
+    pub resolver_id: Option<i64>,
+    pub creation_time: DateTime,
+    pub resolution_time: Option<DateTime>,
+}
@@ -6,7 +6,11 @@ use diesel::dsl::sql;
 use diesel::expression::{SqlLiteral, UncheckedBind};
 use diesel::pg::Pg;
 use diesel::sql_types::Bool;
-use diesel::{AsChangeset, Associations, Identifiable, Insertable, Queryable, Selectable};
+use diesel::{
+    AsChangeset, Associations, ExpressionMethods, Identifiable, Insertable, PgConnection, QueryDsl, QueryResult,
+    Queryable, QueryableByName, RunQueryDsl, Selectable,
+};
+use std::collections::{HashMap, HashSet};
 
 #[derive(Clone, Copy, Default, Insertable)]
 #[diesel(table_name = tag)]
@@ -57,9 +61,21 @@ impl TagName {
     pub fn is_primary() -> SqlLiteral<Bool, UncheckedBind<SqlLiteral<Bool>, tag_name::order>> {
         sql("").bind(tag_name::order).sql(" = 0")
     }
+
+    /// Returns a map from each of the given `tag_ids` to its primary name.
+    pub fn primary_names(conn: &mut PgConnection, tag_ids: &[i64]) -> QueryResult<HashMap<i64, SmallString>> {
+        tag_name::table
+            .select((tag_name::tag_id, tag_name::name))
+            .filter(tag_name::tag_id.eq_any(tag_ids))
+            .filter(Self::is_primary())
+            .load::<(i64, SmallString)>(conn)
+            .map(|rows| rows.into_iter().collect())
+    }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Associations, Identifiable, Insertable, Queryable, Selectable)]
+#[derive(
+    Clone, Copy, PartialEq, Eq, Hash, Associations, Identifiable, Insertable, Queryable, QueryableByName, Selectable,
+)]
 #[diesel(belongs_to(Tag, foreign_key = parent_id))]
 #[diesel(table_name = tag_implication)]
 #[diesel(primary_key(parent_id, child_id))]
@@ -71,6 +87,52 @@ pub struct TagImplication {
 
 diesel::joinable!(tag_implication -> tag (parent_id));
 
+impl TagImplication {
+    /// Returns every implication reachable by following implications downwards from `tag_ids`.
+    /// The children of the returned implications are all tags transitively implied by `tag_ids`.
+    pub fn descendants(conn: &mut PgConnection, tag_ids: &[i64]) -> QueryResult<Vec<Self>> {
+        Self::closure(conn, tag_ids, true)
+    }
+
+    /// Returns every implication reachable by following implications upwards from `tag_ids`.
+    /// The parents of the returned implications are all tags that transitively imply `tag_ids`.
+    pub fn ancestors(conn: &mut PgConnection, tag_ids: &[i64]) -> QueryResult<Vec<Self>> {
+        Self::closure(conn, tag_ids, false)
+    }
+
+    /// Computes the transitive closure one level at a time. Each tag is expanded at most once,
+    /// so this terminates even if the implications contain a cycle.
+    fn closure(conn: &mut PgConnection, tag_ids: &[i64], downwards: bool) -> QueryResult<Vec<Self>> {
+        let mut visited: HashSet<i64> = tag_ids.iter().copied().collect();
+        let mut frontier = tag_ids.to_vec();
+        let mut closure = Vec::new();
+        while !frontier.is_empty() {
+            let implications: Vec<Self> = if downwards {
+                tag_implication::table
+                    .filter(tag_implication::parent_id.eq_any(&frontier))
+                    .load(conn)?
+            } else {
+                tag_implication::table
+                    .filter(tag_implication::child_id.eq_any(&frontier))
+                    .load(conn)?
+            };
+            frontier = implications
+                .iter()
+                .map(|implication| {
+                    if downwards {
+                        implication.child_id
+                    } else {
+                        implication.parent_id
+                    }
+                })
+                .filter(|&tag_id| visited.insert(tag_id))
+                .collect();
+            closure.extend(implications);
+        }
+        Ok(closure)
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Associations, Identifiable, Insertable, Queryable, Selectable)]
 #[diesel(belongs_to(Tag, foreign_key = parent_id))]
 #[diesel(table_name = tag_suggestion)]
@@ -1,5 +1,8 @@
-use crate::model::enums::{AvatarStyle, UserRank};
-use crate::schema::{user, user_token};
+use crate::model::enums::{AvatarStyle, SanctionKind, UserRank};
+use crate::schema::{
+    email_verification, totp_recovery_code, user, user_identity, user_note, user_sanction, user_session, user_token,
+    user_totp,
+};
 use crate::string::{LargeString, SecretString, SmallString};
 use crate::time::DateTime;
 use diesel::pg::Pg;
@@ -65,3 +68,105 @@ pub struct UserToken {
     pub last_edit_time: DateTime,
     pub last_usage_time: DateTime,
 }
+
+#[derive(Insertable)]
+#[diesel(table_name = user_session)]
+#[diesel(check_for_backend(Pg))]
+pub struct NewUserSession<'a> {
+    pub user_id: i64,
+    pub secret_hash: &'a str,
+    pub expiration_time: DateTime,
+}
+
+#[derive(Associations, Identifiable, Queryable, Selectable)]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = user_session)]
+#[diesel(check_for_backend(Pg))]
+pub struct UserSession {
+    pub id: i64,
+    pub user_id: i64,
+    #[allow(dead_code)]
+    secret_hash: String,
+    pub creation_time: DateTime,
+    pub last_usage_time: DateTime,
+    pub expiration_time: DateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = user_sanction)]
+#[diesel(check_for_backend(Pg))]
+pub struct NewUserSanction<'a> {
+    pub user_id: i64,
+    pub issuer_id: Option<i64>,
+    pub kind: SanctionKind,
+    pub reason: &'a str,
+    pub expiration_time: Option<DateTime>,
+}
+
+#[derive(Associations, Identifiable, Queryable, Selectable)]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = user_sanction)]
+#[diesel(check_for_backend(Pg))]
+pub struct UserSanction {
+    pub id: i64,
+    pub user_id: i64,
+    pub issuer_id: Option<i64>,
+    pub kind: SanctionKind,
+    pub reason: LargeString,
+    pub creation_time: DateTime,
+    pub expiration_time: Option<DateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = user_note)]
+#[diesel(check_for_backend(Pg))]
+pub struct NewUserNote<'a> {
+    pub user_id: i64,
+    pub author_id: Option<i64>,
+    pub text: &'a str,
+}
+
+#[derive(Associations, Identifiable, Queryable, Selectable)]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = user_note)]
+#[diesel(check_for_backend(Pg))]
+pub struct UserNote {
+    pub id: i64,
+    pub user_id: i64,
+    pub author_id: Option<i64>,
+    pub text: LargeString,
+    pub creation_time: DateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = user_identity)]
+#[diesel(check_for_backend(Pg))]
+pub struct NewUserIdentity<'a> {
+    pub issuer: &'a str,
+    pub subject: &'a str,
+    pub user_id: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = user_totp)]
+#[diesel(check_for_backend(Pg))]
+pub struct NewUserTotp<'a> {
+    pub user_id: i64,
+    pub secret: &'a [u8],
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = totp_recovery_code)]
+#[diesel(check_for_backend(Pg))]
+pub struct NewTotpRecoveryCode<'a> {
+    pub user_id: i64,
+    pub code_hash: &'a str,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = email_verification)]
+#[diesel(check_for_backend(Pg))]
+pub struct NewEmailVerification {
+    pub user_id: i64,
+    pub rank: UserRank,
+}
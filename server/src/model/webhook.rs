@@ -0,0 +1,30 @@
+use crate::model::enums::WebhookDeliveryStatus;
+use crate::model::snapshot::Snapshot;
+use crate::schema::webhook_delivery;
+use crate::time::DateTime;
+use diesel::pg::Pg;
+use diesel::{Associations, Identifiable, Insertable, Queryable, Selectable};
+
+#[derive(Insertable)]
+#[diesel(table_name = webhook_delivery)]
+#[diesel(check_for_backend(Pg))]
+pub struct NewWebhookDelivery<'a> {
+    pub snapshot_id: i64,
+    pub url: &'a str,
+}
+
+#[derive(Associations, Identifiable, Queryable, Selectable)]
+#[diesel(belongs_to(Snapshot))]
+#[diesel(table_name = webhook_delivery)]
+#[diesel(check_for_backend(Pg))]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub snapshot_id: i64,
+    pub url: String,
+    pub status: WebhookDeliveryStatus,
+    pub attempt_count: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_time: DateTime,
+    pub last_attempt_time: Option<DateTime>,
+    pub creation_time: DateTime,
+}
@@ -0,0 +1,28 @@
+use crate::model::tag::Tag;
+use crate::schema::wiki_page;
+use crate::string::{LargeString, SmallString};
+use crate::time::DateTime;
+use diesel::pg::Pg;
+use diesel::{AsChangeset, Associations, Identifiable, Insertable, Queryable, Selectable};
+
+#[derive(Insertable)]
+#[diesel(table_name = wiki_page)]
+#[diesel(check_for_backend(Pg))]
+pub struct NewWikiPage<'a> {
+    pub title: Option<&'a str>,
+    pub tag_id: Option<i64>,
+    pub body: &'a str,
+}
+
+#[derive(Clone, AsChangeset, Associations, Identifiable, Queryable, Selectable)]
+#[diesel(belongs_to(Tag))]
+#[diesel(table_name = wiki_page)]
+#[diesel(check_for_backend(Pg))]
+pub struct WikiPage {
+    pub id: i64,
+    pub title: Option<SmallString>,
+    pub tag_id: Option<i64>,
+    pub body: LargeString,
+    pub creation_time: DateTime,
+    pub last_edit_time: DateTime,
+}
@@ -0,0 +1,100 @@
+//! Notifications are created alongside snapshots whenever someone acts on content
+//! owned by another user. Users are never notified of their own actions, and no
+//! notifications are created for kinds a recipient has muted.
+
+use crate::auth::Client;
+use crate::model::comment::Comment;
+use crate::model::enums::NotificationKind;
+use crate::model::notification::NewNotification;
+use crate::schema::{comment, muted_notification, notification, post};
+use diesel::{ExpressionMethods, Insertable, OptionalExtension, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
+
+/// Notifies the uploader of the post associated with `post_id` of an event of the given `kind`.
+pub fn notify_uploader(
+    conn: &mut PgConnection,
+    client: Client,
+    post_id: i64,
+    kind: NotificationKind,
+) -> QueryResult<()> {
+    let uploader_id = post::table
+        .find(post_id)
+        .select(post::user_id)
+        .first::<Option<i64>>(conn)
+        .optional()?
+        .flatten();
+    match uploader_id {
+        Some(uploader_id) => send(conn, client, kind, &[uploader_id], Some(post_id), None),
+        None => Ok(()),
+    }
+}
+
+/// Notifies the author of the comment that `comment` replies to, if any, and the `mentioned_users`.
+/// A user who is both replied to and mentioned is only notified of the reply.
+pub fn notify_reply_and_mentions(
+    conn: &mut PgConnection,
+    client: Client,
+    comment: &Comment,
+    mentioned_users: &[i64],
+) -> QueryResult<()> {
+    let parent_author_id: Option<i64> = match comment.parent_comment_id {
+        Some(parent_id) => comment::table
+            .find(parent_id)
+            .select(comment::user_id)
+            .first::<Option<i64>>(conn)
+            .optional()?
+            .flatten(),
+        None => None,
+    };
+    let Some(parent_author_id) = parent_author_id else {
+        return notify_mentions(conn, client, comment, mentioned_users);
+    };
+
+    send(conn, client, NotificationKind::Reply, &[parent_author_id], Some(comment.post_id), Some(comment.id))?;
+    let mentioned_users: Vec<i64> = mentioned_users
+        .iter()
+        .copied()
+        .filter(|&user_id| user_id != parent_author_id)
+        .collect();
+    notify_mentions(conn, client, comment, &mentioned_users)
+}
+
+/// Notifies the `mentioned_users` that they were mentioned in `comment`.
+pub fn notify_mentions(
+    conn: &mut PgConnection,
+    client: Client,
+    comment: &Comment,
+    mentioned_users: &[i64],
+) -> QueryResult<()> {
+    send(conn, client, NotificationKind::Mention, mentioned_users, Some(comment.post_id), Some(comment.id))
+}
+
+/// Creates a notification of the given `kind` for each of the `recipients`,
+/// excluding the client and any recipients who have muted `kind`.
+fn send(
+    conn: &mut PgConnection,
+    client: Client,
+    kind: NotificationKind,
+    recipients: &[i64],
+    post_id: Option<i64>,
+    comment_id: Option<i64>,
+) -> QueryResult<()> {
+    let muted_recipients: Vec<i64> = muted_notification::table
+        .select(muted_notification::user_id)
+        .filter(muted_notification::kind.eq(kind))
+        .filter(muted_notification::user_id.eq_any(recipients))
+        .load(conn)?;
+    let new_notifications: Vec<_> = recipients
+        .iter()
+        .copied()
+        .filter(|&user_id| Some(user_id) != client.id && !muted_recipients.contains(&user_id))
+        .map(|user_id| NewNotification {
+            user_id,
+            kind,
+            actor_id: client.id,
+            post_id,
+            comment_id,
+        })
+        .collect();
+    new_notifications.insert_into(notification::table).execute(conn)?;
+    Ok(())
+}
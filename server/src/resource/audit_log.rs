@@ -0,0 +1,86 @@
+use crate::model::audit_log::AuditLogEntry;
+use crate::model::enums::AuditEvent;
+use crate::resource;
+use crate::resource::field::Mask;
+use crate::schema::audit_log;
+use crate::time::DateTime;
+use diesel::{ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use server_macros::non_nullable_options;
+use strum::EnumString;
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy, EnumString)]
+#[strum(serialize_all = "camelCase")]
+pub enum Field {
+    Id,
+    Event,
+    User,
+    Target,
+    Details,
+    IpAddress,
+    UserAgent,
+    CreationTime,
+}
+
+impl From<Field> for u64 {
+    fn from(value: Field) -> Self {
+        value as u64
+    }
+}
+
+/// A security-relevant event recorded in the audit log.
+#[non_nullable_options]
+#[skip_serializing_none]
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditLogEntryInfo {
+    /// The entry identifier.
+    id: Option<i64>,
+    /// What kind of event happened.
+    event: Option<AuditEvent>,
+    /// Name of the user involved in the event at the time it happened, or null if anonymous.
+    /// For failed authentication attempts, this is the name the client tried to log in as.
+    #[schema(nullable)]
+    user: Option<Option<String>>,
+    /// Name of the user or route that was acted on, if any.
+    #[schema(nullable)]
+    target: Option<Option<String>>,
+    /// Additional information about the event, like the reason authentication failed.
+    #[schema(nullable)]
+    details: Option<Option<String>>,
+    /// IP address the request came from, if known.
+    #[schema(nullable)]
+    ip_address: Option<Option<String>>,
+    /// User agent of the client that sent the request, if any.
+    #[schema(nullable)]
+    user_agent: Option<Option<String>>,
+    /// Time the event happened.
+    creation_time: Option<DateTime>,
+}
+
+impl AuditLogEntryInfo {
+    pub fn new(entry: AuditLogEntry, fields: Mask<Field>) -> Self {
+        Self {
+            id: fields[Field::Id].then_some(entry.id),
+            event: fields[Field::Event].then_some(entry.event),
+            user: fields[Field::User].then_some(entry.user_name),
+            target: fields[Field::Target].then_some(entry.target),
+            details: fields[Field::Details].then_some(entry.details),
+            ip_address: fields[Field::IpAddress].then_some(entry.ip_address),
+            user_agent: fields[Field::UserAgent].then_some(entry.user_agent),
+            creation_time: fields[Field::CreationTime].then_some(entry.creation_time),
+        }
+    }
+
+    pub fn new_batch_from_ids(
+        conn: &mut PgConnection,
+        entry_ids: &[i64],
+        fields: Mask<Field>,
+    ) -> QueryResult<Vec<Self>> {
+        let unordered_entries = audit_log::table.filter(audit_log::id.eq_any(entry_ids)).load(conn)?;
+        let entries = resource::order_as(unordered_entries, entry_ids);
+        Ok(entries.into_iter().map(|entry| Self::new(entry, fields)).collect())
+    }
+}
@@ -1,18 +1,20 @@
 use crate::app::Context;
 use crate::auth::Client;
 use crate::config::Config;
+use crate::markdown;
 use crate::model::comment::{Comment, CommentScore};
-use crate::model::enums::Rating;
+use crate::model::enums::{AvatarStyle, Rating};
 use crate::resource;
 use crate::resource::field::{Batcher, Mask};
 use crate::resource::user::MicroUser;
-use crate::schema::{comment, comment_score, comment_statistics, user};
+use crate::schema::{comment, comment_mention, comment_score, comment_statistics, user};
 use crate::string::{LargeString, SmallString, lower};
 use crate::time::DateTime;
 use diesel::{BelongingToDsl, ExpressionMethods, Identifiable, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
 use serde::Serialize;
 use serde_with::skip_serializing_none;
 use server_macros::non_nullable_options;
+use std::collections::HashMap;
 use strum::EnumString;
 use utoipa::ToSchema;
 
@@ -22,10 +24,13 @@ pub enum Field {
     Version,
     Id,
     PostId,
+    ParentId,
     Text,
+    Html,
     CreationTime,
     LastEditTime,
     User,
+    Mentions,
     Score,
     OwnScore,
 }
@@ -48,8 +53,14 @@ pub struct CommentInfo {
     pub id: Option<i64>,
     /// ID of the post the comment is for.
     pub post_id: Option<i64>,
+    /// ID of the comment this comment is a reply to, or null if it isn't a reply.
+    #[schema(nullable)]
+    pub parent_id: Option<Option<i64>>,
     /// The comment content. The client should render this as Markdown.
     pub text: Option<LargeString>,
+    /// The comment content rendered as sanitized HTML. Only a safe subset of Markdown is supported:
+    /// paragraphs, block quotes, lists, code, emphasis, strikethrough, http(s) links, and mentions.
+    pub html: Option<String>,
     /// Time the comment was created.
     pub creation_time: Option<DateTime>,
     /// Time the comment was last edited.
@@ -57,6 +68,8 @@ pub struct CommentInfo {
     /// A micro user resource for the comment author, or null if anonymous.
     #[schema(nullable)]
     pub user: Option<Option<MicroUser>>,
+    /// Micro user resources for the users mentioned in the comment, sorted by name.
+    pub mentions: Option<Vec<MicroUser>>,
     /// The collective score (+1/-1 rating) of the comment.
     pub score: Option<i64>,
     /// The score (+1/-1 rating) of the comment by the authenticated user.
@@ -85,22 +98,34 @@ impl CommentInfo {
     ) -> QueryResult<Vec<Self>> {
         let f = Batcher::new(fields, comments.len());
         let mut owners = f.exec(Field::User, || get_owners(conn, &ctx.config, &comments))?;
+        let mut mentions = if fields[Field::Html] || fields[Field::Mentions] {
+            let comment_ids: Vec<_> = comments.iter().map(Identifiable::id).copied().collect();
+            get_mentions(conn, &comment_ids)?
+        } else {
+            HashMap::new()
+        };
         let mut scores = f.exec(Field::Score, || get_scores(conn, &comments))?;
         let mut own_scores = f.exec(Field::OwnScore, || get_own_scores(conn, ctx.client, &comments))?;
 
         let mut results = comments
             .into_iter()
             .rev()
-            .map(|comment| Self {
-                version: fields[Field::Version].then_some(comment.last_edit_time),
-                id: fields[Field::Id].then_some(comment.id),
-                post_id: fields[Field::PostId].then_some(comment.post_id),
-                text: fields[Field::Text].then_some(comment.text),
-                creation_time: fields[Field::CreationTime].then_some(comment.creation_time),
-                last_edit_time: fields[Field::LastEditTime].then_some(comment.last_edit_time),
-                user: owners.pop(),
-                score: scores.pop(),
-                own_score: own_scores.pop(),
+            .map(|comment| {
+                let mentioned_users = mentions.remove(&comment.id).unwrap_or_default();
+                Self {
+                    version: fields[Field::Version].then_some(comment.last_edit_time),
+                    id: fields[Field::Id].then_some(comment.id),
+                    post_id: fields[Field::PostId].then_some(comment.post_id),
+                    parent_id: fields[Field::ParentId].then_some(comment.parent_comment_id),
+                    html: fields[Field::Html].then(|| render_html(&comment.text, &mentioned_users)),
+                    text: fields[Field::Text].then_some(comment.text),
+                    creation_time: fields[Field::CreationTime].then_some(comment.creation_time),
+                    last_edit_time: fields[Field::LastEditTime].then_some(comment.last_edit_time),
+                    user: owners.pop(),
+                    mentions: fields[Field::Mentions].then(|| micro_users(&ctx.config, mentioned_users)),
+                    score: scores.pop(),
+                    own_score: own_scores.pop(),
+                }
             })
             .collect::<Vec<_>>();
         results.reverse();
@@ -119,6 +144,43 @@ impl CommentInfo {
     }
 }
 
+/// Name, lowercase name, and avatar style of a user mentioned in a comment.
+pub type MentionedUser = (SmallString, SmallString, AvatarStyle);
+
+/// Retrieves the users mentioned in each of the comments with the given `comment_ids`, sorted by name.
+pub fn get_mentions(conn: &mut PgConnection, comment_ids: &[i64]) -> QueryResult<HashMap<i64, Vec<MentionedUser>>> {
+    let mentions: Vec<(i64, MentionedUser)> = comment_mention::table
+        .inner_join(user::table)
+        .select((comment_mention::comment_id, (user::name, lower(user::name), user::avatar_style)))
+        .filter(comment_mention::comment_id.eq_any(comment_ids))
+        .order(user::name)
+        .load(conn)?;
+
+    let mut mentioned_users: HashMap<i64, Vec<MentionedUser>> = HashMap::new();
+    for (comment_id, mentioned_user) in mentions {
+        mentioned_users.entry(comment_id).or_default().push(mentioned_user);
+    }
+    Ok(mentioned_users)
+}
+
+/// Renders comment `text` as HTML, linking mentions of the `mentioned_users`.
+pub fn render_html(text: &str, mentioned_users: &[MentionedUser]) -> String {
+    markdown::render(text, &|name| {
+        let lowercase_name = name.to_lowercase();
+        mentioned_users
+            .iter()
+            .any(|(_, lowercase_mention, _)| **lowercase_mention == lowercase_name)
+    })
+}
+
+/// Converts mentioned `users` into micro user resources.
+pub fn micro_users(config: &Config, users: Vec<MentionedUser>) -> Vec<MicroUser> {
+    users
+        .into_iter()
+        .map(|(name, lowercase_name, avatar_style)| MicroUser::new(config, name, &lowercase_name, avatar_style))
+        .collect()
+}
+
 fn get_owners(conn: &mut PgConnection, config: &Config, comments: &[Comment]) -> QueryResult<Vec<Option<MicroUser>>> {
     let comment_ids: Vec<_> = comments.iter().map(Identifiable::id).copied().collect();
     comment::table
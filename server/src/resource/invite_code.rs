@@ -0,0 +1,61 @@
+use crate::model::invite_code::InviteCode;
+use crate::resource::field::Mask;
+use crate::resource::user::MicroUser;
+use crate::time::DateTime;
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use server_macros::non_nullable_options;
+use strum::EnumString;
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy, EnumString)]
+#[strum(serialize_all = "camelCase")]
+pub enum Field {
+    Code,
+    Creator,
+    MaxUses,
+    UseCount,
+    ExpirationTime,
+    CreationTime,
+}
+
+impl From<Field> for u64 {
+    fn from(value: Field) -> Self {
+        value as u64
+    }
+}
+
+/// A code that allows registering while the server is in `invite_code` registration mode.
+#[non_nullable_options]
+#[skip_serializing_none]
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InviteCodeInfo {
+    /// The invite code itself.
+    code: Option<String>,
+    /// A micro user resource for the user who created the code, or null if they were deleted.
+    #[schema(nullable)]
+    creator: Option<Option<MicroUser>>,
+    /// How many users can register with the code.
+    max_uses: Option<i32>,
+    /// How many users have registered with the code.
+    use_count: Option<i32>,
+    /// Time the code expires, or null if it never expires.
+    #[schema(nullable)]
+    expiration_time: Option<Option<DateTime>>,
+    /// Time the code was created.
+    creation_time: Option<DateTime>,
+}
+
+impl InviteCodeInfo {
+    pub fn new(creator: Option<MicroUser>, invite_code: InviteCode, fields: Mask<Field>) -> Self {
+        InviteCodeInfo {
+            code: fields[Field::Code].then_some(invite_code.code),
+            creator: fields[Field::Creator].then_some(creator),
+            max_uses: fields[Field::MaxUses].then_some(invite_code.max_uses),
+            use_count: fields[Field::UseCount].then_some(invite_code.use_count),
+            expiration_time: fields[Field::ExpirationTime].then_some(invite_code.expiration_time),
+            creation_time: fields[Field::CreationTime].then_some(invite_code.creation_time),
+        }
+    }
+}
@@ -3,16 +3,26 @@ use diesel::Identifiable;
 use std::collections::HashMap;
 use std::sync::Arc;
 
+pub mod audit_log;
 pub mod comment;
 pub mod field;
+pub mod invite_code;
+pub mod notification;
 pub mod pool;
 pub mod pool_category;
 pub mod post;
+pub mod report;
 pub mod snapshot;
 pub mod tag;
 pub mod tag_category;
 pub mod user;
+pub mod user_note;
+pub mod user_sanction;
+pub mod user_session;
 pub mod user_token;
+pub mod webhook_delivery;
+pub mod wiki_page;
+pub mod wiki_revision;
 
 // NOTE: The more complicated queries in this module rely on the behavior of diesel's
 // grouped_by function preserving the relative order between elements. This seems to be the
@@ -0,0 +1,126 @@
+use crate::config::Config;
+use crate::content::hash::PostHash;
+use crate::model::enums::{AvatarStyle, NotificationKind};
+use crate::model::notification::Notification;
+use crate::resource;
+use crate::resource::field::{Batcher, Mask};
+use crate::resource::post::MicroPost;
+use crate::resource::user::MicroUser;
+use crate::schema::{notification, user};
+use crate::string::{SmallString, lower};
+use crate::time::DateTime;
+use diesel::{
+    ExpressionMethods, Identifiable, JoinOnDsl, NullableExpressionMethods, PgConnection, QueryDsl, QueryResult,
+    RunQueryDsl,
+};
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use server_macros::non_nullable_options;
+use strum::EnumString;
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy, EnumString)]
+#[strum(serialize_all = "camelCase")]
+pub enum Field {
+    Id,
+    Kind,
+    Actor,
+    Post,
+    CommentId,
+    IsRead,
+    CreationTime,
+}
+
+impl From<Field> for u64 {
+    fn from(value: Field) -> Self {
+        value as u64
+    }
+}
+
+/// An event involving the authenticated user or their content.
+#[non_nullable_options]
+#[skip_serializing_none]
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationInfo {
+    /// The notification identifier.
+    id: Option<i64>,
+    /// What happened.
+    kind: Option<NotificationKind>,
+    /// A micro user resource for the user who caused the notification, or null if anonymous or deleted.
+    #[schema(nullable)]
+    actor: Option<Option<MicroUser>>,
+    /// A micro post resource for the post involved, if any.
+    #[schema(nullable)]
+    post: Option<Option<MicroPost>>,
+    /// The ID of the comment involved in replies and mentions.
+    #[schema(nullable)]
+    comment_id: Option<Option<i64>>,
+    /// Whether the notification has been marked as read.
+    is_read: Option<bool>,
+    /// Time the notification was created.
+    creation_time: Option<DateTime>,
+}
+
+impl NotificationInfo {
+    pub fn new(
+        conn: &mut PgConnection,
+        config: &Config,
+        notification: Notification,
+        fields: Mask<Field>,
+    ) -> QueryResult<Self> {
+        Self::new_batch(conn, config, vec![notification], fields).map(resource::single)
+    }
+
+    pub fn new_batch(
+        conn: &mut PgConnection,
+        config: &Config,
+        notifications: Vec<Notification>,
+        fields: Mask<Field>,
+    ) -> QueryResult<Vec<Self>> {
+        let f = Batcher::new(fields, notifications.len());
+        let mut actors = f.exec(Field::Actor, || get_actors(conn, config, &notifications))?;
+
+        let mut results = notifications
+            .into_iter()
+            .rev()
+            .map(|notification| Self {
+                id: fields[Field::Id].then_some(notification.id),
+                kind: fields[Field::Kind].then_some(notification.kind),
+                actor: actors.pop(),
+                post: fields[Field::Post].then(|| {
+                    notification.post_id.map(|post_id| MicroPost {
+                        id: post_id,
+                        thumbnail_url: PostHash::new(config, post_id, None).thumbnail_url(),
+                    })
+                }),
+                comment_id: fields[Field::CommentId].then_some(notification.comment_id),
+                is_read: fields[Field::IsRead].then_some(notification.is_read),
+                creation_time: fields[Field::CreationTime].then_some(notification.creation_time),
+            })
+            .collect::<Vec<_>>();
+        results.reverse();
+        Ok(results)
+    }
+}
+
+fn get_actors(
+    conn: &mut PgConnection,
+    config: &Config,
+    notifications: &[Notification],
+) -> QueryResult<Vec<Option<MicroUser>>> {
+    let notification_ids: Vec<_> = notifications.iter().map(Identifiable::id).collect();
+    let actor_info: Vec<(i64, SmallString, SmallString, AvatarStyle)> = notification::table
+        .inner_join(user::table.on(notification::actor_id.eq(user::id.nullable())))
+        .select((notification::id, user::name, lower(user::name), user::avatar_style))
+        .filter(notification::id.eq_any(notification_ids))
+        .load(conn)?;
+    Ok(resource::order_as_padded(actor_info, notifications, |&(id, ..)| id)
+        .into_iter()
+        .map(|actor_info| {
+            actor_info.map(|(_, name, lowercase_name, avatar_style)| {
+                MicroUser::new(config, name, &lowercase_name, avatar_style)
+            })
+        })
+        .collect())
+}
@@ -1,15 +1,18 @@
 use crate::app::Context;
+use crate::config::Action;
 use crate::content::hash::PostHash;
+use crate::model::enums::PostStatus;
 use crate::model::pool::{Pool, PoolName, PoolPost};
 use crate::resource;
 use crate::resource::field::{Batcher, Mask};
 use crate::resource::post::MicroPost;
-use crate::schema::{pool, pool_category, pool_name, pool_post, pool_statistics};
+use crate::schema::{pool, pool_category, pool_name, pool_post, pool_statistics, post};
 use crate::string::{LargeString, SmallString};
 use crate::time::DateTime;
 use diesel::dsl::{exists, not};
 use diesel::{
-    BelongingToDsl, ExpressionMethods, GroupedBy, Identifiable, PgConnection, QueryDsl, QueryResult, RunQueryDsl,
+    BelongingToDsl, BoolExpressionMethods, ExpressionMethods, GroupedBy, Identifiable, PgConnection, QueryDsl,
+    QueryResult, RunQueryDsl, SelectableHelper,
 };
 use serde::Serialize;
 use serde_with::skip_serializing_none;
@@ -161,7 +164,21 @@ fn get_names(conn: &mut PgConnection, pools: &[Pool]) -> QueryResult<Vec<Vec<Sma
 }
 
 fn get_posts(conn: &mut PgConnection, ctx: &Context, pools: &[Pool]) -> QueryResult<Vec<Vec<MicroPost>>> {
-    let mut pool_posts = PoolPost::belonging_to(pools).order(pool_post::order).into_boxed();
+    let mut pool_posts = PoolPost::belonging_to(pools)
+        .inner_join(post::table)
+        .select(PoolPost::as_select())
+        .filter(post::deletion_time.is_null())
+        .order(pool_post::order)
+        .into_boxed();
+
+    // Pending posts are only visible to their uploader and those who can approve them
+    if !ctx.has_privilege(Action::PostApprove) {
+        pool_posts = pool_posts.filter(
+            post::status
+                .eq(PostStatus::Approved)
+                .or(post::user_id.eq(ctx.client.id)),
+        );
+    }
 
     // Apply preference filters to pool posts
     if let Some(hidden_posts) = ctx.preferences().hidden_posts(pool_post::post_id) {
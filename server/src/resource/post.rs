@@ -1,11 +1,13 @@
 use crate::app::Context;
 use crate::auth::Client;
-use crate::config::Config;
-use crate::content::hash::{Checksum, Md5Checksum, PostHash};
+use crate::config::{Action, Config};
+use crate::content::hash::{Checksum, Md5Checksum, PostFileHash, PostHash};
 use crate::model::comment::Comment;
-use crate::model::enums::{AvatarStyle, MimeType, PostFlags, PostSafety, PostType, Rating, Score};
+use crate::model::enums::{
+    AvatarStyle, MimeType, PostFlags, PostSafety, PostStatus, PostType, Rating, RelationKind, Score,
+};
 use crate::model::pool::PoolPost;
-use crate::model::post::{NewPostNote, Post, PostFavorite, PostNote, PostRelation, PostScore, PostTag};
+use crate::model::post::{NewPostNote, Post, PostFavorite, PostFile, PostNote, PostRelation, PostScore, PostTag};
 use crate::model::tag::TagName;
 use crate::post_stats;
 use crate::resource;
@@ -16,14 +18,14 @@ use crate::resource::tag::MicroTag;
 use crate::resource::user::MicroUser;
 use crate::schema::{
     comment, comment_score, comment_statistics, pool, pool_category, pool_name, pool_statistics, post, post_favorite,
-    post_note, post_relation, post_score, tag, tag_category, tag_name, tag_statistics, user,
+    post_file, post_note, post_relation, post_score, tag, tag_category, tag_name, tag_statistics, user,
 };
 use crate::string::{LargeString, SmallString, lower};
 use crate::time::DateTime;
 use diesel::dsl::{exists, not};
 use diesel::{
-    BelongingToDsl, ExpressionMethods, GroupedBy, Identifiable, NullableExpressionMethods, PgConnection, QueryDsl,
-    QueryResult, RunQueryDsl, SelectableHelper,
+    BelongingToDsl, BoolExpressionMethods, ExpressionMethods, GroupedBy, Identifiable, JoinOnDsl,
+    NullableExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl, SelectableHelper,
 };
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
@@ -85,6 +87,51 @@ pub struct MicroPost {
     pub thumbnail_url: String,
 }
 
+/// An additional content file owned by a post.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PostFileInfo {
+    /// The post file identifier.
+    id: i64,
+    /// The size of the file in bytes.
+    file_size: i64,
+    /// The original width of the file content.
+    canvas_width: i32,
+    /// The original height of the file content.
+    canvas_height: i32,
+    /// The type of the file.
+    type_: PostType,
+    /// Used to tell exact content format.
+    mime_type: MimeType,
+    /// The BLAKE3 file checksum.
+    checksum: Checksum,
+    /// The MD5 file checksum.
+    #[serde(rename = "checksumMD5")]
+    checksum_md5: Md5Checksum,
+    /// Where the file content is located.
+    content_url: String,
+    /// Where the file thumbnail is located.
+    thumbnail_url: String,
+}
+
+impl PostFileInfo {
+    fn new(config: &Config, post_file: PostFile) -> Self {
+        let post_file_hash = PostFileHash::new(config, post_file.id);
+        Self {
+            id: post_file.id,
+            file_size: post_file.file_size,
+            canvas_width: post_file.width,
+            canvas_height: post_file.height,
+            type_: post_file.type_,
+            mime_type: post_file.mime_type,
+            checksum: post_file.checksum,
+            checksum_md5: post_file.checksum_md5,
+            content_url: post_file_hash.content_url(post_file.mime_type),
+            thumbnail_url: post_file_hash.thumbnail_url(),
+        }
+    }
+}
+
 #[derive(Clone, Copy, EnumString)]
 #[strum(serialize_all = "camelCase")]
 pub enum Field {
@@ -103,13 +150,17 @@ pub enum Field {
     Flags,
     Source,
     Description,
+    Status,
     CreationTime,
     LastEditTime,
     ContentUrl,
     ThumbnailUrl,
+    Files,
     Tags,
     Comments,
     Relations,
+    Parent,
+    Children,
     Pools,
     Notes,
     Score,
@@ -168,6 +219,8 @@ pub struct PostInfo {
     source: Option<LargeString>,
     /// Text description for the post. The client should render is as Markdown.
     description: Option<LargeString>,
+    /// Whether the post has been approved or is awaiting moderation.
+    status: Option<PostStatus>,
     /// Time the tag was created.
     creation_time: Option<DateTime>,
     /// Time the tag was last edited.
@@ -176,12 +229,19 @@ pub struct PostInfo {
     content_url: Option<String>,
     /// Where the post thumbnail is located.
     thumbnail_url: Option<String>,
+    /// Ordered list of additional content files. The post content itself comes first.
+    files: Option<Vec<PostFileInfo>>,
     /// List of tags the post is tagged with.
     tags: Option<Vec<MicroTag>>,
     /// List of comments under the post.
     comments: Option<Vec<CommentInfo>>,
     /// List of related posts. Links to related posts are shown to the user by the web client.
     relations: Option<Vec<MicroPost>>,
+    /// The parent of the post in a post hierarchy.
+    #[schema(nullable)]
+    parent: Option<Option<MicroPost>>,
+    /// List of posts that have this post as their parent.
+    children: Option<Vec<MicroPost>>,
     /// List of pools the post is a member of.
     pools: Option<Vec<MicroPool>>,
     /// List of post annotations.
@@ -235,9 +295,12 @@ impl PostInfo {
         let mut owners = f.exec(Field::User, || get_owners(conn, &ctx.config, &posts))?;
         let Ok(mut content_urls) = f.exec(Field::ContentUrl, || get_content_urls(&ctx.config, &posts));
         let Ok(mut thumbnail_urls) = f.exec(Field::ThumbnailUrl, || get_thumbnail_urls(&ctx.config, &posts));
+        let mut files = f.exec(Field::Files, || get_files(conn, &ctx.config, &posts))?;
         let mut tags = f.exec(Field::Tags, || get_tags(conn, &posts))?;
         let mut comments = f.exec(Field::Comments, || get_comments(conn, ctx, &posts))?;
-        let mut relations = f.exec(Field::Relations, || get_relations(conn, ctx, &posts))?;
+        let mut relations = f.exec(Field::Relations, || get_relations(conn, ctx, &posts, RelationKind::Symmetric))?;
+        let mut parents = f.exec(Field::Parent, || get_parents(conn, ctx, &posts))?;
+        let mut children = f.exec(Field::Children, || get_relations(conn, ctx, &posts, RelationKind::Directed))?;
         let mut pools = f.exec(Field::Pools, || get_pools(conn, &posts))?;
         let mut notes = f.exec(Field::Notes, || get_notes(conn, &posts))?;
         let mut scores = f.exec(Field::Score, || post_stats!(conn, &posts, score, i64))?;
@@ -271,12 +334,16 @@ impl PostInfo {
                 flags: fields[Field::Flags].then_some(post.flags),
                 source: fields[Field::Source].then_some(post.source),
                 description: fields[Field::Description].then_some(post.description),
+                status: fields[Field::Status].then_some(post.status),
                 creation_time: fields[Field::CreationTime].then_some(post.creation_time),
                 last_edit_time: fields[Field::LastEditTime].then_some(post.last_edit_time),
                 content_url: content_urls.pop(),
                 thumbnail_url: thumbnail_urls.pop(),
+                files: files.pop(),
                 tags: tags.pop(),
                 relations: relations.pop(),
+                parent: parents.pop(),
+                children: children.pop(),
                 notes: notes.pop(),
                 score: scores.pop(),
                 own_score: own_scores.pop(),
@@ -349,6 +416,22 @@ fn get_thumbnail_urls(config: &Config, posts: &[Post]) -> Result<Vec<String>, In
         .collect())
 }
 
+fn get_files(conn: &mut PgConnection, config: &Config, posts: &[Post]) -> QueryResult<Vec<Vec<PostFileInfo>>> {
+    Ok(PostFile::belonging_to(posts)
+        .select(PostFile::as_select())
+        .order(post_file::order)
+        .load(conn)?
+        .grouped_by(posts)
+        .into_iter()
+        .map(|post_files| {
+            post_files
+                .into_iter()
+                .map(|post_file| PostFileInfo::new(config, post_file))
+                .collect()
+        })
+        .collect())
+}
+
 fn get_tags(conn: &mut PgConnection, posts: &[Post]) -> QueryResult<Vec<Vec<MicroTag>>> {
     let tag_info = tag::table
         .inner_join(tag_statistics::table)
@@ -404,6 +487,7 @@ fn get_comments(conn: &mut PgConnection, ctx: &Context, posts: &[Post]) -> Query
         .order(comment::creation_time)
         .load(conn)?;
     let comment_ids: Vec<i64> = comments.iter().map(|(comment, ..)| comment.id).collect();
+    let mut mentions = resource::comment::get_mentions(conn, &comment_ids)?;
 
     let client_scores: HashMap<i64, Score> = ctx
         .client
@@ -428,13 +512,17 @@ fn get_comments(conn: &mut PgConnection, ctx: &Context, posts: &[Post]) -> Query
                 .into_iter()
                 .map(|(comment, score, owner)| {
                     let id = comment.id;
+                    let mentioned_users = mentions.remove(&id).unwrap_or_default();
                     CommentInfo {
                         version: Some(comment.last_edit_time),
                         id: Some(id),
                         post_id: Some(post.id),
+                        parent_id: Some(comment.parent_comment_id),
                         user: Some(owner.map(|(username, lowercase_username, avatar_style)| {
                             MicroUser::new(&ctx.config, username, &lowercase_username, avatar_style)
                         })),
+                        html: Some(resource::comment::render_html(&comment.text, &mentioned_users)),
+                        mentions: Some(resource::comment::micro_users(&ctx.config, mentioned_users)),
                         text: Some(comment.text),
                         creation_time: Some(comment.creation_time),
                         last_edit_time: Some(comment.last_edit_time),
@@ -447,11 +535,29 @@ fn get_comments(conn: &mut PgConnection, ctx: &Context, posts: &[Post]) -> Query
         .collect())
 }
 
-fn get_relations(conn: &mut PgConnection, ctx: &Context, posts: &[Post]) -> QueryResult<Vec<Vec<MicroPost>>> {
+fn get_relations(
+    conn: &mut PgConnection,
+    ctx: &Context,
+    posts: &[Post],
+    kind: RelationKind,
+) -> QueryResult<Vec<Vec<MicroPost>>> {
     let mut related_posts = PostRelation::belonging_to(posts)
+        .inner_join(post::table.on(post::id.eq(post_relation::child_id)))
+        .select(PostRelation::as_select())
+        .filter(post_relation::kind.eq(kind))
+        .filter(post::deletion_time.is_null())
         .order(post_relation::child_id)
         .into_boxed();
 
+    // Pending posts are only visible to their uploader and those who can approve them
+    if !ctx.has_privilege(Action::PostApprove) {
+        related_posts = related_posts.filter(
+            post::status
+                .eq(PostStatus::Approved)
+                .or(post::user_id.eq(ctx.client.id)),
+        );
+    }
+
     // Apply preference filters to post relations
     if let Some(hidden_posts) = ctx.preferences().hidden_posts(post_relation::child_id) {
         related_posts = related_posts.filter(not(exists(hidden_posts)));
@@ -473,6 +579,42 @@ fn get_relations(conn: &mut PgConnection, ctx: &Context, posts: &[Post]) -> Quer
         .collect())
 }
 
+fn get_parents(conn: &mut PgConnection, ctx: &Context, posts: &[Post]) -> QueryResult<Vec<Option<MicroPost>>> {
+    let post_ids: Vec<_> = posts.iter().map(Identifiable::id).copied().collect();
+    let mut parents = post_relation::table
+        .inner_join(post::table)
+        .select((post_relation::child_id, post_relation::parent_id))
+        .filter(post_relation::child_id.eq_any(&post_ids))
+        .filter(post_relation::kind.eq(RelationKind::Directed))
+        .filter(post::deletion_time.is_null())
+        .into_boxed();
+
+    // Pending posts are only visible to their uploader and those who can approve them
+    if !ctx.has_privilege(Action::PostApprove) {
+        parents = parents.filter(
+            post::status
+                .eq(PostStatus::Approved)
+                .or(post::user_id.eq(ctx.client.id)),
+        );
+    }
+
+    // Apply preference filters to post relations
+    if let Some(hidden_posts) = ctx.preferences().hidden_posts(post_relation::parent_id) {
+        parents = parents.filter(not(exists(hidden_posts)));
+    }
+
+    let parents: Vec<(i64, i64)> = parents.load(conn)?;
+    Ok(resource::order_as_padded(parents, posts, |&(child_id, _)| child_id)
+        .into_iter()
+        .map(|parent| {
+            parent.map(|(_, parent_id)| MicroPost {
+                id: parent_id,
+                thumbnail_url: PostHash::new(&ctx.config, parent_id, None).thumbnail_url(),
+            })
+        })
+        .collect())
+}
+
 fn get_pools(conn: &mut PgConnection, posts: &[Post]) -> QueryResult<Vec<Vec<MicroPool>>> {
     let pool_posts: Vec<(PoolPost, i64, i64)> = PoolPost::belonging_to(posts)
         .inner_join(pool::table.inner_join(pool_statistics::table))
@@ -0,0 +1,155 @@
+use crate::config::Config;
+use crate::model::enums::{AvatarStyle, ReportStatus, ResourceType};
+use crate::model::report::Report;
+use crate::resource;
+use crate::resource::field::{Batcher, Mask};
+use crate::resource::user::MicroUser;
+use crate::schema::{report, user};
+use crate::string::{LargeString, SmallString, lower};
+use crate::time::DateTime;
+use diesel::{
+    ExpressionMethods, Identifiable, JoinOnDsl, NullableExpressionMethods, PgConnection, QueryDsl, QueryResult,
+    RunQueryDsl,
+};
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use server_macros::non_nullable_options;
+use strum::EnumString;
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy, EnumString)]
+#[strum(serialize_all = "camelCase")]
+pub enum Field {
+    Id,
+    User,
+    Type,
+    ResourceId,
+    Reason,
+    Status,
+    Resolver,
+    CreationTime,
+    ResolutionTime,
+}
+
+impl From<Field> for u64 {
+    fn from(value: Field) -> Self {
+        value as u64
+    }
+}
+
+/// A user-submitted report about a problematic post, comment, tag, or user.
+#[non_nullable_options]
+#[skip_serializing_none]
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportInfo {
+    /// The report identifier.
+    pub id: Option<i64>,
+    /// A micro user resource for the user who filed the report, or null if anonymous.
+    #[schema(nullable)]
+    pub user: Option<Option<MicroUser>>,
+    /// The type of the reported resource.
+    #[serde(rename = "type")]
+    pub resource_type: Option<ResourceType>,
+    /// The ID of the reported resource.
+    pub resource_id: Option<SmallString>,
+    /// Why the resource was reported.
+    pub reason: Option<LargeString>,
+    /// Whether the report is open, resolved, or dismissed.
+    pub status: Option<ReportStatus>,
+    /// A micro user resource for the moderator who handled the report, or null if still open.
+    #[schema(nullable)]
+    pub resolver: Option<Option<MicroUser>>,
+    /// Time the report was filed.
+    pub creation_time: Option<DateTime>,
+    /// Time the report was resolved or dismissed.
+    #[schema(nullable)]
+    pub resolution_time: Option<Option<DateTime>>,
+}
+
+impl ReportInfo {
+    pub fn new_from_id(
+        conn: &mut PgConnection,
+        config: &Config,
+        report_id: i64,
+        fields: Mask<Field>,
+    ) -> QueryResult<Self> {
+        Self::new_batch_from_ids(conn, config, &[report_id], fields).map(resource::single)
+    }
+
+    pub fn new_batch(
+        conn: &mut PgConnection,
+        config: &Config,
+        reports: Vec<Report>,
+        fields: Mask<Field>,
+    ) -> QueryResult<Vec<Self>> {
+        let f = Batcher::new(fields, reports.len());
+        let mut users = f.exec(Field::User, || get_users(conn, config, &reports))?;
+        let mut resolvers = f.exec(Field::Resolver, || get_resolvers(conn, config, &reports))?;
+
+        let mut results = reports
+            .into_iter()
+            .rev()
+            .map(|report| Self {
+                id: fields[Field::Id].then_some(report.id),
+                user: users.pop(),
+                resource_type: fields[Field::Type].then_some(report.resource_type),
+                resource_id: fields[Field::ResourceId].then_some(report.resource_id),
+                reason: fields[Field::Reason].then_some(report.reason),
+                status: fields[Field::Status].then_some(report.status),
+                resolver: resolvers.pop(),
+                creation_time: fields[Field::CreationTime].then_some(report.creation_time),
+                resolution_time: fields[Field::ResolutionTime].then_some(report.resolution_time),
+            })
+            .collect::<Vec<_>>();
+        results.reverse();
+        Ok(results)
+    }
+
+    pub fn new_batch_from_ids(
+        conn: &mut PgConnection,
+        config: &Config,
+        report_ids: &[i64],
+        fields: Mask<Field>,
+    ) -> QueryResult<Vec<Self>> {
+        let unordered_reports = report::table.filter(report::id.eq_any(report_ids)).load(conn)?;
+        let reports = resource::order_as(unordered_reports, report_ids);
+        Self::new_batch(conn, config, reports, fields)
+    }
+}
+
+fn get_users(conn: &mut PgConnection, config: &Config, reports: &[Report]) -> QueryResult<Vec<Option<MicroUser>>> {
+    let report_ids: Vec<_> = reports.iter().map(Identifiable::id).collect();
+    report::table
+        .inner_join(user::table)
+        .select((report::id, user::name, lower(user::name), user::avatar_style))
+        .filter(report::id.eq_any(report_ids))
+        .load(conn)
+        .map(|user_info| collect_micro_users(config, user_info, reports))
+}
+
+fn get_resolvers(conn: &mut PgConnection, config: &Config, reports: &[Report]) -> QueryResult<Vec<Option<MicroUser>>> {
+    let report_ids: Vec<_> = reports.iter().map(Identifiable::id).collect();
+    report::table
+        .inner_join(user::table.on(report::resolver_id.eq(user::id.nullable())))
+        .select((report::id, user::name, lower(user::name), user::avatar_style))
+        .filter(report::id.eq_any(report_ids))
+        .load(conn)
+        .map(|resolver_info| collect_micro_users(config, resolver_info, reports))
+}
+
+/// Converts user info into micro users, ordered by `reports`.
+fn collect_micro_users(
+    config: &Config,
+    user_info: Vec<(i64, SmallString, SmallString, AvatarStyle)>,
+    reports: &[Report],
+) -> Vec<Option<MicroUser>> {
+    resource::order_as_padded(user_info, reports, |&(id, ..)| id)
+        .into_iter()
+        .map(|user_info| {
+            user_info.map(|(_, name, lowercase_name, avatar_style)| {
+                MicroUser::new(config, name, &lowercase_name, avatar_style)
+            })
+        })
+        .collect()
+}
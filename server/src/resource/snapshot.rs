@@ -44,6 +44,7 @@ impl From<Field> for u64 {
 ///     - `"modified"` - the resource has been modified
 ///     - `"deleted"` - the resource has been deleted
 ///     - `"merged"` - the resource has been merged to another resource
+///     - `"restored"` - the resource has been restored after deletion
 ///
 /// - `<resource-type>` and `<resource-id>`: the resource that was changed.
 ///
@@ -56,6 +57,10 @@ impl From<Field> for u64 {
 ///     | `"post"`          | post ID                          |
 ///     | `"pool"`          | pool ID                          |
 ///     | `"pool_category"` | pool category name at given time |
+///     | `"report"`        | report ID                        |
+///     | `"user"`          | user name at given time          |
+///     | `"wiki_page"`     | wiki page ID                     |
+///     | `"comment"`       | comment ID                       |
 ///
 /// - `<issuer>`: the user who made the change.
 ///
@@ -136,6 +141,30 @@ impl From<Field> for u64 {
 ///     }
 ///     ```
 ///
+/// - Wiki page snapshot data (`<resource-type> = "wiki_page"`)
+///
+///     *Example*
+///
+///     ```json5
+///     {
+///         "title": null,
+///         "tag":   "tag1",
+///         "body":  "See also [[tag2]] and [[post:3]]."
+///     }
+///     ```
+///
+/// - Comment snapshot data (`<resource-type> = "comment"`)
+///
+///     *Example*
+///
+///     ```json5
+///     {
+///         "post":   3,
+///         "parent": null,
+///         "text":   "Nice post!"
+///     }
+///     ```
+///
 ///
 /// **`<data>` field for modification snapshots**
 ///
@@ -143,6 +172,10 @@ impl From<Field> for u64 {
 /// resource and its current version. Its structure is a `<dictionary-diff>` of
 /// dictionaries as created by creation snapshots, which is described below.
 ///
+/// Users have no creation snapshots. Their modification snapshots diff a
+/// `sanctions` list of `{id, type, expirationTime}` objects and a `notes` list of
+/// note IDs. Sanction reasons and note contents are left out.
+///
 /// `<primitive>`: any primitive (number or a string)
 ///
 /// `<anything>`: any dictionary, list or primitive
@@ -227,6 +260,10 @@ impl From<Field> for u64 {
 /// dealt with by configuring role privileges in the config) or replace database
 /// backups.
 ///
+/// **`<data>` field for restoration snapshots**
+///
+/// Same as creation snapshot. Only posts can currently be restored.
+///
 /// **`<data>` field for merge snapshots**
 ///
 /// A tuple containing 2 elements:
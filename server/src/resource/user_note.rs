@@ -0,0 +1,129 @@
+use crate::config::Config;
+use crate::model::enums::AvatarStyle;
+use crate::model::user::UserNote;
+use crate::resource;
+use crate::resource::field::{Batcher, Mask};
+use crate::resource::user::MicroUser;
+use crate::schema::{user, user_note};
+use crate::string::{LargeString, SmallString, lower};
+use crate::time::DateTime;
+use diesel::{
+    ExpressionMethods, Identifiable, JoinOnDsl, NullableExpressionMethods, PgConnection, QueryDsl, QueryResult,
+    RunQueryDsl,
+};
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use server_macros::non_nullable_options;
+use strum::EnumString;
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy, EnumString)]
+#[strum(serialize_all = "camelCase")]
+pub enum Field {
+    Id,
+    User,
+    Author,
+    Text,
+    CreationTime,
+}
+
+impl From<Field> for u64 {
+    fn from(value: Field) -> Self {
+        value as u64
+    }
+}
+
+/// A moderator-only note attached to a user.
+#[non_nullable_options]
+#[skip_serializing_none]
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserNoteInfo {
+    /// The note identifier.
+    pub id: Option<i64>,
+    /// A micro user resource for the user the note is attached to.
+    pub user: Option<MicroUser>,
+    /// A micro user resource for the moderator who wrote the note, or null if they were deleted.
+    #[schema(nullable)]
+    pub author: Option<Option<MicroUser>>,
+    /// The note contents.
+    pub text: Option<LargeString>,
+    /// Time the note was written.
+    pub creation_time: Option<DateTime>,
+}
+
+impl UserNoteInfo {
+    pub fn new_from_id(
+        conn: &mut PgConnection,
+        config: &Config,
+        note_id: i64,
+        fields: Mask<Field>,
+    ) -> QueryResult<Self> {
+        let note = user_note::table.find(note_id).first(conn)?;
+        Self::new_batch(conn, config, vec![note], fields).map(resource::single)
+    }
+
+    pub fn new_batch(
+        conn: &mut PgConnection,
+        config: &Config,
+        notes: Vec<UserNote>,
+        fields: Mask<Field>,
+    ) -> QueryResult<Vec<Self>> {
+        let f = Batcher::new(fields, notes.len());
+        let mut users = f.exec(Field::User, || get_users(conn, config, &notes))?;
+        let mut authors = f.exec(Field::Author, || get_authors(conn, config, &notes))?;
+
+        let mut results = notes
+            .into_iter()
+            .rev()
+            .map(|note| Self {
+                id: fields[Field::Id].then_some(note.id),
+                user: users.pop(),
+                author: authors.pop(),
+                text: fields[Field::Text].then_some(note.text),
+                creation_time: fields[Field::CreationTime].then_some(note.creation_time),
+            })
+            .collect::<Vec<_>>();
+        results.reverse();
+        Ok(results)
+    }
+}
+
+fn get_users(conn: &mut PgConnection, config: &Config, notes: &[UserNote]) -> QueryResult<Vec<MicroUser>> {
+    let note_ids: Vec<_> = notes.iter().map(Identifiable::id).collect();
+    let user_info = user_note::table
+        .inner_join(user::table)
+        .select((user_note::id, user::name, lower(user::name), user::avatar_style))
+        .filter(user_note::id.eq_any(note_ids))
+        .load(conn)?;
+    Ok(collect_micro_users(config, user_info, notes)
+        .into_iter()
+        .map(|user| user.expect("Note owner must exist"))
+        .collect())
+}
+
+fn get_authors(conn: &mut PgConnection, config: &Config, notes: &[UserNote]) -> QueryResult<Vec<Option<MicroUser>>> {
+    let note_ids: Vec<_> = notes.iter().map(Identifiable::id).collect();
+    user_note::table
+        .inner_join(user::table.on(user_note::author_id.eq(user::id.nullable())))
+        .select((user_note::id, user::name, lower(user::name), user::avatar_style))
+        .filter(user_note::id.eq_any(note_ids))
+        .load(conn)
+        .map(|author_info| collect_micro_users(config, author_info, notes))
+}
+
+/// Converts user info into micro users, ordered by `notes`.
+fn collect_micro_users(
+    config: &Config,
+    user_info: Vec<(i64, SmallString, SmallString, AvatarStyle)>,
+    notes: &[UserNote],
+) -> Vec<Option<MicroUser>> {
+    resource::order_as_padded(user_info, notes, |&(id, ..)| id)
+        .into_iter()
+        .map(|user_info| {
+            user_info.map(|(_, name, lowercase_name, avatar_style)| {
+                MicroUser::new(config, name, &lowercase_name, avatar_style)
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,143 @@
+use crate::config::Config;
+use crate::model::enums::{AvatarStyle, SanctionKind};
+use crate::model::user::UserSanction;
+use crate::resource;
+use crate::resource::field::{Batcher, Mask};
+use crate::resource::user::MicroUser;
+use crate::schema::{user, user_sanction};
+use crate::string::{LargeString, SmallString, lower};
+use crate::time::DateTime;
+use diesel::{
+    ExpressionMethods, Identifiable, JoinOnDsl, NullableExpressionMethods, PgConnection, QueryDsl, QueryResult,
+    RunQueryDsl,
+};
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use server_macros::non_nullable_options;
+use strum::EnumString;
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy, EnumString)]
+#[strum(serialize_all = "camelCase")]
+pub enum Field {
+    Id,
+    User,
+    Issuer,
+    Type,
+    Reason,
+    CreationTime,
+    ExpirationTime,
+}
+
+impl From<Field> for u64 {
+    fn from(value: Field) -> Self {
+        value as u64
+    }
+}
+
+/// A suspension or ban placed on a user.
+#[non_nullable_options]
+#[skip_serializing_none]
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSanctionInfo {
+    /// The sanction identifier.
+    pub id: Option<i64>,
+    /// A micro user resource for the sanctioned user.
+    pub user: Option<MicroUser>,
+    /// A micro user resource for the moderator who issued the sanction, or null if they were deleted.
+    #[schema(nullable)]
+    pub issuer: Option<Option<MicroUser>>,
+    /// Whether the user is suspended or banned.
+    #[serde(rename = "type")]
+    pub kind: Option<SanctionKind>,
+    /// Why the sanction was issued.
+    pub reason: Option<LargeString>,
+    /// Time the sanction was issued.
+    pub creation_time: Option<DateTime>,
+    /// Time the sanction expires, or null if it is permanent.
+    #[schema(nullable)]
+    pub expiration_time: Option<Option<DateTime>>,
+}
+
+impl UserSanctionInfo {
+    pub fn new_from_id(
+        conn: &mut PgConnection,
+        config: &Config,
+        sanction_id: i64,
+        fields: Mask<Field>,
+    ) -> QueryResult<Self> {
+        let sanction = user_sanction::table.find(sanction_id).first(conn)?;
+        Self::new_batch(conn, config, vec![sanction], fields).map(resource::single)
+    }
+
+    pub fn new_batch(
+        conn: &mut PgConnection,
+        config: &Config,
+        sanctions: Vec<UserSanction>,
+        fields: Mask<Field>,
+    ) -> QueryResult<Vec<Self>> {
+        let f = Batcher::new(fields, sanctions.len());
+        let mut users = f.exec(Field::User, || get_users(conn, config, &sanctions))?;
+        let mut issuers = f.exec(Field::Issuer, || get_issuers(conn, config, &sanctions))?;
+
+        let mut results = sanctions
+            .into_iter()
+            .rev()
+            .map(|sanction| Self {
+                id: fields[Field::Id].then_some(sanction.id),
+                user: users.pop(),
+                issuer: issuers.pop(),
+                kind: fields[Field::Type].then_some(sanction.kind),
+                reason: fields[Field::Reason].then_some(sanction.reason),
+                creation_time: fields[Field::CreationTime].then_some(sanction.creation_time),
+                expiration_time: fields[Field::ExpirationTime].then_some(sanction.expiration_time),
+            })
+            .collect::<Vec<_>>();
+        results.reverse();
+        Ok(results)
+    }
+}
+
+fn get_users(conn: &mut PgConnection, config: &Config, sanctions: &[UserSanction]) -> QueryResult<Vec<MicroUser>> {
+    let sanction_ids: Vec<_> = sanctions.iter().map(Identifiable::id).collect();
+    let user_info = user_sanction::table
+        .inner_join(user::table)
+        .select((user_sanction::id, user::name, lower(user::name), user::avatar_style))
+        .filter(user_sanction::id.eq_any(sanction_ids))
+        .load(conn)?;
+    Ok(collect_micro_users(config, user_info, sanctions)
+        .into_iter()
+        .map(|user| user.expect("Sanctioned user must exist"))
+        .collect())
+}
+
+fn get_issuers(
+    conn: &mut PgConnection,
+    config: &Config,
+    sanctions: &[UserSanction],
+) -> QueryResult<Vec<Option<MicroUser>>> {
+    let sanction_ids: Vec<_> = sanctions.iter().map(Identifiable::id).collect();
+    user_sanction::table
+        .inner_join(user::table.on(user_sanction::issuer_id.eq(user::id.nullable())))
+        .select((user_sanction::id, user::name, lower(user::name), user::avatar_style))
+        .filter(user_sanction::id.eq_any(sanction_ids))
+        .load(conn)
+        .map(|issuer_info| collect_micro_users(config, issuer_info, sanctions))
+}
+
+/// Converts user info into micro users, ordered by `sanctions`.
+fn collect_micro_users(
+    config: &Config,
+    user_info: Vec<(i64, SmallString, SmallString, AvatarStyle)>,
+    sanctions: &[UserSanction],
+) -> Vec<Option<MicroUser>> {
+    resource::order_as_padded(user_info, sanctions, |&(id, ..)| id)
+        .into_iter()
+        .map(|user_info| {
+            user_info.map(|(_, name, lowercase_name, avatar_style)| {
+                MicroUser::new(config, name, &lowercase_name, avatar_style)
+            })
+        })
+        .collect()
+}
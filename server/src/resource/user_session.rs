@@ -0,0 +1,55 @@
+use crate::model::user::UserSession;
+use crate::resource::field::Mask;
+use crate::resource::user::MicroUser;
+use crate::time::DateTime;
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use server_macros::non_nullable_options;
+use strum::EnumString;
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy, EnumString)]
+#[strum(serialize_all = "camelCase")]
+pub enum Field {
+    Id,
+    User,
+    CreationTime,
+    LastUsageTime,
+    ExpirationTime,
+}
+
+impl From<Field> for u64 {
+    fn from(value: Field) -> Self {
+        value as u64
+    }
+}
+
+/// A single browser session.
+#[non_nullable_options]
+#[skip_serializing_none]
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSessionInfo {
+    /// The session's unique identifier. Unlike the session cookie, it can't be used for authentication.
+    id: Option<i64>,
+    /// The user that owns the session.
+    user: Option<MicroUser>,
+    /// Time the session was created.
+    creation_time: Option<DateTime>,
+    /// The last time this session was used during a login involving `?bump-login`.
+    last_usage_time: Option<DateTime>,
+    /// Time when the session expires.
+    expiration_time: Option<DateTime>,
+}
+
+impl UserSessionInfo {
+    pub fn new(user: MicroUser, user_session: UserSession, fields: Mask<Field>) -> Self {
+        UserSessionInfo {
+            id: fields[Field::Id].then_some(user_session.id),
+            user: fields[Field::User].then_some(user),
+            creation_time: fields[Field::CreationTime].then_some(user_session.creation_time),
+            last_usage_time: fields[Field::LastUsageTime].then_some(user_session.last_usage_time),
+            expiration_time: fields[Field::ExpirationTime].then_some(user_session.expiration_time),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::model::enums::WebhookDeliveryStatus;
+use crate::model::webhook::WebhookDelivery;
+use crate::resource;
+use crate::resource::field::Mask;
+use crate::schema::webhook_delivery;
+use crate::time::DateTime;
+use diesel::{ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl, SelectableHelper};
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use server_macros::non_nullable_options;
+use strum::EnumString;
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy, EnumString)]
+#[strum(serialize_all = "camelCase")]
+pub enum Field {
+    Id,
+    Snapshot,
+    Url,
+    Status,
+    AttemptCount,
+    LastError,
+    NextAttemptTime,
+    LastAttemptTime,
+    CreationTime,
+}
+
+impl From<Field> for u64 {
+    fn from(value: Field) -> Self {
+        value as u64
+    }
+}
+
+/// An attempt to post a snapshot to a webhook, along with its retries.
+#[non_nullable_options]
+#[skip_serializing_none]
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDeliveryInfo {
+    /// The delivery identifier.
+    id: Option<i64>,
+    /// ID of the snapshot being delivered.
+    snapshot: Option<i64>,
+    /// URL of the webhook.
+    url: Option<String>,
+    /// Whether the delivery is pending, delivered, or failed.
+    status: Option<WebhookDeliveryStatus>,
+    /// Number of attempts made since the delivery was created or last replayed.
+    attempt_count: Option<i32>,
+    /// Why the most recent failed attempt failed, if any attempt has failed.
+    #[schema(nullable)]
+    last_error: Option<Option<String>>,
+    /// Time of the next attempt. Only meaningful for pending deliveries.
+    next_attempt_time: Option<DateTime>,
+    /// Time of the most recent attempt, or null if no attempts have been made.
+    #[schema(nullable)]
+    last_attempt_time: Option<Option<DateTime>>,
+    /// Time the delivery was created.
+    creation_time: Option<DateTime>,
+}
+
+impl WebhookDeliveryInfo {
+    pub fn new(delivery: WebhookDelivery, fields: Mask<Field>) -> Self {
+        Self {
+            id: fields[Field::Id].then_some(delivery.id),
+            snapshot: fields[Field::Snapshot].then_some(delivery.snapshot_id),
+            url: fields[Field::Url].then_some(delivery.url),
+            status: fields[Field::Status].then_some(delivery.status),
+            attempt_count: fields[Field::AttemptCount].then_some(delivery.attempt_count),
+            last_error: fields[Field::LastError].then_some(delivery.last_error),
+            next_attempt_time: fields[Field::NextAttemptTime].then_some(delivery.next_attempt_time),
+            last_attempt_time: fields[Field::LastAttemptTime].then_some(delivery.last_attempt_time),
+            creation_time: fields[Field::CreationTime].then_some(delivery.creation_time),
+        }
+    }
+
+    pub fn new_from_id(conn: &mut PgConnection, delivery_id: i64, fields: Mask<Field>) -> QueryResult<Self> {
+        Self::new_batch_from_ids(conn, &[delivery_id], fields).map(resource::single)
+    }
+
+    pub fn new_batch_from_ids(
+        conn: &mut PgConnection,
+        delivery_ids: &[i64],
+        fields: Mask<Field>,
+    ) -> QueryResult<Vec<Self>> {
+        let unordered_deliveries = webhook_delivery::table
+            .filter(webhook_delivery::id.eq_any(delivery_ids))
+            .select(WebhookDelivery::as_select())
+            .load(conn)?;
+        let deliveries = resource::order_as(unordered_deliveries, delivery_ids);
+        Ok(deliveries
+            .into_iter()
+            .map(|delivery| Self::new(delivery, fields))
+            .collect())
+    }
+}
@@ -0,0 +1,199 @@
+use crate::app::Context;
+use crate::config::Action;
+use crate::content::hash::PostHash;
+use crate::model::enums::PostStatus;
+use crate::model::tag::TagName;
+use crate::model::wiki_page::WikiPage;
+use crate::resource::field::Mask;
+use crate::resource::post::MicroPost;
+use crate::schema::{post, tag_name, wiki_page};
+use crate::string::{LargeString, SmallString};
+use crate::time::DateTime;
+use diesel::dsl::{exists, not};
+use diesel::{
+    BoolExpressionMethods, ExpressionMethods, NullableExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+    QueryResult, RunQueryDsl,
+};
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use server_macros::non_nullable_options;
+use strum::EnumString;
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy, EnumString)]
+#[strum(serialize_all = "camelCase")]
+pub enum Field {
+    Version,
+    Id,
+    Title,
+    Tag,
+    Body,
+    LinkedTags,
+    LinkedPages,
+    LinkedPosts,
+    CreationTime,
+    LastEditTime,
+}
+
+impl From<Field> for u64 {
+    fn from(value: Field) -> Self {
+        value as u64
+    }
+}
+
+/// A wiki page, either describing a tag or standing on its own.
+///
+/// Page bodies can link to other resources using `[[...]]` markup:
+/// `[[name]]` links to a tag, `[[wiki:title]]` links to a standalone wiki page,
+/// and `[[post:id]]` links to a post. Any link may be given a label after a `|`,
+/// as in `[[post:1|the original]]`.
+#[non_nullable_options]
+#[skip_serializing_none]
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WikiPageInfo {
+    /// Resource version. See [versioning](#Versioning).
+    version: Option<DateTime>,
+    /// The wiki page identifier.
+    id: Option<i64>,
+    /// The title of a standalone page, or null if the page belongs to a tag.
+    #[schema(nullable)]
+    title: Option<Option<SmallString>>,
+    /// The primary name of the tag the page belongs to, or null for standalone pages.
+    #[schema(nullable)]
+    tag: Option<Option<SmallString>>,
+    /// The page contents.
+    body: Option<LargeString>,
+    /// Primary names of the existing tags linked to by the page.
+    linked_tags: Option<Vec<SmallString>>,
+    /// Titles of the existing standalone pages linked to by the page.
+    linked_pages: Option<Vec<SmallString>>,
+    /// Micro post resources for the existing posts linked to by the page that are visible to the client.
+    linked_posts: Option<Vec<MicroPost>>,
+    /// Time the page was created.
+    creation_time: Option<DateTime>,
+    /// Time the page was last edited.
+    last_edit_time: Option<DateTime>,
+}
+
+impl WikiPageInfo {
+    pub fn new(conn: &mut PgConnection, ctx: &Context, page: WikiPage, fields: Mask<Field>) -> QueryResult<Self> {
+        let links = Links::parse(&page.body);
+        let tag = match page.tag_id.filter(|_| fields[Field::Tag]) {
+            Some(tag_id) => tag_name::table
+                .select(tag_name::name)
+                .filter(tag_name::tag_id.eq(tag_id))
+                .filter(TagName::is_primary())
+                .first(conn)
+                .optional()?,
+            None => None,
+        };
+        let linked_tags = fields[Field::LinkedTags]
+            .then(|| get_linked_tags(conn, &links.tags))
+            .transpose()?;
+        let linked_pages = fields[Field::LinkedPages]
+            .then(|| get_linked_pages(conn, &links.pages))
+            .transpose()?;
+        let linked_posts = fields[Field::LinkedPosts]
+            .then(|| get_linked_posts(conn, ctx, &links.posts))
+            .transpose()?;
+
+        Ok(Self {
+            version: fields[Field::Version].then_some(page.last_edit_time),
+            id: fields[Field::Id].then_some(page.id),
+            title: fields[Field::Title].then_some(page.title),
+            tag: fields[Field::Tag].then_some(tag),
+            body: fields[Field::Body].then_some(page.body),
+            linked_tags,
+            linked_pages,
+            linked_posts,
+            creation_time: fields[Field::CreationTime].then_some(page.creation_time),
+            last_edit_time: fields[Field::LastEditTime].then_some(page.last_edit_time),
+        })
+    }
+}
+
+/// Resources referenced by the link markup of a wiki page body.
+#[derive(Default)]
+struct Links<'a> {
+    tags: Vec<&'a str>,
+    pages: Vec<&'a str>,
+    posts: Vec<i64>,
+}
+
+impl<'a> Links<'a> {
+    fn parse(body: &'a str) -> Self {
+        let mut links = Self::default();
+        let mut remaining = body;
+        while let Some(start) = remaining.find("[[") {
+            remaining = &remaining[start + 2..];
+            let Some(end) = remaining.find("]]") else {
+                break;
+            };
+            let link = &remaining[..end];
+            let target = link.split_once('|').map_or(link, |(target, _)| target).trim();
+            remaining = &remaining[end + 2..];
+
+            if let Some(title) = target.strip_prefix("wiki:") {
+                links.pages.push(title.trim());
+            } else if let Some(post_id) = target.strip_prefix("post:") {
+                links.posts.extend(post_id.trim().parse::<i64>().ok());
+            } else if !target.is_empty() {
+                links.tags.push(target);
+            }
+        }
+        links
+    }
+}
+
+fn get_linked_tags(conn: &mut PgConnection, names: &[&str]) -> QueryResult<Vec<SmallString>> {
+    let linked_tag_ids = tag_name::table
+        .select(tag_name::tag_id)
+        .filter(tag_name::name.eq_any(names));
+    tag_name::table
+        .select(tag_name::name)
+        .filter(tag_name::tag_id.eq_any(linked_tag_ids))
+        .filter(TagName::is_primary())
+        .order(tag_name::name)
+        .load(conn)
+}
+
+fn get_linked_pages(conn: &mut PgConnection, titles: &[&str]) -> QueryResult<Vec<SmallString>> {
+    wiki_page::table
+        .select(wiki_page::title.assume_not_null())
+        .filter(wiki_page::title.eq_any(titles))
+        .order(wiki_page::title)
+        .load(conn)
+}
+
+fn get_linked_posts(conn: &mut PgConnection, ctx: &Context, post_ids: &[i64]) -> QueryResult<Vec<MicroPost>> {
+    let mut linked_posts = post::table
+        .select(post::id)
+        .filter(post::id.eq_any(post_ids))
+        .filter(post::deletion_time.is_null())
+        .order(post::id)
+        .into_boxed();
+
+    // Pending posts are only visible to their uploader and those who can approve them
+    if !ctx.has_privilege(Action::PostApprove) {
+        linked_posts = linked_posts.filter(
+            post::status
+                .eq(PostStatus::Approved)
+                .or(post::user_id.eq(ctx.client.id)),
+        );
+    }
+
+    // Apply preference filters to linked posts
+    if let Some(hidden_posts) = ctx.preferences().hidden_posts(post::id) {
+        linked_posts = linked_posts.filter(not(exists(hidden_posts)));
+    }
+
+    let linked_post_ids: Vec<i64> = linked_posts.load(conn)?;
+    Ok(linked_post_ids
+        .into_iter()
+        .map(|post_id| MicroPost {
+            id: post_id,
+            thumbnail_url: PostHash::new(&ctx.config, post_id, None).thumbnail_url(),
+        })
+        .collect())
+}
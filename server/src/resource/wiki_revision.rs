@@ -0,0 +1,178 @@
+use crate::config::Config;
+use crate::model::enums::AvatarStyle;
+use crate::resource::field::Mask;
+use crate::resource::user::MicroUser;
+use crate::schema::user;
+use crate::snapshot::wiki_page::{Revision, SnapshotData};
+use crate::string::{LargeString, SmallString, lower};
+use crate::time::DateTime;
+use diesel::{ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use server_macros::non_nullable_options;
+use std::collections::HashMap;
+use strum::EnumString;
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy, EnumString)]
+#[strum(serialize_all = "camelCase")]
+pub enum Field {
+    Revision,
+    User,
+    Title,
+    Body,
+    Time,
+}
+
+impl From<Field> for u64 {
+    fn from(value: Field) -> Self {
+        value as u64
+    }
+}
+
+/// A single version of a wiki page, as recorded in its snapshot history.
+#[non_nullable_options]
+#[skip_serializing_none]
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WikiRevisionInfo {
+    /// The revision number. The first version of a page is revision 1.
+    revision: Option<usize>,
+    /// A micro user resource for the user who made the revision, or null if anonymous or deleted.
+    #[schema(nullable)]
+    user: Option<Option<MicroUser>>,
+    /// The page title at this revision, or null if the page belongs to a tag.
+    #[schema(nullable)]
+    title: Option<Option<SmallString>>,
+    /// The page contents at this revision.
+    body: Option<LargeString>,
+    /// Time the revision was made.
+    time: Option<DateTime>,
+}
+
+impl WikiRevisionInfo {
+    pub fn new_batch(
+        conn: &mut PgConnection,
+        config: &Config,
+        revisions: Vec<Revision>,
+        fields: Mask<Field>,
+    ) -> QueryResult<Vec<Self>> {
+        let users: HashMap<i64, (SmallString, SmallString, AvatarStyle)> = if fields[Field::User] {
+            let user_ids: Vec<i64> = revisions.iter().filter_map(|revision| revision.user_id).collect();
+            user::table
+                .select((user::id, user::name, lower(user::name), user::avatar_style))
+                .filter(user::id.eq_any(user_ids))
+                .load::<(i64, SmallString, SmallString, AvatarStyle)>(conn)?
+                .into_iter()
+                .map(|(id, name, lowercase_name, avatar_style)| (id, (name, lowercase_name, avatar_style)))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        let micro_user = |user_id: i64| {
+            users.get(&user_id).map(|(name, lowercase_name, avatar_style)| {
+                MicroUser::new(config, name.clone(), lowercase_name, *avatar_style)
+            })
+        };
+
+        Ok((1..)
+            .zip(revisions)
+            .map(|(revision_number, revision)| Self {
+                revision: fields[Field::Revision].then_some(revision_number),
+                user: fields[Field::User].then(|| revision.user_id.and_then(&micro_user)),
+                title: fields[Field::Title].then_some(revision.data.title),
+                body: fields[Field::Body].then_some(revision.data.body),
+                time: fields[Field::Time].then_some(revision.time),
+            })
+            .collect())
+    }
+}
+
+/// Whether a line was kept, added, or removed between two revisions.
+#[derive(Clone, Copy, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LineChange {
+    Unchanged,
+    Added,
+    Removed,
+}
+
+/// A line of a wiki page diff.
+#[derive(Serialize, ToSchema)]
+pub struct DiffLine {
+    /// How the line changed.
+    #[serde(rename = "type")]
+    change: LineChange,
+    /// The line contents.
+    text: String,
+}
+
+/// A line-by-line comparison of two revisions of a wiki page.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WikiPageDiff {
+    /// The revision being compared from.
+    from: usize,
+    /// The revision being compared to.
+    to: usize,
+    /// The page title at the `from` revision.
+    old_title: Option<SmallString>,
+    /// The page title at the `to` revision.
+    new_title: Option<SmallString>,
+    /// The lines of both revisions, in order, marked as unchanged, added, or removed.
+    lines: Vec<DiffLine>,
+}
+
+impl WikiPageDiff {
+    pub fn new(from: usize, old: SnapshotData, to: usize, new: SnapshotData) -> Self {
+        Self {
+            from,
+            to,
+            lines: line_diff(&old.body, &new.body),
+            old_title: old.title,
+            new_title: new.title,
+        }
+    }
+}
+
+/// Computes a minimal line diff between `old` and `new` using their longest common subsequence.
+fn line_diff(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lcs_lengths[i * width + j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let width = new.len() + 1;
+    let mut lcs_lengths = vec![0_u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs_lengths[i * width + j] = if old[i] == new[j] {
+                lcs_lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs_lengths[(i + 1) * width + j].max(lcs_lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let line = |change: LineChange, text: &str| DiffLine {
+        change,
+        text: text.to_owned(),
+    };
+    let mut lines = Vec::with_capacity(old.len().max(new.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(line(LineChange::Unchanged, old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs_lengths[(i + 1) * width + j] >= lcs_lengths[i * width + j + 1] {
+            lines.push(line(LineChange::Removed, old[i]));
+            i += 1;
+        } else {
+            lines.push(line(LineChange::Added, new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().map(|text| line(LineChange::Removed, text)));
+    lines.extend(new[j..].iter().map(|text| line(LineChange::Added, text)));
+    lines
+}
@@ -1,5 +1,27 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    activity_event (snapshot_id) {
+        snapshot_id -> Int8,
+        sequence -> Nullable<Int8>,
+        creation_time -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    audit_log (id) {
+        id -> Int8,
+        event -> Int2,
+        user_id -> Nullable<Int8>,
+        user_name -> Nullable<Text>,
+        target -> Nullable<Text>,
+        details -> Nullable<Text>,
+        ip_address -> Nullable<Text>,
+        user_agent -> Nullable<Text>,
+        creation_time -> Timestamptz,
+    }
+}
+
 diesel::table! {
     comment (id) {
         id -> Int8,
@@ -8,6 +30,14 @@ diesel::table! {
         text -> Text,
         creation_time -> Timestamptz,
         last_edit_time -> Timestamptz,
+        parent_comment_id -> Nullable<Int8>,
+    }
+}
+
+diesel::table! {
+    comment_mention (comment_id, user_id) {
+        comment_id -> Int8,
+        user_id -> Int8,
     }
 }
 
@@ -40,6 +70,54 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    digest_subscription (user_id) {
+        user_id -> Int8,
+        frequency -> Int2,
+        last_post_id -> Int8,
+        last_sent_time -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    email_verification (user_id) {
+        user_id -> Int8,
+        rank -> Int2,
+        creation_time -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    invite_code (code) {
+        code -> Text,
+        creator_id -> Nullable<Int8>,
+        max_uses -> Int4,
+        use_count -> Int4,
+        expiration_time -> Nullable<Timestamptz>,
+        creation_time -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    muted_notification (user_id, kind) {
+        user_id -> Int8,
+        kind -> Int2,
+    }
+}
+
+diesel::table! {
+    notification (id) {
+        id -> Int8,
+        user_id -> Int8,
+        kind -> Int2,
+        actor_id -> Nullable<Int8>,
+        post_id -> Nullable<Int8>,
+        comment_id -> Nullable<Int8>,
+        is_read -> Bool,
+        creation_time -> Timestamptz,
+    }
+}
+
 diesel::table! {
     pool (id) {
         id -> Int8,
@@ -110,6 +188,8 @@ diesel::table! {
         generated_thumbnail_size -> Int8,
         custom_thumbnail_size -> Int8,
         description -> Text,
+        deletion_time -> Nullable<Timestamptz>,
+        status -> Int2,
     }
 }
 
@@ -130,6 +210,32 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    post_file (id) {
+        id -> Int8,
+        post_id -> Int8,
+        order -> Int4,
+        file_size -> Int8,
+        width -> Int4,
+        height -> Int4,
+        #[sql_name = "type"]
+        type_ -> Int2,
+        mime_type -> Int2,
+        checksum -> Bytea,
+        checksum_md5 -> Bytea,
+        generated_thumbnail_size -> Int8,
+        creation_time -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    post_file_signature (post_file_id) {
+        post_file_id -> Int8,
+        signature -> Array<Nullable<Int8>>,
+        words -> Array<Nullable<Int4>>,
+    }
+}
+
 diesel::table! {
     post_note (id) {
         id -> Int8,
@@ -140,9 +246,10 @@ diesel::table! {
 }
 
 diesel::table! {
-    post_relation (parent_id, child_id) {
+    post_relation (parent_id, child_id, kind) {
         parent_id -> Int8,
         child_id -> Int8,
+        kind -> Int2,
     }
 }
 
@@ -187,6 +294,27 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    report (id) {
+        id -> Int8,
+        user_id -> Nullable<Int8>,
+        resource_type -> Int2,
+        resource_id -> Text,
+        reason -> Text,
+        status -> Int2,
+        resolver_id -> Nullable<Int8>,
+        creation_time -> Timestamptz,
+        resolution_time -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    saved_search (user_id, query) {
+        user_id -> Int8,
+        query -> Text,
+    }
+}
+
 diesel::table! {
     snapshot (id) {
         id -> Int8,
@@ -258,6 +386,13 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    totp_recovery_code (user_id, code_hash) {
+        user_id -> Int8,
+        code_hash -> Text,
+    }
+}
+
 diesel::table! {
     user (id) {
         id -> Int8,
@@ -277,6 +412,48 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    user_identity (issuer, subject) {
+        issuer -> Text,
+        subject -> Text,
+        user_id -> Int8,
+        creation_time -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    user_note (id) {
+        id -> Int8,
+        user_id -> Int8,
+        author_id -> Nullable<Int8>,
+        text -> Text,
+        creation_time -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    user_sanction (id) {
+        id -> Int8,
+        user_id -> Int8,
+        issuer_id -> Nullable<Int8>,
+        kind -> Int2,
+        reason -> Text,
+        creation_time -> Timestamptz,
+        expiration_time -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    user_session (id) {
+        id -> Int8,
+        user_id -> Int8,
+        secret_hash -> Text,
+        creation_time -> Timestamptz,
+        last_usage_time -> Timestamptz,
+        expiration_time -> Timestamptz,
+    }
+}
+
 diesel::table! {
     user_statistics (user_id) {
         user_id -> Int8,
@@ -300,11 +477,69 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    user_totp (user_id) {
+        user_id -> Int8,
+        secret -> Bytea,
+        enabled -> Bool,
+        creation_time -> Timestamptz,
+        last_used_step -> Nullable<Int8>,
+    }
+}
+
+diesel::table! {
+    watched_tag (user_id, tag_id) {
+        user_id -> Int8,
+        tag_id -> Int8,
+    }
+}
+
+diesel::table! {
+    webhook_delivery (id) {
+        id -> Int8,
+        snapshot_id -> Int8,
+        url -> Text,
+        status -> Int2,
+        attempt_count -> Int4,
+        last_error -> Nullable<Text>,
+        next_attempt_time -> Timestamptz,
+        last_attempt_time -> Nullable<Timestamptz>,
+        creation_time -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    webhook_event (snapshot_id) {
+        snapshot_id -> Int8,
+    }
+}
+
+diesel::table! {
+    wiki_page (id) {
+        id -> Int8,
+        title -> Nullable<Citext>,
+        tag_id -> Nullable<Int8>,
+        body -> Text,
+        creation_time -> Timestamptz,
+        last_edit_time -> Timestamptz,
+    }
+}
+
+diesel::joinable!(activity_event -> snapshot (snapshot_id));
 diesel::joinable!(comment -> post (post_id));
 diesel::joinable!(comment -> user (user_id));
+diesel::joinable!(comment_mention -> comment (comment_id));
+diesel::joinable!(comment_mention -> user (user_id));
 diesel::joinable!(comment_score -> comment (comment_id));
 diesel::joinable!(comment_score -> user (user_id));
 diesel::joinable!(comment_statistics -> comment (comment_id));
+diesel::joinable!(digest_subscription -> user (user_id));
+diesel::joinable!(email_verification -> user (user_id));
+diesel::joinable!(invite_code -> user (creator_id));
+diesel::joinable!(muted_notification -> user (user_id));
+diesel::joinable!(notification -> comment (comment_id));
+diesel::joinable!(notification -> post (post_id));
+diesel::joinable!(notification -> user (user_id));
 diesel::joinable!(pool -> pool_category (category_id));
 diesel::joinable!(pool_category_statistics -> pool_category (category_id));
 diesel::joinable!(pool_name -> pool (pool_id));
@@ -316,6 +551,8 @@ diesel::joinable!(post_favorite -> post (post_id));
 diesel::joinable!(post_favorite -> user (user_id));
 diesel::joinable!(post_feature -> post (post_id));
 diesel::joinable!(post_feature -> user (user_id));
+diesel::joinable!(post_file -> post (post_id));
+diesel::joinable!(post_file_signature -> post_file (post_file_id));
 diesel::joinable!(post_note -> post (post_id));
 diesel::joinable!(post_score -> post (post_id));
 diesel::joinable!(post_score -> user (user_id));
@@ -323,19 +560,40 @@ diesel::joinable!(post_signature -> post (post_id));
 diesel::joinable!(post_statistics -> post (post_id));
 diesel::joinable!(post_tag -> post (post_id));
 diesel::joinable!(post_tag -> tag (tag_id));
+diesel::joinable!(report -> user (user_id));
+diesel::joinable!(saved_search -> user (user_id));
 diesel::joinable!(snapshot -> user (user_id));
 diesel::joinable!(tag -> tag_category (category_id));
 diesel::joinable!(tag_category_statistics -> tag_category (category_id));
 diesel::joinable!(tag_name -> tag (tag_id));
 diesel::joinable!(tag_statistics -> tag (tag_id));
+diesel::joinable!(totp_recovery_code -> user (user_id));
+diesel::joinable!(user_identity -> user (user_id));
+diesel::joinable!(user_note -> user (user_id));
+diesel::joinable!(user_sanction -> user (user_id));
+diesel::joinable!(user_session -> user (user_id));
 diesel::joinable!(user_statistics -> user (user_id));
 diesel::joinable!(user_token -> user (user_id));
+diesel::joinable!(user_totp -> user (user_id));
+diesel::joinable!(watched_tag -> tag (tag_id));
+diesel::joinable!(watched_tag -> user (user_id));
+diesel::joinable!(webhook_delivery -> snapshot (snapshot_id));
+diesel::joinable!(webhook_event -> snapshot (snapshot_id));
+diesel::joinable!(wiki_page -> tag (tag_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    activity_event,
+    audit_log,
     comment,
+    comment_mention,
     comment_score,
     comment_statistics,
     database_statistics,
+    digest_subscription,
+    email_verification,
+    invite_code,
+    muted_notification,
+    notification,
     pool,
     pool_category,
     pool_category_statistics,
@@ -345,12 +603,16 @@ diesel::allow_tables_to_appear_in_same_query!(
     post,
     post_favorite,
     post_feature,
+    post_file,
+    post_file_signature,
     post_note,
     post_relation,
     post_score,
     post_signature,
     post_statistics,
     post_tag,
+    report,
+    saved_search,
     snapshot,
     tag,
     tag_category,
@@ -359,7 +621,17 @@ diesel::allow_tables_to_appear_in_same_query!(
     tag_name,
     tag_statistics,
     tag_suggestion,
+    totp_recovery_code,
     user,
+    user_identity,
+    user_note,
+    user_sanction,
+    user_session,
     user_statistics,
     user_token,
+    user_totp,
+    watched_tag,
+    webhook_delivery,
+    webhook_event,
+    wiki_page,
 );
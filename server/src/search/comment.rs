@@ -1,12 +1,16 @@
 use crate::api::error::{ApiError, ApiResult};
 use crate::app::Context;
-use crate::schema::{comment, comment_statistics, database_statistics, user};
-use crate::search::{Builder, Order, ParsedSort, SearchCriteria};
-use crate::{apply_filter, apply_random_sort, apply_sort, apply_str_filter, apply_time_filter};
+use crate::schema::{comment, comment_mention, comment_statistics, database_statistics, user};
+use crate::search::{Builder, CacheState, Order, ParsedSort, SearchCriteria, UnparsedFilter};
+use crate::{
+    apply_cache_filters, apply_distinct_if_multivalued, apply_filter, apply_random_sort, apply_sort, apply_str_filter,
+    apply_time_filter, update_filter_cache,
+};
 use diesel::dsl::{InnerJoin, IntoBoxed, LeftJoin, Select, exists, not};
 use diesel::pg::Pg;
 use diesel::{ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
 use strum::{Display, EnumIter, EnumString, EnumTable};
+use tracing::instrument;
 
 #[derive(Display, Clone, Copy, EnumTable, EnumIter, EnumString)]
 #[strum(serialize_all = "kebab-case")]
@@ -25,11 +29,13 @@ pub enum Token {
     LastEditTime,
     #[strum(serialize = "user", serialize = "author")]
     User,
+    Mentions,
     Score,
 }
 
 pub struct QueryBuilder<'a> {
     search: SearchCriteria<'a, Token>,
+    cache_state: CacheState,
 }
 
 impl<'a> Builder<'a> for QueryBuilder<'a> {
@@ -52,7 +58,8 @@ impl<'a> Builder<'a> for QueryBuilder<'a> {
         .map_err(ApiError::from)
     }
 
-    fn build_filtered(&mut self, _conn: &mut PgConnection) -> ApiResult<BoxedQuery> {
+    #[instrument(skip_all)]
+    fn build_filtered(&mut self, conn: &mut PgConnection) -> ApiResult<BoxedQuery> {
         let base_query = comment::table
             .select(comment::id)
             .inner_join(comment_statistics::table)
@@ -62,15 +69,17 @@ impl<'a> Builder<'a> for QueryBuilder<'a> {
             .search
             .filters
             .iter()
-            .try_fold(base_query, |query, filter| match filter.kind {
+            .try_fold(base_query, |query, &filter| match filter.kind {
                 Token::Id => apply_filter!(query, comment::id, filter, i64),
                 Token::Post => apply_filter!(query, comment::post_id, filter, i64),
                 Token::Text => Ok(apply_str_filter!(query, comment::text, filter)),
                 Token::CreationTime => apply_time_filter!(query, comment::creation_time, filter),
                 Token::LastEditTime => apply_time_filter!(query, comment::last_edit_time, filter),
                 Token::User => Ok(apply_str_filter!(query, user::name, filter)),
+                Token::Mentions => apply_mention_filter(conn, query, filter, &mut self.cache_state),
                 Token::Score => apply_filter!(query, comment_statistics::score, filter, i64),
             })?;
+        query = apply_cache_filters!(query, comment::id, self.cache_state);
 
         // Apply preference filters to comments
         if let Some(hidden_posts) = self.search.ctx.preferences().hidden_posts(comment::post_id) {
@@ -97,6 +106,8 @@ impl<'a> Builder<'a> for QueryBuilder<'a> {
             Token::CreationTime => apply_sort!(query, comment::creation_time, sort),
             Token::LastEditTime => apply_sort!(query, comment::last_edit_time, sort),
             Token::User => apply_sort!(query, user::name, sort),
+            // Mentions have no natural ordering, so fall back to ordering by ID
+            Token::Mentions => apply_sort!(query, comment::id, sort),
             Token::Score => apply_sort!(query, comment_statistics::score, sort),
         });
         match self.search.extra_args {
@@ -110,7 +121,10 @@ impl<'a> Builder<'a> for QueryBuilder<'a> {
 impl<'a> QueryBuilder<'a> {
     pub fn new(ctx: &'a Context, search_criteria: &'a str) -> ApiResult<Self> {
         let search = SearchCriteria::new(ctx, search_criteria, Token::Text).map_err(Box::from)?;
-        Ok(Self { search })
+        Ok(Self {
+            search,
+            cache_state: CacheState::default(),
+        })
     }
 }
 
@@ -120,6 +134,22 @@ type BoxedQuery = IntoBoxed<
     Pg,
 >;
 
+fn apply_mention_filter(
+    conn: &mut PgConnection,
+    query: BoxedQuery,
+    filter: UnparsedFilter<Token>,
+    state: &mut CacheState,
+) -> ApiResult<BoxedQuery> {
+    let mentions = comment_mention::table
+        .select(comment_mention::comment_id)
+        .inner_join(user::table)
+        .into_boxed();
+    let mentions = apply_distinct_if_multivalued!(mentions, filter);
+    let filtered_comments = apply_str_filter!(mentions, user::name, filter.unnegated());
+    update_filter_cache!(conn, filtered_comments, comment_mention::comment_id, filter, state)?;
+    Ok(query)
+}
+
 #[cfg(test)]
 pub fn filter_table() -> TokenTable<&'static str> {
     TokenTable {
@@ -129,6 +159,7 @@ pub fn filter_table() -> TokenTable<&'static str> {
         _creation_time: "2016",
         _last_edit_time: "-2016",
         _user: "-*user*",
+        _mentions: "regular_user",
         _score: "-0..",
     }
 }
@@ -6,17 +6,21 @@ use diesel::sql_types::Float;
 use diesel::{ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl, declare_sql_function};
 use std::ops::{Not, Range};
 use std::str::FromStr;
+use tracing::instrument;
 
+pub mod audit_log;
 pub mod comment;
 mod macros;
 mod parse;
 pub mod pool;
 pub mod post;
 pub mod preferences;
+pub mod report;
 pub mod snapshot;
 pub mod tag;
 mod temp;
 pub mod user;
+pub mod webhook_delivery;
 
 /// An interface for a search query builder.
 pub trait Builder<'a>: Sized {
@@ -100,6 +104,7 @@ where
     /// Constructs a new [`SearchCriteria`] by parsing `search_criteria` [`str`] into a set terms
     /// (filters or sorts) separated by unescaped whitespace. If a term does not contain an unescaped `:`,
     /// then it will be interpreted as an `anonymous_token`.
+    #[instrument(name = "parse_search_criteria", skip_all)]
     fn new(ctx: &'a Context, search_criteria: &'a str, anonymous_token: T) -> ApiResult<Self> {
         let mut filters: Vec<UnparsedFilter<T>> = Vec::new();
         let mut sorts: Vec<ParsedSort<T>> = Vec::new();
@@ -11,6 +11,7 @@ use diesel::dsl::{InnerJoin, IntoBoxed, Select};
 use diesel::pg::Pg;
 use diesel::{ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
 use strum::{Display, EnumIter, EnumString, EnumTable};
+use tracing::instrument;
 
 #[derive(Display, Clone, Copy, EnumTable, EnumIter, EnumString)]
 #[strum(serialize_all = "kebab-case")]
@@ -54,6 +55,7 @@ impl<'a> Builder<'a> for QueryBuilder<'a> {
         .map_err(ApiError::from)
     }
 
+    #[instrument(skip_all)]
     fn build_filtered(&mut self, conn: &mut PgConnection) -> ApiResult<BoxedQuery> {
         let base_query = pool::table
             .select(pool::id)
@@ -1,11 +1,12 @@
 use crate::api::error::{ApiError, ApiResult};
 use crate::app::Context;
 use crate::auth::Client;
+use crate::config::Action;
 use crate::content::hash::Checksum;
-use crate::model::enums::{PostFlag, PostFlags, PostSafety, PostType};
+use crate::model::enums::{PostFlag, PostFlags, PostSafety, PostStatus, PostType, RelationKind};
 use crate::schema::{
     comment, database_statistics, pool, pool_category, pool_name, pool_post, post, post_favorite, post_feature,
-    post_note, post_score, post_statistics, post_tag, tag, tag_category, tag_name, user,
+    post_note, post_relation, post_score, post_statistics, post_tag, tag, tag_category, tag_name, user,
 };
 use crate::search::{
     Builder, CacheState, Condition, Order, ParsedSort, SearchCriteria, StrCondition, UnparsedFilter, parse,
@@ -23,6 +24,7 @@ use diesel::sql_types::{Float, SmallInt};
 use diesel::{ExpressionMethods, JoinOnDsl, PgConnection, QueryDsl, QueryResult, RunQueryDsl, TextExpressionMethods};
 use std::str::FromStr;
 use strum::{Display, EnumIter, EnumString, EnumTable, IntoStaticStr};
+use tracing::instrument;
 
 #[derive(Display, Clone, Copy, EnumIter, EnumString, EnumTable, IntoStaticStr)]
 #[strum(serialize_all = "kebab-case")]
@@ -45,6 +47,7 @@ pub enum Token {
     #[strum(serialize = "rating", serialize = "safety")]
     Safety,
     Type,
+    Status,
     ContentChecksum,
     Flag,
     Source,
@@ -72,6 +75,8 @@ pub enum Token {
     Fav,
     Comment,
     NoteText,
+    Parent,
+    Child,
     TagCount,
     PoolCount,
     CommentCount,
@@ -92,6 +97,7 @@ pub enum Token {
 pub struct QueryBuilder<'a> {
     search: SearchCriteria<'a, Token>,
     cache_state: CacheState,
+    deleted: bool,
 }
 
 impl<'a> Builder<'a> for QueryBuilder<'a> {
@@ -103,7 +109,7 @@ impl<'a> Builder<'a> for QueryBuilder<'a> {
     }
 
     fn count(&mut self, conn: &mut PgConnection) -> ApiResult<i64> {
-        if self.search.has_filter() || !self.search.ctx.preferences().is_empty() {
+        if self.deleted || self.search.has_filter() || !self.search.ctx.preferences().is_empty() {
             let unsorted_query = self.build_filtered(conn)?;
             unsorted_query.count().first(conn)
         } else {
@@ -114,6 +120,7 @@ impl<'a> Builder<'a> for QueryBuilder<'a> {
         .map_err(ApiError::from)
     }
 
+    #[instrument(skip_all)]
     fn build_filtered(&mut self, conn: &mut PgConnection) -> ApiResult<BoxedQuery> {
         let mut nonmatching_posts = None;
         let nonmatching = &mut nonmatching_posts;
@@ -124,6 +131,11 @@ impl<'a> Builder<'a> for QueryBuilder<'a> {
             .inner_join(post_statistics::table)
             .left_join(user::table)
             .into_boxed();
+        let base_query = if self.deleted {
+            base_query.filter(post::deletion_time.is_not_null())
+        } else {
+            base_query.filter(post::deletion_time.is_null())
+        };
         let mut query = self
             .search
             .filters
@@ -137,6 +149,7 @@ impl<'a> Builder<'a> for QueryBuilder<'a> {
                 Token::AspectRatio => apply_filter!(query, aspect_ratio(), filter, f32),
                 Token::Safety => apply_filter!(query, post::safety, filter, PostSafety),
                 Token::Type => apply_filter!(query, post::type_, filter, PostType),
+                Token::Status => apply_filter!(query, post::status, filter, PostStatus),
                 Token::ContentChecksum => apply_checksum_filter(query, filter),
                 Token::Flag => apply_flag_filter(query, filter),
                 Token::Source => Ok(apply_str_filter!(query, post::source, filter)),
@@ -151,6 +164,8 @@ impl<'a> Builder<'a> for QueryBuilder<'a> {
                 Token::Fav => apply_favorite_filter(conn, query, filter, state),
                 Token::Comment => apply_comment_filter(conn, query, filter, state),
                 Token::NoteText => apply_note_text_filter(conn, query, filter, state),
+                Token::Parent => apply_parent_filter(conn, query, filter, state),
+                Token::Child => apply_child_filter(conn, query, filter, state),
                 Token::TagCount => apply_filter!(query, post_statistics::tag_count, filter, i64),
                 Token::PoolCount => apply_filter!(query, post_statistics::pool_count, filter, i64),
                 Token::CommentCount => apply_filter!(query, post_statistics::comment_count, filter, i64),
@@ -170,6 +185,9 @@ impl<'a> Builder<'a> for QueryBuilder<'a> {
         if let Some(hidden_posts) = self.search.ctx.preferences().hidden_posts(post::id) {
             query = query.filter(not(exists(hidden_posts)));
         }
+        if !self.deleted && !self.can_search_pending() {
+            query = query.filter(post::status.eq(PostStatus::Approved));
+        }
         Ok(apply_cache_filters!(query, post::id, self.cache_state))
     }
 
@@ -193,6 +211,7 @@ impl<'a> Builder<'a> for QueryBuilder<'a> {
             Token::AspectRatio => apply_sort!(query, aspect_ratio(), sort),
             Token::Safety => apply_sort!(query, post::safety, sort),
             Token::Type => apply_sort!(query, post::type_, sort),
+            Token::Status => apply_sort!(query, post::status, sort),
             Token::Flag => apply_sort!(query, post::flags, sort),
             Token::Source => apply_sort!(query, post::source, sort),
             Token::Description => apply_sort!(query, post::description, sort),
@@ -210,9 +229,13 @@ impl<'a> Builder<'a> for QueryBuilder<'a> {
             Token::CommentTime => apply_sort!(query, post_statistics::last_comment_time, sort),
             Token::FavTime => apply_sort!(query, post_statistics::last_favorite_time, sort),
             Token::FeatureTime => apply_sort!(query, post_statistics::last_feature_time, sort),
-            Token::ContentChecksum | Token::NoteText | Token::PoolCategory | Token::Special | Token::TagCategory => {
-                unreachable!()
-            }
+            Token::Child
+            | Token::ContentChecksum
+            | Token::NoteText
+            | Token::Parent
+            | Token::PoolCategory
+            | Token::Special
+            | Token::TagCategory => unreachable!(),
         });
         match self.search.extra_args {
             Some(args) => query.offset(args.offset).limit(args.limit),
@@ -236,7 +259,13 @@ impl<'a> QueryBuilder<'a> {
         for sort in &search.sorts {
             if matches!(
                 sort.kind,
-                Token::ContentChecksum | Token::NoteText | Token::PoolCategory | Token::Special | Token::TagCategory
+                Token::Child
+                    | Token::ContentChecksum
+                    | Token::NoteText
+                    | Token::Parent
+                    | Token::PoolCategory
+                    | Token::Special
+                    | Token::TagCategory
             ) {
                 return Err(ApiError::InvalidSort);
             }
@@ -245,8 +274,25 @@ impl<'a> QueryBuilder<'a> {
         Ok(Self {
             search,
             cache_state: CacheState::default(),
+            deleted: false,
         })
     }
+
+    /// Restricts search to deleted posts instead of excluding them.
+    pub fn set_deleted(&mut self, deleted: bool) {
+        self.deleted = deleted;
+    }
+
+    /// Pending posts are only included in searches if the client is allowed to approve them
+    /// and has explicitly asked for them with a `status` filter.
+    fn can_search_pending(&self) -> bool {
+        self.search.ctx.has_privilege(Action::PostApprove)
+            && self
+                .search
+                .filters
+                .iter()
+                .any(|filter| matches!(filter.kind, Token::Status))
+    }
 }
 
 type BoxedQuery =
@@ -265,6 +311,7 @@ enum SpecialToken {
     Disliked,
     Fav,
     Tumbleweed,
+    HasParent,
 }
 
 type Bind = UncheckedBind<SqlLiteral<Float, UncheckedBind<SqlLiteral<Float>, post::width>>, post::height>;
@@ -439,6 +486,37 @@ fn apply_note_text_filter(
     Ok(query)
 }
 
+fn apply_parent_filter(
+    conn: &mut PgConnection,
+    query: BoxedQuery,
+    filter: UnparsedFilter<Token>,
+    state: &mut CacheState,
+) -> ApiResult<BoxedQuery> {
+    let post_relations = post_relation::table
+        .select(post_relation::child_id)
+        .filter(post_relation::kind.eq(RelationKind::Directed))
+        .into_boxed();
+    let filtered_posts = apply_filter!(post_relations, post_relation::parent_id, filter.unnegated(), i64)?;
+    update_filter_cache!(conn, filtered_posts, post_relation::child_id, filter, state)?;
+    Ok(query)
+}
+
+fn apply_child_filter(
+    conn: &mut PgConnection,
+    query: BoxedQuery,
+    filter: UnparsedFilter<Token>,
+    state: &mut CacheState,
+) -> ApiResult<BoxedQuery> {
+    let post_relations = post_relation::table
+        .select(post_relation::parent_id)
+        .filter(post_relation::kind.eq(RelationKind::Directed))
+        .into_boxed();
+    let post_relations = apply_distinct_if_multivalued!(post_relations, filter);
+    let filtered_posts = apply_filter!(post_relations, post_relation::child_id, filter.unnegated(), i64)?;
+    update_filter_cache!(conn, filtered_posts, post_relation::parent_id, filter, state)?;
+    Ok(query)
+}
+
 fn apply_comment_time_filter(
     conn: &mut PgConnection,
     query: BoxedQuery,
@@ -521,6 +599,12 @@ fn apply_special_filter(
                 .having(count(post_score::post_id).eq(0));
             Ok(update_filter_cache!(conn, filtered_posts, post_statistics::post_id, filter, state))
         }
+        SpecialToken::HasParent => {
+            let filtered_posts = post_relation::table
+                .select(post_relation::child_id)
+                .filter(post_relation::kind.eq(RelationKind::Directed));
+            Ok(update_filter_cache!(conn, filtered_posts, post_relation::child_id, filter, state))
+        }
     }??;
     Ok(query)
 }
@@ -536,6 +620,7 @@ pub fn filter_table() -> TokenTable<&'static str> {
         _aspect_ratio: "1.1..2.5",
         _safety: "-sketchy,unsafe",
         _type: "-image",
+        _status: "approved",
         _content_checksum: "3031000000000000000000000000000000000000000000000000000000000000",
         _flag: "sound",
         _source: "-*_*",
@@ -550,6 +635,8 @@ pub fn filter_table() -> TokenTable<&'static str> {
         _fav: "*user*",
         _comment: "-*user*",
         _note_text: "*fav*",
+        _parent: "3,5",
+        _child: "-4",
         _tag_count: "1..5",
         _pool_count: "-2",
         _comment_count: "-1..",
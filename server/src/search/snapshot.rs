@@ -8,6 +8,7 @@ use diesel::dsl::{IntoBoxed, LeftJoin, Select};
 use diesel::pg::Pg;
 use diesel::{ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
 use strum::EnumString;
+use tracing::instrument;
 
 #[derive(Clone, Copy, EnumString)]
 #[strum(serialize_all = "kebab-case")]
@@ -38,6 +39,7 @@ impl<'a> Builder<'a> for QueryBuilder<'a> {
         unsorted_query.count().first(conn).map_err(ApiError::from)
     }
 
+    #[instrument(skip_all)]
     fn build_filtered(&mut self, _conn: &mut PgConnection) -> ApiResult<BoxedQuery> {
         let base_query = snapshot::table.select(snapshot::id).left_join(user::table).into_boxed();
         self.search
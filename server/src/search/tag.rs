@@ -1,6 +1,6 @@
 use crate::api::error::{ApiError, ApiResult};
 use crate::app::Context;
-use crate::model::tag::TagName;
+use crate::model::tag::{TagImplication, TagName};
 use crate::schema::{
     database_statistics, tag, tag_category, tag_implication, tag_name, tag_statistics, tag_suggestion,
 };
@@ -13,6 +13,7 @@ use diesel::dsl::{InnerJoin, IntoBoxed, Select};
 use diesel::pg::Pg;
 use diesel::{ExpressionMethods, JoinOnDsl, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
 use strum::{Display, EnumIter, EnumString, EnumTable};
+use tracing::instrument;
 
 #[derive(Display, Clone, Copy, EnumTable, EnumIter, EnumString)]
 #[strum(serialize_all = "kebab-case")]
@@ -35,6 +36,8 @@ pub enum Token {
     SuggestionCount,
     Implies,
     Suggests,
+    ImpliedBy,
+    ImpliesTransitive,
 }
 
 pub struct QueryBuilder<'a> {
@@ -62,6 +65,7 @@ impl<'a> Builder<'a> for QueryBuilder<'a> {
         .map_err(ApiError::from)
     }
 
+    #[instrument(skip_all)]
     fn build_filtered(&mut self, conn: &mut PgConnection) -> ApiResult<BoxedQuery> {
         let base_query = tag::table
             .select(tag::id)
@@ -83,6 +87,8 @@ impl<'a> Builder<'a> for QueryBuilder<'a> {
                 Token::SuggestionCount => apply_filter!(query, tag_statistics::suggestion_count, filter, i64),
                 Token::Implies => apply_implies_filter(conn, query, filter, &mut self.cache_state),
                 Token::Suggests => apply_suggests_filter(conn, query, filter, &mut self.cache_state),
+                Token::ImpliedBy => apply_closure_filter(conn, query, filter, &mut self.cache_state, true),
+                Token::ImpliesTransitive => apply_closure_filter(conn, query, filter, &mut self.cache_state, false),
             })?;
         Ok(apply_cache_filters!(query, tag::id, self.cache_state))
     }
@@ -106,7 +112,7 @@ impl<'a> Builder<'a> for QueryBuilder<'a> {
             Token::Category => apply_sort!(query, tag_category::name, sort),
             Token::Description => apply_sort!(query, tag::description, sort),
             Token::UsageCount => apply_sort!(query, tag_statistics::usage_count, sort),
-            Token::ImplicationCount | Token::Implies => {
+            Token::ImplicationCount | Token::Implies | Token::ImpliedBy | Token::ImpliesTransitive => {
                 apply_sort!(query, tag_statistics::implication_count, sort)
             }
             Token::SuggestionCount | Token::Suggests => apply_sort!(query, tag_statistics::suggestion_count, sort),
@@ -200,6 +206,39 @@ fn apply_suggests_filter(
     Ok(query)
 }
 
+/// Filters on the transitive closure of implications of tags matching `filter`.
+/// If `downwards` is true, matches tags implied by the named tags, directly or indirectly.
+/// Otherwise, matches tags which imply the named tags, directly or indirectly.
+fn apply_closure_filter(
+    conn: &mut PgConnection,
+    query: BoxedQuery,
+    filter: UnparsedFilter<Token>,
+    state: &mut CacheState,
+    downwards: bool,
+) -> ApiResult<BoxedQuery> {
+    let names = tag_name::table.select(tag_name::tag_id).into_boxed();
+    let names = apply_distinct_if_multivalued!(names, filter);
+    let named_tags: Vec<i64> = apply_str_filter!(names, tag_name::name, filter.unnegated()).load(conn)?;
+    let closure_ids: Vec<i64> = if downwards {
+        TagImplication::descendants(conn, &named_tags)?
+            .into_iter()
+            .map(|implication| implication.child_id)
+            .collect()
+    } else {
+        TagImplication::ancestors(conn, &named_tags)?
+            .into_iter()
+            .map(|implication| implication.parent_id)
+            .collect()
+    };
+
+    let filtered_tags = tag::table
+        .select(tag::id)
+        .filter(tag::id.eq_any(closure_ids))
+        .into_boxed();
+    update_filter_cache!(conn, filtered_tags, tag::id, filter, state)?;
+    Ok(query)
+}
+
 #[cfg(test)]
 pub fn filter_table() -> TokenTable<&'static str> {
     TokenTable {
@@ -213,5 +252,7 @@ pub fn filter_table() -> TokenTable<&'static str> {
         _suggestion_count: "-2..",
         _implies: "-sky",
         _suggests: "-*k*",
+        _implied_by: "forest",
+        _implies_transitive: "-plant",
     }
 }
@@ -7,6 +7,7 @@ use diesel::dsl::{IntoBoxed, Select};
 use diesel::pg::Pg;
 use diesel::{ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
 use strum::{Display, EnumIter, EnumString, EnumTable, IntoStaticStr};
+use tracing::instrument;
 
 #[derive(Display, Clone, Copy, EnumIter, EnumString, EnumTable, IntoStaticStr)]
 pub enum Token {
@@ -47,6 +48,7 @@ impl<'a> Builder<'a> for QueryBuilder<'a> {
         .map_err(ApiError::from)
     }
 
+    #[instrument(skip_all)]
     fn build_filtered(&mut self, _conn: &mut PgConnection) -> ApiResult<BoxedQuery> {
         let base_query = user::table.select(user::id).into_boxed();
         self.search
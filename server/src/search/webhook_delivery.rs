@@ -0,0 +1,104 @@
+use crate::api::error::{ApiError, ApiResult};
+use crate::app::Context;
+use crate::model::enums::WebhookDeliveryStatus;
+use crate::schema::webhook_delivery;
+use crate::search::{Builder, Order, ParsedSort, SearchCriteria};
+use crate::{apply_filter, apply_random_sort, apply_sort, apply_str_filter, apply_time_filter};
+use diesel::dsl::{IntoBoxed, Select};
+use diesel::pg::Pg;
+use diesel::{ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
+use strum::{Display, EnumIter, EnumString, EnumTable};
+use tracing::instrument;
+
+#[derive(Display, Clone, Copy, EnumTable, EnumIter, EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Token {
+    Id,
+    Snapshot,
+    Url,
+    Status,
+    Attempts,
+    #[strum(serialize = "creation-date", serialize = "creation-time")]
+    CreationTime,
+}
+
+pub struct QueryBuilder<'a> {
+    search: SearchCriteria<'a, Token>,
+}
+
+impl<'a> Builder<'a> for QueryBuilder<'a> {
+    type Token = Token;
+    type BoxedQuery = BoxedQuery;
+
+    fn criteria(&mut self) -> &mut SearchCriteria<'a, Self::Token> {
+        &mut self.search
+    }
+
+    fn count(&mut self, conn: &mut PgConnection) -> ApiResult<i64> {
+        let unsorted_query = self.build_filtered(conn)?;
+        unsorted_query.count().first(conn).map_err(ApiError::from)
+    }
+
+    #[instrument(skip_all)]
+    fn build_filtered(&mut self, _conn: &mut PgConnection) -> ApiResult<BoxedQuery> {
+        let base_query = webhook_delivery::table.select(webhook_delivery::id).into_boxed();
+        self.search
+            .filters
+            .iter()
+            .try_fold(base_query, |query, filter| match filter.kind {
+                Token::Id => apply_filter!(query, webhook_delivery::id, filter, i64),
+                Token::Snapshot => apply_filter!(query, webhook_delivery::snapshot_id, filter, i64),
+                Token::Url => Ok(apply_str_filter!(query, webhook_delivery::url, filter)),
+                Token::Status => apply_filter!(query, webhook_delivery::status, filter, WebhookDeliveryStatus),
+                Token::Attempts => apply_filter!(query, webhook_delivery::attempt_count, filter, i32),
+                Token::CreationTime => apply_time_filter!(query, webhook_delivery::creation_time, filter),
+            })
+    }
+
+    fn get_ordered_ids(&self, conn: &mut PgConnection, unsorted_query: BoxedQuery) -> QueryResult<Vec<i64>> {
+        // If random sort specified, no other sorts matter
+        if self.search.random_sort {
+            return apply_random_sort!(conn, self.search.ctx.client, unsorted_query, self.search).load(conn);
+        }
+
+        let default_sort = std::iter::once(ParsedSort {
+            kind: Token::Id,
+            order: Order::default(),
+        });
+        let sorts = self.search.sorts.iter().copied().chain(default_sort);
+        let query = sorts.fold(unsorted_query, |query, sort| match sort.kind {
+            Token::Id => apply_sort!(query, webhook_delivery::id, sort),
+            Token::Snapshot => apply_sort!(query, webhook_delivery::snapshot_id, sort),
+            Token::Url => apply_sort!(query, webhook_delivery::url, sort),
+            Token::Status => apply_sort!(query, webhook_delivery::status, sort),
+            Token::Attempts => apply_sort!(query, webhook_delivery::attempt_count, sort),
+            Token::CreationTime => apply_sort!(query, webhook_delivery::creation_time, sort),
+        });
+        match self.search.extra_args {
+            Some(args) => query.offset(args.offset).limit(args.limit),
+            None => query,
+        }
+        .load(conn)
+    }
+}
+
+impl<'a> QueryBuilder<'a> {
+    pub fn new(ctx: &'a Context, search_criteria: &'a str) -> ApiResult<Self> {
+        let search = SearchCriteria::new(ctx, search_criteria, Token::Url).map_err(Box::from)?;
+        Ok(Self { search })
+    }
+}
+
+type BoxedQuery = IntoBoxed<'static, Select<webhook_delivery::table, webhook_delivery::id>, Pg>;
+
+#[cfg(test)]
+pub fn filter_table() -> TokenTable<&'static str> {
+    TokenTable {
+        _id: "-2",
+        _snapshot: "1",
+        _url: "*example.org*",
+        _status: "failed,delivered",
+        _attempts: "1..",
+        _creation_time: "2016",
+    }
+}
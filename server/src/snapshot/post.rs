@@ -1,14 +1,16 @@
 use crate::api::error::{ApiError, ApiResult};
 use crate::auth::Client;
 use crate::content::hash::Checksum;
-use crate::model::enums::{PostFlags, PostSafety, ResourceOperation, ResourceType};
+use crate::model::enums::{
+    NotificationKind, PostFlags, PostSafety, PostStatus, RelationKind, ResourceOperation, ResourceType,
+};
 use crate::model::post::{Post, PostNote};
 use crate::model::snapshot::NewSnapshot;
 use crate::model::tag::TagName;
 use crate::resource::post::Note;
-use crate::schema::{post_feature, post_note, post_relation, post_tag, tag_name};
-use crate::snapshot;
+use crate::schema::{post, post_feature, post_file, post_note, post_relation, post_tag, tag_name};
 use crate::string::{LargeString, SmallString};
+use crate::{notification, snapshot};
 use diesel::{
     ExpressionMethods, JoinOnDsl, OptionalExtension, PgConnection, QueryDsl, QueryResult, RunQueryDsl, SelectableHelper,
 };
@@ -24,6 +26,7 @@ pub struct SnapshotData {
     pub description: LargeString,
     pub tags: Vec<SmallString>,
     pub relations: Vec<i64>,
+    pub parent: Option<i64>,
     pub notes: Vec<Note>,
     pub featured: bool,
 }
@@ -39,7 +42,14 @@ impl SnapshotData {
         let relations = post_relation::table
             .select(post_relation::child_id)
             .filter(post_relation::parent_id.eq(post.id))
+            .filter(post_relation::kind.eq(RelationKind::Symmetric))
             .load(conn)?;
+        let parent = post_relation::table
+            .select(post_relation::parent_id)
+            .filter(post_relation::child_id.eq(post.id))
+            .filter(post_relation::kind.eq(RelationKind::Directed))
+            .first(conn)
+            .optional()?;
         let notes = post_note::table
             .select(PostNote::as_select())
             .filter(post_note::post_id.eq(post.id))
@@ -57,6 +67,7 @@ impl SnapshotData {
             description: post.description,
             tags,
             relations,
+            parent,
             notes: notes.into_iter().map(Note::new).collect(),
             featured: latest_feature == Some(post.id),
         })
@@ -69,6 +80,25 @@ impl SnapshotData {
     }
 }
 
+/// The content checksums of a post and its files, in order.
+#[derive(Serialize)]
+pub struct FileSnapshotData {
+    pub checksum: Checksum,
+    pub files: Vec<Checksum>,
+}
+
+impl FileSnapshotData {
+    pub fn retrieve(conn: &mut PgConnection, post_id: i64) -> QueryResult<Self> {
+        let checksum = post::table.find(post_id).select(post::checksum).first(conn)?;
+        let files = post_file::table
+            .select(post_file::checksum)
+            .filter(post_file::post_id.eq(post_id))
+            .order(post_file::order)
+            .load(conn)?;
+        Ok(Self { checksum, files })
+    }
+}
+
 pub fn creation_snapshot(
     conn: &mut PgConnection,
     client: Client,
@@ -117,6 +147,67 @@ pub fn feature_snapshot(
     Ok(())
 }
 
+pub fn status_snapshot(
+    conn: &mut PgConnection,
+    client: Client,
+    post_id: i64,
+    old_status: PostStatus,
+    new_status: PostStatus,
+) -> QueryResult<()> {
+    let old_data = json!({"status": old_status});
+    let new_data = json!({"status": new_status});
+    let Some(data) = snapshot::value_diff(old_data, new_data) else {
+        return Ok(());
+    };
+    NewSnapshot {
+        user_id: client.id,
+        operation: ResourceOperation::Modified,
+        resource_type: ResourceType::Post,
+        resource_id: post_id.into(),
+        data,
+    }
+    .insert(conn)
+}
+
+pub fn files_snapshot(
+    conn: &mut PgConnection,
+    client: Client,
+    post_id: i64,
+    old: FileSnapshotData,
+    new: FileSnapshotData,
+) -> ApiResult<()> {
+    let old_data = serde_json::to_value(&old)?;
+    let new_data = serde_json::to_value(&new)?;
+
+    // List diffs ignore order, so a reordering of the same files is recorded as a change of the whole list
+    let data = snapshot::value_diff(old_data, new_data).or_else(|| {
+        (old.files != new.files).then(|| {
+            json!({
+                "type": "object change",
+                "value": {
+                    "files": {
+                        "type": "primitive change",
+                        "old-value": old.files,
+                        "new-value": new.files,
+                    }
+                }
+            })
+        })
+    });
+    if let Some(data) = data {
+        NewSnapshot {
+            user_id: client.id,
+            operation: ResourceOperation::Modified,
+            resource_type: ResourceType::Post,
+            resource_id: post_id.into(),
+            data,
+        }
+        .insert(conn)?;
+        notification::notify_uploader(conn, client, post_id, NotificationKind::PostEdit)?;
+    }
+    Ok(())
+}
+
 pub fn merge_snapshot(
     conn: &mut PgConnection,
     client: Client,
@@ -154,6 +245,7 @@ pub fn modification_snapshot(
             data,
         }
         .insert(conn)?;
+        notification::notify_uploader(conn, client, post_id, NotificationKind::PostEdit)?;
     }
     Ok(())
 }
@@ -167,6 +259,15 @@ pub fn deletion_snapshot(
     unary_snapshot(conn, client, post_id, post_data, ResourceOperation::Deleted)
 }
 
+pub fn restoration_snapshot(
+    conn: &mut PgConnection,
+    client: Client,
+    post_id: i64,
+    post_data: SnapshotData,
+) -> ApiResult<()> {
+    unary_snapshot(conn, client, post_id, post_data, ResourceOperation::Restored)
+}
+
 pub fn unary_snapshot(
     conn: &mut PgConnection,
     client: Client,
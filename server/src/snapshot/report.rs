@@ -0,0 +1,27 @@
+use crate::auth::Client;
+use crate::model::enums::{ReportStatus, ResourceOperation, ResourceType};
+use crate::model::report::Report;
+use crate::model::snapshot::NewSnapshot;
+use crate::snapshot;
+use diesel::{PgConnection, QueryResult};
+use serde_json::json;
+
+/// Records the resolution or dismissal of an open `report`.
+pub fn resolution_snapshot(
+    conn: &mut PgConnection,
+    client: Client,
+    report: &Report,
+    new_status: ReportStatus,
+) -> QueryResult<()> {
+    let old_data = json!({"status": report.status});
+    let new_data = json!({"status": new_status});
+    let data = snapshot::value_diff(old_data, new_data).expect("Report status must change");
+    NewSnapshot {
+        user_id: client.id,
+        operation: ResourceOperation::Modified,
+        resource_type: ResourceType::Report,
+        resource_id: report.id.into(),
+        data,
+    }
+    .insert(conn)
+}
@@ -0,0 +1,80 @@
+use crate::auth::Client;
+use crate::model::enums::{ResourceOperation, ResourceType};
+use crate::model::snapshot::NewSnapshot;
+use crate::model::user::UserSanction;
+use crate::snapshot;
+use crate::string::SmallString;
+use diesel::{PgConnection, QueryResult};
+use serde_json::{Value, json};
+
+/// Records a `sanction` being issued against the user called `username`.
+///
+/// Snapshots are visible to users who may not be able to view sanctions, so the reason is left out.
+pub fn sanction_creation_snapshot(
+    conn: &mut PgConnection,
+    client: Client,
+    username: SmallString,
+    sanction: &UserSanction,
+) -> QueryResult<()> {
+    let sanction_data = sanction_data(sanction);
+    modification_snapshot(conn, client, username, json!({"sanctions": []}), json!({"sanctions": [sanction_data]}))
+}
+
+/// Records a `sanction` being lifted from the user called `username`.
+pub fn sanction_deletion_snapshot(
+    conn: &mut PgConnection,
+    client: Client,
+    username: SmallString,
+    sanction: &UserSanction,
+) -> QueryResult<()> {
+    let sanction_data = sanction_data(sanction);
+    modification_snapshot(conn, client, username, json!({"sanctions": [sanction_data]}), json!({"sanctions": []}))
+}
+
+/// Records a note being attached to the user called `username`.
+///
+/// Only the note id is recorded, as the contents are visible to moderators only.
+pub fn note_creation_snapshot(
+    conn: &mut PgConnection,
+    client: Client,
+    username: SmallString,
+    note_id: i64,
+) -> QueryResult<()> {
+    modification_snapshot(conn, client, username, json!({"notes": []}), json!({"notes": [note_id]}))
+}
+
+/// Records a note being removed from the user called `username`.
+pub fn note_deletion_snapshot(
+    conn: &mut PgConnection,
+    client: Client,
+    username: SmallString,
+    note_id: i64,
+) -> QueryResult<()> {
+    modification_snapshot(conn, client, username, json!({"notes": [note_id]}), json!({"notes": []}))
+}
+
+fn sanction_data(sanction: &UserSanction) -> Value {
+    json!({
+        "id": sanction.id,
+        "type": sanction.kind,
+        "expirationTime": sanction.expiration_time,
+    })
+}
+
+fn modification_snapshot(
+    conn: &mut PgConnection,
+    client: Client,
+    username: SmallString,
+    old_data: Value,
+    new_data: Value,
+) -> QueryResult<()> {
+    let data = snapshot::value_diff(old_data, new_data).expect("User moderation data must change");
+    NewSnapshot {
+        user_id: client.id,
+        operation: ResourceOperation::Modified,
+        resource_type: ResourceType::User,
+        resource_id: username,
+        data,
+    }
+    .insert(conn)
+}
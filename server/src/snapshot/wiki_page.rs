@@ -0,0 +1,177 @@
+use crate::api::error::{ApiError, ApiResult};
+use crate::auth::Client;
+use crate::model::enums::{ResourceOperation, ResourceType};
+use crate::model::snapshot::{NewSnapshot, Snapshot};
+use crate::model::tag::TagName;
+use crate::model::wiki_page::WikiPage;
+use crate::schema::{snapshot, tag_name};
+use crate::snapshot;
+use crate::string::{LargeString, SmallString};
+use crate::time::DateTime;
+use diesel::{
+    ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, QueryResult, RunQueryDsl, SelectableHelper,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SnapshotData {
+    pub title: Option<SmallString>,
+    pub tag: Option<SmallString>,
+    pub body: LargeString,
+}
+
+impl SnapshotData {
+    pub fn retrieve(conn: &mut PgConnection, page: WikiPage) -> QueryResult<Self> {
+        let tag = match page.tag_id {
+            Some(tag_id) => tag_name::table
+                .select(tag_name::name)
+                .filter(tag_name::tag_id.eq(tag_id))
+                .filter(TagName::is_primary())
+                .first(conn)
+                .optional()?,
+            None => None,
+        };
+        Ok(Self {
+            title: page.title,
+            tag,
+            body: page.body,
+        })
+    }
+}
+
+/// A version of a wiki page, reconstructed from its snapshot history.
+pub struct Revision {
+    pub user_id: Option<i64>,
+    pub data: SnapshotData,
+    pub time: DateTime,
+}
+
+pub fn creation_snapshot(
+    conn: &mut PgConnection,
+    client: Client,
+    page_id: i64,
+    page_data: SnapshotData,
+) -> ApiResult<()> {
+    unary_snapshot(conn, client, page_id, page_data, ResourceOperation::Created)
+}
+
+pub fn modification_snapshot(
+    conn: &mut PgConnection,
+    client: Client,
+    page_id: i64,
+    old: SnapshotData,
+    new: SnapshotData,
+) -> ApiResult<()> {
+    let old_data = serde_json::to_value(old)?;
+    let new_data = serde_json::to_value(new)?;
+    if let Some(data) = snapshot::value_diff(old_data, new_data) {
+        NewSnapshot {
+            user_id: client.id,
+            operation: ResourceOperation::Modified,
+            resource_type: ResourceType::WikiPage,
+            resource_id: page_id.into(),
+            data,
+        }
+        .insert(conn)?;
+    }
+    Ok(())
+}
+
+pub fn deletion_snapshot(
+    conn: &mut PgConnection,
+    client: Client,
+    page_id: i64,
+    page_data: SnapshotData,
+) -> ApiResult<()> {
+    unary_snapshot(conn, client, page_id, page_data, ResourceOperation::Deleted)
+}
+
+/// Reconstructs every revision of the wiki page with id `page_id` by replaying
+/// its creation and modification snapshots. Revisions are ordered from oldest to newest.
+pub fn revisions(conn: &mut PgConnection, page_id: i64) -> ApiResult<Vec<Revision>> {
+    let snapshots: Vec<Snapshot> = snapshot::table
+        .select(Snapshot::as_select())
+        .filter(snapshot::resource_type.eq(ResourceType::WikiPage))
+        .filter(snapshot::resource_id.eq(SmallString::from(page_id)))
+        .filter(snapshot::operation.ne(ResourceOperation::Deleted))
+        .order(snapshot::id)
+        .load(conn)?;
+
+    let mut state: Option<Value> = None;
+    let mut revisions = Vec::with_capacity(snapshots.len());
+    for snapshot in snapshots {
+        if matches!(snapshot.operation, ResourceOperation::Created) {
+            state = Some(snapshot.data);
+        } else if let Some(state) = state.as_mut() {
+            apply_diff(state, snapshot.data);
+        }
+
+        // Modifications made before the creation snapshot can't be replayed
+        let Some(data) = &state else {
+            continue;
+        };
+        revisions.push(Revision {
+            user_id: snapshot.user_id,
+            data: SnapshotData::deserialize(data)?,
+            time: snapshot.creation_time,
+        });
+    }
+    Ok(revisions)
+}
+
+/// Retrieves revision number `revision` of the wiki page with id `page_id`.
+/// Revisions are numbered starting from 1.
+pub fn revision(conn: &mut PgConnection, page_id: i64, revision: usize) -> ApiResult<Revision> {
+    let mut revisions = revisions(conn, page_id)?;
+    if revision == 0 || revision > revisions.len() {
+        return Err(ApiError::RevisionNotFound(revision));
+    }
+    Ok(revisions.swap_remove(revision - 1))
+}
+
+/// Applies the property changes of an object `diff` created by [`snapshot::value_diff`] to `state`.
+fn apply_diff(state: &mut Value, diff: Value) {
+    let (Some(state), Value::Object(mut diff)) = (state.as_object_mut(), diff) else {
+        return;
+    };
+    let Some(Value::Object(changes)) = diff.remove("value") else {
+        return;
+    };
+    for (key, mut change) in changes {
+        match change["type"].as_str() {
+            Some("primitive change" | "property changed type") => {
+                state.insert(key, change["new-value"].take());
+            }
+            Some("added_property") => {
+                state.insert(key, change["value"].take());
+            }
+            Some("deleted property") => {
+                state.remove(&key);
+            }
+            _ => (),
+        }
+    }
+}
+
+fn unary_snapshot(
+    conn: &mut PgConnection,
+    client: Client,
+    page_id: i64,
+    page_data: SnapshotData,
+    operation: ResourceOperation,
+) -> ApiResult<()> {
+    serde_json::to_value(page_data)
+        .map_err(ApiError::from)
+        .and_then(|data| {
+            NewSnapshot {
+                user_id: client.id,
+                operation,
+                resource_type: ResourceType::WikiPage,
+                resource_id: page_id.into(),
+                data,
+            }
+            .insert(conn)
+            .map_err(ApiError::from)
+        })
+}
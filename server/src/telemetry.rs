@@ -0,0 +1,143 @@
+//! Spans can be exported to an OpenTelemetry collector over OTLP when the server is built with the
+//! `otel` feature and trace export is configured. Request spans continue any trace given in the
+//! W3C trace context headers of the request, so that traces started by clients or reverse proxies
+//! extend into the server.
+
+use crate::config::Config;
+use axum::extract::{MatchedPath, Request};
+use axum::response::Response;
+use std::time::Duration;
+use tower_http::trace::{DefaultOnResponse, OnResponse};
+use tracing::field::Empty;
+use tracing::{Span, info_span};
+
+/// Returns a layer that exports spans to the configured collector, or `None` if trace export
+/// isn't configured or fails to start.
+#[cfg(feature = "otel")]
+pub fn layer(
+    config: &Config,
+) -> Option<tracing_opentelemetry::OpenTelemetryLayer<tracing_subscriber::Registry, exporter::Tracer>> {
+    let trace_export_config = config.trace_export()?;
+    match exporter::start(trace_export_config) {
+        Ok(tracer) => Some(tracing_opentelemetry::layer().with_tracer(tracer)),
+        Err(err) => {
+            // Logging isn't initialized yet, as this layer is part of it
+            eprintln!("Failed to start trace export. Details:\n{err}");
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+pub fn layer(_config: &Config) -> Option<tracing_subscriber::layer::Identity> {
+    None
+}
+
+/// Exports any spans that haven't been sent to the collector yet.
+#[cfg(feature = "otel")]
+pub fn shutdown() {
+    exporter::shutdown();
+}
+
+#[cfg(not(feature = "otel"))]
+pub fn shutdown() {}
+
+/// Creates the span of an HTTP request, continuing the trace given in its headers, if any.
+pub fn request_span<B>(request: &Request<B>) -> Span {
+    let method = request.method();
+    let route = request.extensions().get::<MatchedPath>().map(MatchedPath::as_str);
+    let name = format!("{method} {}", route.unwrap_or("unmatched"));
+    let span = info_span!(
+        "request",
+        otel.name = name.as_str(),
+        otel.kind = "server",
+        http.request.method = %method,
+        http.route = route,
+        url.path = request.uri().path(),
+        http.response.status_code = Empty,
+    );
+
+    #[cfg(feature = "otel")]
+    exporter::set_remote_parent(&span, request.headers());
+    span
+}
+
+/// Records the status of a response on the span of its request.
+pub fn record_response<B>(response: &Response<B>, latency: Duration, span: &Span) {
+    span.record("http.response.status_code", response.status().as_u16());
+    DefaultOnResponse::default().on_response(response, latency, span);
+}
+
+#[cfg(feature = "otel")]
+mod exporter {
+    use crate::config::TraceExportConfig;
+    use axum::http::HeaderMap;
+    use axum::http::header::HeaderName;
+    use opentelemetry::propagation::Extractor;
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_otlp::{ExporterBuildError, SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::Resource;
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use std::sync::OnceLock;
+    use tracing::{Span, debug, error};
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    pub use opentelemetry_sdk::trace::SdkTracer as Tracer;
+
+    static TRACER_PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+    /// Reads trace context from request headers.
+    struct HeaderExtractor<'a>(&'a HeaderMap);
+
+    impl Extractor for HeaderExtractor<'_> {
+        fn get(&self, key: &str) -> Option<&str> {
+            self.0.get(key).and_then(|value| value.to_str().ok())
+        }
+
+        fn keys(&self) -> Vec<&str> {
+            self.0.keys().map(HeaderName::as_str).collect()
+        }
+    }
+
+    /// Starts exporting spans in batches to the configured collector.
+    pub fn start(config: &TraceExportConfig) -> Result<Tracer, ExporterBuildError> {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(config.endpoint.as_str())
+            .build()?;
+        let resource = Resource::builder()
+            .with_service_name(config.service_name().to_owned())
+            .build();
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(resource)
+            .build();
+
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+        let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+        let _ = TRACER_PROVIDER.set(provider);
+        Ok(tracer)
+    }
+
+    pub fn shutdown() {
+        if let Some(provider) = TRACER_PROVIDER.get()
+            && let Err(err) = provider.shutdown()
+        {
+            error!("Failed to export remaining spans. Details:\n{err}");
+        }
+    }
+
+    /// Makes `span` a child of the remote span given in the `traceparent` header, if any.
+    pub fn set_remote_parent(span: &Span, headers: &HeaderMap) {
+        if TRACER_PROVIDER.get().is_none() {
+            return;
+        }
+
+        let context =
+            opentelemetry::global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)));
+        if let Err(err) = span.set_parent(context) {
+            debug!("Could not continue remote trace. Details:\n{err}");
+        }
+    }
+}
@@ -7,27 +7,31 @@ use crate::content::hash::{Checksum, Md5Checksum, PostHash};
 use crate::content::{decode, download, signature};
 use crate::db::Connection;
 use crate::filesystem::Directory;
+use crate::model::audit_log::NewAuditLogEntry;
 use crate::model::comment::{NewComment, NewCommentScore};
 use crate::model::enums::{
-    AvatarStyle, MimeType, PostFlag, PostFlags, PostSafety, PostType, ResourceType, Score, UserRank,
+    AuditEvent, AvatarStyle, MimeType, PostFlag, PostFlags, PostSafety, PostStatus, PostType, ReportStatus,
+    ResourceOperation, ResourceType, SanctionKind, Score, UserRank, WebhookDeliveryStatus,
 };
 use crate::model::pool::{NewPool, NewPoolName, PoolPost};
 use crate::model::pool_category::NewPoolCategory;
 use crate::model::post::{
     NewPost, NewPostFavorite, NewPostFeature, NewPostNote, NewPostScore, NewPostSignature, PostRelation, PostTag,
 };
+use crate::model::report::NewReport;
+use crate::model::snapshot::NewSnapshot;
 use crate::model::tag::{NewTag, NewTagName, TagImplication, TagSuggestion};
 use crate::model::tag_category::NewTagCategory;
-use crate::model::user::{NewUser, NewUserToken};
+use crate::model::user::{NewUser, NewUserNote, NewUserSanction, NewUserToken};
 use crate::schema::{
-    comment, comment_score, pool, pool_category, pool_category_statistics, pool_name, pool_post, pool_statistics, post,
-    post_favorite, post_feature, post_note, post_relation, post_score, post_signature, post_statistics, post_tag,
-    snapshot, tag, tag_category, tag_category_statistics, tag_implication, tag_name, tag_statistics, tag_suggestion,
-    user, user_token,
+    audit_log, comment, comment_score, pool, pool_category, pool_category_statistics, pool_name, pool_post,
+    pool_statistics, post, post_favorite, post_feature, post_note, post_relation, post_score, post_signature,
+    post_statistics, post_tag, report, snapshot, tag, tag_category, tag_category_statistics, tag_implication, tag_name,
+    tag_statistics, tag_suggestion, user, user_note, user_sanction, user_token, webhook_delivery,
 };
 use crate::string::SmallString;
 use crate::time::DateTime;
-use crate::{api, config, db};
+use crate::{api, config, db, update};
 use argon2::password_hash::rand_core::{OsRng, RngCore};
 use axum::ServiceExt;
 use axum::extract::Request;
@@ -36,7 +40,7 @@ use axum::http::header::AUTHORIZATION;
 use axum_test::TestServer;
 use diesel::r2d2::{ConnectionManager, Pool, PoolError};
 use diesel::{ExpressionMethods, Insertable, JoinOnDsl, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
-use serde_json::Value;
+use serde_json::{Map, Value};
 use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, MutexGuard};
@@ -45,7 +49,7 @@ use tower_layer::Layer;
 use uuid::Uuid;
 
 pub const TEST_PASSWORD: &str = "test_password";
-pub const TEST_SALT: &str = "test_salt";
+pub const TEST_SALT: &str = "dGVzdF9zYWx0";
 pub const TEST_HASH: &str = "$argon2id$v=19$m=19456,t=2,p=1$dGVzdF9zYWx0$voqGcDZhS6JWiMJy9q12zBgrC6OTBKa9dL8k0O8gD4M";
 pub const TEST_TOKEN: Uuid = uuid::uuid!("67e55044-10b1-426f-9247-bb680e5fe0c8");
 pub const EXPIRED_TOKEN: Uuid = uuid::uuid!("b7188ca3-1391-4abf-bfc1-7d7dfad7d161");
@@ -149,16 +153,13 @@ pub async fn verify_response_with_credentials(
         app_state.config = Arc::new(config);
     }
 
-    let (router, _) = api::routes(app_state).split_for_parts();
-    let app = NormalizePathLayer::trim_trailing_slash().layer(router);
     let (method, path) = request
         .split_once(' ')
         .expect("Request string must have method and path separated by a space");
     let method = Method::try_from(method).expect("Request string must start with a valid method");
     let path = path.replace(' ', "%20"); // Percent-encode all spaces
 
-    let server =
-        TestServer::new(ServiceExt::<Request>::into_make_service(app)).expect("Test server must be constructible");
+    let server = test_server(app_state);
     let mut request = server.method(method, &path);
     if let Some(credentials) = credentials {
         request = request.add_header(AUTHORIZATION, credentials);
@@ -192,6 +193,13 @@ pub async fn verify_response_with_credentials(
     Ok(())
 }
 
+/// Returns a server that serves the API with the given `app_state`.
+pub fn test_server(app_state: AppState) -> TestServer {
+    let (router, _) = api::routes(app_state).split_for_parts();
+    let app = NormalizePathLayer::trim_trailing_slash().layer(router);
+    TestServer::new(ServiceExt::<Request>::into_make_service(app)).expect("Test server must be constructible")
+}
+
 const DATABASE_NAME: &str = "__test";
 
 const USERS: &[NewUser] = &[
@@ -226,6 +234,36 @@ const USER_TOKENS: &[NewUserToken] = &[
     },
 ];
 
+const USER_SANCTIONS: &[NewUserSanction] = &[
+    NewUserSanction {
+        user_id: 1,
+        issuer_id: Some(4),
+        kind: SanctionKind::Ban,
+        reason: "Uploading illegal content",
+        expiration_time: Some(DateTime::test_date()),
+    },
+    NewUserSanction {
+        user_id: 2,
+        issuer_id: Some(5),
+        kind: SanctionKind::Suspension,
+        reason: "Spamming comments",
+        expiration_time: Some(DateTime::test_date()),
+    },
+];
+
+const USER_NOTES: &[NewUserNote] = &[
+    NewUserNote {
+        user_id: 2,
+        author_id: Some(4),
+        text: "Warned about comment spam",
+    },
+    NewUserNote {
+        user_id: 2,
+        author_id: None,
+        text: "Has not spammed since suspension",
+    },
+];
+
 const POOL_CATEGORY_NAMES: &[&str] = &["Setting", "Style"];
 const DEFAULT_POOLS: &[&[&str]] = &[&["favs"]];
 const SETTINGS_POOLS: &[&[&str]] = &[&["fantasy"], &["steampunk"], &["cyberpunk"]];
@@ -348,6 +386,7 @@ const POSTS: &[NewPost] = &[
         flags: PostFlags::none(),
         source: "starry_night.png",
         description: "0101100010",
+        status: PostStatus::Approved,
     },
     NewPost {
         user_id: Some(2),
@@ -362,6 +401,7 @@ const POSTS: &[NewPost] = &[
         flags: PostFlags::none(),
         source: "gif.gif",
         description: "",
+        status: PostStatus::Approved,
     },
     NewPost {
         user_id: Some(2),
@@ -376,6 +416,7 @@ const POSTS: &[NewPost] = &[
         flags: PostFlags::none(),
         source: "bmp.bmp",
         description: "",
+        status: PostStatus::Approved,
     },
     NewPost {
         user_id: Some(2),
@@ -390,6 +431,7 @@ const POSTS: &[NewPost] = &[
         flags: PostFlags::none(),
         source: "1_pixel.png",
         description: "description9000",
+        status: PostStatus::Approved,
     },
     NewPost {
         user_id: None,
@@ -404,11 +446,15 @@ const POSTS: &[NewPost] = &[
         flags: PostFlags::one(PostFlag::Sound),
         source: "mp4.mp4",
         description: "descriptor",
+        status: PostStatus::Approved,
     },
 ];
 
 const POST_RELATIONS: &[(i64, i64)] = &[(1, 2), (1, 3), (4, 5)];
 
+/// (`parent_id`, `child_id`)
+const POST_HIERARCHY: &[(i64, i64)] = &[(5, 3), (3, 4)];
+
 /// (`user_id`, `post_id`)
 const POST_FAVORITES: &[(i64, i64)] = &[(1, 1), (2, 2), (2, 3), (2, 4), (5, 5)];
 
@@ -429,11 +475,14 @@ const POST_SCORES: &[(i64, i64, Score)] = &[
 /// (`user_id`, `post_id`, `text`)
 const COMMENTS: &[(Option<i64>, i64, &str)] = &[
     (Some(2), 1, "Cool post!"),
-    (Some(5), 1, "how did you post this"),
+    (Some(5), 1, "how did you post this @regular_user"),
     (Some(2), 4, "I don't think this uploaded correctly"),
     (None, 5, "Lorem ipsum dolor sit amet, consectetur adipiscing elit"),
 ];
 
+/// (`comment_id`, `parent_comment_id`)
+const COMMENT_REPLIES: &[(i64, i64)] = &[(2, 1)];
+
 /// (`comment_id`, `user_id`, `score`)
 const COMMENT_SCORES: &[(i64, i64, Score)] = &[
     (1, 1, Score::Like),
@@ -446,6 +495,84 @@ const COMMENT_SCORES: &[(i64, i64, Score)] = &[
     (3, 5, Score::Dislike),
 ];
 
+/// (`user_id`, `resource_type`, `resource_id`, `reason`)
+const REPORTS: &[(Option<i64>, ResourceType, &str, &str)] = &[
+    (Some(2), ResourceType::Post, "4", "Image is corrupted"),
+    (Some(3), ResourceType::Comment, "4", "Spam"),
+    (Some(2), ResourceType::Tag, "7", "Misspelled name"),
+];
+
+const AUDIT_LOG: &[NewAuditLogEntry] = &[
+    NewAuditLogEntry {
+        event: AuditEvent::RankChange,
+        user_id: Some(5),
+        user_name: Some("administrator"),
+        target: Some("power_user"),
+        details: Some("regular -> power"),
+        ip_address: Some("127.0.0.1"),
+        user_agent: Some("Mozilla/5.0"),
+    },
+    NewAuditLogEntry {
+        event: AuditEvent::Login,
+        user_id: Some(2),
+        user_name: Some("regular_user"),
+        target: None,
+        details: None,
+        ip_address: Some("192.168.0.7"),
+        user_agent: Some("Mozilla/5.0"),
+    },
+    NewAuditLogEntry {
+        event: AuditEvent::PrivilegeDenial,
+        user_id: Some(1),
+        user_name: Some("restricted_user"),
+        target: Some("GET /reports"),
+        details: None,
+        ip_address: Some("10.0.0.3"),
+        user_agent: None,
+    },
+    NewAuditLogEntry {
+        event: AuditEvent::FailedAuthentication,
+        user_id: None,
+        user_name: Some("audit_mystery"),
+        target: None,
+        details: Some("Invalid username and password combination"),
+        ip_address: Some("127.0.0.1"),
+        user_agent: Some("curl/8.5.0"),
+    },
+];
+
+/// (`user_id`, `operation`, `resource_type`, `resource_id`)
+const SNAPSHOTS: &[(Option<i64>, ResourceOperation, ResourceType, &str)] = &[
+    (Some(4), ResourceOperation::Created, ResourceType::Tag, "sky"),
+    (None, ResourceOperation::Deleted, ResourceType::Comment, "3"),
+];
+
+/// (`snapshot_id`, `url`, `status`, `attempt_count`, `last_error`)
+const WEBHOOK_DELIVERIES: &[(i64, &str, WebhookDeliveryStatus, i32, Option<&str>)] = &[
+    (1, "https://example.com/hook", WebhookDeliveryStatus::Delivered, 1, None),
+    (
+        1,
+        "https://example.org/hook",
+        WebhookDeliveryStatus::Delivered,
+        3,
+        Some("HTTP status server error (503 Service Unavailable) for url (https://example.org/hook)"),
+    ),
+    (
+        2,
+        "https://example.org/hook",
+        WebhookDeliveryStatus::Pending,
+        2,
+        Some("error sending request for url (https://example.org/hook)"),
+    ),
+    (
+        2,
+        "https://example.com/hook",
+        WebhookDeliveryStatus::Failed,
+        10,
+        Some("HTTP status client error (404 Not Found) for url (https://example.com/hook)"),
+    ),
+];
+
 static TEST_STATE: Mutex<Option<AppState>> = Mutex::new(None);
 
 fn get_state_guard() -> MutexGuard<'static, Option<AppState>> {
@@ -639,6 +766,10 @@ fn populate_database(conn: &mut PgConnection, config: &Config) -> AdminResult<()
     // Create user tokens
     USER_TOKENS.insert_into(user_token::table).execute(conn)?;
 
+    // Create expired sanctions and notes
+    USER_SANCTIONS.insert_into(user_sanction::table).execute(conn)?;
+    USER_NOTES.insert_into(user_note::table).execute(conn)?;
+
     // Create tags and pools
     create_tag_categories(conn)?;
     create_tags(conn)?;
@@ -652,6 +783,11 @@ fn populate_database(conn: &mut PgConnection, config: &Config) -> AdminResult<()
         .flat_map(|&(id_1, id_2)| PostRelation::new_pair(id_1, id_2))
         .collect();
     new_post_relations.insert_into(post_relation::table).execute(conn)?;
+    let new_post_hierarchy: Vec<_> = POST_HIERARCHY
+        .iter()
+        .map(|&(parent_id, child_id)| PostRelation::new_directed(parent_id, child_id))
+        .collect();
+    new_post_hierarchy.insert_into(post_relation::table).execute(conn)?;
 
     // Add tags
     for (post_id, &tags) in (1..).zip(POST_TAGS) {
@@ -716,10 +852,20 @@ fn populate_database(conn: &mut PgConnection, config: &Config) -> AdminResult<()
     .execute(conn)?;
 
     // Add comments
-    for &(user_id, post_id, text) in COMMENTS {
-        NewComment { user_id, post_id, text }
-            .insert_into(comment::table)
-            .execute(conn)?;
+    for (comment_id, &(user_id, post_id, text)) in (1..).zip(COMMENTS) {
+        let parent_comment_id = COMMENT_REPLIES
+            .iter()
+            .find(|&&(reply_id, _)| reply_id == comment_id)
+            .map(|&(_, parent_id)| parent_id);
+        NewComment {
+            user_id,
+            post_id,
+            text,
+            parent_comment_id,
+        }
+        .insert_into(comment::table)
+        .execute(conn)?;
+        update::comment::set_mentions(conn, comment_id, text)?;
     }
 
     // Add comment scores
@@ -733,6 +879,58 @@ fn populate_database(conn: &mut PgConnection, config: &Config) -> AdminResult<()
         .collect();
     new_comment_scores.insert_into(comment_score::table).execute(conn)?;
 
+    // Add reports
+    for &(user_id, resource_type, resource_id, reason) in REPORTS {
+        NewReport {
+            user_id,
+            resource_type,
+            resource_id: SmallString::from(resource_id),
+            reason,
+        }
+        .insert_into(report::table)
+        .execute(conn)?;
+    }
+
+    // Dismiss last report
+    diesel::update(report::table.find(3))
+        .set((
+            report::status.eq(ReportStatus::Dismissed),
+            report::resolver_id.eq(4_i64),
+            report::resolution_time.eq(DateTime::now()),
+        ))
+        .execute(conn)?;
+
+    // Entries are inserted together so that they share a creation time
+    AUDIT_LOG.insert_into(audit_log::table).execute(conn)?;
+
+    for &(user_id, operation, resource_type, resource_id) in SNAPSHOTS {
+        NewSnapshot {
+            user_id,
+            operation,
+            resource_type,
+            resource_id: SmallString::from(resource_id),
+            data: Value::Object(Map::new()),
+        }
+        .insert(conn)?;
+    }
+
+    // Deliveries are inserted together so that they share a creation time
+    let new_webhook_deliveries: Vec<_> = WEBHOOK_DELIVERIES
+        .iter()
+        .map(|&(snapshot_id, url, status, attempt_count, last_error)| {
+            (
+                webhook_delivery::snapshot_id.eq(snapshot_id),
+                webhook_delivery::url.eq(url),
+                webhook_delivery::status.eq(status),
+                webhook_delivery::attempt_count.eq(attempt_count),
+                webhook_delivery::last_error.eq(last_error),
+            )
+        })
+        .collect();
+    diesel::insert_into(webhook_delivery::table)
+        .values(new_webhook_deliveries)
+        .execute(conn)?;
+
     Ok(())
 }
 
@@ -931,6 +1129,13 @@ mod statistics_tests {
         Ok(())
     }
 
+    #[test]
+    #[serial]
+    fn reset_disk_usage() -> AdminResult<()> {
+        database::reset_disk_usage(&mut get_connection()?)?;
+        database_statistics()
+    }
+
     #[test]
     #[serial]
     fn reset_statistics() -> AdminResult<()> {
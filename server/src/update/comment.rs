@@ -0,0 +1,31 @@
+use crate::markdown;
+use crate::model::comment::CommentMention;
+use crate::schema::{comment_mention, user};
+use diesel::{ExpressionMethods, Insertable, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
+
+/// Replaces the mentions of comment associated with `comment_id` with the existing users mentioned in `text`.
+/// Returns the ids of mentioned users who weren't mentioned by the comment before.
+pub fn set_mentions(conn: &mut PgConnection, comment_id: i64, text: &str) -> QueryResult<Vec<i64>> {
+    let old_mentions: Vec<i64> = diesel::delete(comment_mention::table)
+        .filter(comment_mention::comment_id.eq(comment_id))
+        .returning(comment_mention::user_id)
+        .get_results(conn)?;
+
+    let names: Vec<&str> = markdown::mentions(text).collect();
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mentioned_users: Vec<i64> = user::table
+        .select(user::id)
+        .filter(user::name.eq_any(names))
+        .load(conn)?;
+    let new_mentions: Vec<_> = mentioned_users
+        .iter()
+        .map(|&user_id| CommentMention { comment_id, user_id })
+        .collect();
+    new_mentions.insert_into(comment_mention::table).execute(conn)?;
+    Ok(mentioned_users
+        .into_iter()
+        .filter(|user_id| !old_mentions.contains(user_id))
+        .collect())
+}
@@ -0,0 +1,133 @@
+use std::collections::hash_map::{Entry, IntoKeys};
+use std::collections::{HashMap, HashSet};
+
+enum TraversalState {
+    Visited,
+    Explored,
+}
+
+/// A graph of parent/child relationships used for cycle detection.
+/// Technically may not be a single graph but instead a collection of disjoint graphs.
+pub struct DependencyGraph {
+    // Maps children to parents
+    nodes: HashMap<i64, HashSet<i64>>,
+}
+
+impl DependencyGraph {
+    /// Initializes graph using `starting_nodes` and no connections.
+    pub fn new(starting_nodes: &[i64]) -> Self {
+        Self {
+            nodes: starting_nodes.iter().map(|&id| (id, HashSet::new())).collect(),
+        }
+    }
+
+    /// Returns the number of nodes in the graph.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Inserts a new connection into the graph. May create a new node
+    /// if `parent_id` or `child_id` is not in graph.
+    pub fn insert(&mut self, parent_id: i64, child_id: i64) -> bool {
+        let parents = self.nodes.entry(child_id).or_default();
+        let new_node = parents.is_empty();
+        parents.insert(parent_id);
+        new_node
+    }
+
+    /// Returns the nodes of the graph as a [`HashSet`].
+    pub fn into_nodes(self) -> IntoKeys<i64, HashSet<i64>> {
+        self.nodes.into_keys()
+    }
+
+    /// Determines if depedency graph has a cycle using a depth-first search approach.
+    /// Runs in O(V + E) time where V is the number of vertices and E is the number of edges of the graph.
+    pub fn has_cycle(&self) -> bool {
+        let mut traversed_nodes = HashMap::new();
+        for (base_node_id, parents) in &self.nodes {
+            let mut traversal_stack = vec![(*base_node_id, parents.iter())];
+            while let Some((current_node_id, parents)) = traversal_stack.last_mut() {
+                let Some(next_node_id) = parents.next() else {
+                    // Once all parents have been traversed, mark node as explored
+                    traversed_nodes.insert(*current_node_id, TraversalState::Explored);
+                    traversal_stack.pop();
+                    continue;
+                };
+
+                match traversed_nodes.entry(*next_node_id) {
+                    Entry::Occupied(entry) => match entry.get() {
+                        TraversalState::Visited => return true,
+                        TraversalState::Explored => continue,
+                    },
+                    Entry::Vacant(entry) => entry.insert(TraversalState::Visited),
+                };
+
+                if let Some(parents) = self.nodes.get(next_node_id) {
+                    traversal_stack.push((*next_node_id, parents.iter()));
+                }
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn trivial_graphs() {
+        let empty_graph = DependencyGraph::new(&[]);
+        assert!(!empty_graph.has_cycle());
+
+        let single_node = DependencyGraph::new(&[0]);
+        assert!(!single_node.has_cycle());
+
+        let mut trivial_cycle = DependencyGraph::new(&[0]);
+        trivial_cycle.insert(0, 0);
+        assert!(trivial_cycle.has_cycle());
+
+        let mut single_link = DependencyGraph::new(&[0, 1]);
+        single_link.insert(1, 0);
+        assert!(!single_link.has_cycle());
+
+        single_link.insert(0, 1);
+        assert!(single_link.has_cycle());
+    }
+
+    #[test]
+    fn dependency_graph_with_cycle() {
+        let mut graph = DependencyGraph::new(&[0]);
+        let links = [(0, 1), (1, 2), (1, 3), (3, 4), (5, 6), (5, 7), (6, 8), (6, 4), (4, 3)];
+        for (parent_id, child_id) in links {
+            graph.insert(parent_id, child_id);
+        }
+
+        assert!(graph.has_cycle());
+    }
+
+    #[test]
+    fn dependency_graph_without_cycle() {
+        let mut graph = DependencyGraph::new(&[0]);
+        let links = [
+            (0, 1),
+            (0, 2),
+            (0, 3),
+            (2, 5),
+            (3, 4),
+            (3, 9),
+            (4, 5),
+            (5, 6),
+            (5, 7),
+            (6, 8),
+            (7, 8),
+            (8, 9),
+            (9, 10),
+        ];
+        for (parent_id, child_id) in links {
+            graph.insert(parent_id, child_id);
+        }
+
+        assert!(!graph.has_cycle());
+    }
+}
@@ -1,3 +1,5 @@
+pub mod comment;
+mod graph;
 pub mod pool;
 pub mod post;
 pub mod tag;
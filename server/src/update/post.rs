@@ -1,23 +1,30 @@
-use crate::api::error::{self, ApiResult};
+use crate::api::error::{self, ApiError, ApiResult};
 use crate::app::Context;
 use crate::config::Config;
-use crate::content::hash::PostHash;
+use crate::content::cache::CachedProperties;
+use crate::content::hash::{Checksum, PostFileHash, PostHash};
+use crate::content::signature;
 use crate::filesystem;
-use crate::model::comment::OrphanedComment;
-use crate::model::enums::{ResourceProperty, ResourceType};
+use crate::model::comment::{CommentMention, OrphanedComment};
+use crate::model::enums::{RelationKind, ResourceProperty, ResourceType};
 use crate::model::pool::PoolPost;
 use crate::model::post::{
-    CompressedSignature, OrphanedPostFeature, Post, PostFavorite, PostRelation, PostScore, PostTag, SignatureIndexes,
+    CompressedSignature, NewPostFile, NewPostFileSignature, OrphanedPostFeature, Post, PostFavorite, PostFile,
+    PostRelation, PostScore, PostTag, SignatureIndexes,
 };
 use crate::resource::post::Note;
 use crate::schema::{
-    comment, pool_post, post, post_favorite, post_feature, post_note, post_relation, post_score, post_signature,
-    post_tag,
+    comment, comment_mention, pool_post, post, post_favorite, post_feature, post_file, post_file_signature, post_note,
+    post_relation, post_score, post_signature, post_tag,
 };
 use crate::time::DateTime;
-use diesel::dsl::exists;
-use diesel::{ExpressionMethods, Insertable, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
-use std::collections::HashSet;
+use crate::update::graph::DependencyGraph;
+use diesel::dsl::{exists, max};
+use diesel::{
+    BoolExpressionMethods, ExpressionMethods, Insertable, OptionalExtension, PgConnection, QueryDsl, QueryResult,
+    RunQueryDsl, SelectableHelper,
+};
+use std::collections::{HashMap, HashSet};
 
 /// Updates `last_edit_time` of post associated with `post_id`.
 pub fn last_edit_time(conn: &mut PgConnection, post_id: i64) -> ApiResult<()> {
@@ -39,6 +46,7 @@ pub fn set_relations(
         let hidden_relations: Vec<i64> = post_relation::table
             .select(post_relation::child_id)
             .filter(post_relation::parent_id.eq(post_id))
+            .filter(post_relation::kind.eq(RelationKind::Symmetric))
             .filter(exists(hidden_posts))
             .load(conn)?;
         new_related_posts.extend(hidden_relations);
@@ -47,8 +55,12 @@ pub fn set_relations(
     // Delete old relations and return old related posts ids.
     // Post relations are bi-directional, so it doesn't matter whether we return parent_id or child_id.
     let old_related_posts: HashSet<_> = diesel::delete(post_relation::table)
-        .filter(post_relation::parent_id.eq(post_id))
-        .or_filter(post_relation::child_id.eq(post_id))
+        .filter(post_relation::kind.eq(RelationKind::Symmetric))
+        .filter(
+            post_relation::parent_id
+                .eq(post_id)
+                .or(post_relation::child_id.eq(post_id)),
+        )
         .returning(post_relation::child_id)
         .get_results(conn)?
         .into_iter()
@@ -81,6 +93,35 @@ pub fn add_relations(conn: &mut PgConnection, post_id: i64, new_related_posts: &
     Ok(())
 }
 
+/// Replaces the current parent of post associated with `post_id` with `new_parent_id`.
+/// Fails if the new parent would make the post its own ancestor.
+pub fn set_parent(conn: &mut PgConnection, post_id: i64, new_parent_id: Option<i64>) -> ApiResult<()> {
+    let old_parent_id: Option<i64> = diesel::delete(post_relation::table)
+        .filter(post_relation::child_id.eq(post_id))
+        .filter(post_relation::kind.eq(RelationKind::Directed))
+        .returning(post_relation::parent_id)
+        .get_result(conn)
+        .optional()?;
+
+    if let Some(parent_id) = new_parent_id {
+        let insert_result = PostRelation::new_directed(parent_id, post_id)
+            .insert_into(post_relation::table)
+            .execute(conn);
+        error::map_unique_or_foreign_key_violation(insert_result, ResourceProperty::PostRelation, ResourceType::Post)?;
+        verify_acyclic(conn, post_id)?;
+    }
+
+    // Update last edit time for the old and new parent
+    if old_parent_id != new_parent_id {
+        let updated_posts: Vec<_> = old_parent_id.into_iter().chain(new_parent_id).collect();
+        diesel::update(post::table)
+            .set(post::last_edit_time.eq(DateTime::now()))
+            .filter(post::id.eq_any(updated_posts))
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
 /// Replaces the current set of tags with `tags` for post associated with `post_id`.
 pub fn set_tags(conn: &mut PgConnection, post_id: i64, tags: &[i64]) -> QueryResult<()> {
     let new_post_tags: Vec<_> = tags.iter().map(|&tag_id| PostTag { post_id, tag_id }).collect();
@@ -103,6 +144,202 @@ pub fn set_notes(conn: &mut PgConnection, post_id: i64, notes: &[Note]) -> Query
     Ok(())
 }
 
+/// Checks that no post has content with the given `checksum`.
+///
+/// Content of posts in the trash is reported separately, as those posts are hidden from the
+/// uploader but can be restored instead.
+pub fn verify_unique_checksum(conn: &mut PgConnection, checksum: Checksum) -> ApiResult<()> {
+    let existing_post: Option<(i64, Option<DateTime>)> = post::table
+        .select((post::id, post::deletion_time))
+        .filter(post::checksum.eq(checksum))
+        .first(conn)
+        .optional()?;
+    match existing_post {
+        Some((post_id, Some(_))) => Err(ApiError::ContentInTrash(post_id)),
+        Some((_, None)) => Err(ApiError::AlreadyExists(ResourceProperty::PostContent)),
+        None => Ok(()),
+    }
+}
+
+/// Checks that no post file has content with the given `checksum`.
+///
+/// Post content checksums are covered by the UNIQUE constraint on the post table,
+/// but that constraint can't see the checksums of post files.
+pub fn verify_unique_file_checksum(conn: &mut PgConnection, checksum: Checksum) -> ApiResult<()> {
+    let existing_file: Option<(i64, Option<DateTime>)> = post_file::table
+        .inner_join(post::table)
+        .select((post::id, post::deletion_time))
+        .filter(post_file::checksum.eq(checksum))
+        .first(conn)
+        .optional()?;
+    match existing_file {
+        Some((post_id, Some(_))) => Err(ApiError::ContentInTrash(post_id)),
+        Some((_, None)) => Err(ApiError::AlreadyExists(ResourceProperty::PostContent)),
+        None => Ok(()),
+    }
+}
+
+/// Appends a file with the given `content_properties` onto the list of files of post associated with `post_id`.
+pub fn add_file(
+    conn: &mut PgConnection,
+    config: &Config,
+    post_id: i64,
+    content_properties: CachedProperties,
+) -> ApiResult<()> {
+    let checksum = content_properties.checksum;
+    verify_unique_checksum(conn, checksum)?;
+
+    // Lock the post so that concurrently added files can't be assigned the same position
+    post::table.find(post_id).select(post::id).for_update().execute(conn)?;
+    let order = post_file::table
+        .select(max(post_file::order) + 1)
+        .filter(post_file::post_id.eq(post_id))
+        .first::<Option<_>>(conn)?
+        .unwrap_or(0);
+    let post_file: PostFile = NewPostFile {
+        post_id,
+        order,
+        file_size: content_properties.file_size,
+        width: content_properties.width,
+        height: content_properties.height,
+        type_: content_properties.post_type,
+        mime_type: content_properties.mime_type,
+        checksum,
+        checksum_md5: content_properties.md5_checksum,
+    }
+    .insert_into(post_file::table)
+    .on_conflict(post_file::checksum)
+    .do_nothing()
+    .returning(PostFile::as_returning())
+    .get_result(conn)
+    .optional()?
+    .ok_or(ApiError::AlreadyExists(ResourceProperty::PostContent))?;
+
+    NewPostFileSignature {
+        post_file_id: post_file.id,
+        signature: content_properties.signature.into(),
+        words: signature::generate_indexes(&content_properties.signature).into(),
+    }
+    .insert_into(post_file_signature::table)
+    .execute(conn)?;
+
+    // Move content to permanent location and create thumbnail
+    let post_file_hash = PostFileHash::new(config, post_file.id);
+    let temp_path = content_properties.token.path(config);
+    filesystem::move_file(&temp_path, &post_file_hash.content_path(post_file.mime_type))?;
+    let thumbnail_size = filesystem::save_post_file_thumbnail(&post_file_hash, content_properties.thumbnail)?;
+    diesel::update(post_file::table.find(post_file.id))
+        .set(post_file::generated_thumbnail_size.eq(thumbnail_size))
+        .execute(conn)?;
+
+    last_edit_time(conn, post_id)
+}
+
+/// Reorders the files of post associated with `post_id` so that they follow the order of `post_file_ids`.
+/// Files that aren't listed keep their relative order and are placed after the listed ones.
+pub fn reorder_files(conn: &mut PgConnection, post_id: i64, post_file_ids: &[i64]) -> ApiResult<()> {
+    let old_order: Vec<i64> = post_file::table
+        .select(post_file::id)
+        .filter(post_file::post_id.eq(post_id))
+        .order(post_file::order)
+        .load(conn)?;
+    let current_files: HashSet<i64> = old_order.iter().copied().collect();
+    if post_file_ids.iter().any(|id| !current_files.contains(id)) {
+        return Err(ApiError::NotFound(ResourceType::PostFile));
+    }
+
+    let mut seen_files = HashSet::new();
+    let new_order = post_file_ids
+        .iter()
+        .chain(&old_order)
+        .copied()
+        .filter(|&id| seen_files.insert(id));
+    for (post_file_id, order) in new_order.zip(0..) {
+        diesel::update(post_file::table.find(post_file_id))
+            .set(post_file::order.eq(order))
+            .execute(conn)?;
+    }
+    last_edit_time(conn, post_id)
+}
+
+/// Swaps the content of post associated with `post_id` with the content of its file associated with `post_file_id`,
+/// making that file the representative content of the post. The previous post content takes the place of the file.
+pub fn set_representative_file(
+    conn: &mut PgConnection,
+    config: &Config,
+    post_id: i64,
+    post_file_id: i64,
+) -> ApiResult<()> {
+    let post: Post = post::table.find(post_id).select(Post::as_select()).first(conn)?;
+    let post_file: PostFile = post_file::table
+        .find(post_file_id)
+        .filter(post_file::post_id.eq(post_id))
+        .select(PostFile::as_select())
+        .first(conn)
+        .optional()?
+        .ok_or(ApiError::NotFound(ResourceType::PostFile))?;
+    let post_signature: (CompressedSignature, SignatureIndexes) = post_signature::table
+        .find(post_id)
+        .select((post_signature::signature, post_signature::words))
+        .first(conn)?;
+    let file_signature: (CompressedSignature, SignatureIndexes) = post_file_signature::table
+        .find(post_file_id)
+        .select((post_file_signature::signature, post_file_signature::words))
+        .first(conn)?;
+
+    diesel::update(post::table.find(post_id))
+        .set((
+            post::file_size.eq(post_file.file_size),
+            post::width.eq(post_file.width),
+            post::height.eq(post_file.height),
+            post::type_.eq(post_file.type_),
+            post::mime_type.eq(post_file.mime_type),
+            post::checksum.eq(post_file.checksum),
+            post::checksum_md5.eq(post_file.checksum_md5),
+            post::generated_thumbnail_size.eq(post_file.generated_thumbnail_size),
+        ))
+        .execute(conn)?;
+    diesel::update(post_file::table.find(post_file_id))
+        .set((
+            post_file::file_size.eq(post.file_size),
+            post_file::width.eq(post.width),
+            post_file::height.eq(post.height),
+            post_file::type_.eq(post.type_),
+            post_file::mime_type.eq(post.mime_type),
+            post_file::checksum.eq(post.checksum),
+            post_file::checksum_md5.eq(post.checksum_md5),
+            post_file::generated_thumbnail_size.eq(post.generated_thumbnail_size),
+        ))
+        .execute(conn)?;
+    diesel::update(post_signature::table.find(post_id))
+        .set((post_signature::signature.eq(file_signature.0), post_signature::words.eq(file_signature.1)))
+        .execute(conn)?;
+    diesel::update(post_file_signature::table.find(post_file_id))
+        .set((post_file_signature::signature.eq(post_signature.0), post_file_signature::words.eq(post_signature.1)))
+        .execute(conn)?;
+
+    let post_hash = PostHash::new(config, post_id, Some(post.custom_thumbnail_size));
+    let post_file_hash = PostFileHash::new(config, post_file_id);
+    filesystem::swap_post_content(&post_hash, post.mime_type, &post_file_hash, post_file.mime_type)?;
+    last_edit_time(conn, post_id)
+}
+
+/// Removes file associated with `post_file_id` from post associated with `post_id`.
+/// Returns the removed file so that its content can be cleaned up afterwards.
+pub fn remove_file(conn: &mut PgConnection, post_id: i64, post_file_id: i64) -> ApiResult<PostFile> {
+    let post_file: PostFile = diesel::delete(
+        post_file::table
+            .find(post_file_id)
+            .filter(post_file::post_id.eq(post_id)),
+    )
+    .returning(PostFile::as_returning())
+    .get_result(conn)
+    .optional()?
+    .ok_or(ApiError::NotFound(ResourceType::PostFile))?;
+    last_edit_time(conn, post_id)?;
+    Ok(post_file)
+}
+
 /// Merges `absorbed_post` to `merge_to_post`.
 ///
 /// Merged post resources typically follow a delete-then-insert pattern even
@@ -128,9 +365,16 @@ pub fn merge(
         .or_filter(post_relation::parent_id.eq(merge_to_id))
         .or_filter(post_relation::child_id.eq(merge_to_id))
         .load(conn)?;
+    let merge_to_has_parent = involved_relations
+        .iter()
+        .any(|relation| relation.kind == RelationKind::Directed && relation.child_id == merge_to_id);
     let merged_relations: HashSet<_> = involved_relations
         .iter()
         .copied()
+        // A post can only have one parent, so the parent of the absorbed post is dropped if necessary
+        .filter(|relation| {
+            !(merge_to_has_parent && relation.kind == RelationKind::Directed && relation.child_id == absorbed_id)
+        })
         .map(|mut relation| {
             if relation.parent_id == absorbed_id {
                 relation.parent_id = merge_to_id;
@@ -141,12 +385,16 @@ pub fn merge(
         })
         .filter(|relation| relation.parent_id != relation.child_id)
         .collect();
+    // Relations of the absorbed post are deleted too, as the children it passes on can't have two parents
     diesel::delete(post_relation::table)
-        .filter(post_relation::parent_id.eq(merge_to_id))
+        .filter(post_relation::parent_id.eq(absorbed_id))
+        .or_filter(post_relation::child_id.eq(absorbed_id))
+        .or_filter(post_relation::parent_id.eq(merge_to_id))
         .or_filter(post_relation::child_id.eq(merge_to_id))
         .execute(conn)?;
     let merged_relations: Vec<_> = merged_relations.into_iter().collect();
     merged_relations.insert_into(post_relation::table).execute(conn)?;
+    verify_acyclic(conn, merge_to_id)?;
 
     // Merge tags
     let merge_to_tags = post_tag::table
@@ -238,21 +486,72 @@ pub fn merge(
     new_features.insert_into(post_feature::table).execute(conn)?;
 
     // Merge comments
-    let removed_comments: Vec<(_, String, _, _)> =
+    let absorbed_comments = comment::table
+        .select(comment::id)
+        .filter(comment::post_id.eq(absorbed_id));
+    let removed_mentions: Vec<CommentMention> = comment_mention::table
+        .filter(comment_mention::comment_id.eq_any(absorbed_comments))
+        .load(conn)?;
+    let removed_comments: Vec<(i64, _, String, _, _, Option<i64>)> =
         diesel::delete(comment::table.filter(comment::post_id.eq(absorbed_id)))
-            .returning((comment::user_id, comment::text, comment::creation_time, comment::last_edit_time))
+            .returning((
+                comment::id,
+                comment::user_id,
+                comment::text,
+                comment::creation_time,
+                comment::last_edit_time,
+                comment::parent_comment_id,
+            ))
             .get_results(conn)?;
+    let mut removed_comment_ids = Vec::with_capacity(removed_comments.len());
+    let mut removed_replies = Vec::new();
     let new_comments: Vec<_> = removed_comments
         .into_iter()
-        .map(|(user_id, text, creation_time, last_edit_time)| OrphanedComment {
-            user_id,
-            post_id: merge_to_id,
-            text,
-            creation_time,
-            last_edit_time,
+        .map(|(id, user_id, text, creation_time, last_edit_time, parent_comment_id)| {
+            removed_comment_ids.push(id);
+            removed_replies.extend(parent_comment_id.map(|parent_id| (id, parent_id)));
+            OrphanedComment {
+                user_id,
+                post_id: merge_to_id,
+                text,
+                creation_time,
+                last_edit_time,
+                parent_comment_id: None,
+            }
+        })
+        .collect();
+    let new_comment_ids: Vec<i64> = new_comments
+        .insert_into(comment::table)
+        .returning(comment::id)
+        .get_results(conn)?;
+
+    // Reattach replies and mentions to the reinserted comments. Replies always share a post
+    // with their parent, so every parent is among the reinserted comments.
+    let new_ids: HashMap<i64, i64> = removed_comment_ids.into_iter().zip(new_comment_ids).collect();
+    for (reply_id, parent_id) in removed_replies {
+        diesel::update(comment::table.find(new_ids[&reply_id]))
+            .set(comment::parent_comment_id.eq(new_ids.get(&parent_id)))
+            .execute(conn)?;
+    }
+    let new_mentions: Vec<_> = removed_mentions
+        .into_iter()
+        .map(|mention| CommentMention {
+            comment_id: new_ids[&mention.comment_id],
+            user_id: mention.user_id,
         })
         .collect();
-    new_comments.insert_into(comment::table).execute(conn)?;
+    new_mentions.insert_into(comment_mention::table).execute(conn)?;
+
+    // Merge files. Files are stored by their own id, so they don't need to be moved on disk.
+    let current_file_count = post_file::table
+        .select(max(post_file::order) + 1)
+        .filter(post_file::post_id.eq(merge_to_id))
+        .first::<Option<_>>(conn)?
+        .unwrap_or(0);
+    diesel::update(post_file::table)
+        .filter(post_file::post_id.eq(absorbed_id))
+        .set((post_file::post_id.eq(merge_to_id), post_file::order.eq(post_file::order + current_file_count)))
+        .execute(conn)?;
 
     // Merge descriptions
     let merged_description = merge_to_post.description.to_string() + "\n\n" + &absorbed_post.description;
@@ -305,3 +604,48 @@ pub fn merge(
     last_edit_time(conn, merge_to_id)?;
     Ok(())
 }
+
+/// Permanently deletes all posts that were soft-deleted before `cutoff`.
+/// Returns the purged posts and their files so that their content can be cleaned up afterwards.
+pub fn purge_deleted(conn: &mut PgConnection, cutoff: DateTime) -> QueryResult<(Vec<Post>, Vec<PostFile>)> {
+    let expired_posts = post::table.select(post::id).filter(post::deletion_time.le(cutoff));
+    let purged_files = diesel::delete(post_file::table)
+        .filter(post_file::post_id.eq_any(expired_posts))
+        .returning(PostFile::as_returning())
+        .get_results(conn)?;
+    let purged_posts = diesel::delete(post::table)
+        .filter(post::deletion_time.le(cutoff))
+        .returning(Post::as_returning())
+        .get_results(conn)?;
+    Ok((purged_posts, purged_files))
+}
+
+/// Checks that post associated with `post_id` is not its own ancestor.
+fn verify_acyclic(conn: &mut PgConnection, post_id: i64) -> ApiResult<()> {
+    // Any cycle involving the post must pass through its descendants, so only those need to be collected
+    let mut descendant_ids = vec![post_id];
+    let mut dependency_graph = DependencyGraph::new(&descendant_ids);
+    loop {
+        let relations: Vec<(i64, i64)> = post_relation::table
+            .select((post_relation::parent_id, post_relation::child_id))
+            .filter(post_relation::kind.eq(RelationKind::Directed))
+            .filter(post_relation::parent_id.eq_any(&descendant_ids))
+            .load(conn)?;
+
+        // Remove ids we've already seen
+        let previous_len = dependency_graph.len();
+        descendant_ids = relations
+            .into_iter()
+            .filter(|&(parent_id, child_id)| dependency_graph.insert(parent_id, child_id))
+            .map(|(_, child_id)| child_id)
+            .collect();
+
+        if dependency_graph.len() == previous_len {
+            break;
+        }
+    }
+    if dependency_graph.has_cycle() {
+        return Err(ApiError::CyclicDependency(ResourceType::Post));
+    }
+    Ok(())
+}
@@ -1,19 +1,23 @@
 use crate::api::error::{self, ApiError, ApiResult};
 use crate::app::Context;
+use crate::auth::Client;
 use crate::config::{Action, Config, RegexType};
-use crate::model::enums::{ResourceProperty, ResourceType};
-use crate::model::post::PostTag;
+use crate::model::digest::WatchedTag;
+use crate::model::enums::{ResourceOperation, ResourceProperty, ResourceType};
+use crate::model::post::{Post, PostTag};
 use crate::model::tag::{NewTag, NewTagName, TagImplication, TagName, TagSuggestion};
-use crate::schema::post_tag;
+use crate::schema::{post, post_tag, watched_tag};
 use crate::schema::{tag, tag_implication, tag_name, tag_suggestion};
+use crate::snapshot::post::SnapshotData;
 use crate::string::SmallString;
 use crate::time::DateTime;
+use crate::update::graph::DependencyGraph;
 use crate::{api, snapshot};
 use diesel::deserialize::QueryableByName;
 use diesel::dsl::max;
-use diesel::sql_types::{Array, Text};
-use diesel::{ExpressionMethods, Insertable, PgConnection, QueryDsl, RunQueryDsl};
-use std::collections::hash_map::{Entry, IntoKeys};
+use diesel::sql_types::{Array, SmallInt, Text};
+use diesel::{ExpressionMethods, Insertable, OptionalExtension, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
+use serde_json::Value;
 use std::collections::{HashMap, HashSet};
 
 /// Specifies how tag ids should be retrieved from database given a list of names.
@@ -116,7 +120,7 @@ pub fn get_or_create_tags(
             let previous_len = dependency_graph.len();
             implied_ids = implications
                 .into_iter()
-                .filter(|&implication| dependency_graph.insert(implication))
+                .filter(|implication| dependency_graph.insert(implication.parent_id, implication.child_id))
                 .map(|implication| implication.child_id)
                 .collect();
 
@@ -130,23 +134,7 @@ pub fn get_or_create_tags(
     }
     let mut tag_ids: Vec<_> = dependency_graph.into_nodes().collect();
 
-    // Gather names that are case-fold distinct from existing names in database.
-    // We use a query here because CITEXT semantics differ from comparing
-    // `str::to_lowercased`-ed strings in certain cases.
-    let new_names: Vec<_> = diesel::sql_query(
-        "SELECT DISTINCT ON (unnest::CITEXT) unnest AS name
-        FROM unnest($1::text[]) WITH ORDINALITY
-        WHERE NOT EXISTS (
-            SELECT 1 FROM tag_name WHERE tag_name.name = unnest::CITEXT
-        )
-        ORDER BY unnest::CITEXT, ordinality",
-    )
-    .bind::<Array<Text>, _>(names)
-    .load::<NewName>(conn)?
-    .into_iter()
-    .map(|row| row.name)
-    .collect();
-
+    let new_names = find_new_names(conn, names)?;
     new_names
         .iter()
         .try_for_each(|name| api::verify_matches_regex(&ctx.config, name, RegexType::Tag))?;
@@ -178,6 +166,24 @@ pub fn get_or_create_tags(
     Ok((tag_ids, primary_tag_names))
 }
 
+/// Returns the names that are case-fold distinct from existing names in database.
+/// Each returned name is distinct and none of them belong to a tag yet.
+pub fn find_new_names(conn: &mut PgConnection, names: Vec<SmallString>) -> QueryResult<Vec<SmallString>> {
+    // We use a query here because CITEXT semantics differ from comparing
+    // `str::to_lowercased`-ed strings in certain cases.
+    diesel::sql_query(
+        "SELECT DISTINCT ON (unnest::CITEXT) unnest AS name
+        FROM unnest($1::text[]) WITH ORDINALITY
+        WHERE NOT EXISTS (
+            SELECT 1 FROM tag_name WHERE tag_name.name = unnest::CITEXT
+        )
+        ORDER BY unnest::CITEXT, ordinality",
+    )
+    .bind::<Array<Text>, _>(names)
+    .load::<NewName>(conn)
+    .map(|rows| rows.into_iter().map(|row| row.name).collect())
+}
+
 /// Merges tag associated with `absorbed_id` to one associated with `merge_to_id`.
 pub fn merge(conn: &mut PgConnection, absorbed_id: i64, merge_to_id: i64) -> ApiResult<()> {
     // Merge implications
@@ -252,6 +258,24 @@ pub fn merge(conn: &mut PgConnection, absorbed_id: i64, merge_to_id: i64) -> Api
         .collect();
     new_post_tags.insert_into(post_tag::table).execute(conn)?;
 
+    // Merge watchers
+    let merge_to_watchers = watched_tag::table
+        .select(watched_tag::user_id)
+        .filter(watched_tag::tag_id.eq(merge_to_id))
+        .into_boxed();
+    let new_watched_tags: Vec<_> = watched_tag::table
+        .select(watched_tag::user_id)
+        .filter(watched_tag::tag_id.eq(absorbed_id))
+        .filter(watched_tag::user_id.ne_all(merge_to_watchers))
+        .load(conn)?
+        .into_iter()
+        .map(|user_id| WatchedTag {
+            user_id,
+            tag_id: merge_to_id,
+        })
+        .collect();
+    new_watched_tags.insert_into(watched_tag::table).execute(conn)?;
+
     // Merge names
     let current_name_count = tag_name::table
         .select(max(tag_name::order) + 1)
@@ -267,80 +291,129 @@ pub fn merge(conn: &mut PgConnection, absorbed_id: i64, merge_to_id: i64) -> Api
     last_edit_time(conn, merge_to_id)
 }
 
-#[derive(QueryableByName)]
-struct NewName {
-    #[diesel(sql_type = Text)]
-    name: SmallString,
-}
-
-enum TraversalState {
-    Visited,
-    Explored,
-}
-
-/// A graph of tag parent/child relationships used for cycle detection.
-/// Technically may not be a single graph but instead a collection of disjoint graphs.
-struct DependencyGraph {
-    // Maps children to parents
-    nodes: HashMap<i64, HashSet<i64>>,
+/// Tags that must be added to or removed from a post.
+pub struct TagDiff {
+    pub post_id: i64,
+    pub added: Vec<i64>,
+    pub removed: Vec<i64>,
 }
 
-impl DependencyGraph {
-    /// Initializes graph using `starting_nodes` and no connections.
-    fn new(starting_nodes: &[i64]) -> Self {
-        Self {
-            nodes: starting_nodes.iter().map(|&tag_id| (tag_id, HashSet::new())).collect(),
+/// Computes which tags are missing from each post in `post_ids` because the implications that
+/// would have added them were created after the post was last tagged. If `remove_stale` is set,
+/// tags which were added by an implication that has since been deleted are marked for removal
+/// as well. Posts that are already consistent are omitted from the result.
+pub fn implication_diffs(conn: &mut PgConnection, post_ids: &[i64], remove_stale: bool) -> QueryResult<Vec<TagDiff>> {
+    const BATCH_SIZE: usize = 10_000;
+
+    let mut children: HashMap<i64, Vec<i64>> = HashMap::new();
+    for implication in tag_implication::table.load::<TagImplication>(conn)? {
+        children
+            .entry(implication.parent_id)
+            .or_default()
+            .push(implication.child_id);
+    }
+    let mut deleted_parents: HashMap<i64, Vec<i64>> = HashMap::new();
+    if remove_stale {
+        for implication in deleted_implications(conn)? {
+            deleted_parents
+                .entry(implication.child_id)
+                .or_default()
+                .push(implication.parent_id);
         }
     }
 
-    /// Returns the number of nodes in the graph.
-    fn len(&self) -> usize {
-        self.nodes.len()
-    }
+    let mut diffs = Vec::new();
+    for post_id_batch in post_ids.chunks(BATCH_SIZE) {
+        let mut post_tags: HashMap<i64, HashSet<i64>> = HashMap::new();
+        for PostTag { post_id, tag_id } in post_tag::table
+            .filter(post_tag::post_id.eq_any(post_id_batch))
+            .load::<PostTag>(conn)?
+        {
+            post_tags.entry(post_id).or_default().insert(tag_id);
+        }
 
-    /// Inserts a new connection into the graph. May create a new node
-    /// if `implication` contains a tag ID not in graph.
-    fn insert(&mut self, implication: TagImplication) -> bool {
-        let parents = self.nodes.entry(implication.child_id).or_default();
-        let new_node = parents.is_empty();
-        parents.insert(implication.parent_id);
-        new_node
+        for &post_id in post_id_batch {
+            let Some(tags) = post_tags.get(&post_id) else {
+                continue;
+            };
+
+            // A tag is stale if the post history shows it was added by a deleted implication from another
+            // tag on the post and none of the remaining tags imply it through the current implications
+            let stale_parents: HashMap<i64, Vec<i64>> = tags
+                .iter()
+                .filter_map(|&tag_id| {
+                    let parents: Vec<i64> = deleted_parents
+                        .get(&tag_id)?
+                        .iter()
+                        .copied()
+                        .filter(|parent_id| tags.contains(parent_id))
+                        .collect();
+                    (!parents.is_empty()).then_some((tag_id, parents))
+                })
+                .collect();
+            let stale = if stale_parents.is_empty() {
+                HashSet::new()
+            } else {
+                implication_added_tags(conn, post_id, &stale_parents)?
+            };
+            let implied = implied_tags(&children, tags.difference(&stale).copied());
+
+            let mut added: Vec<i64> = implied.difference(tags).copied().collect();
+            let mut removed: Vec<i64> = stale.difference(&implied).copied().collect();
+            if added.is_empty() && removed.is_empty() {
+                continue;
+            }
+            added.sort_unstable();
+            removed.sort_unstable();
+            diffs.push(TagDiff {
+                post_id,
+                added,
+                removed,
+            });
+        }
     }
+    Ok(diffs)
+}
 
-    /// Returns the nodes of the graph as a [`HashSet`].
-    fn into_nodes(self) -> IntoKeys<i64, HashSet<i64>> {
-        self.nodes.into_keys()
+/// Applies each of the given `diffs` to its post and creates a modification snapshot for it.
+/// Posts that no longer exist are skipped.
+pub fn apply_tag_diffs(conn: &mut PgConnection, client: Client, diffs: &[TagDiff]) -> ApiResult<()> {
+    for diff in diffs {
+        let Some(old_post): Option<Post> = post::table.find(diff.post_id).first(conn).optional()? else {
+            continue;
+        };
+        let old_snapshot_data = SnapshotData::retrieve(conn, old_post)?;
+
+        diesel::delete(post_tag::table)
+            .filter(post_tag::post_id.eq(diff.post_id))
+            .filter(post_tag::tag_id.eq_any(&diff.removed))
+            .execute(conn)?;
+        let new_post_tags: Vec<_> = diff
+            .added
+            .iter()
+            .map(|&tag_id| PostTag {
+                post_id: diff.post_id,
+                tag_id,
+            })
+            .collect();
+        new_post_tags
+            .insert_into(post_tag::table)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        let new_post: Post = diesel::update(post::table.find(diff.post_id))
+            .set(post::last_edit_time.eq(DateTime::now()))
+            .get_result(conn)?;
+        let new_snapshot_data = SnapshotData::retrieve(conn, new_post)?;
+        snapshot::post::modification_snapshot(conn, client, diff.post_id, old_snapshot_data, new_snapshot_data)?;
     }
+    Ok(())
+}
 
-    /// Determines if depedency graph has a cycle using a depth-first search approach.
-    /// Runs in O(V + E) time where V is the number of vertices and E is the number of edges of the graph.
-    fn has_cycle(&self) -> bool {
-        let mut traversed_nodes = HashMap::new();
-        for (base_node_id, parents) in &self.nodes {
-            let mut traversal_stack = vec![(*base_node_id, parents.iter())];
-            while let Some((current_node_id, parents)) = traversal_stack.last_mut() {
-                let Some(next_node_id) = parents.next() else {
-                    // Once all parents have been traversed, mark node as explored
-                    traversed_nodes.insert(*current_node_id, TraversalState::Explored);
-                    traversal_stack.pop();
-                    continue;
-                };
-
-                match traversed_nodes.entry(*next_node_id) {
-                    Entry::Occupied(entry) => match entry.get() {
-                        TraversalState::Visited => return true,
-                        TraversalState::Explored => continue,
-                    },
-                    Entry::Vacant(entry) => entry.insert(TraversalState::Visited),
-                };
-
-                if let Some(parents) = self.nodes.get(next_node_id) {
-                    traversal_stack.push((*next_node_id, parents.iter()));
-                }
-            }
-        }
-        false
-    }
+#[derive(QueryableByName)]
+struct NewName {
+    #[diesel(sql_type = Text)]
+    name: SmallString,
 }
 
 /// Appends `names` onto the current list of names for the tag associated with `tag_id`.
@@ -358,73 +431,104 @@ fn add_names(conn: &mut PgConnection, tag_id: i64, current_name_count: i32, name
     Ok(())
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use crate::model::tag::TagImplication;
-
-    #[test]
-    fn trivial_graphs() {
-        let empty_graph = DependencyGraph::new(&[]);
-        assert!(!empty_graph.has_cycle());
-
-        let single_node = DependencyGraph::new(&[0]);
-        assert!(!single_node.has_cycle());
-
-        let mut trivial_cycle = DependencyGraph::new(&[0]);
-        trivial_cycle.insert(TagImplication {
-            parent_id: 0,
-            child_id: 0,
-        });
-        assert!(trivial_cycle.has_cycle());
-
-        let mut single_link = DependencyGraph::new(&[0, 1]);
-        single_link.insert(TagImplication {
-            parent_id: 1,
-            child_id: 0,
-        });
-        assert!(!single_link.has_cycle());
-
-        single_link.insert(TagImplication {
-            parent_id: 0,
-            child_id: 1,
-        });
-        assert!(single_link.has_cycle());
+/// Returns every tag implied directly or indirectly by `tag_ids`, given a map from each tag to its `children`.
+fn implied_tags(children: &HashMap<i64, Vec<i64>>, tag_ids: impl Iterator<Item = i64>) -> HashSet<i64> {
+    let mut implied = HashSet::new();
+    let mut frontier: Vec<i64> = tag_ids.collect();
+    while let Some(tag_id) = frontier.pop() {
+        let new_children = children
+            .get(&tag_id)
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|&child_id| implied.insert(child_id));
+        frontier.extend(new_children);
     }
+    implied
+}
 
-    #[test]
-    fn dependency_graph_with_cycle() {
-        let mut graph = DependencyGraph::new(&[0]);
-        let links = [(0, 1), (1, 2), (1, 3), (3, 4), (5, 6), (5, 7), (6, 8), (6, 4), (4, 3)];
-        for (parent_id, child_id) in links {
-            graph.insert(TagImplication { parent_id, child_id });
-        }
+/// Returns the tags in `parents` which the snapshot history of post associated with `post_id` shows
+/// were added by implication. That is the case if a tag was last added to the post together with
+/// one of its `parents`, which the tag is mapped to.
+fn implication_added_tags(
+    conn: &mut PgConnection,
+    post_id: i64,
+    parents: &HashMap<i64, Vec<i64>>,
+) -> QueryResult<HashSet<i64>> {
+    use crate::schema::snapshot;
 
-        assert!(graph.has_cycle());
-    }
+    // Snapshots store tag names as they were at the time, so tags are matched by any of their current names
+    let tag_ids: Vec<i64> = parents
+        .iter()
+        .flat_map(|(&tag_id, parent_ids)| std::iter::once(tag_id).chain(parent_ids.iter().copied()))
+        .collect();
+    let tag_ids_by_name: HashMap<String, i64> = tag_name::table
+        .select((tag_name::name, tag_name::tag_id))
+        .filter(tag_name::tag_id.eq_any(&tag_ids))
+        .load::<(SmallString, i64)>(conn)?
+        .into_iter()
+        .map(|(name, tag_id)| (name.to_lowercase(), tag_id))
+        .collect();
+    let snapshots: Vec<(ResourceOperation, Value)> = snapshot::table
+        .select((snapshot::operation, snapshot::data))
+        .filter(snapshot::resource_type.eq(ResourceType::Post))
+        .filter(snapshot::resource_id.eq(post_id.to_string()))
+        .order(snapshot::id)
+        .load(conn)?;
 
-    #[test]
-    fn dependency_graph_without_cycle() {
-        let mut graph = DependencyGraph::new(&[0]);
-        let links = [
-            (0, 1),
-            (0, 2),
-            (0, 3),
-            (2, 5),
-            (3, 4),
-            (3, 9),
-            (4, 5),
-            (5, 6),
-            (5, 7),
-            (6, 8),
-            (7, 8),
-            (8, 9),
-            (9, 10),
-        ];
-        for (parent_id, child_id) in links {
-            graph.insert(TagImplication { parent_id, child_id });
+    // Creation snapshots store the full list of tags, while modification snapshots store a diff
+    let mut added_alongside: HashMap<i64, HashSet<i64>> = HashMap::new();
+    for (operation, data) in snapshots {
+        let added_names = match operation {
+            ResourceOperation::Created => data.get("tags"),
+            ResourceOperation::Modified => data.pointer("/value/tags/added"),
+            ResourceOperation::Merged | ResourceOperation::Deleted | ResourceOperation::Restored => None,
+        };
+        let added_tags: HashSet<i64> = added_names
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .filter_map(|name| tag_ids_by_name.get(&name.to_lowercase()).copied())
+            .collect();
+        for &tag_id in &added_tags {
+            added_alongside.insert(tag_id, added_tags.clone());
         }
-
-        assert!(!graph.has_cycle());
     }
+    Ok(parents
+        .iter()
+        .filter(|(tag_id, parent_ids)| {
+            added_alongside
+                .get(tag_id)
+                .is_some_and(|added_tags| parent_ids.iter().any(|parent_id| added_tags.contains(parent_id)))
+        })
+        .map(|(&tag_id, _)| tag_id)
+        .collect())
+}
+
+/// Returns implications which were recorded in the tag snapshot history but no longer exist.
+/// Tags are matched by any of their current names, so implications of tags that have since been
+/// renamed or merged are still found as long as the old name survives as an alias.
+fn deleted_implications(conn: &mut PgConnection) -> QueryResult<Vec<TagImplication>> {
+    // Creation snapshots store the full list of implications, while modification snapshots store a diff
+    diesel::sql_query(
+        "SELECT DISTINCT parent.tag_id AS parent_id, child.tag_id AS child_id
+        FROM snapshot
+        CROSS JOIN LATERAL jsonb_array_elements_text(
+            COALESCE(snapshot.data -> 'implications', snapshot.data #> '{value,implications,added}')
+        ) AS implied(name)
+        INNER JOIN tag_name AS parent ON parent.name = snapshot.resource_id::CITEXT
+        INNER JOIN tag_name AS child ON child.name = implied.name::CITEXT
+        WHERE snapshot.resource_type = $1
+        AND snapshot.operation IN ($2, $3)
+        AND parent.tag_id <> child.tag_id
+        AND NOT EXISTS (
+            SELECT 1 FROM tag_implication
+            WHERE tag_implication.parent_id = parent.tag_id AND tag_implication.child_id = child.tag_id
+        )",
+    )
+    .bind::<SmallInt, _>(ResourceType::Tag)
+    .bind::<SmallInt, _>(ResourceOperation::Created)
+    .bind::<SmallInt, _>(ResourceOperation::Modified)
+    .load(conn)
 }
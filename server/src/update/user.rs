@@ -1,7 +1,7 @@
 use crate::api::error::ApiResult;
 use crate::config::Config;
 use crate::filesystem;
-use crate::schema::user;
+use crate::schema::{user, user_session};
 use crate::time::DateTime;
 use diesel::{ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
 use image::DynamicImage;
@@ -14,6 +14,15 @@ pub fn last_login_time(conn: &mut PgConnection, user_id: i64) -> QueryResult<()>
     Ok(())
 }
 
+/// Updates the last known usage time for the session with the given `secret_hash`.
+pub fn session_usage_time(conn: &mut PgConnection, secret_hash: &str) -> QueryResult<()> {
+    diesel::update(user_session::table)
+        .filter(user_session::secret_hash.eq(secret_hash))
+        .set(user_session::last_usage_time.eq(DateTime::now()))
+        .execute(conn)?;
+    Ok(())
+}
+
 /// Updates `last_edit_time` of user with given `user_id`.
 pub fn last_edit_time(conn: &mut PgConnection, user_id: i64) -> QueryResult<()> {
     diesel::update(user::table.find(user_id))
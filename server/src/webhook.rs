@@ -0,0 +1,324 @@
+//! Snapshots are posted to webhooks through a delivery queue kept in the database. A trigger
+//! queues an event for every snapshot in the same transaction that creates it, and a background
+//! task turns queued events into one delivery per interested webhook. Failed deliveries are
+//! retried with exponential backoff until they run out of attempts, after which they can be
+//! replayed by an administrator.
+
+use crate::api::error::{ApiError, ApiResult};
+use crate::app::AppState;
+use crate::config::{Config, WebhookConfig};
+use crate::metrics;
+use crate::model::enums::{ResourceOperation, ResourceType, WebhookDeliveryStatus};
+use crate::model::webhook::{NewWebhookDelivery, WebhookDelivery};
+use crate::resource::field::Mask;
+use crate::resource::snapshot::SnapshotInfo;
+use crate::schema::{snapshot, webhook_delivery, webhook_event};
+use crate::string::SecretString;
+use crate::time::DateTime;
+use diesel::{
+    ExpressionMethods, Insertable, OptionalExtension, PgConnection, QueryDsl, QueryResult, RunQueryDsl,
+    SelectableHelper,
+};
+use hmac::{Hmac, KeyInit, Mac};
+use reqwest::Client;
+use reqwest::header::{CONTENT_TYPE, REFERER};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{error, warn};
+
+/// How often the background task checks for due deliveries when it isn't woken up.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Number of attempts made before a delivery is marked as failed.
+pub const MAX_ATTEMPTS: i32 = 10;
+
+/// Header containing the ID of the delivery, so that receivers can ignore duplicates.
+pub const DELIVERY_HEADER: &str = "X-Oxibooru-Delivery";
+
+/// Delay before the second attempt. Each failed attempt after that doubles the delay.
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// How long a webhook has to respond before the attempt is considered failed.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Maximum number of events or deliveries handled per query.
+const BATCH_SIZE: usize = 100;
+
+static WAKE_UP: Notify = Notify::const_new();
+
+/// Wakes up the background task, so that new snapshots are posted without waiting for the next check.
+pub fn wake_up() {
+    WAKE_UP.notify_one();
+}
+
+/// Waits until the background task is woken up or the check interval has elapsed.
+pub async fn wait_for_work() {
+    let _ = tokio::time::timeout(CHECK_INTERVAL, WAKE_UP.notified()).await;
+}
+
+/// Queues deliveries for new snapshots and attempts every delivery that is due.
+/// Failing to post to one webhook doesn't prevent the others from being posted to.
+pub async fn deliver_due(state: &AppState) -> ApiResult<()> {
+    loop {
+        let config = Arc::clone(&state.config);
+        let queued_events = state
+            .connection_pool
+            .transaction(move |conn| queue_deliveries(conn, &config))
+            .await?;
+        if queued_events < BATCH_SIZE {
+            break;
+        }
+    }
+
+    let client = Client::builder().timeout(REQUEST_TIMEOUT).build()?;
+    loop {
+        let config = Arc::clone(&state.config);
+        let claimed_deliveries = state
+            .connection_pool
+            .transaction(move |conn| claim_due_deliveries(conn, &config))
+            .await?;
+        let claimed_count = claimed_deliveries.len();
+
+        let attempts: Vec<_> = claimed_deliveries
+            .into_iter()
+            .map(|(delivery, payload)| {
+                let client = client.clone();
+                let config = Arc::clone(&state.config);
+                tokio::spawn(async move {
+                    let result = attempt(&client, &config, &delivery, payload.as_deref().map(String::as_str)).await;
+                    (delivery, result)
+                })
+            })
+            .collect();
+        for handle in attempts {
+            let (delivery, result) = handle.await?;
+            if let Err(err) = &result {
+                warn!("Could not post snapshot {} to {}. Details:\n{err}", delivery.snapshot_id, delivery.url);
+                metrics::WEBHOOK_ATTEMPT_FAILURES.increment([]);
+            }
+
+            let delivery_id = delivery.id;
+            let recording = state
+                .connection_pool
+                .transaction(move |conn| record_attempt(conn, &delivery, result, DateTime::now()))
+                .await;
+            if let Err(err) = recording {
+                error!("Failed to record attempt of webhook delivery {delivery_id}. Details:\n{err}");
+            }
+        }
+
+        if claimed_count < BATCH_SIZE {
+            return Ok(());
+        }
+    }
+}
+
+/// Schedules a failed delivery to be attempted again as soon as possible, with a fresh set of attempts.
+pub fn replay(conn: &mut PgConnection, delivery_id: i64) -> ApiResult<()> {
+    let status: WebhookDeliveryStatus = webhook_delivery::table
+        .find(delivery_id)
+        .select(webhook_delivery::status)
+        .for_update()
+        .first(conn)
+        .optional()?
+        .ok_or(ApiError::NotFound(ResourceType::WebhookDelivery))?;
+    if status != WebhookDeliveryStatus::Failed {
+        return Err(ApiError::UnreplayableWebhookDelivery);
+    }
+
+    diesel::update(webhook_delivery::table.find(delivery_id))
+        .set((
+            webhook_delivery::status.eq(WebhookDeliveryStatus::Pending),
+            webhook_delivery::attempt_count.eq(0),
+            webhook_delivery::next_attempt_time.eq(DateTime::now()),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Deletes deliveries that succeeded before `cutoff`. Returns the number of deleted deliveries.
+pub fn purge_delivered(conn: &mut PgConnection, cutoff: DateTime) -> QueryResult<usize> {
+    diesel::delete(webhook_delivery::table)
+        .filter(webhook_delivery::status.eq(WebhookDeliveryStatus::Delivered))
+        .filter(webhook_delivery::last_attempt_time.lt(cutoff))
+        .execute(conn)
+}
+
+/// Returns the delay before the next attempt of a delivery that has failed `attempt_count` times.
+pub fn retry_delay(attempt_count: i32) -> Duration {
+    let doublings = u32::try_from(attempt_count.saturating_sub(1)).unwrap_or(0);
+    INITIAL_RETRY_DELAY.saturating_mul(2_u32.saturating_pow(doublings))
+}
+
+/// Computes the signature of `payload` that is sent to webhooks with a secret.
+pub fn signature(secret: &SecretString, payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC should take key of any size");
+    mac.update(payload.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Creates a delivery to each webhook interested in the oldest queued events and removes them
+/// from the queue. Returns the number of events handled.
+fn queue_deliveries(conn: &mut PgConnection, config: &Config) -> QueryResult<usize> {
+    let events: Vec<(i64, ResourceType, ResourceOperation)> = webhook_event::table
+        .inner_join(snapshot::table)
+        .select((snapshot::id, snapshot::resource_type, snapshot::operation))
+        .order(webhook_event::snapshot_id)
+        .limit(BATCH_SIZE as i64)
+        .for_update()
+        .skip_locked()
+        .load(conn)?;
+
+    let new_deliveries: Vec<_> = events
+        .iter()
+        .flat_map(|&(snapshot_id, resource_type, operation)| {
+            config
+                .webhooks
+                .iter()
+                .filter(move |webhook| webhook.accepts(resource_type, operation))
+                .map(move |webhook| NewWebhookDelivery {
+                    snapshot_id,
+                    url: webhook.url.as_str(),
+                })
+        })
+        .collect();
+    new_deliveries.insert_into(webhook_delivery::table).execute(conn)?;
+
+    let snapshot_ids: Vec<i64> = events.iter().map(|&(snapshot_id, ..)| snapshot_id).collect();
+    diesel::delete(webhook_event::table)
+        .filter(webhook_event::snapshot_id.eq_any(snapshot_ids))
+        .execute(conn)?;
+    Ok(events.len())
+}
+
+/// Claims the oldest deliveries that are due and returns them along with their payloads.
+/// Claimed deliveries are postponed so that no one else attempts them while they are in flight.
+fn claim_due_deliveries(
+    conn: &mut PgConnection,
+    config: &Config,
+) -> QueryResult<Vec<(WebhookDelivery, Option<Arc<String>>)>> {
+    let now = DateTime::now();
+    let due_ids: Vec<i64> = webhook_delivery::table
+        .select(webhook_delivery::id)
+        .filter(webhook_delivery::status.eq(WebhookDeliveryStatus::Pending))
+        .filter(webhook_delivery::next_attempt_time.le(now))
+        .order(webhook_delivery::id)
+        .limit(BATCH_SIZE as i64)
+        .for_update()
+        .skip_locked()
+        .load(conn)?;
+    let mut deliveries: Vec<WebhookDelivery> = diesel::update(webhook_delivery::table)
+        .filter(webhook_delivery::id.eq_any(&due_ids))
+        .set(webhook_delivery::next_attempt_time.eq(DateTime::from(*now + 2 * REQUEST_TIMEOUT)))
+        .returning(WebhookDelivery::as_returning())
+        .get_results(conn)?;
+    deliveries.sort_unstable_by_key(|delivery| delivery.id);
+
+    let mut snapshot_ids: Vec<i64> = deliveries.iter().map(|delivery| delivery.snapshot_id).collect();
+    snapshot_ids.sort_unstable();
+    snapshot_ids.dedup();
+    let payloads: HashMap<i64, Arc<String>> =
+        SnapshotInfo::new_batch_from_ids(conn, config, &snapshot_ids, Mask::all())?
+            .into_iter()
+            .map(|snapshot| serde_json::to_string(&snapshot).map(|payload| (snapshot.id, Arc::new(payload))))
+            .collect::<Result<_, _>>()
+            .map_err(|err| diesel::result::Error::SerializationError(Box::new(err)))?;
+    Ok(deliveries
+        .into_iter()
+        .map(|delivery| {
+            let payload = payloads.get(&delivery.snapshot_id).cloned();
+            (delivery, payload)
+        })
+        .collect())
+}
+
+/// Posts `payload` to the webhook of `delivery`, signing it if the webhook has a secret.
+async fn attempt(
+    client: &Client,
+    config: &Config,
+    delivery: &WebhookDelivery,
+    payload: Option<&str>,
+) -> Result<(), String> {
+    let webhook = find_webhook(config, &delivery.url).ok_or("Webhook is no longer configured")?;
+    let payload = payload.ok_or("Snapshot no longer exists")?;
+
+    let mut request = client
+        .post(webhook.url.clone())
+        .header(REFERER, webhook.url.as_str())
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(DELIVERY_HEADER, delivery.id)
+        .body(payload.to_owned());
+    if let Some(secret) = &webhook.secret {
+        request = request.header(webhook.signature_header(), signature(secret, payload));
+    }
+
+    let post = async {
+        let response = request.send().await?;
+        response.error_for_status().map(|_| ())
+    };
+    post.await.map_err(|err| err.to_string())
+}
+
+fn find_webhook<'a>(config: &'a Config, url: &str) -> Option<&'a WebhookConfig> {
+    config.webhooks.iter().find(|webhook| webhook.url.as_str() == url)
+}
+
+/// Updates `delivery` with the `result` of an attempt made at time `now`.
+fn record_attempt(
+    conn: &mut PgConnection,
+    delivery: &WebhookDelivery,
+    result: Result<(), String>,
+    now: DateTime,
+) -> QueryResult<()> {
+    let attempt_count = delivery.attempt_count + 1;
+    let (status, last_error, next_attempt_time) = match result {
+        Ok(()) => (WebhookDeliveryStatus::Delivered, None, now),
+        Err(err) if attempt_count >= MAX_ATTEMPTS => (WebhookDeliveryStatus::Failed, Some(err), now),
+        Err(err) => {
+            let next_attempt_time = DateTime::from(*now + retry_delay(attempt_count));
+            (WebhookDeliveryStatus::Pending, Some(err), next_attempt_time)
+        }
+    };
+
+    // Keep the error of the last failed attempt around, even if a retry succeeded
+    let last_error = last_error.or_else(|| delivery.last_error.clone());
+    diesel::update(webhook_delivery::table.find(delivery.id))
+        .set((
+            webhook_delivery::status.eq(status),
+            webhook_delivery::attempt_count.eq(attempt_count),
+            webhook_delivery::last_error.eq(last_error),
+            webhook_delivery::next_attempt_time.eq(next_attempt_time),
+            webhook_delivery::last_attempt_time.eq(now),
+        ))
+        .execute(conn)?;
+    if status == WebhookDeliveryStatus::Failed {
+        metrics::WEBHOOK_DELIVERY_FAILURES.increment([]);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exponential_backoff() {
+        assert_eq!(retry_delay(1), Duration::from_secs(30));
+        assert_eq!(retry_delay(2), Duration::from_secs(60));
+        assert_eq!(retry_delay(5), Duration::from_secs(480));
+        assert_eq!(retry_delay(MAX_ATTEMPTS - 1), Duration::from_secs(7680));
+    }
+
+    #[test]
+    fn rfc_signature() {
+        // Test vector from RFC 4231, test case 2
+        let secret = SecretString::from("Jefe");
+        assert_eq!(
+            signature(&secret, "what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}